@@ -0,0 +1,1208 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    ops::Range,
+};
+
+use serde::Serialize;
+
+use crate::{
+    ast::{DrylandAmount, DrylandExercise, Set, Statement, Value, Workout},
+    drills::DrillDictionary,
+    pace::{Fatigue, Intensity, PaceModel},
+    path::SetPath,
+};
+
+/// Modifiers (or stroke names) marking an all-out sprint
+const SPRINT_WORDS: &[&str] = &["sprint", "all-out", "allout", "max"];
+
+/// Modifiers (or stroke names) marking race-pace work
+const RACE_PACE_WORDS: &[&str] = &["race-pace", "racepace", "race", "rp"];
+
+/// Modifiers (or stroke names) marking threshold work
+const THRESHOLD_WORDS: &[&str] = &["threshold", "css", "t-pace"];
+
+/// Modifiers marking reps meant to get faster one after another
+const DESCEND_WORDS: &[&str] = &["descend", "desc"];
+
+/// Modifiers marking reps that get faster from start to finish
+const BUILD_WORDS: &[&str] = &["build", "bld"];
+
+/// Modifiers (or stroke names) marking swimming on restricted breathing
+const HYPOXIC_WORDS: &[&str] = &["hypoxic", "no-breath", "underwater", "uw"];
+
+/// Modifiers (or stroke names) marking drill work, besides the drills in the
+/// drill dictionary
+const DRILL_WORDS: &[&str] = &["drill", "drills"];
+
+/// Modifiers (or stroke names) marking kick sets
+const KICK_WORDS: &[&str] = &["kick", "flutter-kick", "dolphin-kick"];
+
+/// Modifiers naming equipment rather than a drill or instruction, e.g., the
+/// `fins` of `100m kick(fins)`
+pub(crate) const EQUIPMENT_WORDS: &[&str] = &[
+    "fins",
+    "paddles",
+    "snorkel",
+    "buoy",
+    "pull-buoy",
+    "board",
+    "kickboard",
+    "band",
+    "parachute",
+    "chute",
+    "drag-suit",
+];
+
+/// Modifiers (or stroke names) suggesting easy warmup or cooldown swimming
+const EASY_WORDS: &[&str] = &[
+    "easy",
+    "warmup",
+    "warm-up",
+    "wu",
+    "cooldown",
+    "cool-down",
+    "cd",
+    "warmdown",
+    "loosen",
+    "recovery",
+    "smooth",
+    "choice",
+];
+
+/// Minimum distance in meters for a single swim to count as a continuous warmup
+const CONTINUOUS_WARMUP_DISTANCE: u32 = 200;
+
+/// Maximum distance in meters for a single swim without interval to count as a cooldown
+const CONTINUOUS_COOLDOWN_DISTANCE: u32 = 400;
+
+/// Estimated time in seconds for a single dryland repetition (e.g., one squat)
+pub(crate) const SECONDS_PER_DRYLAND_REP: u32 = 3;
+
+/// Workout analysis over every statement that is swum
+pub trait Analyse {
+    /// Calls `f` for each statement along with the number of times it is swum
+    fn for_each_statement(&self, f: &mut dyn FnMut(&Statement, u32));
+
+    /// Calls `f` for each dryland exercise along with the number of times it is done
+    fn for_each_dryland(&self, _f: &mut dyn FnMut(&DrylandExercise, u32)) {}
+
+    /// Total distance in meters, saturating at `u32::MAX`
+    fn total_distance(&self) -> u32 {
+        let mut total: u32 = 0;
+        self.for_each_statement(&mut |stmt, reps| {
+            total = total.saturating_add(stmt.distance.meters().saturating_mul(reps));
+        });
+        total
+    }
+
+    /// Total session time in seconds, including dryland
+    fn total_time(&self) -> u32 {
+        self.swim_time().saturating_add(self.dryland_time())
+    }
+
+    /// Time in seconds implied by the intervals, ignoring statements without
+    /// one, saturating at `u32::MAX`
+    fn swim_time(&self) -> u32 {
+        let mut total: u32 = 0;
+        self.for_each_statement(&mut |stmt, reps| {
+            if let Some(interval) = &stmt.interval {
+                total = total.saturating_add(interval.total_seconds(reps));
+            }
+        });
+        total
+    }
+
+    /// Swim time in seconds, from the intervals where given and predicted by
+    /// `model` otherwise, adding the rest after reps on rest rather than a
+    /// send-off (the model's recovery time for rests to a heart rate),
+    /// saturating at `u32::MAX`
+    fn estimated_swim_time(&self, model: &dyn PaceModel) -> u32 {
+        let mut total: u32 = 0;
+        self.for_each_statement(&mut |stmt, reps| {
+            let predicted =
+                || model.predict(stmt.distance.meters(), &stmt.stroke.name, stmt.intensity());
+            total = total.saturating_add(match &stmt.interval {
+                Some(interval) if interval.is_rest() => predicted()
+                    .saturating_add(interval.rest(model.recovery()))
+                    .saturating_mul(reps),
+                Some(interval) if interval.seconds() > 0 => interval.total_seconds(reps),
+                _ => predicted().saturating_mul(reps),
+            });
+        });
+        total
+    }
+
+    /// Swim time like [`estimated_swim_time`](Analyse::estimated_swim_time),
+    /// with predicted reps slowing by `fatigue` as load builds up through the
+    /// session
+    ///
+    /// Reps on a send-off still take the send-off, but add to the load.
+    fn fatigued_swim_time(&self, model: &dyn PaceModel, fatigue: &Fatigue) -> u32 {
+        let mut total = 0.0;
+        let mut load = 0.0;
+        self.for_each_statement(&mut |stmt, reps| {
+            let meters = stmt.distance.meters();
+            let intensity = stmt.intensity();
+            let send_off = |rep: usize| {
+                stmt.interval
+                    .as_ref()
+                    .map_or(0, |interval| interval.for_rep(rep).seconds())
+            };
+            let rest = stmt
+                .interval
+                .as_ref()
+                .map_or(0, |interval| interval.rest(model.recovery()));
+            let fresh = model
+                .predict_duration(meters, &stmt.stroke.name, intensity)
+                .as_secs_f64();
+            for rep in 0..reps as usize {
+                total += match send_off(rep) {
+                    0 => fresh * fatigue.factor(load) + f64::from(rest),
+                    secs => f64::from(secs),
+                };
+                load += Fatigue::load(meters, intensity);
+            }
+        });
+        total.round() as u32
+    }
+
+    /// Estimated time in seconds spent on dryland exercises, saturating at
+    /// `u32::MAX`
+    fn dryland_time(&self) -> u32 {
+        let mut total: u32 = 0;
+        self.for_each_dryland(&mut |exercise, reps| {
+            let per_set = match exercise.amount {
+                DrylandAmount::Reps(count) => count.saturating_mul(SECONDS_PER_DRYLAND_REP),
+                DrylandAmount::Duration(secs) => secs,
+            };
+            total =
+                total.saturating_add(per_set.saturating_mul(exercise.sets).saturating_mul(reps));
+        });
+        total
+    }
+
+    /// Distance in meters swum with each stroke, counting known drills towards
+    /// the stroke they train
+    fn stroke_distribution(&self) -> BTreeMap<String, u32> {
+        self.stroke_distribution_with(&DrillDictionary::builtin())
+    }
+
+    /// Distance in meters swum with each stroke, counting `choice`, IM and
+    /// the drills in `drills` by the dictionary's attribution
+    fn stroke_distribution_with(&self, drills: &DrillDictionary) -> BTreeMap<String, u32> {
+        let mut distribution = BTreeMap::new();
+        let mut add = |stroke: &str, meters: u32| match distribution.get_mut(stroke) {
+            Some(total) => *total += meters,
+            None => {
+                distribution.insert(stroke.to_string(), meters);
+            }
+        };
+        self.for_each_statement(&mut |stmt, reps| {
+            if stmt.rep_strokes.is_empty() && stmt.segments.is_empty() {
+                for (stroke, meters) in drills.attribute(stmt) {
+                    add(stroke, meters * reps);
+                }
+                return;
+            }
+            for (stmt, reps) in stmt.split_by_rep(reps) {
+                for part in stmt.split_by_segment() {
+                    for (stroke, meters) in drills.attribute(&part) {
+                        add(stroke, meters * reps);
+                    }
+                }
+            }
+        });
+        distribution
+    }
+
+    /// Distance in meters grouped by a key computed from each statement, e.g.,
+    /// `workout.group_distance_by(|stmt| stmt.stroke.name.clone())`
+    ///
+    /// Keys may be anything ordered, so a statement property, a `bool` for
+    /// whether a coach note mentions a word, or a list of equipment all work.
+    fn group_distance_by<K: Ord>(&self, mut key: impl FnMut(&Statement) -> K) -> BTreeMap<K, u32>
+    where
+        Self: Sized,
+    {
+        let mut groups = BTreeMap::new();
+        self.for_each_statement(&mut |stmt, reps| {
+            *groups.entry(key(stmt)).or_default() += stmt.distance.meters() * reps;
+        });
+        groups
+    }
+
+    /// Meters swum at each intensity, counting statements with none
+    /// designated as moderate
+    fn intensity_distribution(&self) -> BTreeMap<Intensity, u32>
+    where
+        Self: Sized,
+    {
+        self.group_distance_by(|stmt| stmt.intensity())
+    }
+
+    /// Meters swum with each piece of equipment, as named by
+    /// [`Statement::gear`]
+    fn equipment_usage(&self) -> BTreeMap<String, u32> {
+        let mut usage = BTreeMap::new();
+        self.for_each_statement(&mut |stmt, reps| {
+            for gear in stmt.gear() {
+                *usage.entry(gear).or_default() += stmt.distance.meters() * reps;
+            }
+        });
+        usage
+    }
+
+    /// Number of all-out sprint reps
+    fn sprint_reps(&self) -> u32 {
+        let mut total = 0;
+        self.for_each_statement(&mut |stmt, reps| {
+            if stmt.is_sprint() {
+                total += reps;
+            }
+        });
+        total
+    }
+
+    /// Distance in meters swum at race pace
+    fn race_pace_distance(&self) -> u32 {
+        let mut total = 0;
+        self.for_each_statement(&mut |stmt, reps| {
+            if stmt.is_race_pace() {
+                total += stmt.distance.meters() * reps;
+            }
+        });
+        total
+    }
+
+    /// Hardest intensity of any statement, if anything is swum
+    fn peak_intensity(&self) -> Option<Intensity> {
+        let mut peak = None;
+        self.for_each_statement(&mut |stmt, _| peak = peak.max(Some(stmt.intensity())));
+        peak
+    }
+}
+
+impl Analyse for Workout {
+    fn for_each_statement(&self, f: &mut dyn FnMut(&Statement, u32)) {
+        for set in &self.sets {
+            set.for_each_statement(f);
+        }
+    }
+
+    fn for_each_dryland(&self, f: &mut dyn FnMut(&DrylandExercise, u32)) {
+        for set in &self.sets {
+            set.for_each_dryland(f);
+        }
+    }
+}
+
+impl Analyse for Set {
+    fn for_each_statement(&self, f: &mut dyn FnMut(&Statement, u32)) {
+        match self {
+            Set::Repetition { count, set, .. } => {
+                set.for_each_statement(&mut |stmt, reps| f(stmt, reps.saturating_mul(*count)))
+            }
+            Set::Block { sets, .. } => {
+                for set in sets {
+                    set.for_each_statement(f);
+                }
+            }
+            Set::Statement(stmt) => stmt.for_each_statement(f),
+            Set::Dryland { .. } => {}
+        }
+    }
+
+    fn for_each_dryland(&self, f: &mut dyn FnMut(&DrylandExercise, u32)) {
+        match self {
+            Set::Repetition { count, set, .. } => {
+                set.for_each_dryland(&mut |exercise, reps| f(exercise, reps.saturating_mul(*count)))
+            }
+            Set::Block { sets, .. } => {
+                for set in sets {
+                    set.for_each_dryland(f);
+                }
+            }
+            Set::Statement(_) => {}
+            Set::Dryland { exercises } => {
+                for exercise in exercises {
+                    f(exercise, 1);
+                }
+            }
+        }
+    }
+}
+
+impl Analyse for Statement {
+    fn for_each_statement(&self, f: &mut dyn FnMut(&Statement, u32)) {
+        f(self, 1)
+    }
+}
+
+impl Statement {
+    /// Whether the statement is an all-out sprint
+    pub fn is_sprint(&self) -> bool {
+        self.intensity() == Intensity::Sprint
+    }
+
+    /// Whether the statement is swum at race pace
+    pub fn is_race_pace(&self) -> bool {
+        self.intensity() == Intensity::RacePace
+    }
+
+    /// How hard the statement is meant to be swum, moderate unless designated
+    pub fn intensity(&self) -> Intensity {
+        self.designated_intensity().unwrap_or(Intensity::Moderate)
+    }
+
+    /// Intensity given with `@z3`, or else implied by the modifiers
+    pub fn designated_intensity(&self) -> Option<Intensity> {
+        if self.intensity.is_some() {
+            self.intensity
+        } else if self.has_word(SPRINT_WORDS) {
+            Some(Intensity::Sprint)
+        } else if self.has_word(RACE_PACE_WORDS) {
+            Some(Intensity::RacePace)
+        } else if self.has_word(THRESHOLD_WORDS) {
+            Some(Intensity::Threshold)
+        } else if self.has_word(EASY_WORDS) {
+            Some(Intensity::Easy)
+        } else {
+            None
+        }
+    }
+
+    /// Meters swum with each stroke, splitting alternated strokes by their
+    /// switching distance
+    pub fn stroke_split(&self) -> Vec<(&str, u32)> {
+        let meters = self.distance.meters();
+        let Some(alternation) = &self.alternation else {
+            return vec![(&self.stroke.name, meters)];
+        };
+
+        let mut split: Vec<(&str, u32)> = alternation
+            .strokes
+            .iter()
+            .map(|stroke| (stroke.as_str(), 0))
+            .collect();
+        let mut swum = 0;
+        for index in (0..split.len()).cycle() {
+            if swum >= meters {
+                break;
+            }
+            let length = alternation.every.min(meters - swum);
+            split[index].1 += length;
+            swum += length;
+        }
+        split
+    }
+
+    /// The statement as swum with each of its rep strokes, along with how
+    /// many of `reps` reps swim it
+    ///
+    /// Reps of enclosing repetitions carry on through the strokes, so 7 reps
+    /// of `odds:free evens:back` are 4 free and 3 back.
+    pub fn split_by_rep(&self, reps: u32) -> Vec<(Statement, u32)> {
+        if self.rep_strokes.is_empty() {
+            return vec![(self.clone(), reps)];
+        }
+        let count = self.rep_strokes.len() as u32;
+        (0..count)
+            .zip(&self.rep_strokes)
+            .filter(|(index, _)| *index < reps)
+            .map(|(index, stroke)| {
+                let mut stmt = self.clone();
+                stmt.stroke.name = stroke.clone();
+                stmt.rep_strokes.clear();
+                (stmt, (reps - index).div_ceil(count))
+            })
+            .collect()
+    }
+
+    /// Each part of a rep swum in parts as a statement of its own, with the
+    /// part's modifiers before the statement's; `swim` parts keep the
+    /// statement's stroke
+    pub fn split_by_segment(&self) -> Vec<Statement> {
+        if self.segments.is_empty() {
+            return vec![self.clone()];
+        }
+        self.segments
+            .iter()
+            .map(|segment| {
+                let mut stmt = self.clone();
+                stmt.segments.clear();
+                stmt.distance = segment.distance.clone();
+                if !segment.stroke.name.eq_ignore_ascii_case("swim") {
+                    stmt.stroke.name = segment.stroke.name.clone();
+                }
+                stmt.stroke.modifiers = segment
+                    .stroke
+                    .modifiers
+                    .iter()
+                    .chain(&self.stroke.modifiers)
+                    .cloned()
+                    .collect();
+                stmt
+            })
+            .collect()
+    }
+
+    /// Pace in seconds per 100m the send-off requires, if there is one,
+    /// saturating at `u32::MAX`
+    pub fn pace_per_100(&self) -> Option<u32> {
+        let meters = self.distance.meters();
+        let secs = self.interval.as_ref()?.seconds();
+        (meters > 0 && secs > 0).then(|| {
+            let pace = u64::from(secs) * 100 / u64::from(meters);
+            u32::try_from(pace).unwrap_or(u32::MAX)
+        })
+    }
+
+    /// Goal time in seconds for the zero-based `rep`, from `targets(...)`
+    ///
+    /// Reps of enclosing repetitions cycle through the list again, so the
+    /// fifth rep of `2x { 4x100m free targets(...) }` has the first target.
+    pub fn target(&self, rep: usize) -> Option<u32> {
+        (!self.targets.is_empty()).then(|| self.targets[rep % self.targets.len()])
+    }
+
+    /// Whether the reps are meant to get faster, either marked `descend` or
+    /// given target times that do
+    pub fn is_descending(&self) -> bool {
+        self.has_word(DESCEND_WORDS) || (self.targets.len() > 1 && self.targets_descend())
+    }
+
+    /// Reps marked to get faster by a range such as `descend 1-4`, one-based
+    /// and as written
+    pub fn descend_range(&self) -> Option<(u32, u32)> {
+        self.stroke.modifiers.iter().find_map(|modifier| {
+            if !DESCEND_WORDS
+                .iter()
+                .any(|word| modifier.name.eq_ignore_ascii_case(word))
+            {
+                return None;
+            }
+            match modifier.args.first()? {
+                Value::NumberList(numbers) if numbers.len() >= 2 => {
+                    Some((numbers[0], numbers[numbers.len() - 1]))
+                }
+                _ => None,
+            }
+        })
+    }
+
+    /// Whether each rep is meant to get faster from start to finish
+    pub fn is_build(&self) -> bool {
+        self.has_word(BUILD_WORDS)
+    }
+
+    /// Whether the swim is on restricted breathing, e.g., `50m free(hypoxic)`
+    pub fn is_hypoxic(&self) -> bool {
+        self.has_word(HYPOXIC_WORDS)
+    }
+
+    /// Whether the swim is a drill, marked as one or a drill the dictionary
+    /// knows, e.g., `100m catch-up`
+    pub fn is_drill(&self, drills: &DrillDictionary) -> bool {
+        self.has_word(DRILL_WORDS) || drills.stroke(&self.stroke.name).is_some()
+    }
+
+    /// Whether the swim is kick, e.g., `200m kick [board]` or `100m free(kick)`
+    pub fn is_kick(&self) -> bool {
+        self.has_word(KICK_WORDS)
+    }
+
+    /// Equipment swum with, lowercased: what's in brackets, `[fins]`, and
+    /// modifiers naming known equipment, `(fins)`
+    pub fn gear(&self) -> BTreeSet<String> {
+        let modifiers = self
+            .stroke
+            .modifiers
+            .iter()
+            .map(|modifier| &*modifier.name)
+            .filter(|name| is_equipment(name));
+        self.equipment
+            .iter()
+            .map(|gear| &*gear.name)
+            .chain(modifiers)
+            .map(str::to_ascii_lowercase)
+            .collect()
+    }
+
+    /// Whether every target time is faster than the one before
+    pub fn targets_descend(&self) -> bool {
+        self.targets.windows(2).all(|pair| pair[1] < pair[0])
+    }
+
+    fn has_word(&self, words: &[&str]) -> bool {
+        std::iter::once(self.stroke.name.as_str())
+            .chain(self.stroke.modifiers.iter().map(|modifier| &*modifier.name))
+            .any(|word| words.iter().any(|w| word.eq_ignore_ascii_case(w)))
+    }
+}
+
+/// Likely warmup and cooldown portions of a workout, as ranges of top-level set indices
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sections {
+    pub warmup: Option<Range<usize>>,
+    pub cooldown: Option<Range<usize>>,
+}
+
+impl Sections {
+    /// Range of top-level sets between the warmup and cooldown
+    pub fn main(&self, workout: &Workout) -> Range<usize> {
+        let start = self.warmup.as_ref().map_or(0, |warmup| warmup.end);
+        let end = self
+            .cooldown
+            .as_ref()
+            .map_or(workout.sets.len(), |cooldown| cooldown.start);
+        start..end.max(start)
+    }
+}
+
+/// What a set is for, as shown next to it in reports and totalled over a
+/// training log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SetKind {
+    Warmup,
+    Drill,
+    Kick,
+    /// Quality work not otherwise classified, e.g., an aerobic or threshold set
+    Main,
+    Sprint,
+    Cooldown,
+}
+
+impl SetKind {
+    /// Kind of every set in a section with this name, if the name says so,
+    /// e.g., `warm-up` or the detected `Cooldown`
+    pub fn of_section(name: &str) -> Option<SetKind> {
+        let name = name.to_ascii_lowercase();
+        if name.starts_with("warm") {
+            Some(SetKind::Warmup)
+        } else if name.starts_with("cool") {
+            Some(SetKind::Cooldown)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for SetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetKind::Warmup => write!(f, "warmup"),
+            SetKind::Drill => write!(f, "drill"),
+            SetKind::Kick => write!(f, "kick"),
+            SetKind::Main => write!(f, "main"),
+            SetKind::Sprint => write!(f, "sprint"),
+            SetKind::Cooldown => write!(f, "cooldown"),
+        }
+    }
+}
+
+impl Set {
+    /// What the set is for, if it's swum at all
+    ///
+    /// Sets in the warmup or cooldown section, as named by [`section_names`],
+    /// are of that kind. Other sets are drill, kick or sprint sets if most of
+    /// their distance is, in that order, and main sets otherwise.
+    pub fn kind(&self, section: &str, drills: &DrillDictionary) -> Option<SetKind> {
+        let meters = self.total_distance();
+        if meters == 0 {
+            return None;
+        }
+        if let Some(kind) = SetKind::of_section(section) {
+            return Some(kind);
+        }
+
+        let (mut drill, mut kick, mut sprint) = (0u32, 0u32, 0u32);
+        self.for_each_statement(&mut |stmt, reps| {
+            let meters = stmt.distance.meters().saturating_mul(reps);
+            if stmt.is_drill(drills) {
+                drill = drill.saturating_add(meters);
+            } else if stmt.is_kick() {
+                kick = kick.saturating_add(meters);
+            }
+            if stmt.is_sprint() {
+                sprint = sprint.saturating_add(meters);
+            }
+        });
+        let most = |part: u32| u64::from(part) * 2 > u64::from(meters);
+        Some(if most(drill) {
+            SetKind::Drill
+        } else if most(kick) {
+            SetKind::Kick
+        } else if most(sprint) {
+            SetKind::Sprint
+        } else {
+            SetKind::Main
+        })
+    }
+}
+
+/// Meters swum in each kind of set, classifying the top-level sets
+pub fn kind_distances(workout: &Workout, drills: &DrillDictionary) -> BTreeMap<SetKind, u32> {
+    let mut distances = BTreeMap::new();
+    for (section, set) in section_names(workout).iter().zip(&workout.sets) {
+        if let Some(kind) = set.kind(section, drills) {
+            *distances.entry(kind).or_default() += set.total_distance();
+        }
+    }
+    distances
+}
+
+/// Sets marked `key`, with their paths, in document order
+///
+/// Sets inside a key set are part of it and not listed on their own.
+pub fn key_sets(workout: &Workout) -> Vec<(SetPath, &Set)> {
+    let mut keys: Vec<(SetPath, &Set)> = Vec::new();
+    for (path, set) in workout.numbered_sets() {
+        let inside_key = keys
+            .last()
+            .is_some_and(|(key, _)| path.indices().starts_with(key.indices()));
+        if set.is_key() && !inside_key {
+            keys.push((path, set));
+        }
+    }
+    keys
+}
+
+/// Number of times swimmers pick up or put down equipment between one
+/// statement and the next, in the order they are swum
+///
+/// Repeated blocks count their changes on every round, including going from
+/// the last statement back to the first.
+pub fn equipment_changes(sets: &[Set]) -> u32 {
+    gear_sequence(sets).changes
+}
+
+/// Gear at the start and end of a run of sets, and the changes within it
+struct GearRun {
+    first: Option<BTreeSet<String>>,
+    last: Option<BTreeSet<String>>,
+    changes: u32,
+}
+
+fn gear_sequence(sets: &[Set]) -> GearRun {
+    let mut run = GearRun {
+        first: None,
+        last: None,
+        changes: 0,
+    };
+    for set in sets {
+        let next = gear_run(set);
+        let Some(first) = next.first else {
+            continue;
+        };
+        match &run.last {
+            Some(last) => run.changes += u32::from(*last != first),
+            None => run.first = Some(first),
+        }
+        run.changes += next.changes;
+        run.last = next.last;
+    }
+    run
+}
+
+fn gear_run(set: &Set) -> GearRun {
+    match set {
+        Set::Statement(stmt) => {
+            let gear = stmt.gear();
+            GearRun {
+                first: Some(gear.clone()),
+                last: Some(gear),
+                changes: 0,
+            }
+        }
+        Set::Repetition { count, set, .. } => {
+            let mut run = gear_run(set);
+            let wrap = u32::from(run.first != run.last);
+            run.changes = run.changes * count + wrap * count.saturating_sub(1);
+            run
+        }
+        Set::Block { sets, .. } => gear_sequence(sets),
+        Set::Dryland { .. } => GearRun {
+            first: None,
+            last: None,
+            changes: 0,
+        },
+    }
+}
+
+/// Guesses the warmup and cooldown from set position, easy modifiers and
+/// continuous swims, for workouts without explicit section labels
+pub fn detect_sections(workout: &Workout) -> Sections {
+    let swim_sets: Vec<usize> = (0..workout.sets.len())
+        .filter(|&i| workout.sets[i].total_distance() > 0)
+        .collect();
+    let mut sections = Sections::default();
+    if swim_sets.len() < 2 {
+        return sections;
+    }
+
+    let first = swim_sets[0];
+    if is_easy(&workout.sets[first]) || is_continuous(&workout.sets[first], true) {
+        let end = swim_sets[1..swim_sets.len() - 1]
+            .iter()
+            .take_while(|&&i| is_easy(&workout.sets[i]))
+            .last()
+            .map_or(first, |&i| i);
+        sections.warmup = Some(first..end + 1);
+    }
+
+    let last = swim_sets[swim_sets.len() - 1];
+    let warmup_end = sections.warmup.as_ref().map_or(0, |warmup| warmup.end);
+    if last >= warmup_end
+        && (is_easy(&workout.sets[last]) || is_continuous(&workout.sets[last], false))
+    {
+        let start = swim_sets
+            .iter()
+            .rev()
+            .skip(1)
+            .take_while(|&&i| i >= warmup_end && is_easy(&workout.sets[i]))
+            .last()
+            .map_or(last, |&i| i);
+        sections.cooldown = Some(start..last + 1);
+    }
+
+    sections
+}
+
+/// Section each top-level set belongs to: its name if it's a named block
+/// such as `warmup: { ... }`, otherwise the detected warmup, main set or
+/// cooldown
+pub fn section_names(workout: &Workout) -> Vec<String> {
+    let sections = detect_sections(workout);
+    let within = |range: &Option<Range<usize>>, i| range.as_ref().is_some_and(|r| r.contains(&i));
+    workout
+        .sets
+        .iter()
+        .enumerate()
+        .map(|(i, set)| match set.label() {
+            Some(label) => label.to_string(),
+            None if within(&sections.warmup, i) => "Warmup".to_string(),
+            None if within(&sections.cooldown, i) => "Cooldown".to_string(),
+            None => "Main set".to_string(),
+        })
+        .collect()
+}
+
+/// Meters swum in each section, in workout order, running sets of the same
+/// section together
+pub fn section_distances(workout: &Workout) -> Vec<(String, u32)> {
+    let mut distances: Vec<(String, u32)> = Vec::new();
+    for (name, set) in section_names(workout).into_iter().zip(&workout.sets) {
+        match distances.last_mut() {
+            Some((last, meters)) if *last == name => *meters += set.total_distance(),
+            _ => distances.push((name, set.total_distance())),
+        }
+    }
+    distances
+}
+
+/// Whether `word` names a piece of equipment
+pub(crate) fn is_equipment(word: &str) -> bool {
+    EQUIPMENT_WORDS
+        .iter()
+        .any(|gear| word.eq_ignore_ascii_case(gear))
+}
+
+/// Whether `word` implies how hard to swim, e.g., `easy` or `sprint`
+pub(crate) fn is_intensity_word(word: &str) -> bool {
+    [SPRINT_WORDS, RACE_PACE_WORDS, THRESHOLD_WORDS, EASY_WORDS]
+        .iter()
+        .any(|words| words.iter().any(|w| word.eq_ignore_ascii_case(w)))
+}
+
+/// Whether every statement in the set is easy swimming
+fn is_easy(set: &Set) -> bool {
+    let mut easy = true;
+    set.for_each_statement(&mut |stmt, _| {
+        easy &= stmt.has_word(EASY_WORDS) && !stmt.is_sprint() && !stmt.is_race_pace()
+    });
+    easy
+}
+
+/// Whether the set is a single unbroken swim suitable for a warmup or cooldown
+fn is_continuous(set: &Set, warmup: bool) -> bool {
+    let Set::Statement(stmt) = set else {
+        return false;
+    };
+    if stmt.is_sprint() || stmt.is_race_pace() {
+        return false;
+    }
+    if warmup {
+        stmt.distance.meters() >= CONTINUOUS_WARMUP_DISTANCE
+    } else {
+        stmt.interval.is_none() && stmt.distance.meters() <= CONTINUOUS_COOLDOWN_DISTANCE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lexer::Token,
+        pace::CssModel,
+        parser::{Parser, ParserOptions},
+    };
+    use logos::Logos;
+
+    fn parse(source: &str) -> Workout {
+        Parser::new(Token::lexer(source)).parse().unwrap()
+    }
+
+    /// Parses without the limits on total distance and time, for totals too
+    /// large to hold
+    fn parse_unbounded(source: &str) -> Workout {
+        let options = ParserOptions {
+            max_total_distance: u64::MAX,
+            max_total_time: u64::MAX,
+            ..ParserOptions::default()
+        };
+        Parser::with_options(Token::lexer(source), options)
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_totals() {
+        let workout = parse("200m free @3:00\n4x { 50m back @1:00\n2x25m fly @30s }\n1km free");
+
+        assert_eq!(workout.total_distance(), 200 + 4 * (50 + 50) + 1000);
+        assert_eq!(workout.total_time(), 180 + 4 * (60 + 60));
+
+        let distribution = workout.stroke_distribution();
+        assert_eq!(distribution["free"], 1200);
+        assert_eq!(distribution["back"], 200);
+        assert_eq!(distribution["fly"], 200);
+        assert_eq!(
+            distribution.keys().collect::<Vec<_>>(),
+            vec!["back", "fly", "free"]
+        );
+
+        let workout = parse("4x50m catch-up @1:00\n100m drill(spin)\n100m free");
+        let distribution = workout.stroke_distribution();
+        assert_eq!(distribution["free"], 300);
+        assert_eq!(distribution["back"], 100);
+        assert_eq!(distribution.len(), 2);
+
+        assert_eq!(parse_unbounded("5000000km free").total_distance(), u32::MAX);
+        assert_eq!(
+            parse_unbounded("100000x 100000m free").total_distance(),
+            u32::MAX
+        );
+    }
+
+    #[test]
+    fn test_pace_per_100() {
+        let workout = parse("50m free @40s\n200m back @3:10\n100m fly");
+        let paces: Vec<_> = workout
+            .sets
+            .iter()
+            .map(|set| set.statement().unwrap().pace_per_100())
+            .collect();
+
+        assert_eq!(paces, vec![Some(80), Some(95), None]);
+
+        let workout = parse_unbounded("100m free @1000000:00\n1m free @1000000:00");
+        let paces: Vec<_> = workout
+            .sets
+            .iter()
+            .map(|set| set.statement().unwrap().pace_per_100())
+            .collect();
+        assert_eq!(paces, vec![Some(60_000_000), Some(u32::MAX)]);
+    }
+
+    #[test]
+    fn test_estimated_swim_time() {
+        let workout = parse("400m free(easy)\n4x100m free @1:40\n200m back");
+        let model = CssModel::new(100);
+
+        assert_eq!(workout.swim_time(), 400);
+        assert_eq!(workout.estimated_swim_time(&model), 460 + 400 + 231);
+
+        let workout = parse("2x100m free rest to HR<120");
+        assert_eq!(workout.estimated_swim_time(&model), 2 * (105 + 45));
+        assert_eq!(
+            workout.estimated_swim_time(&model.clone().with_recovery(60)),
+            2 * (105 + 60)
+        );
+
+        let workout = parse("4x50m free r:20");
+        assert_eq!(workout.swim_time(), 0);
+        assert_eq!(workout.estimated_swim_time(&model), 4 * (52 + 20));
+
+        let workout = parse("2x { 3x100m free @1:40/1:35/1:30 }");
+        assert_eq!(workout.swim_time(), 2 * (100 + 95 + 90));
+        assert_eq!(workout.estimated_swim_time(&model), 2 * (100 + 95 + 90));
+    }
+
+    #[test]
+    fn test_fatigued_swim_time() {
+        let workout = parse("400m free(easy)\n4x100m free @1:40\n200m back");
+        let model = CssModel::new(100);
+
+        assert_eq!(
+            workout.fatigued_swim_time(&model, &Fatigue::new(0.0)),
+            workout.estimated_swim_time(&model)
+        );
+        // The back comes after 200 of easy load and 400 of moderate
+        let back: f64 = 231.0 * (1.0 + 0.05 * 0.6);
+        assert_eq!(
+            workout.fatigued_swim_time(&model, &Fatigue::new(5.0)),
+            (460.0 + 400.0 + back).round() as u32
+        );
+    }
+
+    #[test]
+    fn test_alternation() {
+        let workout = parse("400m free/back by 50\n2x150m fly/breast by 100");
+
+        assert_eq!(
+            workout.sets[0].statement().unwrap().stroke_split(),
+            vec![("free", 200), ("back", 200)]
+        );
+        let distribution = workout.stroke_distribution();
+        assert_eq!(distribution["free"], 200);
+        assert_eq!(distribution["back"], 200);
+        assert_eq!(distribution["fly"], 200);
+        assert_eq!(distribution["breast"], 100);
+        assert!(!distribution.contains_key("free/back"));
+    }
+
+    #[test]
+    fn test_segments() {
+        let workout =
+            parse("4x50m free (25 drill/25 swim) @1:00\n2x100m back (50 kick/25 catch-up/25 swim)");
+
+        let distribution = workout.stroke_distribution();
+        assert_eq!(distribution["drill"], 100);
+        assert_eq!(distribution["free"], 100 + 50);
+        assert_eq!(distribution["kick"], 100);
+        assert_eq!(distribution["back"], 50);
+        assert_eq!(workout.total_distance(), 400);
+    }
+
+    #[test]
+    fn test_rep_strokes() {
+        let workout = parse("8x50m odds:free evens:back @1:00\n7x25m odds:fly evens:catch-up");
+
+        let distribution = workout.stroke_distribution();
+        assert_eq!(distribution["free"], 200 + 75);
+        assert_eq!(distribution["back"], 200);
+        assert_eq!(distribution["fly"], 100);
+        assert_eq!(workout.total_distance(), 575);
+    }
+
+    #[test]
+    fn test_group_distance_by() {
+        let workout = parse(
+            "400m free(easy)\n4x { 100m free [paddles] @1:40 @z3 // coach: long strokes\n50m kick [fins, board] }\n200m back(easy)",
+        );
+
+        let by_intensity = workout.group_distance_by(|stmt| stmt.intensity());
+        assert_eq!(by_intensity[&Intensity::Easy], 600);
+        assert_eq!(by_intensity[&Intensity::Threshold], 400);
+        assert_eq!(workout.intensity_distribution(), by_intensity);
+
+        let by_gear = workout.group_distance_by(|stmt| {
+            stmt.equipment
+                .iter()
+                .map(|gear| gear.name.clone())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(by_gear[&vec![]], 600);
+        assert_eq!(by_gear[&vec!["fins".to_string(), "board".to_string()]], 200);
+
+        let noted = workout.group_distance_by(|stmt| {
+            stmt.coach_notes
+                .iter()
+                .any(|note| note.contains("long strokes"))
+        });
+        assert_eq!(noted[&true], 400);
+        assert_eq!(noted[&false], 800);
+        assert_eq!(
+            workout.sets[1].group_distance_by(|stmt| stmt.stroke.name.clone())["kick"],
+            200
+        );
+    }
+
+    #[test]
+    fn test_targets() {
+        let workout =
+            parse("4x100m free targets(1:10, 1:08, 1:06, 1:04)\n4x100m free(descend)\n100m free");
+        let stmts: Vec<&Statement> = workout
+            .sets
+            .iter()
+            .map(|set| set.statement().unwrap())
+            .collect();
+
+        assert_eq!(stmts[0].target(1), Some(68));
+        assert_eq!(stmts[0].target(5), Some(68));
+        assert_eq!(stmts[2].target(0), None);
+        assert!(stmts[0].is_descending());
+        assert!(stmts[1].is_descending());
+        assert!(!stmts[2].is_descending());
+    }
+
+    #[test]
+    fn test_key_sets() {
+        let workout = parse(
+            "400m free(easy)\n4x { 100m fly @1:40 key\n50m free }\n3x { 200m free @z3\n2x50m free @z5 key } key\n200m back",
+        );
+
+        let keys: Vec<String> = key_sets(&workout)
+            .iter()
+            .map(|(path, _)| path.to_string())
+            .collect();
+        assert_eq!(keys, vec!["2.1", "3"]);
+        assert_eq!(
+            key_sets(&workout)[1].1.peak_intensity(),
+            Some(Intensity::Sprint)
+        );
+        assert_eq!(workout.sets[0].peak_intensity(), Some(Intensity::Easy));
+    }
+
+    #[test]
+    fn test_dryland_time() {
+        let workout = parse("dryland { 3x 20 squats, 2:00 plank }\n4x50m free @1:00");
+
+        assert_eq!(workout.total_distance(), 200);
+        assert_eq!(workout.swim_time(), 240);
+        assert_eq!(workout.dryland_time(), 3 * 20 * 3 + 120);
+        assert_eq!(workout.total_time(), 240 + 300);
+
+        let workout = parse_unbounded("dryland { 100000x 100000 squats }");
+        assert_eq!(workout.dryland_time(), u32::MAX);
+    }
+
+    #[test]
+    fn test_equipment_changes() {
+        let workout = parse(
+            "400m free\n4x {\n    100m pull [buoy, paddles]\n    50m kick [fins]\n}\n4x50m kick [fins]\n200m free",
+        );
+        // Into the block, twice a round with one more from each round to
+        // the next, then out of the fins at the end
+        assert_eq!(equipment_changes(&workout.sets), 1 + 4 + 3 + 1);
+
+        let workout = parse("200m pull [Paddles, buoy]\n200m pull [buoy, paddles]\n200m free");
+        assert_eq!(equipment_changes(&workout.sets), 1);
+        let workout = parse("200m pull(paddles) [buoy]\n200m pull [buoy, paddles]\n200m free");
+        assert_eq!(equipment_changes(&workout.sets), 1);
+    }
+
+    #[test]
+    fn test_equipment_usage() {
+        let workout = parse(
+            "4x { 100m pull [buoy, Paddles]\n50m kick(fins, fast) [fins] }\n200m free(paddles)\n100m drill(catch-up)",
+        );
+        assert_eq!(
+            workout.sets[1].statement().unwrap().gear(),
+            BTreeSet::from(["paddles".to_string()])
+        );
+        assert_eq!(
+            workout.equipment_usage(),
+            BTreeMap::from([
+                ("buoy".to_string(), 400),
+                ("fins".to_string(), 200),
+                ("paddles".to_string(), 600),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_detect_sections() {
+        let workout = parse(
+            "400m free\n4x50m kick(easy) @1:00\n8x100m free @1:30\n4x50m fly(sprint) @1:00\n200m choice",
+        );
+        let sections = detect_sections(&workout);
+        assert_eq!(sections.warmup, Some(0..2));
+        assert_eq!(sections.cooldown, Some(4..5));
+        assert_eq!(sections.main(&workout), 2..4);
+
+        let workout = parse("8x100m free @1:30\n4x50m fly(sprint) @1:00");
+        assert_eq!(detect_sections(&workout), Sections::default());
+
+        let workout = parse("dryland { 20 squats }\n200m free(easy)\n200m back(easy)");
+        let sections = detect_sections(&workout);
+        assert_eq!(sections.warmup, Some(1..2));
+        assert_eq!(sections.cooldown, Some(2..3));
+    }
+
+    #[test]
+    fn test_set_kinds() {
+        let workout = parse(
+            "400m free\n200m kick(easy)\n4x { 50m catch-up\n50m free }\n6x50m kick [fins] @1:00\n8x100m free @1:30\n8x25m fly(sprint) @45s\ndryland { 20 squats }\n200m choice(easy)",
+        );
+        let drills = DrillDictionary::builtin();
+        let kinds: Vec<Option<SetKind>> = section_names(&workout)
+            .iter()
+            .zip(&workout.sets)
+            .map(|(section, set)| set.kind(section, &drills))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                Some(SetKind::Warmup),
+                Some(SetKind::Warmup),
+                Some(SetKind::Main),
+                Some(SetKind::Kick),
+                Some(SetKind::Main),
+                Some(SetKind::Sprint),
+                None,
+                Some(SetKind::Cooldown),
+            ]
+        );
+        assert_eq!(
+            workout.sets[2].kind("Main set", &DrillDictionary::empty()),
+            Some(SetKind::Main)
+        );
+        assert_eq!(
+            parse("4x50m drill(catch-up)").sets[0].kind("Main set", &DrillDictionary::empty()),
+            Some(SetKind::Drill)
+        );
+
+        assert_eq!(
+            parse_unbounded("99999x 99999m kick").sets[0].kind("Main set", &drills),
+            Some(SetKind::Kick)
+        );
+
+        let distances = kind_distances(&workout, &drills);
+        assert_eq!(distances[&SetKind::Warmup], 600);
+        assert_eq!(distances[&SetKind::Main], 1200);
+        assert!(!distances.contains_key(&SetKind::Drill));
+    }
+
+    #[test]
+    fn test_section_distances() {
+        let workout = parse(
+            "400m free\n200m kick(easy)\nmain: { 8x100m free @1:30 }\n4x50m fly @1:00\n200m choice(easy)",
+        );
+        assert_eq!(
+            section_names(&workout),
+            vec!["Warmup", "Warmup", "main", "Main set", "Cooldown"]
+        );
+        assert_eq!(
+            section_distances(&workout),
+            vec![
+                ("Warmup".to_string(), 600),
+                ("main".to_string(), 800),
+                ("Main set".to_string(), 200),
+                ("Cooldown".to_string(), 200),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sprint_and_race_pace() {
+        let workout = parse(
+            "8x25m free(sprint) @45s\n4x { 50m fly(race-pace) @1:00\n50m easy }\n4x50m back(MAX)",
+        );
+
+        assert_eq!(workout.sprint_reps(), 8 + 4);
+        assert_eq!(workout.race_pace_distance(), 200);
+    }
+}
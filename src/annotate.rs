@@ -0,0 +1,219 @@
+//! Diagnostics in formats CI systems show as inline annotations
+//!
+//! Clubs keeping workouts in git can run `swim check --output github` (or
+//! `--output sarif` for code scanning) on each push to get parse errors,
+//! lint warnings and feasibility problems as comments on the offending
+//! lines. Diagnostics with only a set path are placed at the line the set
+//! starts on; those about the whole workout are attached to the file.
+
+use std::{io, ops::Range};
+
+use serde_json::json;
+
+use crate::{
+    diagnostic::{Diagnostic, Diagnostics, Severity},
+    path::SetPath,
+};
+
+/// A workout file the diagnostics are about
+#[derive(Debug, Clone, Copy)]
+pub struct AnnotatedFile<'a> {
+    /// Path of the file as the CI system knows it, e.g., relative to the
+    /// repository root
+    pub name: &'a str,
+    pub source: &'a str,
+    /// Where each set starts, as from [`Parser::set_spans`](crate::parser::Parser::set_spans)
+    pub set_spans: &'a [(SetPath, Range<usize>)],
+}
+
+/// Writes a file's diagnostics in some CI annotation format
+pub trait AnnotationFormatter {
+    fn format(
+        &self,
+        file: &AnnotatedFile,
+        diagnostics: &Diagnostics,
+        out: &mut dyn io::Write,
+    ) -> io::Result<()>;
+}
+
+/// GitHub Actions workflow commands, e.g., `::warning file=a.swim,line=3,col=1::...`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GithubFormatter;
+
+/// A SARIF 2.1.0 log, as uploaded to code scanning
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SarifFormatter;
+
+impl AnnotatedFile<'_> {
+    /// One-based line and column the diagnostic is about: its span, or
+    /// failing that the start of its set
+    pub fn position(&self, diagnostic: &Diagnostic) -> Option<(usize, usize)> {
+        let offset = match (&diagnostic.span, &diagnostic.path) {
+            (Some(span), _) => span.start,
+            (None, Some(path)) => {
+                let (_, span) = self.set_spans.iter().find(|(set, _)| set == path)?;
+                span.start
+            }
+            (None, None) => return None,
+        };
+        let before = &self.source[..offset.min(self.source.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line = before.matches('\n').count() + 1;
+        Some((line, before[line_start..].chars().count() + 1))
+    }
+}
+
+impl AnnotationFormatter for GithubFormatter {
+    fn format(
+        &self,
+        file: &AnnotatedFile,
+        diagnostics: &Diagnostics,
+        out: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        for diagnostic in diagnostics {
+            let command = match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Note => "notice",
+            };
+            write!(out, "::{} file={}", command, escape_property(file.name))?;
+            if let Some((line, col)) = file.position(diagnostic) {
+                write!(out, ",line={},col={}", line, col)?;
+            }
+            writeln!(out, "::{}", escape_data(&message(diagnostic)))?;
+        }
+        Ok(())
+    }
+}
+
+impl AnnotationFormatter for SarifFormatter {
+    fn format(
+        &self,
+        file: &AnnotatedFile,
+        diagnostics: &Diagnostics,
+        out: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        let results: Vec<serde_json::Value> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let mut location = json!({
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": file.name },
+                    },
+                });
+                if let Some((line, col)) = file.position(diagnostic) {
+                    location["physicalLocation"]["region"] =
+                        json!({ "startLine": line, "startColumn": col });
+                }
+                json!({
+                    "level": diagnostic.severity.to_string(),
+                    "message": { "text": message(diagnostic) },
+                    "locations": [location],
+                })
+            })
+            .collect();
+        let log = json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "swim",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                },
+                "results": results,
+            }],
+        });
+        serde_json::to_writer_pretty(&mut *out, &log)?;
+        writeln!(out)
+    }
+}
+
+/// The diagnostic's message with its set, as printed by `swim`
+fn message(diagnostic: &Diagnostic) -> String {
+    match &diagnostic.path {
+        Some(path) => format!("set {}: {}", path, diagnostic.message),
+        None => diagnostic.message.clone(),
+    }
+}
+
+/// Escapes a workflow command's message
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a workflow command's property value, which also can't hold the
+/// `:` and `,` separating properties
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn annotate(formatter: &dyn AnnotationFormatter, source: &str) -> String {
+        let mut parser = Parser::new(Token::lexer(source));
+        let mut diagnostics = Diagnostics::new();
+        match parser.parse() {
+            Ok(_) => diagnostics.extend(parser.diagnostics().iter().cloned()),
+            Err(errors) => diagnostics.extend(errors.iter().map(Diagnostic::from)),
+        }
+        diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            path: Some(SetPath::new(vec![1])),
+            span: None,
+            message: "100% effort, twice".to_string(),
+            fix: None,
+        });
+        let file = AnnotatedFile {
+            name: "workouts/tue,am.swim",
+            source,
+            set_spans: parser.set_spans(),
+        };
+        let mut out = Vec::new();
+        formatter.format(&file, &diagnostics, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_github() {
+        assert_eq!(
+            annotate(&GithubFormatter, "title: Tuesday\ntitle: Wednesday\n400m free\n  8x50m fly @1:00"),
+            "::warning file=workouts/tue%2Cam.swim,line=2,col=1::`title` is given more than once; the first one is used\n\
+             ::warning file=workouts/tue%2Cam.swim,line=4,col=3::set 2: 100%25 effort, twice\n"
+        );
+        assert_eq!(
+            annotate(&GithubFormatter, "400m free\n4x100m free @"),
+            "::error file=workouts/tue%2Cam.swim,line=2,col=1::set 2: expected interval after `@`, found end of input\n\
+             ::warning file=workouts/tue%2Cam.swim,line=2,col=1::set 2: 100%25 effort, twice\n"
+        );
+    }
+
+    #[test]
+    fn test_sarif() {
+        let log: serde_json::Value =
+            serde_json::from_str(&annotate(&SarifFormatter, "400m free\n4x100m fre @1:40"))
+                .unwrap();
+        let results = &log["runs"][0]["results"];
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(results[0]["level"], "warning");
+        assert_eq!(
+            results[0]["message"]["text"],
+            "set 2: unknown stroke `fre`; did you mean `free`?"
+        );
+        assert_eq!(
+            results[0]["locations"][0]["physicalLocation"]["region"],
+            json!({ "startLine": 2, "startColumn": 8 })
+        );
+        assert_eq!(
+            results[1]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "workouts/tue,am.swim"
+        );
+    }
+}
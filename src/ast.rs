@@ -1,57 +1,264 @@
 use std::fmt;
 
-#[derive(Debug, Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Workout {
+    #[serde(default)]
+    pub metadata: Metadata,
     pub sets: Vec<Set>,
 }
 
+/// Header lines at the top of a workout (e.g., `title: Tuesday AM`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub entries: Vec<(String, String)>,
+}
+
 /// A single set in the workout
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Set {
     /// A repeated set of exercises
     Repetition {
         count: u32,
         set: Box<Set>,
+        /// Instructions for single rounds of a repeated block, from
+        /// `(round 3 race pace)`, in round order
+        #[serde(default)]
+        rounds: Vec<Round>,
     },
     /// A block containing multiple sets
     Block {
         sets: Vec<Set>,
+        /// Intensity inner statements inherit unless they set their own
+        intensity: Option<Intensity>,
+        /// Marked `key` as a focus of the workout
+        #[serde(default)]
+        key: bool,
+        /// Name given with `warmup: { ... }` or `Main (25:00): { ... }`
+        #[serde(default)]
+        label: Option<String>,
+        /// Pool time in seconds the block is planned to fit in, given only
+        /// for named blocks
+        #[serde(default)]
+        budget: Option<u32>,
     },
     /// A single swimming statement
-    Statement(Statement),
+    Statement(Box<Statement>),
+    /// An out-of-pool block of dryland exercises
+    Dryland {
+        exercises: Vec<DrylandExercise>,
+    },
+}
+
+/// An instruction for one round of a repeated block, e.g., `round 3 race pace`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Round {
+    /// Round the instruction is for, counting from 1
+    pub round: u32,
+    /// The instruction as written, e.g., `race pace` or `faster`
+    pub instruction: String,
+    /// Intensity the instruction names, which the round is swum at
+    pub intensity: Option<Intensity>,
+}
+
+/// A single dryland exercise (e.g., 3x 20 squats, 2:00 plank)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrylandExercise {
+    pub sets: u32,
+    pub amount: DrylandAmount,
+    pub name: String,
+}
+
+/// How much of a dryland exercise is done per set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrylandAmount {
+    /// A number of repetitions (e.g., 20 squats)
+    Reps(u32),
+    /// A duration in seconds (e.g., 2:00 plank)
+    Duration(u32),
 }
 
 /// A single swimming statement with distance, stroke, and interval
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Statement {
     pub distance: Distance,
     pub stroke: Stroke,
+    #[serde(default)]
+    pub equipment: Vec<Equipment>,
+    #[serde(default)]
+    /// Goal time in seconds for each rep, in order, from `targets(1:10, 1:08)`
+    pub targets: Vec<u32>,
     pub interval: Option<Interval>,
+    /// Intensity given with `@z3`, `!Z3` or `!85%` (or inherited from a block
+    /// once resolved)
+    pub intensity: Option<Intensity>,
+    /// Strokes alternated during the swim, if more than one
+    pub alternation: Option<Alternation>,
+    /// Strokes taken in turn by successive reps, odd reps first, from
+    /// `odds:free evens:back`; empty if every rep swims the statement's stroke
+    #[serde(default)]
+    pub rep_strokes: Vec<String>,
+    /// Parts each rep is swum in, in order, from `(25 drill/25 swim)`; empty
+    /// if each rep is one stroke
+    #[serde(default)]
+    pub segments: Vec<Segment>,
+    /// Marked `key` as a focus of the workout, so transforms keep it whole
+    #[serde(default)]
+    pub key: bool,
+    #[serde(default)]
+    /// Private cues from `// coach:` comments, left out of swimmer-facing output
+    pub coach_notes: Vec<String>,
+    /// Race pace each rep is held at, from `hold rp200+1`, until resolved
+    /// into target times
+    #[serde(default)]
+    pub hold: Option<RacePace>,
+    /// Result of a time trial, from `TT __:__` until the time is recorded
+    #[serde(default)]
+    pub trial: Option<Trial>,
+}
+
+/// Result of a time trial, written `TT __:__` until swum and then `TT 2:31`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trial {
+    /// A placeholder where the result is to be recorded
+    Pending,
+    /// Time swum, in seconds
+    Recorded(u32),
+}
+
+/// Race pace of a goal event from the swimmer's profile scaled to the
+/// statement's distance, plus an offset in seconds (e.g., `rp200+1`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RacePace {
+    /// Distance of the goal event in meters
+    pub event: u32,
+    pub offset: i32,
+}
+
+/// A part of each rep with its own distance and stroke, e.g., `25 drill` in
+/// `4x50m free (25 drill/25 swim)`; `swim` is the statement's stroke
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub distance: Distance,
+    pub stroke: Stroke,
+}
+
+/// Strokes alternated within a single swim (e.g., `400m free/back by 50`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alternation {
+    /// Every stroke in order, starting with the statement's stroke
+    pub strokes: Vec<String>,
+    /// Meters swum with each stroke before switching
+    pub every: u32,
 }
 
 /// Distance specification
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Distance {
     pub value: u32,
     pub unit: DistanceUnit,
 }
 
 /// Distance units
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DistanceUnit {
     Meters,
     Kilometers,
 }
 
 /// Stroke specification with optional modifiers
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Stroke {
     pub name: String,
-    pub modifiers: Vec<String>,
+    #[serde(default)]
+    pub modifiers: Modifiers,
+}
+
+/// Modifiers of a stroke; most strokes have at most two, which the
+/// `compact-modifiers` feature stores without allocating
+#[cfg(feature = "compact-modifiers")]
+pub type Modifiers = smallvec::SmallVec<[Modifier; 2]>;
+#[cfg(not(feature = "compact-modifiers"))]
+pub type Modifiers = Vec<Modifier>;
+
+/// Name of a modifier, shared between every use of the name when the
+/// `compact-modifiers` feature interns them
+#[cfg(feature = "compact-modifiers")]
+pub type Name = std::sync::Arc<str>;
+#[cfg(not(feature = "compact-modifiers"))]
+pub type Name = String;
+
+/// Stroke modifier with optional arguments (e.g., `descend 1-3`, `kick 15m`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ModifierRepr")]
+pub struct Modifier {
+    pub name: Name,
+    #[serde(default)]
+    pub args: Vec<Value>,
+}
+
+/// Modifiers were plain words before they took arguments
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ModifierRepr {
+    Word(String),
+    Full {
+        name: String,
+        #[serde(default)]
+        args: Vec<Value>,
+    },
+}
+
+impl From<ModifierRepr> for Modifier {
+    fn from(repr: ModifierRepr) -> Self {
+        match repr {
+            ModifierRepr::Word(name) => Modifier::new(name),
+            ModifierRepr::Full { name, args } => Modifier {
+                args,
+                ..Modifier::new(name)
+            },
+        }
+    }
+}
+
+impl Modifier {
+    /// Modifier without arguments
+    pub fn new(name: impl Into<Name>) -> Self {
+        Modifier {
+            name: name.into(),
+            args: Vec::new(),
+        }
+    }
+}
+
+/// Argument of a modifier
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Value {
+    /// A count, e.g., `breathe 3`
+    Number(u32),
+    /// Dash-separated numbers, e.g., `descend 1-3`
+    NumberList(Vec<u32>),
+    /// e.g., `kick 15m`
+    Distance(Distance),
+}
+
+/// Equipment with optional parameters (e.g., paddles(small))
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Equipment {
+    pub name: String,
+    #[serde(default)]
+    pub parameters: Vec<String>,
 }
 
 /// Interval timing
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Interval {
     /// Simple seconds interval (e.g., @30s)
     Seconds(u32),
@@ -60,10 +267,266 @@ pub enum Interval {
         minutes: u32,
         seconds: u32,
     },
+    /// Interval relative to the swimmer's base pace per 100 (e.g., @base+5)
+    Base {
+        offset: i32,
+    },
+    /// Interval at the race pace of a goal event (e.g., @rp200+10)
+    RacePace(RacePace),
+    /// Rest after each rep until the heart rate drops below `below` beats
+    /// per minute (e.g., rest to HR<120)
+    HeartRate {
+        below: u32,
+    },
+    /// Seconds of rest after each rep rather than a send-off (e.g., r:20)
+    Rest(u32),
+    /// Send-offs taken in turn by successive reps (e.g., @1:40/1:35/1:30),
+    /// starting over if there are more reps than send-offs
+    Sequence(Vec<Interval>),
+}
+
+/// How hard a swim is meant to be, from zone 1 (easy) to zone 5 (sprint)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Intensity {
+    Easy,
+    Moderate,
+    Threshold,
+    RacePace,
+    Sprint,
+}
+
+impl Distance {
+    /// Distance converted to meters, saturating at `u32::MAX`
+    pub fn meters(&self) -> u32 {
+        match self.unit {
+            DistanceUnit::Meters => self.value,
+            DistanceUnit::Kilometers => self.value.saturating_mul(1000),
+        }
+    }
+}
+
+impl Set {
+    /// Statement swum by a set that is a single, possibly repeated, statement
+    pub fn statement(&self) -> Option<&Statement> {
+        match self {
+            Set::Statement(stmt) => Some(stmt),
+            Set::Repetition { set, .. } => set.statement(),
+            _ => None,
+        }
+    }
+
+    /// Name of a labeled block, or of the block a repetition repeats
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            Set::Block { label, .. } => label.as_deref(),
+            Set::Repetition { set, .. } => set.label(),
+            _ => None,
+        }
+    }
+
+    /// Whether the set is marked `key`, directly or as a repeated key set
+    pub fn is_key(&self) -> bool {
+        match self {
+            Set::Statement(stmt) => stmt.key,
+            Set::Block { key, .. } => *key,
+            Set::Repetition { set, .. } => set.is_key(),
+            Set::Dryland { .. } => false,
+        }
+    }
+}
+
+impl Interval {
+    /// Interval in its usual notation: seconds below a minute, minutes and seconds otherwise
+    pub fn from_seconds(secs: u32) -> Self {
+        if secs < 60 {
+            Interval::Seconds(secs)
+        } else {
+            Interval::MinutesSeconds {
+                minutes: secs / 60,
+                seconds: secs % 60,
+            }
+        }
+    }
+
+    /// Interval converted to seconds
+    ///
+    /// Base and race-pace intervals have no concrete duration until resolved
+    /// and count as zero, as do rests, which are not send-offs: how long a
+    /// rep takes with its rest depends on the swimmer. A list of send-offs
+    /// counts as its fastest, the hardest to make; see
+    /// [`total_seconds`](Interval::total_seconds) for the time over its reps.
+    pub fn seconds(&self) -> u32 {
+        match self {
+            Interval::Seconds(secs) => *secs,
+            Interval::MinutesSeconds { minutes, seconds } => minutes * 60 + seconds,
+            Interval::Base { .. }
+            | Interval::RacePace(_)
+            | Interval::HeartRate { .. }
+            | Interval::Rest(_) => 0,
+            Interval::Sequence(intervals) => intervals
+                .iter()
+                .map(Interval::seconds)
+                .min()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Interval of the zero-based `rep`, the same for every rep unless it is
+    /// a list of send-offs
+    ///
+    /// Reps of enclosing repetitions cycle through the list again, as they do
+    /// target times.
+    pub fn for_rep(&self, rep: usize) -> &Interval {
+        match self {
+            Interval::Sequence(intervals) if !intervals.is_empty() => {
+                &intervals[rep % intervals.len()]
+            }
+            interval => interval,
+        }
+    }
+
+    /// Seconds of send-off over the first `reps` reps, each taking its own
+    /// from a list, saturating at `u32::MAX`
+    pub fn total_seconds(&self, reps: u32) -> u32 {
+        match self {
+            Interval::Sequence(intervals) if !intervals.is_empty() => {
+                let count = intervals.len() as u32;
+                let cycle = intervals
+                    .iter()
+                    .fold(0u32, |total, interval| total.saturating_add(interval.seconds()));
+                intervals[..(reps % count) as usize]
+                    .iter()
+                    .fold(cycle.saturating_mul(reps / count), |total, interval| {
+                        total.saturating_add(interval.seconds())
+                    })
+            }
+            interval => interval.seconds().saturating_mul(reps),
+        }
+    }
+
+    /// Seconds of rest taken after each rep, with `recovery` standing in for
+    /// the time taken to rest to a heart rate; send-offs take none
+    pub fn rest(&self, recovery: u32) -> u32 {
+        match self {
+            Interval::Rest(secs) => *secs,
+            Interval::HeartRate { .. } => recovery,
+            _ => 0,
+        }
+    }
+
+    /// Whether the interval is rest after each rep rather than a send-off
+    pub fn is_rest(&self) -> bool {
+        matches!(self, Interval::Rest(_) | Interval::HeartRate { .. })
+    }
+}
+
+impl Intensity {
+    /// Intensity of a training zone, from 1 to 5
+    pub fn from_zone(zone: u8) -> Option<Self> {
+        match zone {
+            1 => Some(Intensity::Easy),
+            2 => Some(Intensity::Moderate),
+            3 => Some(Intensity::Threshold),
+            4 => Some(Intensity::RacePace),
+            5 => Some(Intensity::Sprint),
+            _ => None,
+        }
+    }
+
+    /// Intensity of swimming at a share of all-out effort, e.g., threshold
+    /// for `!85%`
+    pub fn from_effort(percent: u32) -> Option<Self> {
+        match percent {
+            1..=60 => Some(Intensity::Easy),
+            61..=75 => Some(Intensity::Moderate),
+            76..=85 => Some(Intensity::Threshold),
+            86..=94 => Some(Intensity::RacePace),
+            95..=100 => Some(Intensity::Sprint),
+            _ => None,
+        }
+    }
+
+    /// Intensity from its name (e.g., `threshold`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "easy" => Some(Intensity::Easy),
+            "moderate" => Some(Intensity::Moderate),
+            "threshold" => Some(Intensity::Threshold),
+            "race-pace" => Some(Intensity::RacePace),
+            "sprint" => Some(Intensity::Sprint),
+            _ => None,
+        }
+    }
+
+    /// Training zone, from 1 to 5
+    pub fn zone(self) -> u8 {
+        match self {
+            Intensity::Easy => 1,
+            Intensity::Moderate => 2,
+            Intensity::Threshold => 3,
+            Intensity::RacePace => 4,
+            Intensity::Sprint => 5,
+        }
+    }
+}
+
+impl Round {
+    /// Instruction for `round`, with the intensity it names if it names one:
+    /// an intensity name (`race pace` or `race-pace`) or a zone (`z4`)
+    pub fn new(round: u32, instruction: &str) -> Self {
+        let zone = instruction
+            .strip_prefix('z')
+            .and_then(|zone| zone.parse().ok())
+            .and_then(Intensity::from_zone);
+        Round {
+            round,
+            instruction: instruction.to_string(),
+            intensity: zone.or_else(|| Intensity::from_name(&instruction.replace(' ', "-"))),
+        }
+    }
+}
+
+impl Metadata {
+    /// Value of the first entry with `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the first entry with `key` to `value`, adding one if there is none
+    pub fn set(&mut self, key: &str, value: &str) {
+        match self.entries.iter_mut().find(|(k, _)| k == key) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.entries.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    /// Tags from a `tags: [threshold, freestyle]` entry, lowercased
+    pub fn tags(&self) -> Vec<String> {
+        let Some(value) = self.get("tags") else {
+            return Vec::new();
+        };
+        let value = value.trim();
+        let value = value
+            .strip_prefix('[')
+            .and_then(|value| value.strip_suffix(']'))
+            .unwrap_or(value);
+        value
+            .split(',')
+            .map(|tag| tag.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
 }
 
 impl fmt::Display for Workout {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.metadata.entries {
+            writeln!(f, "{}: {}", key, value)?;
+        }
         for set in &self.sets {
             writeln!(f, "{}", set)?;
         }
@@ -74,35 +537,183 @@ impl fmt::Display for Workout {
 impl fmt::Display for Set {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Set::Repetition { count, set } => write!(f, "{}x {}", count, set),
-            Set::Block { sets } => {
+            Set::Repetition { count, set, rounds } => {
+                write!(f, "{}x {}", count, set)?;
+                if !rounds.is_empty() {
+                    let rounds: Vec<String> = rounds.iter().map(ToString::to_string).collect();
+                    write!(f, " ({})", rounds.join(", "))?;
+                }
+                Ok(())
+            }
+            Set::Block {
+                sets,
+                intensity,
+                key,
+                label,
+                budget,
+            } => {
+                if let Some(label) = label {
+                    write!(f, "{}", label)?;
+                    if let Some(budget) = budget {
+                        write!(f, " ({}:{:02})", budget / 60, budget % 60)?;
+                    }
+                    write!(f, ": ")?;
+                }
                 writeln!(f, "{{")?;
                 for set in sets {
                     writeln!(f, "    {}", set)?;
                 }
-                write!(f, "}}")
+                write!(f, "}}")?;
+                if let Some(intensity) = intensity {
+                    write!(f, " @z{}", intensity.zone())?;
+                }
+                if *key {
+                    write!(f, " key")?;
+                }
+                Ok(())
             }
             Set::Statement(stmt) => write!(f, "{}", stmt),
+            Set::Dryland { exercises } => {
+                writeln!(f, "dryland {{")?;
+                for exercise in exercises {
+                    writeln!(f, "    {}", exercise)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Trial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trial::Pending => write!(f, "TT __:__"),
+            Trial::Recorded(secs) => write!(f, "TT {}:{:02}", secs / 60, secs % 60),
+        }
+    }
+}
+
+impl fmt::Display for Round {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "round {} {}", self.round, self.instruction)
+    }
+}
+
+impl fmt::Display for DrylandExercise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.sets != 1 {
+            write!(f, "{}x ", self.sets)?;
+        }
+        match self.amount {
+            DrylandAmount::Reps(reps) => write!(f, "{} {}", reps, self.name),
+            DrylandAmount::Duration(secs) => {
+                write!(f, "{}:{:02} {}", secs / 60, secs % 60, self.name)
+            }
         }
     }
 }
 
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {}", self.distance, self.stroke)?;
+        match &self.alternation {
+            Some(alternation) => {
+                let stroke = Stroke {
+                    name: alternation.strokes.join("/"),
+                    modifiers: self.stroke.modifiers.clone(),
+                };
+                write!(f, "{} {} by {}", self.distance, stroke, alternation.every)?;
+            }
+            None if self.rep_strokes.len() == 2 => {
+                let stroke = Stroke {
+                    name: format!("evens:{}", self.rep_strokes[1]),
+                    modifiers: self.stroke.modifiers.clone(),
+                };
+                write!(f, "{} odds:{} {}", self.distance, self.rep_strokes[0], stroke)?;
+            }
+            None => write!(f, "{} {}", self.distance, self.stroke)?,
+        }
+        if !self.segments.is_empty() {
+            write!(f, " (")?;
+            for (i, segment) in self.segments.iter().enumerate() {
+                if i > 0 {
+                    write!(f, "/")?;
+                }
+                write!(f, "{}", segment)?;
+            }
+            write!(f, ")")?;
+        }
+        if !self.equipment.is_empty() {
+            write!(f, " [")?;
+            for (i, equipment) in self.equipment.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", equipment)?;
+            }
+            write!(f, "]")?;
+        }
+        if let Some(hold) = &self.hold {
+            write!(f, " hold {}", hold)?;
+        }
+        if let Some(trial) = &self.trial {
+            write!(f, " {}", trial)?;
+        }
+        if !self.targets.is_empty() {
+            write!(f, " targets(")?;
+            for (i, secs) in self.targets.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                match secs {
+                    0..=59 => write!(f, "{}s", secs)?,
+                    _ => write!(f, "{}:{:02}", secs / 60, secs % 60)?,
+                }
+            }
+            write!(f, ")")?;
+        }
         if let Some(interval) = &self.interval {
             write!(f, " {}", interval)?;
         }
+        if let Some(intensity) = self.intensity {
+            write!(f, " @z{}", intensity.zone())?;
+        }
+        if self.key {
+            write!(f, " key")?;
+        }
+        if !self.coach_notes.is_empty() {
+            write!(f, " // coach: {}", self.coach_notes.join("; "))?;
+        }
         Ok(())
     }
 }
 
+impl fmt::Display for Intensity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Intensity::Easy => write!(f, "easy"),
+            Intensity::Moderate => write!(f, "moderate"),
+            Intensity::Threshold => write!(f, "threshold"),
+            Intensity::RacePace => write!(f, "race-pace"),
+            Intensity::Sprint => write!(f, "sprint"),
+        }
+    }
+}
+
 impl fmt::Display for Distance {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}{}", self.value, self.unit)
     }
 }
 
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.distance.unit {
+            DistanceUnit::Meters => write!(f, "{} {}", self.distance.value, self.stroke),
+            DistanceUnit::Kilometers => write!(f, "{} {}", self.distance, self.stroke),
+        }
+    }
+}
+
 impl fmt::Display for DistanceUnit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -129,6 +740,44 @@ impl fmt::Display for Stroke {
     }
 }
 
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::NumberList(numbers) => {
+                for (i, number) in numbers.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "-")?;
+                    }
+                    write!(f, "{}", number)?;
+                }
+                Ok(())
+            }
+            Value::Distance(distance) => write!(f, "{}", distance),
+        }
+    }
+}
+
+impl fmt::Display for Equipment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.parameters.is_empty() {
+            write!(f, "({})", self.parameters.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
 impl fmt::Display for Interval {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -136,6 +785,33 @@ impl fmt::Display for Interval {
             Interval::MinutesSeconds { minutes, seconds } => {
                 write!(f, "@{}:{:02}", minutes, seconds)
             }
+            Interval::Base { offset } => match offset {
+                0 => write!(f, "@base"),
+                _ => write!(f, "@base{:+}", offset),
+            },
+            Interval::RacePace(race_pace) => write!(f, "@{}", race_pace),
+            Interval::HeartRate { below } => write!(f, "rest to HR<{}", below),
+            Interval::Rest(secs @ 0..=59) => write!(f, "r:{}", secs),
+            Interval::Rest(secs) => write!(f, "r:{}:{:02}", secs / 60, secs % 60),
+            Interval::Sequence(intervals) => {
+                for (i, interval) in intervals.iter().enumerate() {
+                    let written = interval.to_string();
+                    match i {
+                        0 => write!(f, "{}", written)?,
+                        _ => write!(f, "/{}", written.trim_start_matches('@'))?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for RacePace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.offset {
+            0 => write!(f, "rp{}", self.event),
+            offset => write!(f, "rp{}{:+}", self.event, offset),
         }
     }
 }
@@ -148,36 +824,65 @@ mod tests {
     fn test_ast_construction() {
         // Create a sample workout: 4x { 100m freestyle @1:30, 50m butterfly(drill) @45s }
         let workout = Workout {
+            metadata: Metadata::default(),
             sets: vec![Set::Repetition {
                 count: 4,
+                rounds: vec![],
                 set: Box::new(Set::Block {
                     sets: vec![
-                        Set::Statement(Statement {
+                        Set::Statement(Box::new(Statement {
                             distance: Distance {
                                 value: 100,
                                 unit: DistanceUnit::Meters,
                             },
                             stroke: Stroke {
                                 name: "freestyle".to_string(),
-                                modifiers: vec![],
+                                modifiers: Modifiers::new(),
                             },
+                            equipment: vec![],
+                            targets: vec![],
                             interval: Some(Interval::MinutesSeconds {
                                 minutes: 1,
                                 seconds: 30,
                             }),
-                        }),
-                        Set::Statement(Statement {
+                            intensity: None,
+                            alternation: None,
+                            rep_strokes: vec![],
+                            segments: vec![],
+                            key: false,
+                            coach_notes: vec![],
+                            hold: None,
+                            trial: None,
+                        })),
+                        Set::Statement(Box::new(Statement {
                             distance: Distance {
                                 value: 50,
                                 unit: DistanceUnit::Meters,
                             },
                             stroke: Stroke {
                                 name: "butterfly".to_string(),
-                                modifiers: vec!["drill".to_string()],
+                                modifiers: [Modifier::new("drill")].into_iter().collect(),
                             },
+                            equipment: vec![Equipment {
+                                name: "fins".to_string(),
+                                parameters: vec!["short".to_string()],
+                            }],
+                            targets: vec![],
                             interval: Some(Interval::Seconds(45)),
-                        }),
+                            intensity: Some(Intensity::Threshold),
+                            alternation: None,
+                            rep_strokes: vec![],
+                            segments: vec![],
+                            key: false,
+                            coach_notes: vec![],
+                            hold: None,
+                            trial: None,
+                        })),
                     ],
+                    intensity: None,
+                    key: false,
+                    label: None,
+                    budget: None,
                 }),
             }],
         };
@@ -189,7 +894,7 @@ mod tests {
         assert!(output.contains("freestyle"));
         assert!(output.contains("@1:30"));
         assert!(output.contains("50m"));
-        assert!(output.contains("butterfly(drill)"));
-        assert!(output.contains("@45s"));
+        assert!(output.contains("butterfly(drill) [fins(short)]"));
+        assert!(output.contains("@45s @z3"));
     }
 }
@@ -0,0 +1,311 @@
+//! A weekly plan laid out on the calendar, for scheduling a season
+//!
+//! Each planned day takes its date in the weeks from a start date. Days that
+//! fall on a rest day, a weekday the squad doesn't train or a date the pool
+//! is closed, move to the next free training day. The schedule can be
+//! written as dated workout files for the library or as one iCalendar file
+//! of all-day events.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime, Weekday};
+use serde::Deserialize;
+
+use crate::{
+    analysis::Analyse, ast::Workout, check::format_duration, log::week_start, plan::WeekPlan,
+};
+
+/// A day without training, from the `rest_days` config, e.g., `sunday` or
+/// `2024-06-10`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum RestDay {
+    Weekday(Weekday),
+    Date(NaiveDate),
+}
+
+/// A planned workout placed on a date
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    /// The day of the plan the workout is from
+    pub day: Weekday,
+    pub path: PathBuf,
+}
+
+impl TryFrom<String> for RestDay {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        if let Ok(date) = text.parse() {
+            return Ok(RestDay::Date(date));
+        }
+        text.parse()
+            .map(RestDay::Weekday)
+            .map_err(|_| format!("invalid rest day `{}`; expected a weekday or a date", text))
+    }
+}
+
+impl RestDay {
+    pub fn includes(&self, date: NaiveDate) -> bool {
+        match self {
+            RestDay::Weekday(day) => date.weekday() == *day,
+            RestDay::Date(rest) => date == *rest,
+        }
+    }
+}
+
+/// Dates of the plan's days over `weeks` weeks from the week of `start`
+///
+/// Days before `start` in its week are left out. A day on a rest day, or on
+/// a date an earlier moved day took, moves to the next free date, so the
+/// workouts keep their order. Fails if every weekday is a rest day, or if
+/// the dates run past the last one the calendar holds.
+pub fn calendar(
+    plan: &WeekPlan,
+    start: NaiveDate,
+    weeks: u32,
+    rest_days: &[RestDay],
+) -> Result<Vec<CalendarDay>, String> {
+    let mut weekdays = std::iter::successors(Some(Weekday::Mon), |day| Some(day.succ())).take(7);
+    if !plan.days.is_empty() && weekdays.all(|day| rest_days.contains(&RestDay::Weekday(day))) {
+        return Err("every weekday is a rest day, so there is no day to train".to_string());
+    }
+    let too_late = || {
+        format!(
+            "{} weeks from {} run past the last date supported",
+            weeks, start
+        )
+    };
+    // Checked up front so a huge `weeks` fails before laying out any days
+    week_start(start)
+        .checked_add_days(Days::new(7 * u64::from(weeks)))
+        .ok_or_else(too_late)?;
+
+    let mut days: Vec<CalendarDay> = Vec::new();
+    for week in 0..weeks {
+        let monday = week_start(start)
+            .checked_add_days(Days::new(7 * u64::from(week)))
+            .ok_or_else(too_late)?;
+        for (day, path) in &plan.days {
+            let planned = monday
+                .checked_add_days(Days::new(day.num_days_from_monday().into()))
+                .ok_or_else(too_late)?;
+            if planned < start {
+                continue;
+            }
+            let mut date = match days.last() {
+                Some(last) if last.date >= planned => last.date.succ_opt().ok_or_else(too_late)?,
+                _ => planned,
+            };
+            while rest_days.iter().any(|rest| rest.includes(date)) {
+                date = date.succ_opt().ok_or_else(too_late)?;
+            }
+            days.push(CalendarDay {
+                date,
+                day: *day,
+                path: path.clone(),
+            });
+        }
+    }
+    Ok(days)
+}
+
+/// `source` dated `date`, replacing a `date:` line it already has
+pub fn with_date(source: &str, date: NaiveDate) -> String {
+    let line = format!("date: {}", date);
+    let mut offset = 0;
+    for text in source.split_inclusive('\n') {
+        let trimmed = text.trim_start();
+        if trimmed.starts_with("date:") {
+            let start = offset + text.len() - trimmed.len();
+            let end = offset + text.trim_end().len();
+            return format!("{}{}{}", &source[..start], line, &source[end..]);
+        }
+        offset += text.len();
+    }
+    format!("{}\n{}", line, source)
+}
+
+/// Name of the dated copy of a workout file, e.g., `2024-06-03-threshold.swim`
+pub fn dated_file_name(date: NaiveDate, path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map_or("workout".into(), |stem| stem.to_string_lossy());
+    format!("{}-{}.swim", date, stem)
+}
+
+/// Writes the scheduled workouts as an iCalendar file of all-day events,
+/// stamped `stamp` (UTC)
+pub fn write_ics(
+    days: &[CalendarDay],
+    workouts: &[Workout],
+    stamp: NaiveDateTime,
+    out: &mut dyn io::Write,
+) -> io::Result<()> {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//swim//{}//EN", env!("CARGO_PKG_VERSION")),
+    ];
+    for (day, workout) in days.iter().zip(workouts) {
+        let name = dated_file_name(day.date, &day.path);
+        let title = workout
+            .metadata
+            .get("title")
+            .map(str::to_string)
+            .unwrap_or_else(|| name[11..name.len() - 5].to_string());
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@swim", name),
+            format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%SZ")),
+            format!("DTSTART;VALUE=DATE:{}", day.date.format("%Y%m%d")),
+            format!(
+                "DTEND;VALUE=DATE:{}",
+                (day.date + Days::new(1)).format("%Y%m%d")
+            ),
+            format!("SUMMARY:{}", escape_text(&title)),
+            format!(
+                "DESCRIPTION:{}",
+                escape_text(&format!(
+                    "{}m, about {}",
+                    workout.total_distance(),
+                    format_duration(workout.total_time())
+                ))
+            ),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    for line in lines {
+        write!(out, "{}\r\n", fold(&line))?;
+    }
+    Ok(())
+}
+
+/// Escapes an iCalendar text value
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line longer than 75 octets onto continuation lines
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_calendar() {
+        let plan = WeekPlan::parse(
+            "[days]\nmonday = \"threshold.swim\"\nwednesday = \"drills.swim\"\nsunday = \"long.swim\"",
+        )
+        .unwrap();
+        let rest_days = [
+            RestDay::try_from("sunday".to_string()).unwrap(),
+            RestDay::try_from("2024-06-10".to_string()).unwrap(),
+        ];
+        let days: Vec<(NaiveDate, Weekday)> = calendar(&plan, date("2024-06-05"), 2, &rest_days)
+            .unwrap()
+            .iter()
+            .map(|day| (day.date, day.day))
+            .collect();
+        assert_eq!(
+            days,
+            vec![
+                (date("2024-06-05"), Weekday::Wed),
+                (date("2024-06-11"), Weekday::Sun),
+                (date("2024-06-12"), Weekday::Mon),
+                (date("2024-06-13"), Weekday::Wed),
+                (date("2024-06-17"), Weekday::Sun),
+            ]
+        );
+        assert_eq!(
+            RestDay::try_from("someday".to_string()),
+            Err("invalid rest day `someday`; expected a weekday or a date".to_string())
+        );
+
+        let every_day: Vec<RestDay> = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"]
+            .iter()
+            .map(|day| RestDay::try_from(day.to_string()).unwrap())
+            .collect();
+        assert_eq!(
+            calendar(&plan, date("2024-06-05"), 2, &every_day),
+            Err("every weekday is a rest day, so there is no day to train".to_string())
+        );
+        assert_eq!(
+            calendar(&plan, date("2024-06-05"), u32::MAX, &rest_days),
+            Err("4294967295 weeks from 2024-06-05 run past the last date supported".to_string())
+        );
+    }
+
+    #[test]
+    fn test_with_date() {
+        let date = date("2024-06-03");
+        assert_eq!(
+            with_date("title: Tuesday\n400m free", date),
+            "date: 2024-06-03\ntitle: Tuesday\n400m free"
+        );
+        assert_eq!(
+            with_date("title: Tuesday\ndate: 2023-01-01\n400m free", date),
+            "title: Tuesday\ndate: 2024-06-03\n400m free"
+        );
+        assert_eq!(
+            dated_file_name(date, Path::new("plans/threshold.swim")),
+            "2024-06-03-threshold.swim"
+        );
+    }
+
+    #[test]
+    fn test_ics() {
+        let days = [CalendarDay {
+            date: date("2024-06-03"),
+            day: Weekday::Mon,
+            path: "threshold.swim".into(),
+        }];
+        let workout = Parser::new(Token::lexer(
+            "title: Threshold, then sprints; a title long enough that the line folds in two\n8x100m free @1:30",
+        ))
+        .parse()
+        .unwrap();
+        let mut out = Vec::new();
+        let stamp = date("2024-05-01").and_hms_opt(9, 30, 0).unwrap();
+        write_ics(&days, &[workout], stamp, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//swim//{}//EN\r\nBEGIN:VEVENT\r\n\
+                 UID:2024-06-03-threshold.swim@swim\r\nDTSTAMP:20240501T093000Z\r\n\
+                 DTSTART;VALUE=DATE:20240603\r\nDTEND;VALUE=DATE:20240604\r\n\
+                 SUMMARY:Threshold\\, then sprints\\; a title long enough that the line folds \r\n in two\r\n\
+                 DESCRIPTION:800m\\, about 12:00\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+}
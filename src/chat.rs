@@ -0,0 +1,342 @@
+//! Workouts as chat messages, for squads that get their practices on Discord
+//! or Slack
+//!
+//! The sets go in a code block so they line up, each statement marked with an
+//! icon for its stroke, under a bold title and totals. Workouts too long for
+//! one message are split between top-level sets where possible, and lines too
+//! long for one wrap with `\` continuations. Coach notes are left out, as the
+//! messages go to the whole squad.
+
+use crate::{
+    analysis::Analyse,
+    ast::{Set, Statement, Workout},
+    check::format_duration,
+};
+
+/// Longest message Discord accepts, in characters; Slack allows more
+pub const DISCORD_LIMIT: usize = 2000;
+
+/// Characters kept free in each message for the `*(2/3)*` part marker
+const PART_MARKER: usize = 16;
+
+/// Fewest characters each message must have free for lines of sets
+const MIN_LINE: usize = 20;
+
+/// Indentation of each nesting level in the code block
+const INDENT: &str = "  ";
+
+/// Icon shown before a statement of the stroke
+fn stroke_icon(stroke: &str) -> &'static str {
+    match stroke.to_ascii_lowercase().as_str() {
+        "free" | "freestyle" => "🏊",
+        "back" | "backstroke" => "🔙",
+        "breast" | "breaststroke" => "🐸",
+        "fly" | "butterfly" => "🦋",
+        "im" => "🔄",
+        "kick" => "🦵",
+        "pull" => "💪",
+        "drill" => "🛠️",
+        "choice" => "🎲",
+        _ => "🌊",
+    }
+}
+
+/// The workout as chat messages of at most `limit` characters each
+///
+/// Fails if `limit` is too small to hold the header or a useful part of a
+/// line.
+pub fn chat_messages(workout: &Workout, limit: usize) -> Result<Vec<String>, String> {
+    let workout = &workout.swimmer_view();
+
+    let mut header = String::new();
+    if let Some(title) = workout.metadata.get("title") {
+        header.push_str(&format!("**{}**\n", title));
+    }
+    header.push_str(&format!("**{}m**", workout.total_distance()));
+    let time = workout.total_time();
+    if time > 0 {
+        header.push_str(&format!(" · **{}**", format_duration(time)));
+    }
+    header.push('\n');
+
+    let groups: Vec<Vec<String>> = workout
+        .sets
+        .iter()
+        .map(|set| {
+            let mut lines = Vec::new();
+            set_lines(set, 0, "", &mut lines);
+            lines
+        })
+        .collect();
+
+    // Code block fences and their newlines
+    let fences = "```\n".len() + "```".len();
+    let first_budget = limit.saturating_sub(chars(&header) + fences + PART_MARKER);
+    let budget = limit.saturating_sub(fences + PART_MARKER);
+    let least = (fences + PART_MARKER + MIN_LINE).max(chars(&header) + PART_MARKER);
+    if limit < least {
+        return Err(format!(
+            "a message limit of {} is too small; messages need at least {} characters",
+            limit, least
+        ));
+    }
+
+    let mut blocks: Vec<Vec<String>> = vec![Vec::new()];
+    let mut used = 0;
+    for group in groups {
+        let size: usize = group.iter().map(|line| chars(line) + 1).sum();
+        let available = if blocks.len() == 1 {
+            first_budget
+        } else {
+            budget
+        };
+        if used + size <= available {
+            used += size;
+            blocks.last_mut().unwrap().extend(group);
+            continue;
+        }
+        // The set doesn't fit: start it in a new message, splitting it
+        // between lines if it doesn't fit in one either. A line too long for
+        // the header's message goes to the next, leaving the header alone.
+        if used > 0 {
+            blocks.push(Vec::new());
+            used = 0;
+        }
+        for line in group.iter().flat_map(|line| wrap(line, budget - 1)) {
+            let size = chars(&line) + 1;
+            let available = if blocks.len() == 1 {
+                first_budget
+            } else {
+                budget
+            };
+            if used + size > available && (used > 0 || blocks.len() == 1) {
+                blocks.push(Vec::new());
+                used = 0;
+            }
+            used += size;
+            blocks.last_mut().unwrap().push(line);
+        }
+    }
+
+    let parts = blocks.len();
+    Ok(blocks
+        .into_iter()
+        .enumerate()
+        .map(|(i, lines)| {
+            let mut message = String::new();
+            if i == 0 {
+                message.push_str(&header);
+            }
+            if parts > 1 {
+                message.push_str(&format!("*({}/{})*\n", i + 1, parts));
+            }
+            if lines.is_empty() {
+                message.pop();
+                return message;
+            }
+            message.push_str("```\n");
+            for line in lines {
+                message.push_str(&line);
+                message.push('\n');
+            }
+            message.push_str("```");
+            message
+        })
+        .collect())
+}
+
+/// Lines of a set at `depth`, the first one after `prefix` (e.g., `4x `)
+fn set_lines(set: &Set, depth: usize, prefix: &str, lines: &mut Vec<String>) {
+    let indent = INDENT.repeat(depth);
+    match set {
+        Set::Repetition { count, set, .. } => {
+            set_lines(set, depth, &format!("{}{}x ", prefix, count), lines)
+        }
+        Set::Block {
+            sets,
+            intensity,
+            key,
+            label,
+            budget,
+        } => {
+            let mut open = format!("{}{}", indent, prefix);
+            if let Some(label) = label {
+                open.push_str(label);
+                open.push(' ');
+            }
+            if let Some(budget) = budget {
+                open.push_str(&format!("({}): ", format_duration(*budget)));
+            }
+            open.push('{');
+            lines.push(open);
+            for set in sets {
+                set_lines(set, depth + 1, "", lines);
+            }
+            let mut close = format!("{}}}", indent);
+            if let Some(intensity) = intensity {
+                close.push_str(&format!(" @z{}", intensity.zone()));
+            }
+            if *key {
+                close.push_str(" key");
+            }
+            lines.push(close);
+        }
+        Set::Statement(stmt) => lines.push(format!(
+            "{}{} {}{}",
+            indent,
+            statement_icon(stmt),
+            prefix,
+            stmt
+        )),
+        Set::Dryland { exercises } => {
+            lines.push(format!("{}🏋️ {}dryland {{", indent, prefix));
+            for exercise in exercises {
+                lines.push(format!("{}{}{}", indent, INDENT, exercise));
+            }
+            lines.push(format!("{}}}", indent));
+        }
+    }
+}
+
+fn statement_icon(stmt: &Statement) -> &'static str {
+    match &stmt.alternation {
+        Some(alternation) => stroke_icon(&alternation.strokes[0]),
+        None => stroke_icon(&stmt.stroke.name),
+    }
+}
+
+fn chars(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// `line` wrapped at spaces into lines of at most `width` characters, each
+/// but the last ending in a `\` continuation
+///
+/// Words too long for a line of their own are cut between lines, and
+/// indentation past half the width is dropped, so no piece is ever longer.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    if chars(line) <= width {
+        return vec![line.to_string()];
+    }
+    let indent = (chars(line) - chars(line.trim_start())).min(width / 2);
+    let continued = " ".repeat((indent + 2 * INDENT.len()).min(width / 2));
+
+    let mut pieces = Vec::new();
+    let mut current = " ".repeat(indent);
+    let mut start = true;
+    for word in line.trim_start().split(' ') {
+        let mut word = word;
+        loop {
+            let space = usize::from(!start);
+            if chars(&current) + space + chars(word) + " \\".len() <= width {
+                if !start {
+                    current.push(' ');
+                }
+                current.push_str(word);
+                start = false;
+                break;
+            }
+            if !start {
+                pieces.push(current + " \\");
+                current = continued.clone();
+                start = true;
+                continue;
+            }
+            let room = width - chars(&current) - " \\".len();
+            let cut = word.char_indices().nth(room).map_or(word.len(), |(i, _)| i);
+            current.push_str(&word[..cut]);
+            pieces.push(current + " \\");
+            current = continued.clone();
+            word = &word[cut..];
+        }
+    }
+    pieces.push(current);
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn parse(source: &str) -> Workout {
+        Parser::new(Token::lexer(source)).parse().unwrap()
+    }
+
+    #[test]
+    fn test_chat_messages() {
+        let workout = parse(
+            "title: Tuesday\n400m free(easy)\nMain (10:00): {\n    4x 100m fly @1:40\n    4x 50m back/breast by 25 @1:00\n}\ndryland {\n    3x 20 squats\n}",
+        );
+
+        assert_eq!(
+            chat_messages(&workout, DISCORD_LIMIT).unwrap(),
+            vec![
+                "**Tuesday**\n**1000m** · **13:40**\n```\n🏊 400m free(easy)\nMain (10:00): {\n  🦋 4x 100m fly @1:40\n  🔙 4x 50m back/breast by 25 @1:00\n}\n🏋️ dryland {\n  3x 20 squats\n}\n```"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_messages() {
+        let source: String = (1..=40)
+            .map(|i| format!("{}x 100m free @1:30\n", i))
+            .collect();
+        let workout = parse(&source);
+
+        let messages = chat_messages(&workout, 300).unwrap();
+        assert!(messages.len() > 1);
+        assert!(messages.iter().all(|message| chars(message) <= 300));
+        assert!(messages[0].starts_with("**"));
+        assert!(messages[1].starts_with(&format!("*(2/{})*\n```\n", messages.len())));
+        let lines: usize = messages
+            .iter()
+            .map(|message| message.lines().filter(|line| line.contains("100m")).count())
+            .sum();
+        assert_eq!(lines, 40);
+    }
+
+    #[test]
+    fn test_long_lines() {
+        let workout = parse("100m fly(catch-up, fist) [paddles, snorkel] @1:40");
+
+        assert_eq!(
+            chat_messages(&workout, 60).unwrap(),
+            vec![
+                "**100m** · **1:40**\n*(1/3)*",
+                "*(2/3)*\n```\n🦋 100m fly(catch-up, fist) \\\n```",
+                "*(3/3)*\n```\n    [paddles, snorkel] @1:40\n```",
+            ]
+        );
+
+        let workout = parse(&format!("100m free({}) @1:40", "a".repeat(100)));
+        let messages = chat_messages(&workout, 60).unwrap();
+        assert!(messages.iter().all(|message| chars(message) <= 60));
+        let text: String = messages.concat();
+        assert_eq!(text.matches('a').count(), 100);
+        assert!(text.contains("@1:40"));
+    }
+
+    #[test]
+    fn test_small_limit() {
+        let workout = parse("100m fly @1:40");
+
+        assert_eq!(
+            chat_messages(&workout, 30),
+            Err(
+                "a message limit of 30 is too small; messages need at least 43 characters"
+                    .to_string()
+            )
+        );
+        assert!(chat_messages(&workout, 43).is_ok());
+    }
+
+    #[test]
+    fn test_coach_notes() {
+        let workout = parse("200m free // coach: watch her breathing");
+
+        let messages = chat_messages(&workout, DISCORD_LIMIT).unwrap();
+        assert!(!messages.concat().contains("breathing"));
+    }
+}
@@ -0,0 +1,391 @@
+use std::fmt;
+
+use crate::{
+    analysis::{detect_sections, Analyse},
+    ast::{Interval, Set, Workout},
+    history::PaceHistory,
+    pace::PaceModel,
+    path::SetPath,
+};
+
+/// Rest in seconds suggested on top of the predicted swim time when
+/// lengthening a send-off
+const SUGGESTED_REST: u32 = 5;
+
+/// Spread of rep times, as a percent of the expected time, assumed for a
+/// swimmer whose profile doesn't give one
+pub const DEFAULT_VARIABILITY: f64 = 3.0;
+
+/// Chance below which making every send-off of a set is flagged
+const UNLIKELY: f64 = 0.5;
+
+/// A reason the workout doesn't fit the swimmer or the session, with a fix
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// Set the problem applies to, or `None` for the whole workout
+    pub path: Option<SetPath>,
+    pub message: String,
+    pub suggestion: String,
+}
+
+/// Send-off for a set from the swimmer's recent times on similar reps
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntervalSuggestion {
+    pub path: SetPath,
+    /// Expected seconds per rep
+    pub expected: u32,
+    pub current: Option<Interval>,
+    pub suggested: Interval,
+}
+
+impl fmt::Display for IntervalSuggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "set {}: {} from recent swims of {}",
+            self.path,
+            self.suggested,
+            format_duration(self.expected)
+        )?;
+        if let Some(current) = &self.current {
+            write!(f, " (written {})", current)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "set {}: ", path)?;
+        }
+        write!(f, "{} ({})", self.message, self.suggestion)
+    }
+}
+
+/// Checks that every send-off is reachable at the pace `model` predicts and,
+/// given the available pool time in seconds, that the session fits in it
+pub fn check(workout: &Workout, model: &dyn PaceModel, available: Option<u32>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    for (path, set) in workout.numbered_sets() {
+        let Some(stmt) = set.statement() else {
+            continue;
+        };
+        let Some(send_off) = stmt.interval.as_ref().map(Interval::seconds) else {
+            continue;
+        };
+        let needed = model.predict(stmt.distance.meters(), &stmt.stroke.name, stmt.intensity());
+        if send_off > 0 && send_off < needed {
+            let suggested = (needed + SUGGESTED_REST).div_ceil(5) * 5;
+            problems.push(Problem {
+                path: Some(path.clone()),
+                message: format!(
+                    "send-off {} is faster than the predicted {} for {} {} at {} intensity",
+                    Interval::from_seconds(send_off),
+                    format_duration(needed),
+                    stmt.distance,
+                    stmt.stroke.name,
+                    stmt.intensity()
+                ),
+                suggestion: format!(
+                    "lengthen the interval on set {} to {}",
+                    path,
+                    Interval::from_seconds(suggested)
+                ),
+            });
+        }
+    }
+
+    let estimated = workout.estimated_swim_time(model) + workout.dryland_time();
+    if let Some(available) = available.filter(|&available| estimated > available) {
+        problems.push(Problem {
+            path: None,
+            message: format!(
+                "estimated session of {} exceeds the available {}",
+                format_duration(estimated),
+                format_duration(available)
+            ),
+            suggestion: suggest_cuts(workout, model, estimated - available),
+        });
+    }
+
+    problems
+}
+
+/// Flags sets whose send-offs the swimmer is more likely to miss than make
+/// on every rep
+///
+/// Rep times are taken as normally distributed around the swimmer's recent
+/// times on similar reps in `history`, or else around the time `model`
+/// predicts with a spread of `variability` percent. Send-offs faster than
+/// the predicted time are left to [`check`].
+pub fn unlikely_send_offs(
+    workout: &Workout,
+    model: &dyn PaceModel,
+    history: &PaceHistory,
+    variability: f64,
+) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    for (path, set) in workout.numbered_sets() {
+        let Some(stmt) = set.statement() else {
+            continue;
+        };
+        let send_off = stmt.interval.as_ref().map_or(0, Interval::seconds);
+        let meters = stmt.distance.meters();
+        let predicted = model.predict(meters, &stmt.stroke.name, stmt.intensity());
+        if send_off == 0 || send_off < predicted {
+            continue;
+        }
+        let (mean, spread) = history.time_spread(&stmt.stroke.name, meters).unwrap_or((
+            f64::from(predicted),
+            f64::from(predicted) * variability.max(0.0) / 100.0,
+        ));
+        let mut reps = 0;
+        set.for_each_statement(&mut |_, count| reps += count);
+        let odds = |secs: u32| make_chance(f64::from(secs), mean, spread).powi(reps as i32);
+
+        let chance = odds(send_off);
+        if chance >= UNLIKELY {
+            continue;
+        }
+        let mut suggested = send_off.div_ceil(5) * 5;
+        while odds(suggested) < UNLIKELY {
+            suggested += 5;
+        }
+        problems.push(Problem {
+            path: Some(path.clone()),
+            message: format!(
+                "{} {} {} is made on all {} reps only {:.0}% of the time",
+                stmt.distance,
+                stmt.stroke.name,
+                Interval::from_seconds(send_off),
+                reps,
+                chance * 100.0
+            ),
+            suggestion: format!(
+                "lengthen the interval on set {} to {}",
+                path,
+                Interval::from_seconds(suggested)
+            ),
+        });
+    }
+
+    problems
+}
+
+/// Chance of swimming a rep within `secs` when times are normally
+/// distributed with `mean` and standard deviation `spread`
+fn make_chance(secs: f64, mean: f64, spread: f64) -> f64 {
+    if spread <= 0.0 {
+        return if secs >= mean { 1.0 } else { 0.0 };
+    }
+    let z = (secs - mean) / (spread * std::f64::consts::SQRT_2);
+    0.5 * (1.0 + erf(z))
+}
+
+/// Error function, to within 1.5e-7 (Abramowitz and Stegun 7.1.26)
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 {
+        -y
+    } else {
+        y
+    }
+}
+
+/// Suggests a send-off for every statement with similar reps in `history`:
+/// the recent time plus some rest, rounded up to 5 seconds
+pub fn suggest_intervals(workout: &Workout, history: &PaceHistory) -> Vec<IntervalSuggestion> {
+    workout
+        .numbered_sets()
+        .into_iter()
+        .filter_map(|(path, set)| {
+            let stmt = set.statement()?;
+            let expected = history.recent_time(&stmt.stroke.name, stmt.distance.meters())?;
+            Some(IntervalSuggestion {
+                path,
+                expected,
+                current: stmt.interval.clone(),
+                suggested: Interval::from_seconds((expected + SUGGESTED_REST).div_ceil(5) * 5),
+            })
+        })
+        .collect()
+}
+
+/// Suggests main sets to cut to save `over` seconds: the shortest single set
+/// that is long enough, or else the longest sets until enough is saved
+///
+/// Sets that are or contain a `key` set are never cut.
+fn suggest_cuts(workout: &Workout, model: &dyn PaceModel, over: u32) -> String {
+    let time = |set: &Set| set.estimated_swim_time(model) + set.dryland_time();
+    let mut candidates: Vec<(usize, u32)> = detect_sections(workout)
+        .main(workout)
+        .filter(|&i| !contains_key(&workout.sets[i]))
+        .map(|i| (i, time(&workout.sets[i])))
+        .filter(|(_, secs)| *secs > 0)
+        .collect();
+    candidates.sort_by_key(|(_, secs)| *secs);
+
+    let cuts: Vec<(usize, u32)> = match candidates.iter().find(|(_, secs)| *secs >= over) {
+        Some(cut) => vec![*cut],
+        None => {
+            let mut saved = 0;
+            let mut cuts: Vec<_> = candidates
+                .iter()
+                .rev()
+                .take_while(|(_, secs)| {
+                    let needed = saved < over;
+                    saved += secs;
+                    needed
+                })
+                .copied()
+                .collect();
+            cuts.sort();
+            cuts
+        }
+    };
+
+    if cuts.is_empty() {
+        return "shorten the warmup or cooldown".to_string();
+    }
+    let saved: u32 = cuts.iter().map(|(_, secs)| secs).sum();
+    let sets: Vec<String> = cuts.iter().map(|(i, _)| (i + 1).to_string()).collect();
+    format!(
+        "cut set{} {} to save {}",
+        if sets.len() == 1 { "" } else { "s" },
+        sets.join(" and "),
+        format_duration(saved)
+    )
+}
+
+fn contains_key(set: &Set) -> bool {
+    set.is_key() || set.children().iter().any(contains_key)
+}
+
+/// Formats seconds as `m:ss`, or `h:mm:ss` from an hour
+pub(crate) fn format_duration(secs: u32) -> String {
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fit::Lap, lexer::Token, pace::CssModel, parser::Parser, reconcile::reconcile,
+        steps::WorkoutSteps,
+    };
+    use logos::Logos;
+
+    fn parse(source: &str) -> Workout {
+        Parser::new(Token::lexer(source)).parse().unwrap()
+    }
+
+    #[test]
+    fn test_send_offs() {
+        let workout = parse("400m free(easy)\n4x100m back @1:40\n8x50m free @50s\n200m free(easy)");
+        let problems = check(&workout, &CssModel::new(90), None);
+
+        assert_eq!(
+            problems.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["set 2: send-off @1:40 is faster than the predicted 1:43 for 100m back at moderate intensity (lengthen the interval on set 2 to @1:50)"]
+        );
+    }
+
+    #[test]
+    fn test_duration() {
+        let workout = parse(
+            "400m free(easy)\n10x100m free @1:40\n4x50m fly @1:00\n8x100m back @2:00\n200m free(easy)",
+        );
+        let model = CssModel::new(90);
+        assert!(check(&workout, &model, Some(60 * 60)).is_empty());
+
+        let problems = check(&workout, &model, Some(45 * 60));
+        assert_eq!(problems.len(), 1);
+        assert_eq!(
+            problems[0].to_string(),
+            "estimated session of 47:01 exceeds the available 45:00 (cut set 3 to save 4:00)"
+        );
+
+        let problems = check(&workout, &model, Some(30 * 60));
+        assert_eq!(problems[0].suggestion, "cut sets 2 and 4 to save 32:40");
+
+        let workout = parse(
+            "400m free(easy)\n10x100m free @1:40\n4x50m fly @1:00 key\n8x100m back @2:00\n200m free(easy)",
+        );
+        let problems = check(&workout, &model, Some(45 * 60));
+        assert_eq!(problems[0].suggestion, "cut set 4 to save 16:00");
+    }
+
+    #[test]
+    fn test_unlikely_send_offs() {
+        let workout =
+            parse("400m free(easy)\n8x100m free @1:50\n2x100m free @1:50\n4x50m back @1:00");
+        let model = CssModel::new(100);
+        let history = PaceHistory::new();
+
+        // 100m free predicted at 1:45 with a 3% spread: each rep is made 94%
+        // of the time, all eight 63%
+        assert!(unlikely_send_offs(&workout, &model, &history, DEFAULT_VARIABILITY).is_empty());
+        assert_eq!(
+            unlikely_send_offs(&workout, &model, &history, 5.0)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["set 2: 100m free @1:50 is made on all 8 reps only 22% of the time (lengthen the interval on set 2 to @1:55)"]
+        );
+
+        // Recent sets of 100s averaged 1:46, 1:52 and 1:58
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let swum = parse("2x100m free");
+        let mut history = PaceHistory::new();
+        for seconds in [106, 112, 118] {
+            let laps = [Lap {
+                meters: 100,
+                seconds,
+            }; 2];
+            history.add(date, &reconcile(&WorkoutSteps::from_workout(&swum), &laps));
+        }
+        let problems = unlikely_send_offs(&workout, &model, &history, DEFAULT_VARIABILITY);
+        assert_eq!(
+            problems
+                .iter()
+                .map(|problem| problem.path.as_ref().unwrap().to_string())
+                .collect::<Vec<_>>(),
+            vec!["2", "3"]
+        );
+    }
+
+    #[test]
+    fn test_suggest_intervals() {
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let swum = parse("4x100m free\n4x50m back");
+        let laps = [(100, 82), (100, 84), (50, 44), (50, 46)]
+            .map(|(meters, seconds)| Lap { meters, seconds });
+        let mut history = PaceHistory::new();
+        history.add(date, &reconcile(&WorkoutSteps::from_workout(&swum), &laps));
+
+        let workout =
+            parse("400m free(easy)\n8x100m free @1:30\n4x100m fly @2:00\n2x { 100m back }");
+        assert_eq!(
+            suggest_intervals(&workout, &history)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "set 2: @1:30 from recent swims of 1:23 (written @1:30)",
+                "set 4.1: @1:35 from recent swims of 1:30",
+            ]
+        );
+    }
+}
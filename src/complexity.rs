@@ -0,0 +1,143 @@
+//! How hard a workout is to remember and swim as written
+//!
+//! Young swimmers lose track of deep nesting, long lists of different reps
+//! and statements piled with modifiers long before they tire, so coaches
+//! writing for them can keep an eye on the score.
+
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::ast::{Set, Statement, Workout};
+
+/// Score points for each level of block nesting
+const DEPTH_WEIGHT: u32 = 3;
+
+/// Score points for each modifier per statement, on average
+const DENSITY_WEIGHT: f64 = 5.0;
+
+/// What makes a workout hard to follow, and a score summing it up
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Complexity {
+    /// Most blocks a statement is nested in
+    pub depth: u32,
+    /// Different reps to remember, by distance, stroke, modifiers and
+    /// equipment
+    pub instructions: usize,
+    /// Modifiers, equipment, alternations and target times per statement
+    /// as written
+    pub modifier_density: f64,
+    /// Instructions, plus 3 for each level of nesting and 5 for each
+    /// modifier per statement; under 10 is easy to follow
+    pub score: u32,
+}
+
+impl Workout {
+    /// How hard the workout is to remember and swim
+    pub fn complexity(&self) -> Complexity {
+        let mut walk = Walk::default();
+        for set in &self.sets {
+            walk.set(set, 0);
+        }
+
+        let modifier_density = if walk.statements == 0 {
+            0.0
+        } else {
+            walk.modifiers as f64 / walk.statements as f64
+        };
+        Complexity {
+            depth: walk.depth,
+            instructions: walk.instructions.len(),
+            modifier_density,
+            score: walk.instructions.len() as u32
+                + DEPTH_WEIGHT * walk.depth
+                + (DENSITY_WEIGHT * modifier_density).round() as u32,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Walk {
+    depth: u32,
+    instructions: BTreeSet<String>,
+    statements: u32,
+    modifiers: u32,
+}
+
+impl Walk {
+    fn set(&mut self, set: &Set, depth: u32) {
+        match set {
+            Set::Repetition { set, .. } => self.set(set, depth),
+            Set::Block { sets, .. } => {
+                for set in sets {
+                    self.set(set, depth + 1);
+                }
+            }
+            Set::Statement(stmt) => {
+                self.depth = self.depth.max(depth);
+                self.statements += 1;
+                self.modifiers += modifier_count(stmt);
+                self.instructions.insert(instruction(stmt));
+            }
+            Set::Dryland { .. } => {}
+        }
+    }
+}
+
+/// What a swimmer has to remember about a rep, leaving out the clock
+fn instruction(stmt: &Statement) -> String {
+    let mut words = vec![
+        stmt.distance.meters().to_string(),
+        stmt.stroke.name.to_ascii_lowercase(),
+    ];
+    words.extend(
+        stmt.stroke
+            .modifiers
+            .iter()
+            .map(|modifier| modifier.name.to_ascii_lowercase()),
+    );
+    words.extend(
+        stmt.equipment
+            .iter()
+            .map(|gear| gear.name.to_ascii_lowercase()),
+    );
+    if let Some(alternation) = &stmt.alternation {
+        words.extend(alternation.strokes.iter().map(|s| s.to_ascii_lowercase()));
+    }
+    words.join(" ")
+}
+
+fn modifier_count(stmt: &Statement) -> u32 {
+    (stmt.stroke.modifiers.len()
+        + stmt.equipment.len()
+        + usize::from(stmt.alternation.is_some())
+        + usize::from(!stmt.targets.is_empty() || stmt.hold.is_some())) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    #[test]
+    fn test_complexity() {
+        let simple = Parser::new(Token::lexer("400m free\n8x50m free @1:00\n200m free"))
+            .parse()
+            .unwrap()
+            .complexity();
+        assert_eq!(simple.depth, 0);
+        assert_eq!(simple.instructions, 3);
+        assert_eq!(simple.score, 3);
+
+        let nested = Parser::new(Token::lexer(
+            "400m free(easy)\n3x {\n    2x {\n        50m fly(drill) [fins]\n        50m free(build) [paddles, snorkel]\n    }\n    100m back/breast by 25\n}\n400m free(easy)",
+        ))
+        .parse()
+        .unwrap()
+        .complexity();
+        assert_eq!(nested.depth, 2);
+        assert_eq!(nested.instructions, 4);
+        assert_eq!(nested.modifier_density, 1.6);
+        assert_eq!(nested.score, 4 + 6 + 8);
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    ast::Intensity,
+    calendar::RestDay,
+    drills::{Attribution, DrillDictionary},
+    lanes::LaneScale,
+    preset::Preset,
+    resolve::Rounding,
+};
+
+/// User settings, usually loaded from a `swim.toml` file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Drills to add to the built-in dictionary, mapped to the stroke they train
+    pub drills: HashMap<String, String>,
+    /// How `choice`, IM and drills count in stroke distributions
+    pub attribution: Attribution,
+    /// Directory of workout files used by the library commands
+    pub library: Option<PathBuf>,
+    /// SQLite index of the library, so commands only parse files that
+    /// changed; needs the `sqlite` feature
+    pub library_index: Option<PathBuf>,
+    /// Required share of each stroke, checked by the linter (e.g., club policy)
+    pub strokes: BTreeMap<String, StrokeLimits>,
+    /// Default values of the parameters tested by `if` blocks
+    pub parameters: BTreeMap<String, bool>,
+    /// Weekly targets the training log is measured against
+    pub goals: SeasonGoals,
+    /// Kind of squad, bundling limits for the linter and feasibility check
+    pub preset: Option<Preset>,
+    /// Scaling of each lane's version of a workout, by lane name
+    pub lanes: BTreeMap<String, LaneScale>,
+    /// Rounding of send-offs worked out from bases or scaled for a lane, if
+    /// not each transform's own default
+    pub rounding: Option<Rounding>,
+    /// Most equipment changes in a workout before the linter suggests
+    /// grouping sets by gear, if not the default of 6
+    pub max_equipment_changes: Option<u32>,
+    /// Weekdays and dates without training, skipped by `swim schedule
+    /// --start`, e.g., `["sunday", "2024-06-10"]`
+    pub rest_days: Vec<RestDay>,
+}
+
+/// Bounds on the distance of a stroke in a workout, from a `[strokes.<stroke>]` table
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StrokeLimits {
+    /// Fewest meters of the stroke
+    pub min: Option<u32>,
+    /// Most meters of the stroke
+    pub max: Option<u32>,
+    /// Smallest percentage of the workout's distance
+    pub min_percent: Option<u32>,
+    /// Largest percentage of the workout's distance
+    pub max_percent: Option<u32>,
+}
+
+/// Weekly targets for a season, from a `[goals]` table or a goals file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeasonGoals {
+    /// Meters to swim each week
+    pub weekly_volume: Option<u32>,
+    /// Percentage of each week's meters to swim at each intensity
+    pub intensity: BTreeMap<Intensity, u32>,
+}
+
+impl Config {
+    /// Reads the config from a TOML file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+        Self::parse(&contents).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    /// Parses the config from TOML
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|error| error.message().to_string())
+    }
+
+    /// Built-in drills extended with the configured ones
+    pub fn drill_dictionary(&self) -> DrillDictionary {
+        let mut dictionary = DrillDictionary::builtin();
+        for (drill, stroke) in &self.drills {
+            dictionary.insert(drill, stroke);
+        }
+        dictionary.set_attribution(self.attribution);
+        dictionary
+    }
+}
+
+impl SeasonGoals {
+    /// Reads goals from a TOML file such as `season.toml`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+        Self::parse(&contents).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    /// Parses goals from TOML, checking the intensity mix adds up to at most 100%
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let goals: Self = toml::from_str(contents).map_err(|error| error.message().to_string())?;
+        let total: u32 = goals.intensity.values().sum();
+        if total > 100 {
+            return Err(format!("intensity goals add up to {}%", total));
+        }
+        Ok(goals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse("[drills]\nsculling = \"breast\"\n").unwrap();
+        assert_eq!(config.drill_dictionary().stroke("sculling"), Some("breast"));
+        assert_eq!(config.drill_dictionary().stroke("zipper"), Some("free"));
+        assert_eq!(
+            config.drill_dictionary().attribution(),
+            Attribution::Strokes
+        );
+        let config = Config::parse("attribution = \"exclude\"").unwrap();
+        assert_eq!(
+            config.drill_dictionary().attribution(),
+            Attribution::Exclude
+        );
+        assert!(Config::parse("attribution = \"drills\"").is_err());
+
+        assert_eq!(
+            Config::parse("library = \"workouts\"").unwrap().library,
+            Some(PathBuf::from("workouts"))
+        );
+        let config =
+            Config::parse("[strokes.back]\nmin = 200\n[strokes.fly]\nmax_percent = 10").unwrap();
+        assert_eq!(config.strokes["back"].min, Some(200));
+        assert_eq!(config.strokes["fly"].max_percent, Some(10));
+        assert!(Config::parse("[strokes.fly]\nmost = 10").is_err());
+        let config = Config::parse("[parameters]\nsprint_day = true").unwrap();
+        assert!(config.parameters["sprint_day"]);
+        let config = Config::parse("[lanes.a]\n[lanes.c]\ndistance = 0.75").unwrap();
+        assert_eq!(config.lanes["a"], LaneScale::default());
+        assert_eq!(config.lanes["c"].distance, 0.75);
+        assert_eq!(config.lanes["c"].interval, 1.0);
+        let config = Config::parse("rounding = \"up-5\"").unwrap();
+        assert_eq!(config.rounding, Some(Rounding::Up(5)));
+        assert!(Config::parse("rounding = \"sideways\"").is_err());
+
+        let config =
+            Config::parse("[goals]\nweekly_volume = 20000\n[goals.intensity]\nrace-pace = 10")
+                .unwrap();
+        assert_eq!(config.goals.weekly_volume, Some(20000));
+        assert_eq!(config.goals.intensity[&Intensity::RacePace], 10);
+
+        assert_eq!(
+            Config::parse("preset = \"age-group-10u\"").unwrap().preset,
+            Some(Preset::AgeGroup10U)
+        );
+        assert!(Config::parse("preset = \"novice\"").is_err());
+
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+        assert!(Config::parse("drills = 3").is_err());
+    }
+
+    #[test]
+    fn test_season_goals() {
+        let goals = SeasonGoals::parse("[intensity]\neasy = 60\nthreshold = 25").unwrap();
+        assert_eq!(goals.weekly_volume, None);
+        assert_eq!(goals.intensity.len(), 2);
+
+        assert_eq!(
+            SeasonGoals::parse("[intensity]\neasy = 80\nsprint = 30"),
+            Err("intensity goals add up to 110%".to_string())
+        );
+        assert!(SeasonGoals::parse("[intensity]\nhard = 10").is_err());
+        assert!(SeasonGoals::parse("volume = 10").is_err());
+    }
+}
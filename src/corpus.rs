@@ -0,0 +1,221 @@
+//! Snapshot checks over a directory of workouts, guarding against unintended
+//! changes to parsing and analysis
+//!
+//! Each `.swim` file is checked against a `.snap` file next to it holding
+//! the workout's AST as JSON and its summary, or its parse errors. The crate
+//! runs this over `tests/corpus/`; `swim corpus` runs it over a user's own
+//! workouts. Blessing writes the current output as the new snapshots.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use logos::Logos;
+
+use crate::{
+    drills::DrillDictionary,
+    lexer::Token,
+    parser::Parser,
+    resolve::resolve_intensities,
+    summary::{Summary, SummaryFormatter, TextFormatter},
+};
+
+/// How a workout's output compares with its snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Unchanged,
+    /// Output differs from the snapshot, at the first differing line
+    Changed {
+        line: usize,
+        expected: String,
+        found: String,
+    },
+    /// No snapshot to compare with
+    Missing,
+    /// Snapshot written from the current output
+    Blessed,
+}
+
+/// Outcome for every workout in a corpus, in path order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CorpusReport {
+    pub results: Vec<(PathBuf, Outcome)>,
+}
+
+impl CorpusReport {
+    /// Workouts whose output doesn't match a snapshot
+    pub fn failures(&self) -> impl Iterator<Item = &(PathBuf, Outcome)> {
+        self.results
+            .iter()
+            .filter(|(_, outcome)| matches!(outcome, Outcome::Changed { .. } | Outcome::Missing))
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+/// Snapshot of a workout source: the AST as JSON and the summary once
+/// intensities are resolved, or the parse errors
+pub fn snapshot(source: &str) -> String {
+    match Parser::new(Token::lexer(source)).parse() {
+        Ok(mut workout) => {
+            resolve_intensities(&mut workout);
+            let summary = Summary::new(&workout, &DrillDictionary::builtin(), None, None);
+            let mut text = Vec::new();
+            TextFormatter
+                .format(&summary, &mut text)
+                .expect("writing to memory doesn't fail");
+            format!(
+                "== ast ==\n{}\n== summary ==\n{}",
+                workout.to_json(),
+                String::from_utf8_lossy(&text)
+            )
+        }
+        Err(errors) => {
+            let mut text = String::from("== errors ==\n");
+            for error in &errors {
+                text.push_str(&format!("{}\n", error));
+            }
+            text
+        }
+    }
+}
+
+/// Compares every `.swim` file under `dir`, recursively, with its snapshot,
+/// writing the snapshots instead if `bless` is set
+pub fn check_corpus(dir: &Path, bless: bool) -> Result<CorpusReport, String> {
+    let mut paths = Vec::new();
+    collect_workouts(dir, &mut paths)?;
+    paths.sort();
+
+    let mut report = CorpusReport::default();
+    for path in paths {
+        let source = std::fs::read_to_string(&path)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+        let output = snapshot(&source);
+        let snapshot_path = path.with_extension("snap");
+
+        let outcome = if bless {
+            std::fs::write(&snapshot_path, &output)
+                .map_err(|error| format!("{}: {}", snapshot_path.display(), error))?;
+            Outcome::Blessed
+        } else {
+            match std::fs::read_to_string(&snapshot_path) {
+                Ok(expected) => compare(&expected, &output),
+                Err(_) => Outcome::Missing,
+            }
+        };
+        report.results.push((path, outcome));
+    }
+    Ok(report)
+}
+
+fn collect_workouts(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|error| format!("{}: {}", dir.display(), error))?;
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_workouts(&path, paths)?;
+        } else if path.extension().is_some_and(|ext| ext == "swim") {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn compare(expected: &str, found: &str) -> Outcome {
+    if expected == found {
+        return Outcome::Unchanged;
+    }
+    let mut expected_lines = expected.lines();
+    let mut found_lines = found.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), found_lines.next()) {
+            (Some(a), Some(b)) if a == b => line += 1,
+            (a, b) => {
+                return Outcome::Changed {
+                    line,
+                    expected: a.unwrap_or("end of snapshot").to_string(),
+                    found: b.unwrap_or("end of output").to_string(),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Unchanged => write!(f, "unchanged"),
+            Outcome::Changed {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "changed at line {}: expected `{}`, found `{}`",
+                line,
+                expected.trim(),
+                found.trim()
+            ),
+            Outcome::Missing => write!(f, "no snapshot; bless the corpus to write one"),
+            Outcome::Blessed => write!(f, "snapshot written"),
+        }
+    }
+}
+
+impl fmt::Display for CorpusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, outcome) in &self.results {
+            if *outcome != Outcome::Unchanged {
+                writeln!(f, "{}: {}", path.display(), outcome)?;
+            }
+        }
+        let failed = self.failures().count();
+        write!(
+            f,
+            "{} workout{} checked, {} failed",
+            self.results.len(),
+            if self.results.len() == 1 { "" } else { "s" },
+            failed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot() {
+        let output = snapshot("400m free\n4x100m fly @1:40");
+        assert!(output.starts_with("== ast ==\n{\n"));
+        assert!(output.contains("\n== summary ==\nTotal distance: 800m\n"));
+
+        assert_eq!(
+            snapshot("4x100m free @"),
+            "== errors ==\nset 1: expected interval after `@`, found end of input\n"
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare("a\nb\n", "a\nb\n"), Outcome::Unchanged);
+        assert_eq!(
+            compare("a\nb\n", "a\nc\n"),
+            Outcome::Changed {
+                line: 2,
+                expected: "b".to_string(),
+                found: "c".to_string()
+            }
+        );
+        assert_eq!(
+            compare("a\n", "a\nb\n").to_string(),
+            "changed at line 2: expected `end of snapshot`, found `b`"
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! Import of interval lists kept in spreadsheets
+//!
+//! Each row is one rep or a repeated rep: `distance,stroke,interval,reps`.
+//! A header row naming those columns may put them in any order; the interval
+//! and reps columns are optional. Consecutive identical rows are grouped into
+//! a single repetition. The workout is held to the parser's default limits.
+
+use crate::{
+    ast::{Distance, DistanceUnit, Interval, Modifiers, Set, Statement, Stroke, Workout},
+    json::is_word,
+    parser::{limit_error, parse_duration, ParserOptions},
+};
+
+/// Columns of a headerless file, in order
+const COLUMNS: &[&str] = &["distance", "stroke", "interval", "reps"];
+
+impl Workout {
+    /// Builds a workout from CSV rows of distance, stroke, interval and reps
+    pub fn from_csv(csv: &str) -> Result<Workout, Vec<String>> {
+        let mut lines = csv
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, fields(line)))
+            .filter(|(_, fields)| fields.iter().any(|field| !field.is_empty()))
+            .peekable();
+
+        let mut columns: Vec<String> = COLUMNS.iter().map(|c| c.to_string()).collect();
+        if let Some((_, header)) = lines.next_if(|(_, fields)| is_header(fields)) {
+            columns = header
+                .iter()
+                .map(|name| name.to_ascii_lowercase())
+                .collect();
+            for required in &COLUMNS[..2] {
+                if !columns.iter().any(|name| name == required) {
+                    return Err(vec![format!("missing `{}` column", required)]);
+                }
+            }
+        }
+        let column = |name: &str| columns.iter().position(|c| c == name);
+        let (distance, stroke, interval, reps) = (
+            column("distance"),
+            column("stroke"),
+            column("interval"),
+            column("reps"),
+        );
+
+        let mut rows: Vec<(Statement, u32)> = Vec::new();
+        let mut errors = Vec::new();
+        for (number, fields) in lines {
+            let field =
+                |index: Option<usize>| index.and_then(|i| fields.get(i)).map_or("", String::as_str);
+            match row(field(distance), field(stroke), field(interval), field(reps)) {
+                Ok((stmt, count)) => match rows.last_mut() {
+                    Some((last, total)) if *last == stmt => match total.checked_add(count) {
+                        Some(sum) => *total = sum,
+                        None => errors.push(format!("line {}: too many reps", number)),
+                    },
+                    _ => rows.push((stmt, count)),
+                },
+                Err(error) => errors.push(format!("line {}: {}", number, error)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let sets = rows
+            .into_iter()
+            .map(|(stmt, count)| match count {
+                1 => Set::Statement(Box::new(stmt)),
+                count => Set::Repetition {
+                    count,
+                    set: Box::new(Set::Statement(Box::new(stmt))),
+                    rounds: Vec::new(),
+                },
+            })
+            .collect();
+
+        let workout = Workout {
+            metadata: Default::default(),
+            sets,
+        };
+        match limit_error(&workout, &ParserOptions::default()) {
+            Some(error) => Err(vec![error.to_string()]),
+            None => Ok(workout),
+        }
+    }
+}
+
+/// Statement and rep count of a single row
+fn row(
+    distance: &str,
+    stroke: &str,
+    interval: &str,
+    reps: &str,
+) -> Result<(Statement, u32), String> {
+    let distance = match distance.strip_suffix("km") {
+        Some(km) => km.trim().parse().ok().map(|value| Distance {
+            value,
+            unit: DistanceUnit::Kilometers,
+        }),
+        None => distance
+            .trim_end_matches('m')
+            .trim()
+            .parse()
+            .ok()
+            .map(|value| Distance {
+                value,
+                unit: DistanceUnit::Meters,
+            }),
+    }
+    .filter(|distance| distance.value > 0)
+    .ok_or_else(|| format!("invalid distance `{}`", distance))?;
+
+    if !is_word(stroke) {
+        return Err(format!("invalid stroke `{}`", stroke));
+    }
+
+    let interval = match interval {
+        "" => None,
+        interval => Some(
+            parse_duration(interval.trim_start_matches('@'))
+                .map(Interval::from_seconds)
+                .ok_or_else(|| format!("invalid interval `{}`", interval))?,
+        ),
+    };
+
+    let reps = match reps {
+        "" => 1,
+        reps => reps
+            .parse()
+            .ok()
+            .filter(|reps| *reps > 0)
+            .ok_or_else(|| format!("invalid reps `{}`", reps))?,
+    };
+
+    let stmt = Statement {
+        distance,
+        stroke: Stroke {
+            name: stroke.to_ascii_lowercase(),
+            modifiers: Modifiers::new(),
+        },
+        equipment: Vec::new(),
+        targets: Vec::new(),
+        interval,
+        intensity: None,
+        alternation: None,
+        rep_strokes: Vec::new(),
+        segments: Vec::new(),
+        key: false,
+        coach_notes: Vec::new(),
+        hold: None,
+        trial: None,
+    };
+    Ok((stmt, reps))
+}
+
+/// Fields of a CSV line, trimmed and with surrounding quotes removed
+fn fields(line: &str) -> Vec<String> {
+    line.split(',')
+        .map(|field| field.trim().trim_matches('"').trim().to_string())
+        .collect()
+}
+
+fn is_header(fields: &[String]) -> bool {
+    fields
+        .iter()
+        .any(|field| COLUMNS.contains(&field.to_ascii_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_csv() {
+        let workout = Workout::from_csv(
+            "distance,stroke,interval,reps\n400,free,,1\n100,fly,1:40,1\n100,fly,1:40,1\n100m,fly,100,2\n\n50,back,45s,1\n1km,Free,,\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            workout.to_string(),
+            "400m free\n4x 100m fly @1:40\n50m back @45s\n1km free\n"
+        );
+    }
+
+    #[test]
+    fn test_columns() {
+        let workout = Workout::from_csv("Reps,Stroke,Distance\n8,back,50\n").unwrap();
+        assert_eq!(workout.to_string(), "8x 50m back\n");
+
+        let workout = Workout::from_csv("200,free,3:00,2\n").unwrap();
+        assert_eq!(workout.to_string(), "2x 200m free @3:00\n");
+
+        assert_eq!(
+            Workout::from_csv("reps,stroke\n2,free").unwrap_err(),
+            vec!["missing `distance` column"]
+        );
+        assert_eq!(
+            Workout::from_csv("0,free\n100,free style\n100,free,1:75\n100,free,,x").unwrap_err(),
+            vec![
+                "line 1: invalid distance `0`",
+                "line 2: invalid stroke `free style`",
+                "line 3: invalid interval `1:75`",
+                "line 4: invalid reps `x`",
+            ]
+        );
+        assert_eq!(
+            Workout::from_csv(
+                "100,@x,,2
+100,free2,,1"
+            )
+            .unwrap_err(),
+            vec![
+                "line 1: invalid stroke `@x`",
+                "line 2: invalid stroke `free2`"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(
+            Workout::from_csv(
+                "100,free,,4294967295
+100,free,,4294967295"
+            )
+            .unwrap_err(),
+            vec!["line 2: too many reps"]
+        );
+        assert_eq!(
+            Workout::from_csv(
+                "100,free,,5000
+100,back,,100000"
+            )
+            .unwrap_err(),
+            vec!["set 2: number of expanded reps exceeds the maximum of 100000"]
+        );
+        assert_eq!(
+            Workout::from_csv("1001km,free").unwrap_err(),
+            vec!["set 1: total distance exceeds the maximum of 1000000"]
+        );
+    }
+}
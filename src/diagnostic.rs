@@ -0,0 +1,203 @@
+//! Errors, warnings and notes about a workout, collected as they are found
+//!
+//! Parsing and linting report into a [`Diagnostics`] sink that travels with
+//! a successful result, so callers can summarize it ("parsed with 2
+//! warnings") and pick their own severity threshold.
+
+use std::{fmt, ops::Range};
+
+use crate::{check::Problem, fix::Fix, lint::Warning, parser::ParseError, path::SetPath};
+
+/// How serious a diagnostic is, from least to most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Worth knowing, but nothing to fix
+    Note,
+    /// A likely mistake in a workout that still makes sense
+    Warning,
+    /// A workout that can't be used as written
+    Error,
+}
+
+/// A message about a workout, located at a set and, while parsing, a span
+/// of the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Set the diagnostic applies to, or `None` for the whole workout
+    pub path: Option<SetPath>,
+    /// Byte range of the source it was found at, if known
+    pub span: Option<Range<usize>>,
+    pub message: String,
+    /// Edit that would resolve the diagnostic, applied by `swim fix`
+    pub fix: Option<Fix>,
+}
+
+/// Diagnostics in the order they were found
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.0.push(diagnostic);
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut Diagnostic> {
+        self.0.last_mut()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Number of diagnostics of exactly `severity`
+    pub fn count(&self, severity: Severity) -> usize {
+        self.0
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    /// Diagnostics at `severity` or worse, e.g., everything that should fail
+    /// a `--deny-warnings` run
+    pub fn at_least(&self, severity: Severity) -> impl Iterator<Item = &Diagnostic> {
+        self.0
+            .iter()
+            .filter(move |diagnostic| diagnostic.severity >= severity)
+    }
+
+    /// Counts by severity, worst first, e.g., `1 error, 3 warnings`, or
+    /// `None` when there are no diagnostics
+    pub fn summary(&self) -> Option<String> {
+        let counts: Vec<String> = [Severity::Error, Severity::Warning, Severity::Note]
+            .into_iter()
+            .map(|severity| (severity, self.count(severity)))
+            .filter(|(_, count)| *count > 0)
+            .map(|(severity, count)| {
+                let plural = if count == 1 { "" } else { "s" };
+                format!("{} {}{}", count, severity, plural)
+            })
+            .collect();
+        (!counts.is_empty()).then(|| counts.join(", "))
+    }
+}
+
+impl Extend<Diagnostic> for Diagnostics {
+    fn extend<I: IntoIterator<Item = Diagnostic>>(&mut self, diagnostics: I) {
+        self.0.extend(diagnostics);
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            path: error.path.clone(),
+            span: error.span.clone(),
+            message: error.message(),
+            fix: error.fix.clone(),
+        }
+    }
+}
+
+impl From<Warning> for Diagnostic {
+    fn from(warning: Warning) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            path: warning.path,
+            span: None,
+            message: warning.message,
+            fix: None,
+        }
+    }
+}
+
+impl From<Problem> for Diagnostic {
+    fn from(problem: Problem) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            path: problem.path,
+            span: None,
+            message: format!("{} ({})", problem.message, problem.suggestion),
+            fix: None,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        if let Some(path) = &self.path {
+            write!(f, "set {}: ", path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    #[test]
+    fn test_diagnostics() {
+        let mut parser = Parser::new(Token::lexer(
+            "title: Tuesday\ntitle: Wednesday\nintervals { threshold = 1:40, spare = 2:00 }\n4x100m free @threshold",
+        ));
+        assert!(parser.parse().is_ok());
+        let diagnostics = parser.diagnostics();
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "warning: `title` is given more than once; the first one is used",
+                "note: `@threshold` means the named interval rather than the intensity",
+                "warning: interval `spare` is never used",
+            ]
+        );
+        assert_eq!(diagnostics.summary().as_deref(), Some("2 warnings, 1 note"));
+        assert_eq!(diagnostics.at_least(Severity::Warning).count(), 2);
+        assert_eq!(diagnostics.iter().next().unwrap().span, Some(15..20));
+
+        let mut parser = Parser::new(Token::lexer("4x {\n100m free @1:30\n50m fly @"));
+        let errors = parser.parse().unwrap_err();
+        let error = Diagnostic::from(&errors[0]);
+        assert_eq!(error.severity, Severity::Error);
+        assert_eq!(
+            error.to_string(),
+            "error: set 1.2: expected interval after `@`, found end of input"
+        );
+        assert_eq!(Diagnostics::new().summary(), None);
+    }
+}
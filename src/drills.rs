@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::ast::Statement;
+
+/// Drills known out of the box, with the stroke each one trains
+const BUILTIN_DRILLS: &[(&str, &str)] = &[
+    ("catch-up", "free"),
+    ("fist", "free"),
+    ("6-1-6", "free"),
+    ("6-3-6", "free"),
+    ("zipper", "free"),
+    ("fingertip-drag", "free"),
+    ("side-kick", "free"),
+    ("tarzan", "free"),
+    ("single-arm", "free"),
+    ("head-up", "free"),
+    ("doggy-paddle", "free"),
+    ("one-arm-fly", "fly"),
+    ("3-3-3", "fly"),
+    ("body-dolphin", "fly"),
+    ("single-arm-back", "back"),
+    ("double-arm-back", "back"),
+    ("spin", "back"),
+    ("cup", "back"),
+    ("pull-buoy-breast", "breast"),
+    ("2-kick-1-pull", "breast"),
+    ("breast-with-fly-kick", "breast"),
+    ("heads-up-breast", "breast"),
+];
+
+/// Strokes of an individual medley, in medley order
+const IM_STROKES: &[&str] = &["fly", "back", "breast", "free"];
+
+/// How `choice`, `IM` and drill distance counts in a stroke distribution
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Attribution {
+    /// Drills count towards the stroke they train and IM is split evenly over
+    /// its four strokes; `choice` has no stroke to count towards and keeps its
+    /// own bucket
+    #[default]
+    Strokes,
+    /// `choice`, IM and drills all count as `choice`
+    Choice,
+    /// `choice`, IM and drills are left out
+    Exclude,
+}
+
+/// Drill names mapped to the stroke they train
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrillDictionary {
+    drills: HashMap<String, String>,
+    attribution: Attribution,
+}
+
+impl DrillDictionary {
+    /// Dictionary with no drills
+    pub fn empty() -> Self {
+        Self {
+            drills: HashMap::new(),
+            attribution: Attribution::default(),
+        }
+    }
+
+    /// Dictionary of commonly used drills
+    pub fn builtin() -> Self {
+        let mut dictionary = Self::empty();
+        for (drill, stroke) in BUILTIN_DRILLS {
+            dictionary.insert(drill, stroke);
+        }
+        dictionary
+    }
+
+    /// Adds a drill, replacing any existing entry with the same name
+    pub fn insert(&mut self, drill: &str, stroke: &str) {
+        self.drills
+            .insert(drill.to_ascii_lowercase(), stroke.to_string());
+    }
+
+    /// How `choice`, IM and drills count in stroke distributions
+    pub fn attribution(&self) -> Attribution {
+        self.attribution
+    }
+
+    pub fn set_attribution(&mut self, attribution: Attribution) {
+        self.attribution = attribution;
+    }
+
+    /// Stroke trained by `drill`, if it is known
+    pub fn stroke(&self, drill: &str) -> Option<&str> {
+        let stroke = if drill.bytes().any(|b| b.is_ascii_uppercase()) {
+            self.drills.get(&drill.to_ascii_lowercase())
+        } else {
+            self.drills.get(drill)
+        };
+        stroke.map(String::as_str)
+    }
+
+    /// Stroke a statement's distance counts towards
+    ///
+    /// A drill used as the stroke (`100m catch-up`) or named in a `drill(...)`
+    /// statement counts towards the stroke it trains.
+    pub fn attributed_stroke<'a>(&'a self, stmt: &'a Statement) -> &'a str {
+        if let Some(stroke) = self.stroke(&stmt.stroke.name) {
+            return stroke;
+        }
+        if stmt.stroke.name.eq_ignore_ascii_case("drill") {
+            if let Some(stroke) = stmt
+                .stroke
+                .modifiers
+                .iter()
+                .find_map(|modifier| self.stroke(&modifier.name))
+            {
+                return stroke;
+            }
+        }
+        &stmt.stroke.name
+    }
+
+    /// Meters of one swim of the statement counted towards each stroke under
+    /// the dictionary's attribution, splitting alternated strokes by their
+    /// switching distance
+    pub fn attribute<'a>(&'a self, stmt: &'a Statement) -> Vec<(&'a str, u32)> {
+        let mut attributed = Vec::new();
+        for (stroke, meters) in stmt.stroke_split() {
+            let is_im = stroke.eq_ignore_ascii_case("im");
+            let is_mixed = is_im
+                || stroke.eq_ignore_ascii_case("choice")
+                || stroke.eq_ignore_ascii_case("drill")
+                || self.stroke(stroke).is_some();
+
+            match self.attribution {
+                _ if !is_mixed => attributed.push((stroke, meters)),
+                Attribution::Exclude => {}
+                Attribution::Choice => attributed.push(("choice", meters)),
+                Attribution::Strokes if is_im => {
+                    let count = IM_STROKES.len() as u32;
+                    for (index, im_stroke) in (0..).zip(IM_STROKES) {
+                        let share = meters * (index + 1) / count - meters * index / count;
+                        attributed.push((im_stroke, share));
+                    }
+                }
+                Attribution::Strokes if stmt.alternation.is_none() => {
+                    attributed.push((self.attributed_stroke(stmt), meters))
+                }
+                Attribution::Strokes => {
+                    attributed.push((self.stroke(stroke).unwrap_or(stroke), meters))
+                }
+            }
+        }
+        attributed
+    }
+}
+
+impl Default for DrillDictionary {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::Set, lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn statement(source: &str) -> Statement {
+        let workout = Parser::new(Token::lexer(source)).parse().unwrap();
+        let Set::Statement(stmt) = &workout.sets[0] else {
+            panic!("expected statement");
+        };
+        *stmt.clone()
+    }
+
+    #[test]
+    fn test_attributed_stroke() {
+        let mut dictionary = DrillDictionary::builtin();
+        dictionary.insert("Sculling", "breast");
+
+        let attributed = |source| dictionary.attributed_stroke(&statement(source)).to_string();
+        assert_eq!(attributed("100m catch-up"), "free");
+        assert_eq!(attributed("100m drill(easy, 6-1-6)"), "free");
+        assert_eq!(attributed("100m drill(sculling)"), "breast");
+        assert_eq!(attributed("100m drill(unknown)"), "drill");
+        assert_eq!(attributed("100m back(spin)"), "back");
+    }
+
+    #[test]
+    fn test_attribute() {
+        let mut dictionary = DrillDictionary::builtin();
+        let source =
+            "100m IM\n100m choice\n100m catch-up\n100m drill(unknown)\n100m free/fist by 50";
+        let workout = Parser::new(Token::lexer(source)).parse().unwrap();
+        let attribute = |dictionary: &DrillDictionary| {
+            let mut meters = Vec::new();
+            for set in &workout.sets {
+                let stmt = set.statement().unwrap();
+                for (stroke, stroke_meters) in dictionary.attribute(stmt) {
+                    meters.push((stroke.to_string(), stroke_meters));
+                }
+            }
+            meters
+        };
+        let expected = |meters: &[(&str, u32)]| {
+            meters
+                .iter()
+                .map(|&(stroke, meters)| (stroke.to_string(), meters))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            attribute(&dictionary),
+            expected(&[
+                ("fly", 25),
+                ("back", 25),
+                ("breast", 25),
+                ("free", 25),
+                ("choice", 100),
+                ("free", 100),
+                ("drill", 100),
+                ("free", 50),
+                ("free", 50),
+            ])
+        );
+        dictionary.set_attribution(Attribution::Choice);
+        assert_eq!(
+            attribute(&dictionary),
+            expected(&[
+                ("choice", 100),
+                ("choice", 100),
+                ("choice", 100),
+                ("choice", 100),
+                ("free", 50),
+                ("choice", 50),
+            ])
+        );
+        dictionary.set_attribution(Attribution::Exclude);
+        assert_eq!(attribute(&dictionary), expected(&[("free", 50)]));
+    }
+}
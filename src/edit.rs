@@ -0,0 +1,234 @@
+//! Edits to a workout's source by set path, for editors built on the crate
+//!
+//! Each edit rewrites only the text of the set it touches, so comments,
+//! blank lines and the layout of the rest of the file are kept. Changing an
+//! interval replaces just the interval where it can be found as written.
+//! The edited source is parsed again and only kept if it still parses.
+
+use std::ops::Range;
+
+use logos::Logos;
+
+use crate::{
+    ast::{Interval, Set, Workout},
+    fix::{apply_fixes, Fix},
+    lexer::Token,
+    parser::{Parser, ParserOptions},
+    path::SetPath,
+};
+
+/// A workout's source along with the workout it parses to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    source: String,
+    options: ParserOptions,
+    workout: Workout,
+    set_spans: Vec<(SetPath, Range<usize>)>,
+}
+
+impl Document {
+    /// Parses `source` for editing
+    pub fn parse(source: &str, options: ParserOptions) -> Result<Self, Vec<String>> {
+        let mut parser = Parser::with_options(Token::lexer(source), options.clone());
+        let workout = parser
+            .parse()
+            .map_err(|errors| errors.iter().map(ToString::to_string).collect::<Vec<_>>())?;
+        Ok(Self {
+            source: source.to_string(),
+            options,
+            workout,
+            set_spans: parser.set_spans().to_vec(),
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn workout(&self) -> &Workout {
+        &self.workout
+    }
+
+    /// Replaces the set at `path` with `set`
+    pub fn replace_set(&mut self, path: &SetPath, set: &Set) -> Result<(), Vec<String>> {
+        let span = self.span(path)?;
+        let text = self.indented(span.start, &set.to_string());
+        self.apply(Fix::replace(span, &text))
+    }
+
+    /// Adds `set` on a line of its own after the set at `path`, at the same
+    /// indentation
+    pub fn insert_after(&mut self, path: &SetPath, set: &Set) -> Result<(), Vec<String>> {
+        let span = self.span(path)?;
+        let text = format!(
+            "\n{}{}",
+            self.indentation(span.start),
+            self.indented(span.start, &set.to_string())
+        );
+        self.apply(Fix::insert(span.end, &text))
+    }
+
+    /// Removes the set at `path`, along with its line if nothing but a
+    /// comment is left on it
+    pub fn remove(&mut self, path: &SetPath) -> Result<(), Vec<String>> {
+        let span = self.span(path)?;
+        let line_start = self.source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.source[span.end..]
+            .find('\n')
+            .map_or(self.source.len(), |i| span.end + i + 1);
+        let rest = self.source[span.end..line_end].trim();
+        let alone = self.source[line_start..span.start].trim().is_empty()
+            && (rest.is_empty() || rest.starts_with("//"));
+        let span = if alone { line_start..line_end } else { span };
+        self.apply(Fix::replace(span, ""))
+    }
+
+    /// Sends off the statement at `path`, or the statement it repeats, on
+    /// `interval`
+    pub fn set_interval(&mut self, path: &SetPath, interval: Interval) -> Result<(), Vec<String>> {
+        let mut set = self
+            .workout
+            .get(path)
+            .ok_or_else(|| vec![format!("no set {}", path)])?
+            .clone();
+        let mut inner = &mut set;
+        while let Set::Repetition { set, .. } = inner {
+            inner = set;
+        }
+        let Set::Statement(stmt) = inner else {
+            return Err(vec![format!("set {} is not a statement", path)]);
+        };
+
+        if let Some(old) = stmt.interval.replace(interval.clone()) {
+            let span = self.span(path)?;
+            let written = old.to_string();
+            let text = &self.source[span.clone()];
+            if let (Some(at), 1) = (text.find(&written), text.matches(&written).count()) {
+                let start = span.start + at;
+                return self.apply(Fix::replace(
+                    start..start + written.len(),
+                    &interval.to_string(),
+                ));
+            }
+        }
+        self.replace_set(path, &set)
+    }
+
+    /// Where the set at `path` is in the source
+    fn span(&self, path: &SetPath) -> Result<Range<usize>, Vec<String>> {
+        self.set_spans
+            .iter()
+            .find(|(set, _)| set == path)
+            .map(|(_, span)| span.clone())
+            .ok_or_else(|| vec![format!("no set {}", path)])
+    }
+
+    /// Whitespace the line holding `offset` starts with
+    fn indentation(&self, offset: usize) -> &str {
+        let line_start = self.source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line = &self.source[line_start..];
+        &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+    }
+
+    /// `text` with every line after the first indented like the line holding
+    /// `offset`, so a block's sets and closing brace line up
+    fn indented(&self, offset: usize, text: &str) -> String {
+        text.replace('\n', &format!("\n{}", self.indentation(offset)))
+    }
+
+    /// Applies `fix` if the edited source still parses
+    fn apply(&mut self, fix: Fix) -> Result<(), Vec<String>> {
+        let (source, _) = apply_fixes(&self.source, [&fix]);
+        *self = Self::parse(&source, self.options.clone())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str =
+        "title: Tuesday\n400m free // easy\n\n4x {\n    100m back @1:40\n    50m fly\n}\n200m free";
+
+    fn document() -> Document {
+        Document::parse(SOURCE, ParserOptions::default()).unwrap()
+    }
+
+    fn set(source: &str) -> Set {
+        Document::parse(source, ParserOptions::default())
+            .unwrap()
+            .workout()
+            .sets[0]
+            .clone()
+    }
+
+    fn path(path: &str) -> SetPath {
+        path.parse().unwrap()
+    }
+
+    #[test]
+    fn test_replace_and_insert() {
+        let mut document = document();
+        document
+            .replace_set(&path("2.2"), &set("2x { 25m fly\n25m free }"))
+            .unwrap();
+        assert_eq!(
+            document.source(),
+            "title: Tuesday\n400m free // easy\n\n4x {\n    100m back @1:40\n    2x {\n        25m fly\n        25m free\n    }\n}\n200m free"
+        );
+
+        let mut document = self::document();
+        document
+            .insert_after(&path("2.1"), &set("100m kick [board]"))
+            .unwrap();
+        document
+            .insert_after(&path("3"), &set("100m back"))
+            .unwrap();
+        assert_eq!(
+            document.source(),
+            "title: Tuesday\n400m free // easy\n\n4x {\n    100m back @1:40\n    100m kick [board]\n    50m fly\n}\n200m free\n100m back"
+        );
+        assert_eq!(document.workout().sets[1].children().len(), 3);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut document = document();
+        document.remove(&path("2.2")).unwrap();
+        document.remove(&path("1")).unwrap();
+        assert_eq!(
+            document.source(),
+            "title: Tuesday\n\n4x {\n    100m back @1:40\n}\n200m free"
+        );
+
+        assert_eq!(
+            document.remove(&path("1.1")).unwrap_err(),
+            vec!["set 1: block must contain at least one set at 21..22, found `}`"]
+        );
+        assert_eq!(document.remove(&path("5")).unwrap_err(), vec!["no set 5"]);
+        assert_eq!(
+            document.source(),
+            "title: Tuesday\n\n4x {\n    100m back @1:40\n}\n200m free"
+        );
+    }
+
+    #[test]
+    fn test_set_interval() {
+        let mut document = document();
+        document
+            .set_interval(&path("2.1"), Interval::Seconds(95))
+            .unwrap();
+        document
+            .set_interval(&path("3"), Interval::from_seconds(200))
+            .unwrap();
+        assert_eq!(
+            document.source(),
+            "title: Tuesday\n400m free // easy\n\n4x {\n    100m back @95s\n    50m fly\n}\n200m free @3:20"
+        );
+        assert_eq!(
+            document.set_interval(&path("2"), Interval::Seconds(60)),
+            Err(vec!["set 2 is not a statement".to_string()])
+        );
+    }
+}
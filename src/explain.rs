@@ -0,0 +1,687 @@
+//! Workouts explained line by line, for swimmers new to written sets
+//!
+//! Each line of the workout is followed by what it asks for in plain words:
+//! strokes and shorthand spelled out, repeats counted and send-offs turned
+//! into the rest they leave at the swimmer's pace, when there is a pace model
+//! to predict it.
+
+use std::fmt;
+
+use crate::{
+    analysis::{is_equipment, is_intensity_word, SetKind},
+    ast::{
+        Distance, DistanceUnit, DrylandAmount, DrylandExercise, Intensity, Interval, RacePace, Set,
+        Statement, Trial, Workout,
+    },
+    check::format_duration,
+    drills::DrillDictionary,
+    pace::PaceModel,
+    pool::Course,
+};
+
+/// Indentation of each nesting level, as the workout is written
+const INDENT: &str = "    ";
+
+/// What strokes and modifiers mean, for words beginners may not know
+///
+/// Words implying an intensity are left to the effort note, and equipment to
+/// the equipment note.
+const GLOSSARY: &[(&str, &str)] = &[
+    (
+        "im",
+        "Individual medley (IM): butterfly, backstroke, breaststroke and freestyle in that order, an equal share of the distance each",
+    ),
+    ("kick", "Kick: legs only, usually holding a kickboard"),
+    (
+        "pull",
+        "Pull: arms only, usually with a pull buoy between the legs",
+    ),
+    (
+        "drill",
+        "Drill: an exercise practising one part of a stroke",
+    ),
+    ("descend", "Descend: get faster on each rep"),
+    ("desc", "Descend: get faster on each rep"),
+    ("build", "Build: start each rep slow and speed up to the end"),
+    ("bld", "Build: start each rep slow and speed up to the end"),
+    (
+        "dps",
+        "Distance per stroke: take as few strokes per length as you can",
+    ),
+    (
+        "negative-split",
+        "Negative split: swim the second half faster than the first",
+    ),
+    (
+        "neg-split",
+        "Negative split: swim the second half faster than the first",
+    ),
+    ("hypoxic", "Hypoxic: breathe less often than usual"),
+    ("no-breath", "No breathing: hold your breath for the whole swim"),
+    ("underwater", "Underwater: stay below the surface, kicking"),
+    ("uw", "Underwater: stay below the surface, kicking"),
+    (
+        "breathe",
+        "Breathe every so many strokes, as the number says",
+    ),
+];
+
+/// A line of the workout as written, with what it means
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainedLine {
+    /// How deep in blocks the line is
+    pub depth: usize,
+    pub text: String,
+    /// What the line means, a sentence each
+    pub notes: Vec<String>,
+}
+
+/// Explains every line of the workout, working out the rest send-offs leave
+/// at the pace `model` predicts if given
+///
+/// Coach notes are left out, as in [`Workout::swimmer_view`].
+pub fn explain(
+    workout: &Workout,
+    drills: &DrillDictionary,
+    model: Option<&dyn PaceModel>,
+) -> Vec<ExplainedLine> {
+    let mut explainer = Explainer {
+        drills,
+        model,
+        course: workout.course(),
+        lines: Vec::new(),
+    };
+    for (key, value) in &workout.metadata.entries {
+        explainer.metadata(key, value);
+    }
+    for set in &workout.swimmer_view().sets {
+        explainer.set(set, 0, "", 1);
+    }
+    explainer.lines
+}
+
+struct Explainer<'a> {
+    drills: &'a DrillDictionary,
+    model: Option<&'a dyn PaceModel>,
+    course: Option<Course>,
+    lines: Vec<ExplainedLine>,
+}
+
+impl Explainer<'_> {
+    fn push(&mut self, depth: usize, text: String, notes: Vec<String>) {
+        self.lines.push(ExplainedLine { depth, text, notes });
+    }
+
+    fn metadata(&mut self, key: &str, value: &str) {
+        let notes = match key {
+            "title" => vec!["The name of the workout".to_string()],
+            "pool" | "course" => self
+                .course
+                .map(|course| {
+                    format!(
+                        "Swum in a {}m pool, so one length is {} meters",
+                        course.length(),
+                        course.length()
+                    )
+                })
+                .into_iter()
+                .collect(),
+            "start" => vec![format!("The workout starts at {}", value)],
+            _ => Vec::new(),
+        };
+        self.push(0, format!("{}: {}", key, value), notes);
+    }
+
+    /// Lines of `set` at `depth`, the first one after `prefix` (e.g., `4x `),
+    /// swum `reps` times in a row
+    fn set(&mut self, set: &Set, depth: usize, prefix: &str, reps: u32) {
+        match set {
+            Set::Repetition { count, set, rounds } => match set.as_ref() {
+                Set::Block { .. } => {
+                    let mut notes =
+                        vec![format!("Do everything inside the braces {}", times(*count))];
+                    notes.extend(
+                        rounds.iter().map(|round| {
+                            format!("On round {}: {}", round.round, round.instruction)
+                        }),
+                    );
+                    let prefix = format!("{}{}x ", prefix, count);
+                    self.block(set, depth, &prefix, notes);
+                    if !rounds.is_empty() {
+                        let rounds: Vec<String> = rounds.iter().map(ToString::to_string).collect();
+                        let close = self.lines.last_mut().expect("a block closes");
+                        close.text.push_str(&format!(" ({})", rounds.join(", ")));
+                    }
+                }
+                set => self.set(set, depth, &format!("{}{}x ", prefix, count), reps * count),
+            },
+            Set::Block { .. } => self.block(set, depth, prefix, Vec::new()),
+            Set::Statement(stmt) => {
+                let notes = self.statement(stmt, reps);
+                self.push(depth, format!("{}{}", prefix, stmt), notes);
+            }
+            Set::Dryland { exercises } => {
+                self.push(
+                    depth,
+                    format!("{}dryland {{", prefix),
+                    vec![format!(
+                        "Exercises out of the water{}",
+                        match reps {
+                            1 => String::new(),
+                            reps => format!(", all of them {}", times(reps)),
+                        }
+                    )],
+                );
+                for exercise in exercises {
+                    self.push(depth + 1, exercise.to_string(), vec![dryland(exercise)]);
+                }
+                self.push(depth, "}".to_string(), Vec::new());
+            }
+        }
+    }
+
+    fn block(&mut self, set: &Set, depth: usize, prefix: &str, mut notes: Vec<String>) {
+        let Set::Block {
+            sets,
+            intensity,
+            key,
+            label,
+            budget,
+        } = set
+        else {
+            return;
+        };
+
+        let mut open = prefix.to_string();
+        if let Some(label) = label {
+            open.push_str(label);
+            notes.insert(
+                0,
+                match SetKind::of_section(label) {
+                    Some(SetKind::Warmup) => {
+                        "Warmup: easy swimming to loosen up before the harder work".to_string()
+                    }
+                    Some(SetKind::Cooldown) => {
+                        "Cooldown: easy swimming to bring your heart rate down at the end"
+                            .to_string()
+                    }
+                    _ => format!("The {} part of the workout", label),
+                },
+            );
+            if let Some(budget) = budget {
+                open.push_str(&format!(" ({})", format_duration(*budget)));
+                notes.push(format!("Planned to take {}", format_duration(*budget)));
+            }
+            open.push_str(": ");
+        }
+        open.push('{');
+        self.push(depth, open, notes);
+
+        for set in sets {
+            self.set(set, depth + 1, "", 1);
+        }
+
+        let mut close = "}".to_string();
+        let mut notes = Vec::new();
+        if let Some(intensity) = intensity {
+            close.push_str(&format!(" @z{}", intensity.zone()));
+            notes.push(format!(
+                "Everything inside is swum {} unless its own line says otherwise",
+                effort(*intensity)
+            ));
+        }
+        if *key {
+            close.push_str(" key");
+            notes.push(KEY_NOTE.to_string());
+        }
+        self.push(depth, close, notes);
+    }
+
+    /// What a statement swum `reps` times in a row asks for
+    fn statement(&self, stmt: &Statement, reps: u32) -> Vec<String> {
+        let mut notes = vec![self.what(stmt, reps)];
+
+        if let Some(meaning) = glossary(&stmt.stroke.name) {
+            notes.push(meaning.to_string());
+        }
+        for modifier in &stmt.stroke.modifiers {
+            let name = &*modifier.name;
+            if is_equipment(name) || is_intensity_word(name) {
+                continue;
+            }
+            let range = stmt.descend_range().filter(|_| !modifier.args.is_empty());
+            if let (Some((from, to)), "descend" | "desc") = (range, name) {
+                notes.push(format!(
+                    "Descend: get faster on each rep, from rep {} to rep {}",
+                    from, to
+                ));
+            } else if let Some(meaning) = glossary(name) {
+                notes.push(meaning.to_string());
+            } else if let Some(stroke) = self.drills.stroke(name) {
+                notes.push(format!("`{}` is a {} drill", name, stroke_name(stroke)));
+            } else {
+                notes.push(format!(
+                    "`{}` is your coach's own instruction; ask if it isn't clear",
+                    modifier
+                ));
+            }
+        }
+
+        let gear: Vec<String> = stmt.gear().into_iter().collect();
+        if !gear.is_empty() {
+            notes.push(format!("Use {}", list(&gear)));
+        }
+        if let Some(intensity) = stmt.designated_intensity() {
+            notes.push(format!("Swim it {}", effort(intensity)));
+        }
+        if let Some(interval) = &stmt.interval {
+            notes.extend(self.interval(stmt, interval, reps));
+        }
+        if !stmt.targets.is_empty() {
+            let targets: Vec<String> = stmt.targets.iter().map(|&t| format_duration(t)).collect();
+            notes.push(format!("Aim for {}, one rep after another", list(&targets)));
+        }
+        if let Some(race_pace) = &stmt.hold {
+            notes.push(format!("Hold {} on every rep", race_pace_words(race_pace)));
+        }
+        match stmt.trial {
+            Some(Trial::Pending) => notes.push(
+                "Time trial: swim it once, as fast as you can, and write down your time"
+                    .to_string(),
+            ),
+            Some(Trial::Recorded(secs)) => {
+                notes.push(format!("Time trial, swum in {}", format_duration(secs)))
+            }
+            None => {}
+        }
+        if stmt.key {
+            notes.push(KEY_NOTE.to_string());
+        }
+        notes
+    }
+
+    /// The swim itself, e.g., `Swim 100 meters of backstroke, 4 times`
+    fn what(&self, stmt: &Statement, reps: u32) -> String {
+        let distance = distance_words(&stmt.distance);
+        let mut what = match &stmt.alternation {
+            Some(alternation) => {
+                let strokes: Vec<String> = alternation
+                    .strokes
+                    .iter()
+                    .map(|stroke| stroke_name(stroke).to_string())
+                    .collect();
+                format!(
+                    "Swim {}, switching between {} every {} meters",
+                    distance,
+                    list(&strokes),
+                    alternation.every
+                )
+            }
+            None if stmt.rep_strokes.len() == 2 => format!(
+                "Swim {}, {} on odd reps and {} on even reps",
+                distance,
+                stroke_name(&stmt.rep_strokes[0]),
+                stroke_name(&stmt.rep_strokes[1])
+            ),
+            None => match stmt.stroke.name.to_ascii_lowercase().as_str() {
+                "kick" => format!("Kick {}", distance),
+                "pull" => format!("Pull {}", distance),
+                "drill" => format!("Swim {} of drills", distance),
+                "choice" => format!("Swim {} of any stroke you like", distance),
+                "swim" => format!("Swim {}", distance),
+                _ => format!("Swim {} of {}", distance, stroke_name(&stmt.stroke.name)),
+            },
+        };
+        if let Some(course) = self.course {
+            let meters = stmt.distance.meters();
+            if meters.is_multiple_of(course.length()) {
+                let lengths = meters / course.length();
+                what += &format!(
+                    " ({} length{} of the pool)",
+                    lengths,
+                    if lengths == 1 { "" } else { "s" }
+                );
+            }
+        }
+        if !stmt.segments.is_empty() {
+            let parts: Vec<String> = stmt
+                .segments
+                .iter()
+                .map(|segment| {
+                    let stroke = if segment.stroke.name.eq_ignore_ascii_case("swim") {
+                        &stmt.stroke.name
+                    } else {
+                        &segment.stroke.name
+                    };
+                    format!(
+                        "{} of {}",
+                        distance_words(&segment.distance),
+                        stroke_name(stroke)
+                    )
+                })
+                .collect();
+            what += &format!(" as {}", parts.join(" then "));
+        }
+        if reps > 1 {
+            what += &format!(", {}", times(reps));
+        }
+        what
+    }
+
+    /// What the interval asks for, with the rest it leaves if there is a
+    /// pace model
+    fn interval(&self, stmt: &Statement, interval: &Interval, reps: u32) -> Vec<String> {
+        let predicted = self.model.map(|model| {
+            model.predict(stmt.distance.meters(), &stmt.stroke.name, stmt.intensity())
+        });
+        let takes =
+            |predicted: u32| format!("At your pace each one takes about {}", spoken(predicted));
+
+        let mut notes = Vec::new();
+        match interval {
+            Interval::Seconds(_) | Interval::MinutesSeconds { .. } => {
+                let send_off = interval.seconds();
+                notes.push(match reps {
+                    1 => format!(
+                        "Take {} for it, rest included, before starting the next line",
+                        spoken(send_off)
+                    ),
+                    _ => format!(
+                        "Leave every {}: start each one {} after you started the last",
+                        spoken(send_off),
+                        spoken(send_off)
+                    ),
+                });
+                if let Some(predicted) = predicted {
+                    notes.push(match send_off.checked_sub(predicted) {
+                        Some(rest) if rest > 0 => {
+                            format!("{}, leaving about {} rest", takes(predicted), spoken(rest))
+                        }
+                        _ => format!(
+                            "{}, longer than the send-off: ask for a slower one",
+                            takes(predicted)
+                        ),
+                    });
+                }
+            }
+            Interval::Sequence(intervals) => {
+                let send_offs: Vec<String> = intervals
+                    .iter()
+                    .map(|interval| spoken(interval.seconds()))
+                    .collect();
+                let mut note = format!(
+                    "Leave on {} in turn, each rep on the next send-off",
+                    send_offs.join(", then ")
+                );
+                if reps as usize > intervals.len() {
+                    note += ", starting over after the last";
+                }
+                notes.push(note);
+                if let Some(predicted) = predicted {
+                    let rests: Vec<String> = intervals
+                        .iter()
+                        .map(|interval| {
+                            let send_off = interval.seconds();
+                            match send_off.saturating_sub(predicted) {
+                                0 => format!("no rest on {}", spoken(send_off)),
+                                rest => format!("{} rest on {}", spoken(rest), spoken(send_off)),
+                            }
+                        })
+                        .collect();
+                    notes.push(format!(
+                        "{}, leaving about {}",
+                        takes(predicted),
+                        list(&rests)
+                    ));
+                }
+            }
+            Interval::Base { offset } => notes.push(format!(
+                "Leave on your base interval, your usual send-off for the distance{}",
+                match offset {
+                    0 => String::new(),
+                    offset if *offset > 0 => format!(", plus {}", spoken(offset.unsigned_abs())),
+                    offset => format!(", less {}", spoken(offset.unsigned_abs())),
+                }
+            )),
+            Interval::RacePace(race_pace) => {
+                notes.push(format!("Leave on {}", race_pace_words(race_pace)))
+            }
+            Interval::Rest(secs) => {
+                notes.push(format!("Rest {} after each one", spoken(*secs)));
+                if let Some(predicted) = predicted {
+                    notes.push(format!(
+                        "{}, so about {} with the rest",
+                        takes(predicted),
+                        spoken(predicted + secs)
+                    ));
+                }
+            }
+            Interval::HeartRate { below } => {
+                notes.push(format!(
+                    "After each one, rest until your heart rate is below {} beats a minute",
+                    below
+                ));
+                if let Some(model) = self.model {
+                    notes.push(format!(
+                        "That usually takes you about {}",
+                        spoken(model.recovery())
+                    ));
+                }
+            }
+        }
+        notes
+    }
+}
+
+const KEY_NOTE: &str = "Key set: the part of the workout that matters most";
+
+/// A dryland exercise in words, e.g., `3 sets of 20 squats`
+fn dryland(exercise: &DrylandExercise) -> String {
+    let amount = match exercise.amount {
+        DrylandAmount::Reps(reps) => format!("{} {}", reps, exercise.name),
+        DrylandAmount::Duration(secs) => format!("{} for {}", exercise.name, spoken(secs)),
+    };
+    match exercise.sets {
+        1 => format!("Do {}", amount),
+        sets => format!("Do {} sets of {}", sets, amount),
+    }
+}
+
+fn glossary(word: &str) -> Option<&'static str> {
+    GLOSSARY
+        .iter()
+        .find(|(name, _)| word.eq_ignore_ascii_case(name))
+        .map(|(_, meaning)| *meaning)
+}
+
+/// Stroke spelled out, e.g., `backstroke` for `back`
+fn stroke_name(stroke: &str) -> &str {
+    match stroke.to_ascii_lowercase().as_str() {
+        "free" | "freestyle" => "freestyle",
+        "back" | "backstroke" => "backstroke",
+        "breast" | "breaststroke" => "breaststroke",
+        "fly" | "butterfly" => "butterfly",
+        "im" => "individual medley",
+        _ => stroke,
+    }
+}
+
+/// How hard to swim, e.g., `easy (zone 1): relaxed enough to talk`
+fn effort(intensity: Intensity) -> String {
+    let feel = match intensity {
+        Intensity::Easy => "relaxed enough to talk",
+        Intensity::Moderate => "steady and comfortable",
+        Intensity::Threshold => "hard but steady, a pace you could hold for half an hour",
+        Intensity::RacePace => "the speed you would race at",
+        Intensity::Sprint => "as fast as you can",
+    };
+    format!("{} (zone {}): {}", intensity, intensity.zone(), feel)
+}
+
+fn race_pace_words(race_pace: &RacePace) -> String {
+    let mut words = format!("your {}m race pace", race_pace.event);
+    match race_pace.offset {
+        0 => {}
+        offset if offset > 0 => words += &format!(" plus {}", spoken(offset.unsigned_abs())),
+        offset => words += &format!(" less {}", spoken(offset.unsigned_abs())),
+    }
+    words
+}
+
+fn distance_words(distance: &Distance) -> String {
+    let unit = match distance.unit {
+        DistanceUnit::Meters => "meter",
+        DistanceUnit::Kilometers => "kilometer",
+    };
+    format!(
+        "{} {}{}",
+        distance.value,
+        unit,
+        if distance.value == 1 { "" } else { "s" }
+    )
+}
+
+/// A duration as said aloud: seconds below a minute, `1:40` otherwise
+fn spoken(secs: u32) -> String {
+    if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format_duration(secs)
+    }
+}
+
+fn times(count: u32) -> String {
+    match count {
+        1 => "once".to_string(),
+        2 => "twice".to_string(),
+        count => format!("{} times", count),
+    }
+}
+
+/// Items joined as in a sentence, e.g., `fins, paddles and snorkel`
+fn list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+impl fmt::Display for ExplainedLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indent = INDENT.repeat(self.depth);
+        write!(f, "{}{}", indent, self.text)?;
+        for note in &self.notes {
+            write!(f, "\n{}  → {}", indent, note)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, pace::CssModel, parser::Parser};
+    use logos::Logos;
+
+    fn explained(source: &str, model: Option<&dyn PaceModel>) -> Vec<ExplainedLine> {
+        let workout = Parser::new(Token::lexer(source)).parse().unwrap();
+        explain(&workout, &DrillDictionary::builtin(), model)
+    }
+
+    #[test]
+    fn test_statement() {
+        let lines = explained(
+            "pool: scm\n4x100m free(descend 1-4, catch-up) [paddles] @1:40 // coach: watch the kick",
+            None,
+        );
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0].notes,
+            vec!["Swum in a 25m pool, so one length is 25 meters"]
+        );
+        assert_eq!(
+            lines[1].text,
+            "4x 100m free(descend 1-4, catch-up) [paddles] @1:40"
+        );
+        assert_eq!(
+            lines[1].notes,
+            vec![
+                "Swim 100 meters of freestyle (4 lengths of the pool), 4 times",
+                "Descend: get faster on each rep, from rep 1 to rep 4",
+                "`catch-up` is a freestyle drill",
+                "Use paddles",
+                "Leave every 1:40: start each one 1:40 after you started the last",
+            ]
+        );
+
+        let lines = explained("4x50m free (25 drill/25 swim)", None);
+        assert_eq!(
+            lines[0].notes,
+            vec!["Swim 50 meters of freestyle as 25 meters of drill then 25 meters of freestyle, 4 times"]
+        );
+
+        let lines = explained("8x50m odds:fly evens:free", None);
+        assert_eq!(
+            lines[0].notes,
+            vec!["Swim 50 meters, butterfly on odd reps and freestyle on even reps, 8 times"]
+        );
+    }
+
+    #[test]
+    fn test_rests() {
+        let model = CssModel::new(100);
+        let lines = explained(
+            "6x100m free @1:50/1:45/1:40\n4x50m back(easy) r:20\n200m im @4:00",
+            Some(&model),
+        );
+        assert_eq!(
+            lines[0].notes[1..],
+            [
+                "Leave on 1:50, then 1:45, then 1:40 in turn, each rep on the next send-off, starting over after the last",
+                "At your pace each one takes about 1:45, leaving about 5s rest on 1:50, no rest on 1:45 and no rest on 1:40",
+            ]
+        );
+        assert_eq!(
+            lines[1].notes[1..],
+            [
+                "Swim it easy (zone 1): relaxed enough to talk",
+                "Rest 20s after each one",
+                "At your pace each one takes about 1:03, so about 1:23 with the rest",
+            ]
+        );
+        assert_eq!(lines[2].notes[0], "Swim 200 meters of individual medley");
+        assert!(lines[2].notes[1].starts_with("Individual medley (IM)"));
+        assert_eq!(
+            lines[2].notes[2],
+            "Take 4:00 for it, rest included, before starting the next line"
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        let lines = explained(
+            "warm-up: { 400m choice }\n3x {\n    2x 50m kick\n    dryland { 3x 20 squats }\n} @z3 key (round 3 sprint)",
+            None,
+        );
+        let text: Vec<String> = lines.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            text.join("\n"),
+            "warm-up: {\n  → Warmup: easy swimming to loosen up before the harder work\n\
+             \x20   400m choice\n\
+             \x20     → Swim 400 meters of any stroke you like\n\
+             \x20     → Swim it easy (zone 1): relaxed enough to talk\n\
+             }\n\
+             3x {\n  → Do everything inside the braces 3 times\n  → On round 3: sprint\n\
+             \x20   2x 50m kick\n\
+             \x20     → Kick 50 meters, twice\n\
+             \x20     → Kick: legs only, usually holding a kickboard\n\
+             \x20   dryland {\n\
+             \x20     → Exercises out of the water\n\
+             \x20       3x 20 squats\n\
+             \x20         → Do 3 sets of 20 squats\n\
+             \x20   }\n\
+             } @z3 key (round 3 sprint)\n  → Everything inside is swum threshold (zone 3): hard but steady, a pace you could hold for half an hour unless its own line says otherwise\n  → Key set: the part of the workout that matters most"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Workouts as numeric feature vectors, for finding similar workouts
+//!
+//! Where [`similarity`](crate::library::similarity) compares the statements
+//! swum one by one, features describe a workout's overall shape: how far it
+//! goes, with which strokes, in reps of what length and how hard. Two
+//! workouts with the same shape are close even if no statement is the same,
+//! e.g., 10x100 and 20x50 threshold freestyle.
+
+use crate::{
+    analysis::Analyse,
+    ast::{Intensity, Workout},
+};
+
+/// Meters of volume counted as much as a workout's whole stroke mix
+pub const VOLUME_SCALE: f64 = 5000.0;
+
+/// Strokes given their own share, in vector order; everything else,
+/// e.g., `choice` or unknown drills, shares a last entry
+pub const STROKES: &[&str] = &["free", "back", "breast", "fly", "kick"];
+
+/// Upper bounds of the rep length buckets in meters, in vector order, with a
+/// last bucket for longer reps
+pub const REP_LENGTHS: &[u32] = &[25, 50, 100, 200, 400];
+
+const INTENSITIES: &[Intensity] = &[
+    Intensity::Easy,
+    Intensity::Moderate,
+    Intensity::Threshold,
+    Intensity::RacePace,
+    Intensity::Sprint,
+];
+
+/// The shape of a workout
+///
+/// Mixes are shares of the total distance, so each adds up to 1 unless the
+/// workout is empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Features {
+    /// Total distance in meters
+    pub meters: u32,
+    /// Share swum with each of [`STROKES`], then with any other stroke
+    pub strokes: Vec<f64>,
+    /// Share swum in reps up to each of [`REP_LENGTHS`], then in longer reps
+    pub rep_lengths: Vec<f64>,
+    /// Share swum at each intensity from easy to sprint
+    pub intensity: Vec<f64>,
+}
+
+impl Features {
+    pub fn of(workout: &Workout) -> Self {
+        let meters = workout.total_distance();
+        let share = |part: u32| {
+            if meters == 0 {
+                0.0
+            } else {
+                f64::from(part) / f64::from(meters)
+            }
+        };
+
+        let mut strokes = vec![0; STROKES.len() + 1];
+        for (stroke, distance) in workout.stroke_distribution() {
+            let slot = STROKES
+                .iter()
+                .position(|known| stroke.eq_ignore_ascii_case(known))
+                .unwrap_or(STROKES.len());
+            strokes[slot] += distance;
+        }
+
+        let mut rep_lengths = vec![0; REP_LENGTHS.len() + 1];
+        workout.for_each_statement(&mut |stmt, reps| {
+            let length = stmt.distance.meters();
+            let slot = REP_LENGTHS
+                .iter()
+                .position(|&bound| length <= bound)
+                .unwrap_or(REP_LENGTHS.len());
+            rep_lengths[slot] += length * reps;
+        });
+
+        let intensities = workout.intensity_distribution();
+        Self {
+            meters,
+            strokes: strokes.into_iter().map(share).collect(),
+            rep_lengths: rep_lengths.into_iter().map(share).collect(),
+            intensity: INTENSITIES
+                .iter()
+                .map(|intensity| share(intensities.get(intensity).copied().unwrap_or(0)))
+                .collect(),
+        }
+    }
+
+    /// The features as one vector: volume in [`VOLUME_SCALE`]s, then the
+    /// stroke, rep length and intensity mixes
+    pub fn vector(&self) -> Vec<f64> {
+        let mut vector = vec![f64::from(self.meters) / VOLUME_SCALE];
+        vector.extend(&self.strokes);
+        vector.extend(&self.rep_lengths);
+        vector.extend(&self.intensity);
+        vector
+    }
+
+    /// Euclidean distance between the two vectors, 0 for workouts of the
+    /// same shape
+    pub fn distance(&self, other: &Features) -> f64 {
+        self.vector()
+            .iter()
+            .zip(other.vector())
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn features(source: &str) -> Features {
+        Features::of(&Parser::new(Token::lexer(source)).parse().unwrap())
+    }
+
+    #[test]
+    fn test_features() {
+        let features = features("400m free @easy\n8x50m fly @1:00 @sprint\n4x100m kick @z3");
+        assert_eq!(features.meters, 1200);
+        assert_eq!(
+            features.strokes,
+            vec![1.0 / 3.0, 0.0, 0.0, 1.0 / 3.0, 1.0 / 3.0, 0.0]
+        );
+        assert_eq!(
+            features.rep_lengths,
+            vec![0.0, 1.0 / 3.0, 1.0 / 3.0, 0.0, 1.0 / 3.0, 0.0]
+        );
+        assert_eq!(
+            features.intensity,
+            vec![1.0 / 3.0, 0.0, 1.0 / 3.0, 0.0, 1.0 / 3.0]
+        );
+        assert_eq!(features.vector().len(), 18);
+    }
+
+    #[test]
+    fn test_distance() {
+        let workout = features("10x100m free @1:30 @z3");
+        assert_eq!(
+            workout.distance(&features("1km free @threshold")),
+            2.0f64.sqrt()
+        );
+        assert_eq!(workout.distance(&features("10x100m free @1:20 @z3")), 0.0);
+        assert!(
+            workout.distance(&features("20x50m free @45s @z3"))
+                < workout.distance(&features("20x50m back @45s @z3"))
+        );
+    }
+}
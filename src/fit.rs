@@ -0,0 +1,238 @@
+//! Minimal reader for FIT activity files
+//!
+//! Only lap messages are decoded: they are all reconciliation needs, and pool
+//! swimming watches record one lap per interval. Developer fields and
+//! compressed timestamp headers are understood but ignored.
+
+use std::{collections::HashMap, path::Path};
+
+/// Global message number of lap messages
+const LAP_MESSAGE: u16 = 19;
+/// Lap field holding the elapsed time in milliseconds
+const TOTAL_ELAPSED_TIME: u8 = 7;
+/// Lap field holding the distance in centimeters
+const TOTAL_DISTANCE: u8 = 9;
+const INVALID_U32: u32 = u32::MAX;
+
+/// A lap recorded by the watch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lap {
+    pub meters: u32,
+    /// Elapsed time in seconds, rounded
+    pub seconds: u32,
+}
+
+impl Lap {
+    /// Whether the lap is a rest between swims
+    pub fn is_rest(&self) -> bool {
+        self.meters == 0
+    }
+}
+
+/// Layout of a local message type, from its definition message
+struct Definition {
+    global: u16,
+    big_endian: bool,
+    /// Field number and size of each field, in order
+    fields: Vec<(u8, usize)>,
+    developer_size: usize,
+}
+
+/// Reads the laps from a FIT file
+pub fn load_laps(path: &Path) -> Result<Vec<Lap>, String> {
+    let bytes = std::fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    read_laps(&bytes).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+/// Reads the laps from the contents of a FIT file
+pub fn read_laps(bytes: &[u8]) -> Result<Vec<Lap>, String> {
+    let header_size = *bytes.first().ok_or("empty file")? as usize;
+    if bytes.len() < header_size || header_size < 12 || &bytes[8..12] != b".FIT" {
+        return Err("not a FIT file".to_string());
+    }
+    let data_size = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+    let data = bytes
+        .get(header_size..header_size + data_size)
+        .ok_or("truncated FIT file")?;
+
+    let mut reader = Reader { data, position: 0 };
+    let mut definitions: HashMap<u8, Definition> = HashMap::new();
+    let mut laps = Vec::new();
+
+    while !reader.at_end() {
+        let header = reader.take(1)?[0];
+        if header & 0x80 != 0 {
+            // Compressed timestamp header: always a data message
+            let local = (header >> 5) & 0x03;
+            reader.data_message(local, &definitions, &mut laps)?;
+        } else if header & 0x40 != 0 {
+            let local = header & 0x0f;
+            definitions.insert(local, reader.definition(header & 0x20 != 0)?);
+        } else {
+            reader.data_message(header & 0x0f, &definitions, &mut laps)?;
+        }
+    }
+
+    Ok(laps)
+}
+
+/// Cursor over the data records of a FIT file
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn at_end(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let slice = self
+            .data
+            .get(self.position..self.position + len)
+            .ok_or("truncated FIT record")?;
+        self.position += len;
+        Ok(slice)
+    }
+
+    /// Reads the body of a definition message
+    fn definition(&mut self, has_developer_fields: bool) -> Result<Definition, String> {
+        let fixed = self.take(5)?;
+        let big_endian = fixed[1] == 1;
+        let global = if big_endian {
+            u16::from_be_bytes([fixed[2], fixed[3]])
+        } else {
+            u16::from_le_bytes([fixed[2], fixed[3]])
+        };
+        let fields = self
+            .take(fixed[4] as usize * 3)?
+            .chunks(3)
+            .map(|field| (field[0], field[1] as usize))
+            .collect();
+        let mut developer_size = 0;
+        if has_developer_fields {
+            let count = self.take(1)?[0] as usize;
+            developer_size = self
+                .take(count * 3)?
+                .chunks(3)
+                .map(|field| field[1] as usize)
+                .sum();
+        }
+
+        Ok(Definition {
+            global,
+            big_endian,
+            fields,
+            developer_size,
+        })
+    }
+
+    /// Reads the body of a data message, keeping it if it is a lap
+    fn data_message(
+        &mut self,
+        local: u8,
+        definitions: &HashMap<u8, Definition>,
+        laps: &mut Vec<Lap>,
+    ) -> Result<(), String> {
+        let definition = definitions
+            .get(&local)
+            .ok_or_else(|| format!("data message for undefined local type {}", local))?;
+
+        let mut elapsed = INVALID_U32;
+        let mut distance = INVALID_U32;
+        for &(number, size) in &definition.fields {
+            let value = self.take(size)?;
+            if definition.global != LAP_MESSAGE || size != 4 {
+                continue;
+            }
+            let value: [u8; 4] = value.try_into().unwrap();
+            let value = if definition.big_endian {
+                u32::from_be_bytes(value)
+            } else {
+                u32::from_le_bytes(value)
+            };
+            match number {
+                TOTAL_ELAPSED_TIME => elapsed = value,
+                TOTAL_DISTANCE => distance = value,
+                _ => {}
+            }
+        }
+        self.take(definition.developer_size)?;
+
+        if definition.global == LAP_MESSAGE {
+            laps.push(Lap {
+                meters: if distance == INVALID_U32 {
+                    0
+                } else {
+                    (distance + 50) / 100
+                },
+                seconds: if elapsed == INVALID_U32 {
+                    0
+                } else {
+                    (elapsed + 500) / 1000
+                },
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Encodes laps as a FIT file, for tests
+#[cfg(test)]
+pub(crate) fn encode_laps(laps: &[Lap]) -> Vec<u8> {
+    // Definition: local type 0, little endian, lap message, two uint32 fields
+    // plus a uint16 message index to check unknown fields are skipped
+    let mut data = vec![0x40, 0, 0, LAP_MESSAGE as u8, 0, 3];
+    data.extend([
+        254,
+        2,
+        0x84,
+        TOTAL_ELAPSED_TIME,
+        4,
+        0x86,
+        TOTAL_DISTANCE,
+        4,
+        0x86,
+    ]);
+    for (i, lap) in laps.iter().enumerate() {
+        data.push(0x00);
+        data.extend((i as u16).to_le_bytes());
+        data.extend((lap.seconds * 1000).to_le_bytes());
+        data.extend((lap.meters * 100).to_le_bytes());
+    }
+
+    let mut bytes = vec![12, 0x10, 0, 0];
+    bytes.extend((data.len() as u32).to_le_bytes());
+    bytes.extend(b".FIT");
+    bytes.extend(data);
+    bytes.extend([0, 0]);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_laps() {
+        let laps = vec![
+            Lap {
+                meters: 100,
+                seconds: 85,
+            },
+            Lap {
+                meters: 0,
+                seconds: 15,
+            },
+        ];
+
+        assert_eq!(read_laps(&encode_laps(&laps)).unwrap(), laps);
+        assert!(laps[1].is_rest());
+        assert!(read_laps(b"not a fit file").is_err());
+
+        let mut truncated = encode_laps(&laps);
+        truncated.truncate(30);
+        assert!(read_laps(&truncated).is_err());
+    }
+}
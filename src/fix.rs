@@ -0,0 +1,202 @@
+//! Machine-applicable fixes attached to diagnostics, applied by `swim fix`
+//!
+//! A fix is a replacement of a byte range of the source; an empty range
+//! inserts, e.g., the `m` missing from `100 free`.
+
+use std::ops::Range;
+
+use logos::Logos;
+
+use crate::{
+    lexer::Token,
+    parser::{Parser, ParserOptions},
+};
+
+/// Stroke names the parser knows the spelling of
+pub(crate) const STROKES: &[&str] = &[
+    "free",
+    "freestyle",
+    "back",
+    "backstroke",
+    "breast",
+    "breaststroke",
+    "fly",
+    "butterfly",
+    "im",
+    "kick",
+    "pull",
+    "drill",
+    "choice",
+    "swim",
+];
+
+/// Most times a source is re-parsed, since the parser stops at the first
+/// error and fixing it can reveal the next
+const MAX_PASSES: usize = 16;
+
+/// Text to put in place of a span of the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    pub span: Range<usize>,
+    pub replacement: String,
+}
+
+impl Fix {
+    pub fn insert(at: usize, text: &str) -> Self {
+        Fix {
+            span: at..at,
+            replacement: text.to_string(),
+        }
+    }
+
+    pub fn replace(span: Range<usize>, text: &str) -> Self {
+        Fix {
+            span,
+            replacement: text.to_string(),
+        }
+    }
+}
+
+/// Known stroke a misspelled stroke name was most likely meant to be, with
+/// the number of edits between them, or `None` if the name is known or not
+/// close to any stroke
+///
+/// Names of up to four letters may be one edit away, longer ones two. Two
+/// edits also turn real words into strokes, e.g., `scull` into `pull`, so
+/// only a single edit is safe to apply unasked.
+pub fn closest_stroke(name: &str) -> Option<(&'static str, usize)> {
+    let name = name.to_ascii_lowercase();
+    let allowed = if name.len() <= 4 { 1 } else { 2 };
+    let (distance, stroke) = STROKES
+        .iter()
+        .map(|stroke| (edit_distance(&name, stroke), *stroke))
+        .min_by_key(|(distance, _)| *distance)?;
+    (distance > 0 && distance <= allowed).then_some((stroke, distance))
+}
+
+/// Applies fixes to the source, skipping any that overlap one applied before
+/// it, and returns the fixed source with the number of fixes applied
+pub fn apply_fixes<'a>(source: &str, fixes: impl IntoIterator<Item = &'a Fix>) -> (String, usize) {
+    let mut fixes: Vec<&Fix> = fixes
+        .into_iter()
+        .filter(|fix| fix.span.start <= fix.span.end && fix.span.end <= source.len())
+        .collect();
+    fixes.sort_by_key(|fix| fix.span.start);
+
+    let mut fixed = String::with_capacity(source.len());
+    let mut end = 0;
+    let mut applied = 0;
+    for fix in fixes {
+        if fix.span.start < end {
+            continue;
+        }
+        fixed.push_str(&source[end..fix.span.start]);
+        fixed.push_str(&fix.replacement);
+        end = fix.span.end;
+        applied += 1;
+    }
+    fixed.push_str(&source[end..]);
+    (fixed, applied)
+}
+
+/// Source with every suggested fix applied, re-parsing after each round of
+/// fixes, along with the number of fixes applied
+pub fn fix_source(source: &str, options: &ParserOptions) -> (String, usize) {
+    let mut source = source.to_string();
+    let mut total = 0;
+    for _ in 0..MAX_PASSES {
+        let mut parser = Parser::with_options(Token::lexer(&source), options.clone());
+        let errors = parser.parse().err().unwrap_or_default();
+        let fixes = errors
+            .iter()
+            .filter_map(|error| error.fix.as_ref())
+            .chain(parser.diagnostics().iter().filter_map(|d| d.fix.as_ref()));
+        let (fixed, applied) = apply_fixes(&source, fixes);
+        if applied == 0 {
+            break;
+        }
+        source = fixed;
+        total += applied;
+    }
+    (source, total)
+}
+
+/// Number of single-character insertions, deletions and substitutions
+/// turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drills::DrillDictionary;
+
+    #[test]
+    fn test_closest_stroke() {
+        assert_eq!(closest_stroke("frestyle"), Some(("freestyle", 1)));
+        assert_eq!(closest_stroke("Bakc"), None);
+        assert_eq!(closest_stroke("bak"), Some(("back", 1)));
+        assert_eq!(closest_stroke("breastroke"), Some(("breaststroke", 2)));
+        assert_eq!(closest_stroke("scull"), Some(("pull", 2)));
+        assert_eq!(closest_stroke("free"), None);
+        assert_eq!(closest_stroke("squats"), None);
+    }
+
+    #[test]
+    fn test_apply_fixes() {
+        let fixes = [
+            Fix::insert(3, "m"),
+            Fix::replace(4..7, "free"),
+            Fix::replace(5..8, "x"),
+        ];
+        assert_eq!(
+            apply_fixes("100 fre @1:30", &fixes),
+            ("100m free @1:30".to_string(), 2)
+        );
+    }
+
+    #[test]
+    fn test_fix_source() {
+        let (fixed, applied) = fix_source(
+            "400 free\n4x {\n    50m frestyle @1:00\n    50 bak @1:00\n",
+            &ParserOptions::default(),
+        );
+        assert_eq!(
+            fixed,
+            "400m free\n4x {\n    50m freestyle @1:00\n    50m back @1:00\n}\n"
+        );
+        assert_eq!(applied, 5);
+
+        let (fixed, applied) = fix_source("4x100m free @1:30", &ParserOptions::default());
+        assert_eq!((fixed.as_str(), applied), ("4x100m free @1:30", 0));
+
+        // Two edits away, `scull` is a drill rather than a misspelled `pull`
+        let (fixed, applied) = fix_source("4x50m scull\n4x50m brest", &ParserOptions::default());
+        assert_eq!((fixed.as_str(), applied), ("4x50m scull\n4x50m breast", 1));
+
+        let mut drills = DrillDictionary::builtin();
+        drills.insert("bak", "back");
+        let options = ParserOptions {
+            drills,
+            ..ParserOptions::default()
+        };
+        let (fixed, applied) = fix_source("4x50m bak", &options);
+        assert_eq!((fixed.as_str(), applied), ("4x50m bak", 0));
+    }
+}
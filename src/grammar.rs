@@ -0,0 +1,408 @@
+//! The workout language's grammar, from which `syntax.bnf` and the Markdown
+//! reference printed by `swim grammar` are generated
+//!
+//! Every section carries an example that the tests parse, and every rule
+//! referenced must be defined, so the reference can't drift from what the
+//! parser accepts without a test failing.
+
+/// A production, e.g., `<distance> ::= <number> ("m" | "km")`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    /// Rule name without the angle brackets
+    pub name: &'static str,
+    pub definition: &'static str,
+    /// Remark printed after the rule as a comment
+    pub note: Option<&'static str>,
+}
+
+/// Related rules, under a heading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Section {
+    pub title: &'static str,
+    pub rules: &'static [Rule],
+    /// Workout using the section's rules, checked to parse with the `long`
+    /// parameter defined
+    pub example: &'static str,
+}
+
+const fn rule(name: &'static str, definition: &'static str) -> Rule {
+    Rule {
+        name,
+        definition,
+        note: None,
+    }
+}
+
+const fn noted(name: &'static str, definition: &'static str, note: &'static str) -> Rule {
+    Rule {
+        name,
+        definition,
+        note: Some(note),
+    }
+}
+
+/// The grammar, in the order it is documented
+pub const GRAMMAR: &[Section] = &[
+    Section {
+        title: "Top level structure",
+        rules: &[
+            rule("workout", r#"(<metadata> | <intervals> | <let>)* <entry>*"#),
+            rule("metadata", r#"<word> ":" <text-to-end-of-line>"#),
+            noted(
+                "intervals",
+                r#""intervals" "{" (<word> "=" <duration> ","?)* "}""#,
+                "named send-offs, resolved at parse time",
+            ),
+            rule("let", r#""let" <word> "=" <duration>"#),
+        ],
+        example: "title: Tuesday\nintervals { threshold = 1:40 }\nlet fast = 1:20\n4x100m free @threshold\n4x50m free @fast-40",
+    },
+    Section {
+        title: "Set definitions",
+        rules: &[
+            rule("entry", r#"<set> | <conditional>"#),
+            noted(
+                "conditional",
+                r#""if" <word> "{" <entry>+ "}" ("else" "{" <entry>+ "}")?"#,
+                "keeps the branch the parameter selects; a block must keep a set either way",
+            ),
+            rule(
+                "set",
+                r#"<repetition> | <statement> | <ladder> | <block> | <named-block> | <dryland>"#,
+            ),
+            rule(
+                "repetition",
+                r#"<number> "x" ((<block> | <named-block>) <rounds>? | <statement>)"#,
+            ),
+            noted(
+                "rounds",
+                r#""(" <round> ("," <round>)* ")""#,
+                "instructions for single rounds of the repeated block",
+            ),
+            noted(
+                "round",
+                r#""round" <number> <word>+"#,
+                "an intensity name or zone sets the round's intensity",
+            ),
+            rule("block", r#""{" <entry>+ "}" <intensity>? <key>?"#),
+            noted(
+                "named-block",
+                r#"<word> ("(" (<seconds> | <minutes-seconds>) ")")? ":" <block>"#,
+                "a section, e.g. warmup; the time budget is checked by the linter",
+            ),
+            rule("dryland", r#""dryland" "{" <exercise> (","? <exercise>)* "}""#),
+            rule(
+                "exercise",
+                r#"(<number> "x")? (<number> | <seconds> | <minutes-seconds>) <word>+"#,
+            ),
+        ],
+        example: "warmup: {\n    400m free\n}\nif long {\n    400m free\n} else {\n    200m free\n}\nMain (10:00): {\n    4x { 100m free\n50m back } @z3 key (round 4 sprint)\n}\ndryland {\n    3x 20 squats, 1:00 plank\n}",
+    },
+    Section {
+        title: "Statement structure",
+        rules: &[
+            rule(
+                "statement",
+                r#"<distance> <stroke> <equipment>? (<targets> | <hold> | <trial>)? <interval>? <intensity>? <key>?"#,
+            ),
+            noted(
+                "ladder",
+                r#"<number> ("-" <number>)+ ("m" | "km") <stroke> <equipment>? (<targets> | <hold> | <trial>)? <interval>? <intensity>? <key>?"#,
+                "a block of the statement at each distance in turn; `@base` scales with each",
+            ),
+            rule("distance", r#"<number> ("m" | "km")"#),
+            rule(
+                "stroke",
+                r#"(<stroke-name> ("/" <stroke-name>)* ("(" <modifier-list> ")")? <alternation>? | <rep-strokes>) <segments>?"#,
+            ),
+            rule("stroke-name", r#"<word> | <number-list>"#),
+            noted(
+                "rep-strokes",
+                r#"("odds:" <stroke-name> "evens:" | "evens:" <stroke-name> "odds:") <stroke-name> ("(" <modifier-list> ")")?"#,
+                "strokes taken by alternate reps of a repetition; modifiers apply to every rep",
+            ),
+            noted(
+                "alternation",
+                r#""by" <number> "m"?"#,
+                "required when strokes alternate",
+            ),
+            noted(
+                "segments",
+                r#""(" <segment> ("/" <segment>)* ")""#,
+                "parts of each rep in order, adding up to its distance; not with <alternation>",
+            ),
+            noted(
+                "segment",
+                r#"<number> ("m" | "km")? <stroke-name> ("(" <modifier-list> ")")?"#,
+                "`swim` is the statement's stroke",
+            ),
+            rule("modifier-list", r#"<modifier> ("," <modifier>)*"#),
+            rule("equipment", r#""[" <gear> ("," <gear>)* "]""#),
+            rule("gear", r#"<word> ("(" <word> ("," <word>)* ")")?"#),
+            noted(
+                "targets",
+                r#""targets" "(" <goal> ("," <goal>)* ")""#,
+                "one per rep of the innermost repetition",
+            ),
+            rule("goal", r#"<number> | <seconds> | <minutes-seconds>"#),
+            noted(
+                "hold",
+                r#""hold" <race-pace>"#,
+                "a target per rep at the profile's goal race pace",
+            ),
+            noted(
+                "trial",
+                r#""TT" (<placeholder> | <seconds> | <minutes-seconds>)"#,
+                "a time trial, never repeated; `swim record` fills in the placeholder",
+            ),
+            rule("placeholder", r#""_"+ ":" "_"+"#),
+        ],
+        example: "400m free/back by 50\n4x100m free(descend 1-4) [paddles, snorkel(center)] targets(1:20, 1:18, 1:16, 1:14) @1:40\n100-200-300-200-100m free @base\n8x50m odds:fly evens:free @1:00\n4x50m free (25 drill(catch-up)/25 swim) @1:00\n1km pull [buoy]\n200m free TT __:__",
+    },
+    Section {
+        title: "Interval timing",
+        rules: &[
+            noted(
+                "interval",
+                r#""@" (<send-off> ("/" <send-off>)* | <base> | <race-pace>) | <rest> | <heart-rate-rest>"#,
+                "reps take a list of send-offs in turn",
+            ),
+            noted(
+                "send-off",
+                r#"<duration> | <word> (("+" | "-") <number>)?"#,
+                "<word> names a send-off from <intervals> or <let>",
+            ),
+            noted(
+                "rest",
+                r#""r:" (<number> | <number> ":" <number>)"#,
+                "seconds of rest after each rep instead of a send-off",
+            ),
+            noted(
+                "heart-rate-rest",
+                r#""rest" "to" "HR<" <number>"#,
+                "rest after each rep until the heart rate is below the number",
+            ),
+            rule("duration", r#"<number> | <seconds> | <minutes-seconds>"#),
+            rule("base", r#""base" (("+" | "-") <number>)?"#),
+            noted(
+                "race-pace",
+                r#""rp" <number> (("+" | "-") <number>)?"#,
+                "goal event distance, e.g. rp200+1",
+            ),
+            rule("seconds", r#"<number> "s""#),
+            noted(
+                "minutes-seconds",
+                r#"<number>? ":" <number> "s"?"#,
+                "seconds below 60",
+            ),
+        ],
+        example: "4x50m free @50s\n4x100m free @1:40\n6x100m free @1:40/1:35/1:30\n8x25m fly @:30\n4x50m free r:20\n6x200m free rest to HR<130",
+    },
+    Section {
+        title: "Intensity, inherited by the statements of a block",
+        rules: &[
+            rule(
+                "intensity",
+                r#""@" (<zone> | "easy" | "moderate" | "threshold" | "race-pace" | "sprint") | "!" (<zone> | <effort>)"#,
+            ),
+            rule("zone", r#"("z" | "Z") [1-5]"#),
+            noted(
+                "effort",
+                r#"<number> "%""#,
+                "share of all-out effort from 1% to 100%, e.g. 85% is threshold",
+            ),
+        ],
+        example: "400m free @easy\n4x { 50m fly\n50m free } @z4\n8x100m free @1:30 !Z3\n4x200m free !85%",
+    },
+    Section {
+        title: "Focus of the workout, kept whole when sets are cut",
+        rules: &[rule("key", r#""key" | "primary""#)],
+        example: "400m free\n8x50m fly @1:00 key",
+    },
+    Section {
+        title: "Basic elements",
+        rules: &[
+            rule("number", r#"[0-9]+"#),
+            rule("word", r#"[a-zA-Z] [a-zA-Z._-]*"#),
+            noted(
+                "modifier",
+                r#"(<word> | <number-list>) <modifier-arg>*"#,
+                "e.g. (descend 1-3, breathe 3, kick 15m)",
+            ),
+            rule("modifier-arg", r#"<number-list> | <distance> | <number>"#),
+            rule("number-list", r#"<number> ("-" <number>)+"#),
+        ],
+        example: "4x100m free(descend 1-3, breathe 3, kick 15m)\n200m 3-5-7(breathing)",
+    },
+    Section {
+        title: "Whitespace handling",
+        rules: &[rule("whitespace", r#"[ \t\n\r]+"#)],
+        example: "400m   free\n\n\t4x100m free \\\n    @1:40",
+    },
+    Section {
+        title: "Comment syntax",
+        rules: &[
+            rule(
+                "comment",
+                r#"<shell-comment> | <c-comment> | <cpp-comment>"#,
+            ),
+            rule("shell-comment", r##""#" [^\n]*"##),
+            rule("cpp-comment", r#""//" [^\n]*"#),
+            rule("c-comment", r#""/*" ([^*] | "*" [^/])* "*/""#),
+            rule("coach-note", r##"("#" | "//") [ \t]* "coach:" [^\n]*"##),
+        ],
+        example: "# warmup\n400m free // easy\n/* main */ 4x100m free @1:40\n// coach: watch the turns\n200m back",
+    },
+];
+
+/// Rules about tokens rather than productions
+pub const LEXICAL_RULES: &[&str] = &[
+    "Whitespace and comments are ignored between tokens",
+    "Comments can appear anywhere whitespace is allowed",
+    "A <coach-note> is kept on the statement on its line, or on the next\nstatement when it stands on its own line, and left out of swimmer views",
+    "A trailing \"\\\" continues a line; it is ignored like whitespace",
+    "Case is significant in <word>",
+];
+
+/// Names used in the grammar for input no rule spells out
+const PRIMITIVES: &[&str] = &["text-to-end-of-line"];
+
+/// The grammar in Extended BNF, as in `syntax.bnf`
+pub fn ebnf() -> String {
+    let mut out = String::from("/* Swim workout syntax in Extended BNF (EBNF) notation */\n");
+    out.push_str("/* Generated by `swim grammar --format ebnf`; edit src/grammar.rs instead */\n");
+    for section in GRAMMAR {
+        out.push_str(&format!("\n/* {} */\n", section.title));
+        for rule in section.rules {
+            let head = format!("<{}>", rule.name);
+            out.push_str(&format!("{:<12} ::= {}", head, rule.definition));
+            if let Some(note) = rule.note {
+                out.push_str(&format!("  /* {} */", note));
+            }
+            out.push('\n');
+        }
+    }
+    out.push_str("\n/* Lexical rules */\n");
+    for rule in LEXICAL_RULES {
+        out.push_str(&format!("- {}\n", rule.replace('\n', "\n  ")));
+    }
+    out
+}
+
+/// The grammar as a Markdown reference, with an example for each section
+pub fn markdown() -> String {
+    let mut out = String::from("# Swim workout syntax\n");
+    for section in GRAMMAR {
+        out.push_str(&format!("\n## {}\n\n```ebnf\n", capitalize(section.title)));
+        for rule in section.rules {
+            out.push_str(&format!("<{}> ::= {}\n", rule.name, rule.definition));
+        }
+        out.push_str("```\n\n");
+        for rule in section.rules.iter().filter(|rule| rule.note.is_some()) {
+            out.push_str(&format!(
+                "- `<{}>`: {}\n",
+                rule.name,
+                rule.note.unwrap_or_default()
+            ));
+        }
+        if section.rules.iter().any(|rule| rule.note.is_some()) {
+            out.push('\n');
+        }
+        out.push_str(&format!("Example:\n\n```\n{}\n```\n", section.example));
+    }
+    out.push_str("\n## Lexical rules\n\n");
+    for rule in LEXICAL_RULES {
+        out.push_str(&format!("- {}\n", rule.replace('\n', " ")));
+    }
+    out
+}
+
+fn capitalize(title: &str) -> String {
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Names in angle brackets in a definition or note
+fn references(text: &str) -> impl Iterator<Item = &str> {
+    text.split('<')
+        .skip(1)
+        .filter_map(|part| part.split_once('>').map(|(name, _)| name))
+        .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '-'))
+}
+
+/// References to rules that aren't defined, for keeping the grammar whole
+pub fn undefined_rules() -> Vec<&'static str> {
+    let defined: Vec<&str> = GRAMMAR
+        .iter()
+        .flat_map(|section| section.rules)
+        .map(|rule| rule.name)
+        .chain(PRIMITIVES.iter().copied())
+        .collect();
+    let mut undefined: Vec<&str> = GRAMMAR
+        .iter()
+        .flat_map(|section| section.rules)
+        .flat_map(|rule| {
+            references(rule.definition).chain(rule.note.into_iter().flat_map(references))
+        })
+        .chain(LEXICAL_RULES.iter().flat_map(|rule| references(rule)))
+        .filter(|name| !defined.contains(name))
+        .collect();
+    undefined.sort();
+    undefined.dedup();
+    undefined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lexer::Token,
+        parser::{Parser, ParserOptions},
+    };
+    use logos::Logos;
+
+    #[test]
+    fn test_examples_parse() {
+        let options = ParserOptions {
+            parameters: [("long".to_string(), true)].into(),
+            ..ParserOptions::default()
+        };
+        for section in GRAMMAR {
+            let result =
+                Parser::with_options(Token::lexer(section.example), options.clone()).parse();
+            assert!(
+                result.is_ok(),
+                "example of `{}` doesn't parse: {:?}",
+                section.title,
+                result.unwrap_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_rules_defined() {
+        assert_eq!(undefined_rules(), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_syntax_bnf_up_to_date() {
+        assert_eq!(
+            ebnf(),
+            include_str!("../syntax.bnf"),
+            "syntax.bnf is stale; regenerate it with `swim grammar --format ebnf`"
+        );
+    }
+
+    #[test]
+    fn test_markdown() {
+        let reference = markdown();
+        assert!(reference.starts_with(
+            "# Swim workout syntax\n\n## Top level structure\n\n```ebnf\n<workout> ::= "
+        ));
+        assert!(reference.contains("- `<alternation>`: required when strokes alternate\n"));
+        assert!(reference.contains("\n## Lexical rules\n\n- Whitespace and comments"));
+    }
+}
@@ -0,0 +1,198 @@
+//! Rep times a swimmer achieved in past workouts, for suggesting send-offs
+//!
+//! A log directory pairs each dated workout file with the FIT file of the
+//! swim (`tuesday.swim` and `tuesday.fit`); workouts without one are skipped.
+
+use chrono::NaiveDate;
+
+use crate::{
+    fit::load_laps,
+    library::Library,
+    reconcile::{reconcile, Reconciliation},
+    steps::WorkoutSteps,
+};
+
+/// Number of most recent similar results a time is averaged over
+const RECENT: usize = 3;
+
+/// Number of most recent similar results the spread of times is taken over
+const SPREAD: usize = 10;
+
+/// Average rep times of the sets in reconciled workouts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PaceHistory {
+    results: Vec<Achieved>,
+}
+
+/// Average time of the swum reps of a set
+#[derive(Debug, Clone, PartialEq)]
+pub struct Achieved {
+    pub date: NaiveDate,
+    pub stroke: String,
+    /// Meters per rep
+    pub meters: u32,
+    /// Seconds per rep
+    pub seconds: u32,
+}
+
+impl PaceHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// History of the dated library entries with a FIT file next to them,
+    /// returning errors for FIT files that can't be read along with the
+    /// history of the rest
+    pub fn from_library(library: &Library) -> (Self, Vec<String>) {
+        let mut history = Self::new();
+        let mut errors = Vec::new();
+
+        for entry in &library.entries {
+            let fit = entry.path.with_extension("fit");
+            let Some(date) = entry.date.filter(|_| fit.exists()) else {
+                continue;
+            };
+            match load_laps(&fit) {
+                Ok(laps) => {
+                    let plan = WorkoutSteps::from_workout(&entry.workout);
+                    history.add(date, &reconcile(&plan, &laps));
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+
+        (history, errors)
+    }
+
+    /// Adds the sets swum in a reconciled workout
+    pub fn add(&mut self, date: NaiveDate, reconciliation: &Reconciliation) {
+        for set in &reconciliation.sets {
+            let Some(seconds) = set.achieved else {
+                continue;
+            };
+            let result = Achieved {
+                date,
+                stroke: set.stroke.clone(),
+                meters: set.swum_distance / set.swum_reps,
+                seconds,
+            };
+            let index = self.results.partition_point(|result| result.date <= date);
+            self.results.insert(index, result);
+        }
+    }
+
+    /// Results in date order
+    pub fn results(&self) -> &[Achieved] {
+        &self.results
+    }
+
+    /// Expected seconds for a rep of `meters` of `stroke`, averaged over the
+    /// most recent results of the stroke at between half and twice the
+    /// distance, each scaled to `meters`
+    pub fn recent_time(&self, stroke: &str, meters: u32) -> Option<u32> {
+        let similar: Vec<u64> = self
+            .similar(stroke, meters)
+            .take(RECENT)
+            .map(|result| u64::from(result.seconds) * u64::from(meters) / u64::from(result.meters))
+            .collect();
+
+        (!similar.is_empty()).then(|| {
+            let mean = similar.iter().sum::<u64>() / similar.len() as u64;
+            u32::try_from(mean).unwrap_or(u32::MAX)
+        })
+    }
+
+    /// Mean and standard deviation in seconds of a rep of `meters` of
+    /// `stroke`, over the most recent similar results scaled to `meters`,
+    /// or `None` with fewer than two to go on
+    pub fn time_spread(&self, stroke: &str, meters: u32) -> Option<(f64, f64)> {
+        let times: Vec<f64> = self
+            .similar(stroke, meters)
+            .take(SPREAD)
+            .map(|result| f64::from(result.seconds) * f64::from(meters) / f64::from(result.meters))
+            .collect();
+        if times.len() < 2 {
+            return None;
+        }
+        let mean = times.iter().sum::<f64>() / times.len() as f64;
+        let variance =
+            times.iter().map(|time| (time - mean).powi(2)).sum::<f64>() / (times.len() - 1) as f64;
+        Some((mean, variance.sqrt()))
+    }
+
+    /// Results of `stroke` at between half and twice `meters`, most recent
+    /// first
+    fn similar<'a>(&'a self, stroke: &'a str, meters: u32) -> impl Iterator<Item = &'a Achieved> {
+        self.results
+            .iter()
+            .rev()
+            .filter(move |result| result.stroke == stroke && result.meters > 0)
+            .filter(move |result| {
+                let (done, meters) = (u64::from(result.meters), u64::from(meters));
+                done * 2 >= meters && done <= meters * 2
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fit::Lap, lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn reconciled(source: &str, laps: &[(u32, u32)]) -> Reconciliation {
+        let workout = Parser::new(Token::lexer(source)).parse().unwrap();
+        let laps: Vec<Lap> = laps
+            .iter()
+            .map(|&(meters, seconds)| Lap { meters, seconds })
+            .collect();
+        reconcile(&WorkoutSteps::from_workout(&workout), &laps)
+    }
+
+    #[test]
+    fn test_recent_time() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        let mut history = PaceHistory::new();
+        history.add(
+            date(11),
+            &reconciled("4x100m free @1:40", &[(100, 80), (100, 82)]),
+        );
+        history.add(
+            date(4),
+            &reconciled(
+                "2x100m free\n200m back",
+                &[(100, 90), (100, 90), (200, 200)],
+            ),
+        );
+        history.add(date(18), &reconciled("4x50m free", &[(50, 40); 4]));
+        history.add(date(20), &reconciled("400m free", &[(400, 400)]));
+
+        assert_eq!(history.results().len(), 5);
+        assert_eq!(history.results()[0].stroke, "free");
+        assert_eq!(history.recent_time("free", 100), Some((80 + 81 + 90) / 3));
+        assert_eq!(history.time_spread("back", 200), None);
+        let (mean, spread) = history.time_spread("free", 100).unwrap();
+        assert_eq!(mean, (80.0 + 81.0 + 90.0) / 3.0);
+        assert!(spread > 5.0 && spread < 6.0);
+        assert_eq!(
+            history.recent_time("free", 200),
+            Some((200 + 162 + 180) / 3)
+        );
+        assert_eq!(history.recent_time("back", 100), Some(100));
+        assert_eq!(history.recent_time("fly", 100), None);
+        assert_eq!(history.recent_time("back", 25), None);
+    }
+
+    #[test]
+    fn test_huge_results() {
+        let history = PaceHistory {
+            results: vec![Achieved {
+                date: NaiveDate::from_ymd_opt(2024, 3, 4).unwrap(),
+                stroke: "free".to_string(),
+                meters: 3_000_000_000,
+                seconds: 4_000_000_000,
+            }],
+        };
+        assert_eq!(history.recent_time("free", 4_000_000_000), Some(u32::MAX));
+    }
+}
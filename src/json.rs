@@ -0,0 +1,761 @@
+//! Workouts as JSON, for tools that generate or transform them
+//!
+//! The JSON mirrors the AST. Reading it back checks what the parser would
+//! otherwise guarantee (positive counts, one target per rep, names that print
+//! as single words, the parser's default limits), so an imported workout
+//! formats to a file that parses to the same workout.
+//!
+//! The JSON carries the [`SCHEMA_VERSION`] it was written with, so tools
+//! reading it can tell when the AST, and with it the JSON, has changed.
+
+use serde::Serialize;
+
+use crate::{
+    ast::{Interval, Round, Set, Statement, Value, Workout},
+    diagnostic::{Diagnostic, Diagnostics, Severity},
+    parser::{limit_error, ParserOptions},
+    path::SetPath,
+};
+
+/// Version of the JSON written by [`Workout::to_json`], raised whenever a
+/// change to the AST changes the JSON
+pub const SCHEMA_VERSION: u32 = 11;
+
+#[derive(Serialize)]
+struct Versioned<'a> {
+    version: u32,
+    #[serde(flatten)]
+    workout: &'a Workout,
+}
+
+impl Workout {
+    /// The workout as pretty-printed JSON, with the schema version
+    pub fn to_json(&self) -> String {
+        let versioned = Versioned {
+            version: SCHEMA_VERSION,
+            workout: self,
+        };
+        serde_json::to_string_pretty(&versioned).expect("workouts always serialize")
+    }
+
+    /// Reads a workout from JSON, rejecting workouts the syntax can't express
+    ///
+    /// JSON without a version is taken to be from before versioning, which is
+    /// version 1. Newer versions are rejected rather than misread.
+    pub fn from_json(json: &str) -> Result<Workout, Vec<String>> {
+        let value = read_versioned(json)?;
+        let workout: Workout = serde_json::from_value(value)
+            .map_err(|error| vec![format!("invalid JSON: {}", error)])?;
+
+        let issues = workout.validate();
+        if issues.is_empty() {
+            Ok(workout)
+        } else {
+            Err(issues
+                .iter()
+                .map(|issue| match &issue.path {
+                    Some(path) => format!("set {}: {}", path, issue.message),
+                    None => issue.message.clone(),
+                })
+                .collect())
+        }
+    }
+
+    /// What keeps the workout from formatting to a file that parses back to
+    /// it, such as a workout built in code or read from JSON, each issue at
+    /// the set it is about
+    ///
+    /// A workout that is otherwise valid is also held to the parser's default
+    /// limits, e.g., on its total distance.
+    pub fn validate(&self) -> Diagnostics {
+        let mut issues = Diagnostics::new();
+        for (key, value) in &self.metadata.entries {
+            let mut metadata_issue = |message| {
+                issues.push(Diagnostic {
+                    severity: Severity::Error,
+                    path: None,
+                    span: None,
+                    message,
+                    fix: None,
+                })
+            };
+            if key.is_empty() || key.contains(|c: char| c == ':' || c.is_whitespace()) {
+                metadata_issue(format!("invalid metadata key `{}`", key));
+            }
+            if value.contains('\n') {
+                metadata_issue(format!("metadata `{}` spans several lines", key));
+            }
+        }
+        for (i, set) in self.sets.iter().enumerate() {
+            validate_set(set, SetPath::new(vec![i]), 1, &mut issues);
+        }
+        if issues.is_empty() {
+            if let Some(error) = limit_error(self, &ParserOptions::default()) {
+                issues.push(Diagnostic {
+                    severity: Severity::Error,
+                    path: error.path.clone(),
+                    span: None,
+                    message: error.message(),
+                    fix: None,
+                });
+            }
+        }
+        issues
+    }
+}
+
+/// Checks JSON written by another tool before importing it: everything
+/// [`Workout::from_json`] checks, and also that there are no fields the
+/// schema doesn't know, which would otherwise be silently dropped
+pub fn validate_json(json: &str) -> Result<Workout, Vec<String>> {
+    let workout = Workout::from_json(json)?;
+
+    let input = read_versioned(json)?;
+    let output = serde_json::to_value(&workout).expect("workouts always serialize");
+    let mut errors = Vec::new();
+    unknown_fields(&input, &output, "", &mut errors);
+    if errors.is_empty() {
+        Ok(workout)
+    } else {
+        Err(errors)
+    }
+}
+
+/// The JSON with its `version` field checked and removed
+fn read_versioned(json: &str) -> Result<serde_json::Value, Vec<String>> {
+    let mut value: serde_json::Value =
+        serde_json::from_str(json).map_err(|error| vec![format!("invalid JSON: {}", error)])?;
+    let Some(version) = value
+        .as_object_mut()
+        .and_then(|object| object.remove("version"))
+    else {
+        return Ok(value);
+    };
+    match version.as_u64() {
+        Some(version) if version > 0 && version <= SCHEMA_VERSION as u64 => Ok(value),
+        Some(version) if version > SCHEMA_VERSION as u64 => Err(vec![format!(
+            "schema version {} is newer than the version this tool reads ({})",
+            version, SCHEMA_VERSION
+        )]),
+        _ => Err(vec![format!("invalid schema version `{}`", version)]),
+    }
+}
+
+/// Reports fields of `input` missing from `output`, the same JSON after a
+/// round trip through the AST
+fn unknown_fields(
+    input: &serde_json::Value,
+    output: &serde_json::Value,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    use serde_json::Value;
+
+    match (input, output) {
+        (Value::Object(input), Value::Object(output)) => {
+            for (key, value) in input {
+                let field = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                match output.get(key) {
+                    Some(output) => unknown_fields(value, output, &field, errors),
+                    None => errors.push(format!("unknown field `{}`", field)),
+                }
+            }
+        }
+        (Value::Array(input), Value::Array(output)) => {
+            for (i, (input, output)) in input.iter().zip(output).enumerate() {
+                unknown_fields(input, output, &format!("{}[{}]", path, i), errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// An error about the set at `path`
+fn invalid(path: &SetPath, message: String) -> Diagnostic {
+    Diagnostic {
+        severity: Severity::Error,
+        path: Some(path.clone()),
+        span: None,
+        message,
+        fix: None,
+    }
+}
+
+/// Checks a set and the sets inside it, `reps` being the count of the
+/// innermost enclosing repetition
+fn validate_set(set: &Set, path: SetPath, reps: u32, issues: &mut Diagnostics) {
+    let mut messages = Vec::new();
+
+    match set {
+        Set::Repetition { count, set, rounds } => {
+            if *count == 0 {
+                issues.push(invalid(
+                    &path,
+                    "repetition count must be at least 1".to_string(),
+                ));
+            }
+            if !rounds.is_empty() && !matches!(set.as_ref(), Set::Block { .. }) {
+                issues.push(invalid(
+                    &path,
+                    "only repeated blocks have round instructions".to_string(),
+                ));
+            }
+            for (i, round) in rounds.iter().enumerate() {
+                if !(1..=*count).contains(&round.round) {
+                    issues.push(invalid(
+                        &path,
+                        format!(
+                            "round {} is not one of the {} repetitions",
+                            round.round, count
+                        ),
+                    ));
+                }
+                if rounds[..i].iter().any(|before| before.round >= round.round) {
+                    issues.push(invalid(
+                        &path,
+                        "rounds must be in order, each given once".to_string(),
+                    ));
+                }
+                let words_ok = round
+                    .instruction
+                    .split(' ')
+                    .all(|word| is_word(word) || matches!(word, "z1" | "z2" | "z3" | "z4" | "z5"));
+                if !words_ok {
+                    issues.push(invalid(
+                        &path,
+                        format!(
+                            "invalid instruction `{}` for round {}",
+                            round.instruction, round.round
+                        ),
+                    ));
+                } else if Round::new(round.round, &round.instruction) != *round {
+                    issues.push(invalid(
+                        &path,
+                        format!(
+                            "intensity of round {} doesn't match `{}`",
+                            round.round, round.instruction
+                        ),
+                    ));
+                }
+            }
+            match set.as_ref() {
+                Set::Block { sets, .. } => validate_block(sets, &path, *count, issues),
+                set => validate_set(set, path.clone(), *count, issues),
+            }
+        }
+        Set::Block {
+            sets,
+            label,
+            budget,
+            ..
+        } => {
+            if let Some(label) = label.as_ref().filter(|label| !is_word(label)) {
+                issues.push(invalid(&path, format!("invalid block name `{}`", label)));
+            }
+            if label.is_none() && budget.is_some() {
+                issues.push(invalid(
+                    &path,
+                    "only named blocks have a time budget".to_string(),
+                ));
+            }
+            validate_block(sets, &path, reps, issues)
+        }
+        Set::Statement(stmt) => messages = statement_errors(stmt, reps),
+        Set::Dryland { exercises } => {
+            if exercises.is_empty() {
+                messages.push("dryland block must contain at least one exercise".to_string());
+            }
+            for exercise in exercises {
+                if exercise.sets == 0 {
+                    messages.push(format!("`{}` must have at least one set", exercise.name));
+                }
+                if !exercise.name.split(' ').all(is_word) {
+                    messages.push(format!("invalid exercise name `{}`", exercise.name));
+                }
+            }
+        }
+    }
+
+    issues.extend(messages.into_iter().map(|message| invalid(&path, message)));
+}
+
+fn validate_block(sets: &[Set], path: &SetPath, reps: u32, issues: &mut Diagnostics) {
+    if sets.is_empty() {
+        issues.push(invalid(
+            path,
+            "block must contain at least one set".to_string(),
+        ));
+    }
+    for (i, set) in sets.iter().enumerate() {
+        validate_set(set, path.child(i), reps, issues);
+    }
+}
+
+fn statement_errors(stmt: &Statement, reps: u32) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if stmt.distance.value == 0 {
+        errors.push("distance must be positive".to_string());
+    }
+    let words = std::iter::once(stmt.stroke.name.as_str())
+        .chain(stmt.stroke.modifiers.iter().map(|modifier| &*modifier.name))
+        .chain(
+            stmt.equipment
+                .iter()
+                .map(|equipment| equipment.name.as_str()),
+        )
+        .chain(
+            stmt.equipment
+                .iter()
+                .flat_map(|equipment| equipment.parameters.iter().map(String::as_str)),
+        )
+        .chain(stmt.segments.iter().flat_map(|segment| {
+            std::iter::once(segment.stroke.name.as_str()).chain(
+                segment
+                    .stroke
+                    .modifiers
+                    .iter()
+                    .map(|modifier| &*modifier.name),
+            )
+        }));
+    for word in words {
+        if !is_word(word) && !is_number_list(word) {
+            errors.push(format!("`{}` is not a single word", word));
+        }
+    }
+    if stmt.hold.is_some() && !stmt.targets.is_empty() {
+        errors.push("statement has both `hold` and target times".to_string());
+    }
+    if stmt.trial.is_some() && (stmt.hold.is_some() || !stmt.targets.is_empty()) {
+        errors.push("time trial also has target times".to_string());
+    }
+    if stmt.trial.is_some() && reps > 1 {
+        errors.push("a time trial is a single swim and can't be repeated".to_string());
+    }
+    let race_paces = stmt.hold.iter().chain(match &stmt.interval {
+        Some(Interval::RacePace(race_pace)) => Some(race_pace),
+        _ => None,
+    });
+    for race_pace in race_paces {
+        if race_pace.event == 0 {
+            errors.push("race-pace event distance must be positive".to_string());
+        }
+    }
+    for modifier in &stmt.stroke.modifiers {
+        for arg in &modifier.args {
+            match arg {
+                Value::NumberList(numbers) if numbers.len() < 2 => errors.push(format!(
+                    "modifier `{}` has a number list with fewer than two numbers",
+                    modifier.name
+                )),
+                Value::Distance(distance) if distance.value == 0 => errors.push(format!(
+                    "modifier `{}` has a distance that isn't positive",
+                    modifier.name
+                )),
+                _ => {}
+            }
+        }
+    }
+    match stmt.interval {
+        Some(Interval::HeartRate { below: 0 }) => {
+            errors.push("heart rate to rest to must be positive".to_string())
+        }
+        Some(Interval::Rest(0)) => errors.push("rest must be positive".to_string()),
+        Some(Interval::Sequence(ref intervals)) => {
+            if intervals.len() < 2 {
+                errors.push("interval list has fewer than two send-offs".to_string());
+            }
+            if intervals.iter().any(|interval| {
+                !matches!(
+                    interval,
+                    Interval::Seconds(_) | Interval::MinutesSeconds { .. }
+                )
+            }) {
+                errors.push("interval list has something other than a send-off time".to_string());
+            }
+        }
+        _ => {}
+    }
+    let send_offs = match &stmt.interval {
+        Some(Interval::Sequence(intervals)) => intervals.as_slice(),
+        interval => interval.as_slice(),
+    };
+    for interval in send_offs {
+        if let Interval::MinutesSeconds { minutes, seconds } = interval {
+            if *seconds >= 60 {
+                errors.push(format!("interval has {} seconds past the minute", seconds));
+            } else if minutes
+                .checked_mul(60)
+                .and_then(|secs| secs.checked_add(*seconds))
+                .is_none()
+            {
+                errors.push(format!("interval of {} minutes is too long", minutes));
+            }
+        }
+    }
+    if !stmt.targets.is_empty() && stmt.targets.len() != reps as usize {
+        errors.push(format!(
+            "expected one target time per rep ({}) but got {}",
+            reps,
+            stmt.targets.len()
+        ));
+    }
+    if let Some(alternation) = &stmt.alternation {
+        if alternation.every == 0 {
+            errors.push("alternation distance must be positive".to_string());
+        }
+        if alternation.strokes.len() < 2 || alternation.strokes[0] != stmt.stroke.name {
+            errors.push(
+                "alternation must list at least two strokes, starting with the statement's"
+                    .to_string(),
+            );
+        }
+    }
+    if !stmt.rep_strokes.is_empty() {
+        if stmt.rep_strokes.len() != 2 || stmt.rep_strokes[0] != stmt.stroke.name {
+            errors.push(
+                "rep strokes must be an odd and an even stroke, starting with the statement's"
+                    .to_string(),
+            );
+        }
+        if stmt.alternation.is_some() {
+            errors.push("statement has both alternating and rep strokes".to_string());
+        }
+        if reps < 2 {
+            errors.push("odd and even reps need a repeated statement".to_string());
+        }
+    }
+    if !stmt.segments.is_empty() {
+        let total: u64 = stmt
+            .segments
+            .iter()
+            .map(|segment| u64::from(segment.distance.meters()))
+            .sum();
+        if stmt
+            .segments
+            .iter()
+            .any(|segment| segment.distance.value == 0)
+        {
+            errors.push("distance of a part must be positive".to_string());
+        } else if total != u64::from(stmt.distance.meters()) {
+            errors.push(format!(
+                "parts add up to {}m, not the {}m of the statement",
+                total,
+                stmt.distance.meters()
+            ));
+        }
+        if stmt.alternation.is_some() {
+            errors.push("statement both alternates strokes and is swum in parts".to_string());
+        }
+    }
+    if stmt.coach_notes.iter().any(|note| note.contains('\n')) {
+        errors.push("coach notes must fit on one line".to_string());
+    }
+
+    errors
+}
+
+/// Whether `text` lexes as a single word
+pub(crate) fn is_word(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphabetic() || matches!(c, '.' | '_' | '-'))
+}
+
+/// Whether `text` lexes as a number list, e.g., `6-1-6`
+fn is_number_list(text: &str) -> bool {
+    let mut parts = text.split('-');
+    parts.clone().count() > 1
+        && parts.all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    #[test]
+    fn test_round_trip() {
+        let source = "title: Tuesday\n400m free(easy, kick 25m) [fins]\n4x {\n    100m fly targets(1:20, 1:18, 1:16, 1:14) @1:40 @z4\n    50m drill(6-1-6, breathe 3) @base+5\n}\nMain (12:00): {\n    300m free/back by 50 // coach: long strokes\n}\ndryland {\n    3x 20 squats\n    2:00 plank\n}\n";
+        let workout = Parser::new(Token::lexer(source)).parse().unwrap();
+
+        let imported = Workout::from_json(&workout.to_json()).unwrap();
+        assert_eq!(imported, workout);
+        assert_eq!(imported.to_string(), source);
+    }
+
+    #[test]
+    fn test_minimal_json() {
+        let workout = Workout::from_json(
+            r#"{"sets": [{"repetition": {"count": 4, "set": {"statement": {
+                "distance": {"value": 100, "unit": "meters"},
+                "stroke": {"name": "free", "modifiers": [
+                    "easy", {"name": "descend", "args": [{"number_list": [1, 4]}]}
+                ]},
+                "interval": {"minutes_seconds": {"minutes": 1, "seconds": 30}},
+                "intensity": "race-pace"
+            }}}}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            workout.to_string(),
+            "4x 100m free(easy, descend 1-4) @1:30 @z4\n"
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let errors = Workout::from_json(
+            r#"{"metadata": {"entries": [["swim mer", "Sam"]]}, "sets": [
+                {"repetition": {"count": 0, "set": {"block": {"sets": [], "intensity": null}}}},
+                {"repetition": {"count": 2, "set": {"statement": {
+                    "distance": {"value": 0, "unit": "meters"},
+                    "stroke": {"name": "free style", "modifiers": [
+                        {"name": "descend", "args": [{"number_list": [3]}]}
+                    ]},
+                    "targets": [70],
+                    "interval": {"minutes_seconds": {"minutes": 1, "seconds": 75}}
+                }}}}
+            ]}"#,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                "invalid metadata key `swim mer`",
+                "set 1: repetition count must be at least 1",
+                "set 1: block must contain at least one set",
+                "set 2: distance must be positive",
+                "set 2: `free style` is not a single word",
+                "set 2: modifier `descend` has a number list with fewer than two numbers",
+                "set 2: interval has 75 seconds past the minute",
+                "set 2: expected one target time per rep (2) but got 1",
+            ]
+        );
+        assert!(Workout::from_json("{\"sets\": 3}").unwrap_err()[0].starts_with("invalid JSON"));
+
+        let mut workout = Workout::from_json(
+            r#"{"sets": [{"block": {"sets": [
+            {"repetition": {"count": 4, "set": {"statement": {
+                "distance": {"value": 100, "unit": "meters"},
+                "stroke": {"name": "free"}
+            }}}}
+        ]}}]}"#,
+        )
+        .unwrap();
+        assert!(workout.validate().is_empty());
+        let path = "1.1".parse().unwrap();
+        let Some(Set::Repetition { count, .. }) = workout.get_mut(&path) else {
+            panic!("expected a repetition at 1.1");
+        };
+        *count = 0;
+        let issues = workout.validate();
+        let issue = issues.iter().next().unwrap();
+        assert_eq!(issue.path, Some(path));
+        assert_eq!(issue.message, "repetition count must be at least 1");
+    }
+
+    #[test]
+    fn test_limits() {
+        let statement = |count: u32, interval: &str| {
+            format!(
+                r#"{{"sets": [{{"repetition": {{"count": {}, "set": {{"statement": {{
+                    "distance": {{"value": 100, "unit": "meters"}},
+                    "stroke": {{"name": "free"}},
+                    "interval": {}
+                }}}}}}}}]}}"#,
+                count, interval
+            )
+        };
+        assert!(Workout::from_json(&statement(
+            4,
+            r#"{"minutes_seconds": {"minutes": 1, "seconds": 30}}"#
+        ))
+        .is_ok());
+        assert_eq!(
+            Workout::from_json(&statement(
+                4,
+                r#"{"minutes_seconds": {"minutes": 4294967295, "seconds": 0}}"#
+            )),
+            Err(vec![
+                "set 1: interval of 4294967295 minutes is too long".to_string()
+            ])
+        );
+        assert_eq!(
+            Workout::from_json(&statement(4294967295, "null")),
+            Err(vec![
+                "set 1: number of expanded reps exceeds the maximum of 100000".to_string()
+            ])
+        );
+        assert_eq!(
+            Workout::from_json(&statement(
+                100,
+                r#"{"minutes_seconds": {"minutes": 100000, "seconds": 0}}"#
+            )),
+            Err(vec![
+                "set 1: total time exceeds the maximum of 10000000".to_string()
+            ])
+        );
+
+        let mut nested = r#"{"statement": {"distance": {"value": 100, "unit": "meters"}, "stroke": {"name": "free"}}}"#.to_string();
+        for _ in 0..40 {
+            nested = format!(r#"{{"repetition": {{"count": 1, "set": {}}}}}"#, nested);
+        }
+        assert_eq!(
+            Workout::from_json(&format!(r#"{{"sets": [{}]}}"#, nested)),
+            Err(vec![
+                "set 1: nesting depth exceeds the maximum of 32".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_interval_lists() {
+        let workout = Parser::new(Token::lexer("6x100m free @1:40/1:35/90s"))
+            .parse()
+            .unwrap();
+        assert_eq!(Workout::from_json(&workout.to_json()), Ok(workout));
+
+        let errors = Workout::from_json(
+            r#"{"sets": [{"statement": {
+                "distance": {"value": 100, "unit": "meters"},
+                "stroke": {"name": "free"},
+                "interval": {"sequence": [{"base": {"offset": 5}}]}
+            }}]}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "set 1: interval list has fewer than two send-offs",
+                "set 1: interval list has something other than a send-off time",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rep_strokes() {
+        let workout = Parser::new(Token::lexer("8x50m odds:free evens:back @1:00"))
+            .parse()
+            .unwrap();
+        assert_eq!(Workout::from_json(&workout.to_json()), Ok(workout));
+
+        let errors = Workout::from_json(
+            r#"{"sets": [{"statement": {
+                "distance": {"value": 50, "unit": "meters"},
+                "stroke": {"name": "free"},
+                "rep_strokes": ["back", "free"]
+            }}]}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "set 1: rep strokes must be an odd and an even stroke, starting with the statement's",
+                "set 1: odd and even reps need a repeated statement",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_segments() {
+        let workout = Parser::new(Token::lexer("4x50m free (25 drill/25 swim) @1:00"))
+            .parse()
+            .unwrap();
+        assert_eq!(Workout::from_json(&workout.to_json()), Ok(workout));
+
+        let errors = Workout::from_json(
+            r#"{"sets": [{"statement": {
+                "distance": {"value": 50, "unit": "meters"},
+                "stroke": {"name": "free"},
+                "segments": [
+                    {"distance": {"value": 25, "unit": "meters"}, "stroke": {"name": "drill"}},
+                    {"distance": {"value": 20, "unit": "meters"}, "stroke": {"name": "swim"}}
+                ]
+            }}]}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            vec!["set 1: parts add up to 45m, not the 50m of the statement"]
+        );
+    }
+
+    #[test]
+    fn test_rounds() {
+        let workout = Parser::new(Token::lexer(
+            "3x {\n    100m free @1:40\n} (round 2 faster, round 3 race pace)",
+        ))
+        .parse()
+        .unwrap();
+        assert_eq!(Workout::from_json(&workout.to_json()), Ok(workout));
+
+        let errors = Workout::from_json(
+            r#"{"sets": [{"repetition": {"count": 2, "set": {"block": {
+                "sets": [{"statement": {
+                    "distance": {"value": 100, "unit": "meters"},
+                    "stroke": {"name": "free", "modifiers": []},
+                    "interval": null, "intensity": null
+                }}],
+                "intensity": null
+            }}, "rounds": [
+                {"round": 2, "instruction": "sprint", "intensity": "easy"},
+                {"round": 3, "instruction": "all out!", "intensity": null}
+            ]}}]}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "set 1: intensity of round 2 doesn't match `sprint`",
+                "set 1: round 3 is not one of the 2 repetitions",
+                "set 1: invalid instruction `all out!` for round 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_versions() {
+        let workout = Parser::new(Token::lexer("4x100m free @1:30"))
+            .parse()
+            .unwrap();
+        let json = workout.to_json();
+        assert!(json.starts_with("{\n  \"version\": 11,\n"));
+        assert_eq!(validate_json(&json), Ok(workout));
+
+        assert_eq!(
+            Workout::from_json(r#"{"version": 12, "sets": []}"#),
+            Err(vec![
+                "schema version 12 is newer than the version this tool reads (11)".to_string()
+            ])
+        );
+        assert_eq!(
+            Workout::from_json(r#"{"version": "one", "sets": []}"#),
+            Err(vec!["invalid schema version `\"one\"`".to_string()])
+        );
+
+        let errors = validate_json(
+            r#"{"version": 1, "title": "Tuesday", "sets": [{"statement": {
+                "distance": {"value": 100, "unit": "meters"},
+                "stroke": {"name": "free", "modifiers": ["easy"]},
+                "interval": {"seconds": 90},
+                "rest": 10
+            }}]}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "unknown field `sets[0].statement.rest`",
+                "unknown field `title`",
+            ]
+        );
+    }
+}
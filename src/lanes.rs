@@ -0,0 +1,278 @@
+//! Lane versions of one workout, scaled to each lane's ability
+//!
+//! ```toml
+//! [lanes.a]
+//!
+//! [lanes.b]
+//! interval = 1.1
+//!
+//! [lanes.c]
+//! distance = 0.75
+//! ```
+//!
+//! Lane `b` swims the same reps on send-offs 10% longer; lane `c` swims reps
+//! a quarter shorter on the same send-offs.
+
+use std::fmt;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    ast::{Interval, Set, Statement, Workout},
+    pool::{snap_statement, Course},
+    resolve::Rounding,
+};
+
+/// How a lane's version differs from the base workout, from a
+/// `[lanes.<name>]` table
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LaneScale {
+    /// Factor applied to each rep distance
+    #[serde(deserialize_with = "factor")]
+    pub distance: f64,
+    /// Factor applied to send-offs, target times and block budgets
+    #[serde(deserialize_with = "factor")]
+    pub interval: f64,
+}
+
+impl Default for LaneScale {
+    fn default() -> Self {
+        LaneScale {
+            distance: 1.0,
+            interval: 1.0,
+        }
+    }
+}
+
+/// Lane versions of a workout, printed side by side as a Markdown table with
+/// a row per top-level set
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaneBoard {
+    pub lanes: Vec<(String, Workout)>,
+}
+
+impl Workout {
+    /// Version of the workout for a lane, with rep distances rounded to whole
+    /// lengths of `course` and send-offs by `rounding`
+    ///
+    /// Send-offs and target times keep pace with the distance, so a rep a
+    /// quarter shorter is also due a quarter sooner before the lane's
+    /// interval factor is applied.
+    pub fn for_lane(&self, scale: &LaneScale, course: Course, rounding: Rounding) -> Workout {
+        let mut workout = self.clone();
+        for set in &mut workout.sets {
+            scale_set(set, scale, course, rounding);
+        }
+        workout
+    }
+}
+
+impl LaneBoard {
+    /// Versions of the workout for each of `lanes`, in order, without coach
+    /// notes, as the board is for the whole squad
+    pub fn new<'a>(
+        workout: &Workout,
+        lanes: impl IntoIterator<Item = (&'a String, &'a LaneScale)>,
+        course: Course,
+        rounding: Rounding,
+    ) -> Self {
+        let workout = workout.swimmer_view();
+        LaneBoard {
+            lanes: lanes
+                .into_iter()
+                .map(|(name, scale)| (name.clone(), workout.for_lane(scale, course, rounding)))
+                .collect(),
+        }
+    }
+}
+
+fn scale_set(set: &mut Set, scale: &LaneScale, course: Course, rounding: Rounding) {
+    match set {
+        Set::Repetition { set, .. } => scale_set(set, scale, course, rounding),
+        Set::Block { sets, budget, .. } => {
+            if let Some(budget) = budget {
+                *budget = scale_time(*budget, scale.interval);
+            }
+            for set in sets {
+                scale_set(set, scale, course, rounding);
+            }
+        }
+        Set::Statement(stmt) => scale_statement(stmt, scale, course, rounding),
+        Set::Dryland { .. } => {}
+    }
+}
+
+fn scale_statement(stmt: &mut Statement, scale: &LaneScale, course: Course, rounding: Rounding) {
+    let from = stmt.distance.meters();
+    let to = snap_statement(
+        stmt,
+        course.snap((from as f64 * scale.distance).round() as u32),
+        course,
+    );
+
+    let factor = to as f64 / from.max(1) as f64 * scale.interval;
+    if let Some(interval) = &mut stmt.interval {
+        scale_interval(interval, factor, rounding);
+    }
+    for target in &mut stmt.targets {
+        *target = scale_time(*target, factor);
+    }
+}
+
+/// Scales a send-off, or each send-off of a list, leaving intervals with no
+/// concrete time alone
+fn scale_interval(interval: &mut Interval, factor: f64, rounding: Rounding) {
+    match interval {
+        Interval::Seconds(_) | Interval::MinutesSeconds { .. } => {
+            let secs = scale_time(interval.seconds(), factor).max(1);
+            *interval = Interval::from_seconds(rounding.apply(secs));
+        }
+        Interval::Sequence(intervals) => {
+            for interval in intervals {
+                scale_interval(interval, factor, rounding);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn scale_time(secs: u32, factor: f64) -> u32 {
+    (secs as f64 * factor).round() as u32
+}
+
+/// A positive, finite scale factor
+fn factor<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let factor = f64::deserialize(deserializer)?;
+    if factor.is_finite() && factor > 0.0 {
+        Ok(factor)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "invalid scale `{}`; expected a positive factor, e.g., `1.1`",
+            factor
+        )))
+    }
+}
+
+impl fmt::Display for LaneBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.lanes.iter().map(|(name, _)| name.as_str()).collect();
+        writeln!(f, "| Lane {} |", names.join(" | Lane "))?;
+        writeln!(f, "|{}", "---|".repeat(names.len()))?;
+
+        let rows = self
+            .lanes
+            .iter()
+            .map(|(_, workout)| workout.sets.len())
+            .max()
+            .unwrap_or(0);
+        for row in 0..rows {
+            let cells: Vec<String> = self
+                .lanes
+                .iter()
+                .map(|(_, workout)| match workout.sets.get(row) {
+                    Some(set) => set
+                        .to_string()
+                        .lines()
+                        .map(|line| line.trim().replace('|', "\\|"))
+                        .collect::<Vec<_>>()
+                        .join("<br>"),
+                    None => String::new(),
+                })
+                .collect();
+            writeln!(f, "| {} |", cells.join(" | "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    #[test]
+    fn test_for_lane() {
+        let workout = Parser::new(Token::lexer(
+            "400m free(easy)\nMain (12:00): {\n    4x 100m free targets(1:20, 1:18, 1:16, 1:14) @1:40\n    8x 50m fly @55s\n}",
+        ))
+        .parse()
+        .unwrap();
+
+        let shorter = LaneScale {
+            distance: 0.75,
+            ..LaneScale::default()
+        };
+        assert_eq!(
+            workout.for_lane(&shorter, Course::Short, Rounding::Nearest(5)).to_string(),
+            "300m free(easy)\nMain (12:00): {\n    4x 75m free targets(1:00, 59s, 57s, 56s) @1:15\n    8x 50m fly @55s\n}\n"
+        );
+        let drills = Parser::new(Token::lexer("4x100m free (25 drill/75 swim)"))
+            .parse()
+            .unwrap();
+        assert_eq!(
+            drills
+                .for_lane(&shorter, Course::Short, Rounding::Nearest(5))
+                .to_string(),
+            "4x 75m free (25 drill/50 swim)\n"
+        );
+
+        let slower = LaneScale {
+            interval: 1.1,
+            ..LaneScale::default()
+        };
+        assert_eq!(
+            workout.for_lane(&slower, Course::Short, Rounding::Nearest(5)).to_string(),
+            "400m free(easy)\nMain (13:12): {\n    4x 100m free targets(1:28, 1:26, 1:24, 1:21) @1:50\n    8x 50m fly @1:00\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_lane_board() {
+        let workout = Parser::new(Token::lexer(
+            "400m free(easy) // coach: watch his turns\n4x {\n100m free @1:40\n}",
+        ))
+        .parse()
+        .unwrap();
+        let lanes = [
+            ("a".to_string(), LaneScale::default()),
+            (
+                "b".to_string(),
+                LaneScale {
+                    distance: 0.5,
+                    interval: 1.0,
+                },
+            ),
+        ];
+        let board = LaneBoard::new(
+            &workout,
+            lanes.iter().map(|(name, scale)| (name, scale)),
+            Course::Short,
+            Rounding::Nearest(5),
+        );
+
+        assert_eq!(
+            board.to_string(),
+            "| Lane a | Lane b |\n|---|---|\n| 400m free(easy) | 200m free(easy) |\n| 4x {<br>100m free @1:40<br>} | 4x {<br>50m free @50s<br>} |\n"
+        );
+    }
+
+    #[test]
+    fn test_scale_factors() {
+        for factor in ["0.0", "-1.0", "nan", "inf"] {
+            let error = toml::from_str::<LaneScale>(&format!("interval = {}", factor)).unwrap_err();
+            assert!(error.to_string().contains("invalid scale"));
+        }
+
+        let workout = Parser::new(Token::lexer("100m free targets(1:20) @1:40"))
+            .parse()
+            .unwrap();
+        let huge = LaneScale {
+            distance: 1e30,
+            interval: 1e30,
+        };
+        let lane = workout.for_lane(&huge, Course::Short, Rounding::Up(5));
+        assert_eq!(lane.sets.len(), 1);
+    }
+}
@@ -1,11 +1,27 @@
+use std::ops::Range;
+
 use logos::Logos;
 
-#[derive(Debug, Logos, PartialEq)]
+/// Why input isn't a token
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LexError {
+    /// Input that starts no token
+    #[default]
+    Invalid,
+    /// A number, or a time in seconds, above [`u32::MAX`]
+    NumberTooLarge,
+}
+
+#[derive(Debug, Clone, Copy, Logos, PartialEq)]
 #[logos(skip r"[ \t\n\r]+")]
+#[logos(error = LexError)]
 pub enum Token<'source> {
-    #[regex(r"[0-9]+", |lex| lex.slice().parse::<u32>().ok(), priority = 3)]
+    #[regex(r"[0-9]+", |lex| number(lex.slice()), priority = 3)]
     Number(u32),
 
+    #[regex(r"[0-9]+(-[0-9]+)+", |lex| lex.slice())]
+    NumberList(&'source str),
+
     #[token("x", priority = 4)]
     Times,
 
@@ -21,7 +37,7 @@ pub enum Token<'source> {
     #[token("km", priority = 4)]
     Kilometers,
 
-    #[regex(r"[a-zA-Z][a-zA-Z.-]*", |lex| lex.slice(), priority = 2)]
+    #[regex(r"[a-zA-Z][a-zA-Z._-]*", |lex| lex.slice(), priority = 2)]
     Word(&'source str),
 
     #[token("(")]
@@ -30,17 +46,77 @@ pub enum Token<'source> {
     #[token(")")]
     ParenClose,
 
+    #[token("[")]
+    BracketOpen,
+
+    #[token("]")]
+    BracketClose,
+
     #[token(",")]
     Comma,
 
     #[token("@")]
     At,
 
-    #[regex(r"[0-9]+s", |lex| lex.slice().trim_end_matches('s').parse::<u32>().ok())]
+    #[token("!")]
+    Bang,
+
+    #[token(":")]
+    Colon,
+
+    #[token("=")]
+    Equals,
+
+    #[token("/")]
+    Slash,
+
+    #[regex(r"base([+-][0-9]+)?", |lex| lex.slice()[4..].parse::<i32>().ok().or(Some(0)), priority = 3)]
+    Base(i32),
+
+    /// A named send-off with an offset in seconds, e.g., `sprint+5` or
+    /// `fast-10`
+    #[regex(r"[a-zA-Z][a-zA-Z_]*[+-][0-9]+", named_offset, priority = 2)]
+    NamedOffset((&'source str, i32)),
+
+    /// Race pace of a goal event with an optional offset, e.g., `rp200+1`
+    #[regex(r"rp[0-9]+([+-][0-9]+)?", race_pace, priority = 3)]
+    RacePace((u32, i32)),
+
+    #[regex(r"[zZ][1-5]", |lex| lex.slice()[1..].parse::<u8>().ok(), priority = 3)]
+    Zone(u8),
+
+    /// Share of all-out effort, e.g., `85%`
+    #[regex(r"[0-9]+%", |lex| number(lex.slice().trim_end_matches('%')))]
+    Effort(u32),
+
+    /// Seconds, e.g., `90s`
+    #[regex(r"[0-9]+s", |lex| number(lex.slice().trim_end_matches('s')))]
     Seconds(u32),
 
-    #[regex(r"[0-9]+:[0-9]+s?", |lex| lex.slice())]
-    Time(&'source str),
+    /// Minutes and seconds as written, e.g., `1:30` or `1:30s`; the parser
+    /// checks the seconds are below 60, and the lexer that the time fits in
+    /// seconds
+    #[regex(r"[0-9]+:[0-9]+s?", time)]
+    Time((u32, u32)),
+
+    /// Rest between reps, e.g., `r:20` or `r:1:30`, in seconds
+    #[regex(r"r:[0-9]+(:[0-9]+)?", rest)]
+    Rest(u32),
+
+    /// Heart rate to rest down to, in beats per minute, e.g., `HR<120`
+    #[regex(r"HR<[0-9]+", |lex| number(&lex.slice()[3..]))]
+    HeartRate(u32),
+
+    /// A `__:__` placeholder for a result still to be recorded
+    #[regex(r"_+:_+")]
+    Placeholder,
+
+    #[regex(r"\\[ \t]*\r?\n", logos::skip)]
+    LineContinuation,
+
+    /// A `// coach: ...` or `# coach: ...` comment, kept for the coach's view
+    #[regex(r"(//|#)[ \t]*coach:[^\n]*", |lex| lex.slice().split_once("coach:").unwrap().1.trim(), priority = 10)]
+    CoachNote(&'source str),
 
     #[regex(r"#[^\n]*", logos::skip)]
     #[regex(r"//[^\n]*", logos::skip)]
@@ -48,6 +124,122 @@ pub enum Token<'source> {
     Comment,
 }
 
+impl<'source> Token<'source> {
+    /// The token as read after `previous`, the token before it
+    ///
+    /// The lexer alone can't tell a unit or keyword from a word spelled the
+    /// same, so units and repeats (`m`, `km`, `x`) only count straight after
+    /// a number (or, for units, a ladder's `100-200-100`), zones, bases,
+    /// offsets from named send-offs and race paces (`z3`, `base`, `fast+5`,
+    /// `rp200`) only after `@` (or, for zones, `!`, and for race paces,
+    /// `hold`), and heart rates (`HR<120`) only after `to`. Anywhere else
+    /// they are words, so a drill named `x` or a modifier named `base` parses
+    /// as written.
+    pub fn in_context(self, previous: Option<&Token<'source>>, slice: &'source str) -> Self {
+        let valid = match self {
+            Token::Times => matches!(previous, Some(Token::Number(_))),
+            Token::Meters | Token::Kilometers => {
+                matches!(previous, Some(Token::Number(_) | Token::NumberList(_)))
+            }
+            Token::Zone(_) => matches!(previous, Some(Token::At) | Some(Token::Bang)),
+            Token::Base(_) | Token::NamedOffset(_) => matches!(previous, Some(Token::At)),
+            Token::HeartRate(_) => matches!(previous, Some(Token::Word("to"))),
+            Token::RacePace(_) => {
+                matches!(previous, Some(Token::At) | Some(Token::Word("hold")))
+            }
+            _ => true,
+        };
+        if valid {
+            self
+        } else {
+            Token::Word(slice)
+        }
+    }
+}
+
+/// Tokens of a lexer with their spans, each read in the context of the
+/// token before it (see [`Token::in_context`])
+pub fn contextual<'source>(
+    lexer: logos::Lexer<'source, Token<'source>>,
+) -> impl Iterator<Item = (Result<Token<'source>, LexError>, Range<usize>)> {
+    let source = lexer.source();
+    let mut previous = None;
+    lexer.spanned().map(move |(token, span)| {
+        let token = match token {
+            Ok(Token::CoachNote(note)) => Ok(Token::CoachNote(note)),
+            Ok(token) => {
+                let token = token.in_context(previous.as_ref(), &source[span.clone()]);
+                previous = Some(token);
+                Ok(token)
+            }
+            Err(error) => {
+                previous = None;
+                Err(error)
+            }
+        };
+        (token, span)
+    })
+}
+
+/// Digits as a number, or [`LexError::NumberTooLarge`] if they don't fit
+fn number(digits: &str) -> Result<u32, LexError> {
+    digits.parse().map_err(|_| LexError::NumberTooLarge)
+}
+
+/// Seconds in `minutes:seconds`, if they fit
+fn total_seconds(minutes: u32, seconds: u32) -> Result<u32, LexError> {
+    minutes
+        .checked_mul(60)
+        .and_then(|total| total.checked_add(seconds))
+        .ok_or(LexError::NumberTooLarge)
+}
+
+fn time<'source>(lex: &mut logos::Lexer<'source, Token<'source>>) -> Result<(u32, u32), LexError> {
+    let (minutes, seconds) = lex
+        .slice()
+        .trim_end_matches('s')
+        .split_once(':')
+        .ok_or(LexError::Invalid)?;
+    let (minutes, seconds) = (number(minutes)?, number(seconds)?);
+    total_seconds(minutes, seconds)?;
+    Ok((minutes, seconds))
+}
+
+fn rest<'source>(lex: &mut logos::Lexer<'source, Token<'source>>) -> Result<u32, LexError> {
+    let time = &lex.slice()[2..];
+    match time.split_once(':') {
+        Some((minutes, seconds)) => {
+            let seconds = number(seconds)?;
+            if seconds >= 60 {
+                return Err(LexError::Invalid);
+            }
+            total_seconds(number(minutes)?, seconds)
+        }
+        None => number(time),
+    }
+}
+
+fn named_offset<'source>(
+    lex: &mut logos::Lexer<'source, Token<'source>>,
+) -> Result<(&'source str, i32), LexError> {
+    let slice = lex.slice();
+    let split = slice.find(['+', '-']).ok_or(LexError::Invalid)?;
+    let offset = slice[split..]
+        .parse()
+        .map_err(|_| LexError::NumberTooLarge)?;
+    Ok((&slice[..split], offset))
+}
+
+fn race_pace<'source>(lex: &mut logos::Lexer<'source, Token<'source>>) -> Option<(u32, i32)> {
+    let rest = &lex.slice()[2..];
+    let split = rest.find(['+', '-']).unwrap_or(rest.len());
+    let offset = match &rest[split..] {
+        "" => 0,
+        offset => offset.parse().ok()?,
+    };
+    Some((rest[..split].parse().ok()?, offset))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -62,6 +254,13 @@ mod tests {
         assert_eq!(lex.slice(), "100");
 
         assert_eq!(lex.next(), None);
+
+        let mut lex = Token::lexer("4294967296 90000000:00 r:1:75 ~");
+        assert_eq!(lex.next(), Some(Err(LexError::NumberTooLarge)));
+        assert_eq!(lex.slice(), "4294967296");
+        assert_eq!(lex.next(), Some(Err(LexError::NumberTooLarge)));
+        assert_eq!(lex.next(), Some(Err(LexError::Invalid)));
+        assert_eq!(lex.next(), Some(Err(LexError::Invalid)));
     }
 
     #[test]
@@ -84,7 +283,7 @@ mod tests {
         assert_eq!(lex.span(), 15..16);
         assert_eq!(lex.slice(), "@");
 
-        assert_eq!(lex.next(), Some(Ok(Token::Time("1:30"))));
+        assert_eq!(lex.next(), Some(Ok(Token::Time((1, 30)))));
         assert_eq!(lex.span(), 16..20);
         assert_eq!(lex.slice(), "1:30");
 
@@ -122,6 +321,36 @@ mod tests {
         assert_eq!(lex.next(), None);
     }
 
+    #[test]
+    fn test_equipment() {
+        let mut lex = Token::lexer("[paddles(small)]");
+
+        assert_eq!(lex.next(), Some(Ok(Token::BracketOpen)));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("paddles"))));
+        assert_eq!(lex.next(), Some(Ok(Token::ParenOpen)));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("small"))));
+        assert_eq!(lex.next(), Some(Ok(Token::ParenClose)));
+        assert_eq!(lex.next(), Some(Ok(Token::BracketClose)));
+        assert_eq!(lex.span(), 15..16);
+
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn test_number_lists() {
+        let mut lex = Token::lexer("drill(6-1-6) 100-");
+
+        assert_eq!(lex.next(), Some(Ok(Token::Word("drill"))));
+        assert_eq!(lex.next(), Some(Ok(Token::ParenOpen)));
+        assert_eq!(lex.next(), Some(Ok(Token::NumberList("6-1-6"))));
+        assert_eq!(lex.span(), 6..11);
+        assert_eq!(lex.next(), Some(Ok(Token::ParenClose)));
+        assert_eq!(lex.next(), Some(Ok(Token::Number(100))));
+        assert_eq!(lex.next(), Some(Err(LexError::Invalid)));
+
+        assert_eq!(lex.next(), None);
+    }
+
     #[test]
     fn test_intervals() {
         let mut lex = Token::lexer("@30s");
@@ -137,6 +366,201 @@ mod tests {
         assert_eq!(lex.next(), None);
     }
 
+    #[test]
+    fn test_rests() {
+        let mut lex = Token::lexer("r:20 r:1:30 r:1:75 r");
+
+        assert_eq!(lex.next(), Some(Ok(Token::Rest(20))));
+        assert_eq!(lex.next(), Some(Ok(Token::Rest(90))));
+        assert_eq!(lex.next(), Some(Err(LexError::Invalid)));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("r"))));
+    }
+
+    #[test]
+    fn test_heart_rates() {
+        let tokens: Vec<_> = contextual(Token::lexer("rest to HR<120 HR<120"))
+            .map(|(token, _)| token.unwrap())
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Word("rest"),
+                Token::Word("to"),
+                Token::HeartRate(120),
+                Token::Word("HR<120"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_base_intervals() {
+        let mut lex = Token::lexer("@base @base+5 @base-10 baseline base-");
+
+        assert_eq!(lex.next(), Some(Ok(Token::At)));
+        assert_eq!(lex.next(), Some(Ok(Token::Base(0))));
+        assert_eq!(lex.next(), Some(Ok(Token::At)));
+        assert_eq!(lex.next(), Some(Ok(Token::Base(5))));
+        assert_eq!(lex.next(), Some(Ok(Token::At)));
+        assert_eq!(lex.next(), Some(Ok(Token::Base(-10))));
+        assert_eq!(lex.slice(), "base-10");
+        assert_eq!(lex.next(), Some(Ok(Token::Word("baseline"))));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("base-"))));
+
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn test_context() {
+        let tokens: Vec<Token> = contextual(Token::lexer(
+            "4 x 50m drill(x, m) base z2 @z2 km hold rp200 @base",
+        ))
+        .map(|(token, _)| token.unwrap())
+        .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Number(4),
+                Token::Times,
+                Token::Number(50),
+                Token::Meters,
+                Token::Word("drill"),
+                Token::ParenOpen,
+                Token::Word("x"),
+                Token::Comma,
+                Token::Word("m"),
+                Token::ParenClose,
+                Token::Word("base"),
+                Token::Word("z2"),
+                Token::At,
+                Token::Zone(2),
+                Token::Word("km"),
+                Token::Word("hold"),
+                Token::RacePace((200, 0)),
+                Token::At,
+                Token::Base(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_named_offsets() {
+        let tokens: Vec<Token> = contextual(Token::lexer("@fast+5 @t_pace-10 breathe-3 @base+5"))
+            .map(|(token, _)| token.unwrap())
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::At,
+                Token::NamedOffset(("fast", 5)),
+                Token::At,
+                Token::NamedOffset(("t_pace", -10)),
+                Token::Word("breathe-3"),
+                Token::At,
+                Token::Base(5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zones() {
+        let mut lex = Token::lexer("@z3 z5 zipper z6");
+
+        assert_eq!(lex.next(), Some(Ok(Token::At)));
+        assert_eq!(lex.next(), Some(Ok(Token::Zone(3))));
+        assert_eq!(lex.next(), Some(Ok(Token::Zone(5))));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("zipper"))));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("z"))));
+        assert_eq!(lex.next(), Some(Ok(Token::Number(6))));
+
+        assert_eq!(lex.next(), None);
+
+        let tokens: Vec<Token> = contextual(Token::lexer("!Z3 !85% Z3"))
+            .map(|(token, _)| token.unwrap())
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Bang,
+                Token::Zone(3),
+                Token::Bang,
+                Token::Effort(85),
+                Token::Word("Z3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alternation() {
+        let mut lex = Token::lexer("free/back by 50");
+
+        assert_eq!(lex.next(), Some(Ok(Token::Word("free"))));
+        assert_eq!(lex.next(), Some(Ok(Token::Slash)));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("back"))));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("by"))));
+        assert_eq!(lex.next(), Some(Ok(Token::Number(50))));
+
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn test_coach_notes() {
+        let mut lex =
+            Token::lexer("100m free // coach: elbows high \n# coach:breathe\n// coaching");
+
+        assert_eq!(lex.next(), Some(Ok(Token::Number(100))));
+        assert_eq!(lex.next(), Some(Ok(Token::Meters)));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("free"))));
+        assert_eq!(lex.next(), Some(Ok(Token::CoachNote("elbows high"))));
+        assert_eq!(lex.next(), Some(Ok(Token::CoachNote("breathe"))));
+
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn test_durations() {
+        let mut lex = Token::lexer("@1:30s @90s @90 @1:75 @:50 @99999999999:00");
+
+        assert_eq!(lex.nth(1), Some(Ok(Token::Time((1, 30)))));
+        assert_eq!(lex.nth(1), Some(Ok(Token::Seconds(90))));
+        assert_eq!(lex.nth(1), Some(Ok(Token::Number(90))));
+        assert_eq!(lex.nth(1), Some(Ok(Token::Time((1, 75)))));
+        assert_eq!(lex.nth(1), Some(Ok(Token::Colon)));
+        assert_eq!(lex.next(), Some(Ok(Token::Number(50))));
+        assert_eq!(lex.nth(1), Some(Err(LexError::NumberTooLarge)));
+
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn test_race_pace() {
+        let mut lex = Token::lexer("@rp200 hold rp100+1 rp50-2 rp");
+
+        assert_eq!(lex.nth(1), Some(Ok(Token::RacePace((200, 0)))));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("hold"))));
+        assert_eq!(lex.next(), Some(Ok(Token::RacePace((100, 1)))));
+        assert_eq!(lex.next(), Some(Ok(Token::RacePace((50, -2)))));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("rp"))));
+
+        assert_eq!(lex.next(), None);
+    }
+
+    #[test]
+    fn test_line_continuation() {
+        let mut lex = Token::lexer("100m free \\\n  @1:30 \\ x");
+
+        assert_eq!(lex.next(), Some(Ok(Token::Number(100))));
+        assert_eq!(lex.next(), Some(Ok(Token::Meters)));
+        assert_eq!(lex.next(), Some(Ok(Token::Word("free"))));
+        assert_eq!(lex.next(), Some(Ok(Token::At)));
+        assert_eq!(lex.next(), Some(Ok(Token::Time((1, 30)))));
+        assert_eq!(lex.next(), Some(Err(LexError::Invalid)));
+        assert_eq!(lex.next(), Some(Ok(Token::Times)));
+
+        assert_eq!(lex.next(), None);
+    }
+
     #[test]
     fn test_comments() {
         let mut lex = Token::lexer("100m # comment");
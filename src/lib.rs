@@ -1,3 +1,47 @@
 pub mod lexer;
 pub mod parser;
 pub mod ast;
+pub mod analysis;
+pub mod lint;
+pub mod path;
+pub mod query;
+pub mod resolve;
+pub mod drills;
+pub mod config;
+pub mod profile;
+pub mod steps;
+pub mod fit;
+pub mod reconcile;
+pub mod pace;
+pub mod log;
+pub mod library;
+pub mod merge;
+pub mod timeline;
+pub mod scrub;
+pub mod check;
+pub mod json;
+pub mod csv;
+pub mod tree;
+pub mod pool;
+pub mod history;
+pub mod share;
+pub mod preset;
+pub mod skeleton;
+pub mod plan;
+pub mod diagnostic;
+pub mod summary;
+pub mod fix;
+pub mod shuffle;
+pub mod lanes;
+pub mod chat;
+pub mod complexity;
+pub mod grammar;
+pub mod corpus;
+pub mod annotate;
+pub mod edit;
+pub mod notes;
+pub mod explain;
+pub mod features;
+pub mod calendar;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
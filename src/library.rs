@@ -0,0 +1,453 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+use logos::Logos;
+
+use crate::{
+    analysis::Analyse,
+    ast::{Interval, Workout},
+    features::Features,
+    lexer::Token,
+    parser::Parser,
+    resolve::resolve_intensities,
+};
+
+/// Similarity at or above which two workouts count as near duplicates
+pub const NEAR_DUPLICATE: f64 = 0.9;
+
+/// A collection of workout files, e.g., a coach's practice archive
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Library {
+    pub entries: Vec<LibraryEntry>,
+}
+
+/// A workout file in a library
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibraryEntry {
+    pub path: PathBuf,
+    /// The workout's `date` metadata, if it has a valid one
+    pub date: Option<NaiveDate>,
+    pub workout: Workout,
+}
+
+/// Filters for finding workouts in a library
+///
+/// Filters combine: an entry is returned only if it matches every filter given.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Search {
+    pub(crate) tags: Vec<String>,
+    pub(crate) min_distance: Option<u32>,
+    pub(crate) max_distance: Option<u32>,
+}
+
+/// Where a library's workouts are kept
+///
+/// Each method returns errors for files that can't be read or parsed along
+/// with what it found in the rest. The default searches load the whole
+/// library; stores with an index can answer them without parsing anything.
+pub trait Storage {
+    /// Every workout, in path order
+    fn load(&self) -> (Library, Vec<String>);
+
+    /// Workouts matching `search`, in path order
+    fn search(&self, search: &Search) -> (Vec<LibraryEntry>, Vec<String>) {
+        let (library, errors) = self.load();
+        let entries = library
+            .entries
+            .into_iter()
+            .filter(|entry| search.matches(&entry.workout))
+            .collect();
+        (entries, errors)
+    }
+
+    /// Every tag used in the library with the number of workouts carrying it
+    fn tags(&self) -> (BTreeMap<String, usize>, Vec<String>) {
+        let (library, errors) = self.load();
+        (library.tags(), errors)
+    }
+
+    /// Brings any index kept of the files up to date, e.g., after writing
+    /// new workouts
+    fn refresh(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// The `.swim` files of a directory, parsed again on every load
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryStorage {
+    pub dir: PathBuf,
+}
+
+/// Two library entries that are the same or nearly the same workout
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub first: usize,
+    pub second: usize,
+    pub similarity: f64,
+}
+
+impl Library {
+    /// Loads every `.swim` file in `dir`, returning errors for files that
+    /// can't be read or parsed along with the library of the rest
+    pub fn load_dir(dir: &Path) -> (Self, Vec<String>) {
+        let mut library = Self::default();
+        let mut errors = Vec::new();
+
+        let paths = match workout_paths(dir) {
+            Ok(paths) => paths,
+            Err(error) => return (library, vec![error]),
+        };
+
+        for path in paths {
+            match read_workout(&path) {
+                Ok(workout) => library.insert(path, workout),
+                Err(read_errors) => errors.extend(read_errors),
+            }
+        }
+
+        (library, errors)
+    }
+
+    /// Adds a workout, dating it from its metadata
+    pub fn insert(&mut self, path: PathBuf, workout: Workout) {
+        let date = workout
+            .metadata
+            .get("date")
+            .and_then(|date| date.parse().ok());
+        self.entries.push(LibraryEntry {
+            path,
+            date,
+            workout,
+        });
+    }
+
+    /// Pairs of entries at least `threshold` similar, most similar first
+    pub fn duplicates(&self, threshold: f64) -> Vec<Duplicate> {
+        // Similarity is at most 2 * shorter / (shorter + longer) of the two
+        // volumes, so each entry is only compared with those of close volume
+        let meters: Vec<u32> = self
+            .entries
+            .iter()
+            .map(|entry| entry.workout.total_distance())
+            .collect();
+        let mut by_volume: Vec<usize> = (0..self.entries.len()).collect();
+        by_volume.sort_by_key(|&index| meters[index]);
+
+        let mut duplicates = Vec::new();
+        for (rank, &a) in by_volume.iter().enumerate() {
+            for &b in &by_volume[rank + 1..] {
+                let (shorter, longer) = (f64::from(meters[a]), f64::from(meters[b]));
+                if 2.0 * shorter < threshold * (shorter + longer) {
+                    break;
+                }
+                let similarity = similarity(&self.entries[a].workout, &self.entries[b].workout);
+                if similarity >= threshold {
+                    duplicates.push(Duplicate {
+                        first: a.min(b),
+                        second: a.max(b),
+                        similarity,
+                    });
+                }
+            }
+        }
+        duplicates.sort_by(|a, b| {
+            b.similarity
+                .total_cmp(&a.similarity)
+                .then((a.first, a.second).cmp(&(b.first, b.second)))
+        });
+        duplicates
+    }
+
+    /// The `count` entries whose [`Features`] are closest to the workout's,
+    /// closest first, with their distance
+    pub fn nearest(&self, workout: &Workout, count: usize) -> Vec<(&LibraryEntry, f64)> {
+        let features = Features::of(workout);
+        let mut nearest: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| (entry, features.distance(&Features::of(&entry.workout))))
+            .collect();
+        nearest.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        nearest.truncate(count);
+        nearest
+    }
+
+    /// Entries matching `search`, in path order
+    pub fn search(&self, search: &Search) -> Vec<&LibraryEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| search.matches(&entry.workout))
+            .collect()
+    }
+
+    /// Every tag used in the library with the number of workouts carrying it
+    pub fn tags(&self) -> BTreeMap<String, usize> {
+        let mut tags = BTreeMap::new();
+        for entry in &self.entries {
+            for tag in entry.workout.metadata.tags() {
+                *tags.entry(tag).or_insert(0) += 1;
+            }
+        }
+        tags
+    }
+
+    /// Entries at least `threshold` similar to `workout`, most recently swum first
+    pub fn similar_to(&self, workout: &Workout, threshold: f64) -> Vec<(&LibraryEntry, f64)> {
+        let mut similar: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| (entry, similarity(workout, &entry.workout)))
+            .filter(|(_, similarity)| *similarity >= threshold)
+            .collect();
+        similar.sort_by_key(|(entry, _)| std::cmp::Reverse(entry.date));
+        similar
+    }
+}
+
+impl DirectoryStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl Storage for DirectoryStorage {
+    fn load(&self) -> (Library, Vec<String>) {
+        Library::load_dir(&self.dir)
+    }
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep only workouts tagged `tag`
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_lowercase());
+        self
+    }
+
+    /// Keep only workouts of at least `meters`
+    pub fn min_distance(mut self, meters: u32) -> Self {
+        self.min_distance = Some(meters);
+        self
+    }
+
+    /// Keep only workouts of at most `meters`
+    pub fn max_distance(mut self, meters: u32) -> Self {
+        self.max_distance = Some(meters);
+        self
+    }
+
+    /// Whether the workout passes every filter
+    pub fn matches(&self, workout: &Workout) -> bool {
+        let tags = workout.metadata.tags();
+        let distance = workout.total_distance();
+        self.tags.iter().all(|tag| tags.contains(tag))
+            && self.min_distance.is_none_or(|min| distance >= min)
+            && self.max_distance.is_none_or(|max| distance <= max)
+    }
+}
+
+/// The `.swim` files in `dir`, sorted
+pub(crate) fn workout_paths(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|error| format!("{}: {}", dir.display(), error))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "swim"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Reads and parses a library file, with its intensities resolved
+pub(crate) fn read_workout(path: &Path) -> Result<Workout, Vec<String>> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|error| vec![format!("{}: {}", path.display(), error)])?;
+    let mut workout = Parser::new(Token::lexer(&source))
+        .parse()
+        .map_err(|errors| {
+            errors
+                .iter()
+                .map(|error| format!("{}: {}", path.display(), error))
+                .collect::<Vec<_>>()
+        })?;
+    resolve_intensities(&mut workout);
+    Ok(workout)
+}
+
+/// How alike two workouts are, from 0 (nothing in common) to 1 (the same swims)
+///
+/// Workouts are compared as the sequence of statements swum, normalized so
+/// that e.g. `1km` and `1000m` or `@60s` and `@1:00` are equal. The score is
+/// the share of the total distance in the longest common subsequence.
+/// Metadata and how sets are grouped into blocks are ignored.
+pub fn similarity(a: &Workout, b: &Workout) -> f64 {
+    let a = normalize(a);
+    let b = normalize(b);
+    let total: u32 = a.iter().chain(&b).map(|(_, meters)| meters).sum();
+    if total == 0 {
+        return 1.0;
+    }
+
+    // Longest common subsequence, weighted by distance
+    let mut table = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for (i, (key_a, meters)) in a.iter().enumerate() {
+        for (j, (key_b, _)) in b.iter().enumerate() {
+            table[i + 1][j + 1] = if key_a == key_b {
+                table[i][j] + meters
+            } else {
+                table[i][j + 1].max(table[i + 1][j])
+            };
+        }
+    }
+
+    f64::from(2 * table[a.len()][b.len()]) / f64::from(total)
+}
+
+/// Statements swum as normalized descriptions with the distance they cover
+fn normalize(workout: &Workout) -> Vec<(String, u32)> {
+    let mut statements = Vec::new();
+    workout.for_each_statement(&mut |stmt, reps| {
+        let mut modifiers: Vec<String> = stmt
+            .stroke
+            .modifiers
+            .iter()
+            .map(|modifier| modifier.to_string().to_ascii_lowercase())
+            .collect();
+        modifiers.sort();
+        let mut equipment: Vec<String> = stmt.equipment.iter().map(ToString::to_string).collect();
+        equipment.sort();
+        let key = format!(
+            "{}x{} {}({}) [{}] @{}",
+            reps,
+            stmt.distance.meters(),
+            stmt.stroke.name.to_ascii_lowercase(),
+            modifiers.join(","),
+            equipment.join(","),
+            match &stmt.interval {
+                Some(
+                    interval @ (Interval::Base { .. }
+                    | Interval::RacePace(_)
+                    | Interval::HeartRate { .. }
+                    | Interval::Rest(_)
+                    | Interval::Sequence(_)),
+                ) => {
+                    interval.to_string()
+                }
+                Some(interval) => interval.seconds().to_string(),
+                None => String::new(),
+            }
+        );
+        statements.push((key, stmt.distance.meters() * reps));
+    });
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Workout {
+        Parser::new(Token::lexer(source)).parse().unwrap()
+    }
+
+    #[test]
+    fn test_similarity() {
+        let workout = parse("1000m free\n8x100m free @1:30\n200m back");
+
+        assert_eq!(
+            similarity(
+                &workout,
+                &parse("title: Again\n1km free\n8x { 100m Free @90s }\n200m back")
+            ),
+            1.0
+        );
+        let close = similarity(&workout, &parse("1000m free\n8x100m free @1:30\n100m back"));
+        assert!((NEAR_DUPLICATE..1.0).contains(&close));
+        assert!(similarity(&workout, &parse("10x50m fly @1:00")) == 0.0);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut library = Library::default();
+        library.insert(
+            "a.swim".into(),
+            parse("date: 2024-01-08\n400m free\n8x100m free @1:30"),
+        );
+        library.insert("b.swim".into(), parse("10x50m fly @1:00"));
+        library.insert(
+            "c.swim".into(),
+            parse("date: 2024-02-12\n400m free\n8x100m free @1:30"),
+        );
+
+        let duplicates = library.duplicates(NEAR_DUPLICATE);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!((duplicates[0].first, duplicates[0].second), (0, 2));
+
+        let similar = library.similar_to(&parse("400m free\n8x100m free @1:30"), NEAR_DUPLICATE);
+        let paths: Vec<_> = similar.iter().map(|(entry, _)| &entry.path).collect();
+        assert_eq!(paths, vec![Path::new("c.swim"), Path::new("a.swim")]);
+
+        library.insert("d.swim".into(), parse("400m free\n8x100m free @1:20"));
+        let duplicates = library.duplicates(0.3);
+        let pairs: Vec<_> = duplicates
+            .iter()
+            .map(|duplicate| (duplicate.first, duplicate.second))
+            .collect();
+        assert_eq!(pairs, vec![(0, 2), (0, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let mut library = Library::default();
+        library.insert("a.swim".into(), parse("20x50m free @45s @z3"));
+        library.insert("b.swim".into(), parse("10x100m back @1:40 @z3"));
+        library.insert("c.swim".into(), parse("3km free @easy"));
+
+        let nearest = library.nearest(&parse("400m free\n10x100m free @1:30 @z3"), 2);
+        let paths: Vec<_> = nearest.iter().map(|(entry, _)| &entry.path).collect();
+        assert_eq!(paths, vec![Path::new("a.swim"), Path::new("b.swim")]);
+        assert!(nearest[0].1 < nearest[1].1);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut library = Library::default();
+        library.insert(
+            "a.swim".into(),
+            parse("tags: [Threshold, freestyle]\n400m free\n30x100m free @1:30"),
+        );
+        library.insert(
+            "b.swim".into(),
+            parse("tags: threshold\n10x100m free @1:30"),
+        );
+        library.insert("c.swim".into(), parse("10x50m fly @1:00"));
+
+        let paths = |search: &Search| -> Vec<PathBuf> {
+            library
+                .search(search)
+                .iter()
+                .map(|entry| entry.path.clone())
+                .collect()
+        };
+        assert_eq!(
+            paths(&Search::new().tag("threshold")),
+            vec![PathBuf::from("a.swim"), PathBuf::from("b.swim")]
+        );
+        assert_eq!(
+            paths(&Search::new().tag("threshold").min_distance(3000)),
+            vec![PathBuf::from("a.swim")]
+        );
+        assert_eq!(
+            paths(&Search::new().max_distance(1000)),
+            vec![PathBuf::from("b.swim"), PathBuf::from("c.swim")]
+        );
+        assert_eq!(library.tags()["threshold"], 2);
+        assert_eq!(library.tags()["freestyle"], 1);
+    }
+}
@@ -0,0 +1,642 @@
+use std::fmt;
+
+use crate::{
+    analysis::{equipment_changes, is_equipment, section_names, Analyse, SetKind},
+    ast::{Interval, Set, Statement, Workout},
+    check::format_duration,
+    config::Config,
+    drills::DrillDictionary,
+    pace::{Intensity, PaceModel},
+    path::SetPath,
+    preset::Preset,
+};
+
+/// Most equipment changes in a workout before suggesting it be reordered,
+/// unless configured
+const MAX_EQUIPMENT_CHANGES: u32 = 6;
+
+/// Fastest send-off per 100m in seconds taken at face value; anything
+/// quicker beats the world record, so the units are surely off
+const FASTEST_SEND_OFF_PER_100: u64 = 40;
+
+/// Slowest send-off per 100m in seconds taken at face value, generous enough
+/// for beginners kicking with long rests
+const SLOWEST_SEND_OFF_PER_100: u64 = 10 * 60;
+
+/// A likely problem in a workout that still parses
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    /// Set the warning applies to, or `None` for the whole workout
+    pub path: Option<SetPath>,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "set {}: {}", path, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Checks a parsed workout for likely mistakes
+///
+/// Send-offs far out of proportion to their distance, e.g., `50m free @15:00`,
+/// are flagged as likely unit typos. With a pace model, other send-offs
+/// faster than the swimmer could sprint are flagged as impossible.
+pub fn lint(workout: &Workout, config: &Config, model: Option<&dyn PaceModel>) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    let sections = section_names(workout);
+    if !workout.sets.is_empty()
+        && !sections
+            .iter()
+            .any(|name| SetKind::of_section(name).is_some())
+    {
+        warnings.push(Warning {
+            path: None,
+            message: "workout appears to have neither a warmup nor a cooldown".to_string(),
+        });
+    }
+
+    let drills = config.drill_dictionary();
+    if !config.strokes.is_empty() {
+        check_stroke_limits(workout, config, &drills, &mut warnings);
+    }
+    if let Some(preset) = config.preset {
+        check_preset(workout, preset, &mut warnings);
+    }
+    check_equipment_changes(workout, config, &mut warnings);
+
+    for (path, set) in workout.numbered_sets() {
+        check_budget(&path, set, model, &mut warnings);
+
+        let Some(stmt) = set.statement() else {
+            continue;
+        };
+
+        let mismatched = check_send_off_units(&path, stmt, &mut warnings);
+        if let (Some(model), Some(interval), false) = (model, &stmt.interval, mismatched) {
+            let fastest =
+                model.predict(stmt.distance.meters(), &stmt.stroke.name, Intensity::Sprint);
+            if interval.seconds() > 0 && interval.seconds() < fastest {
+                warnings.push(Warning {
+                    path: Some(path.clone()),
+                    message: format!(
+                        "send-off {} is faster than an all-out {} (about {}s)",
+                        interval, stmt.distance, fastest
+                    ),
+                });
+            }
+        }
+
+        check_progression(&path, set, &mut warnings);
+
+        for modifier in &stmt.stroke.modifiers {
+            if is_equipment(&modifier.name) {
+                warnings.push(Warning {
+                    path: Some(path.clone()),
+                    message: format!(
+                        "`{}` is equipment; list it in brackets, e.g., `[{}]`",
+                        modifier.name,
+                        modifier.name.to_ascii_lowercase()
+                    ),
+                });
+            }
+        }
+
+        if !stmt.stroke.name.eq_ignore_ascii_case("drill") {
+            continue;
+        }
+        for modifier in &stmt.stroke.modifiers {
+            if drills.stroke(&modifier.name).is_none() && !is_equipment(&modifier.name) {
+                warnings.push(Warning {
+                    path: Some(path.clone()),
+                    message: format!("unknown drill `{}`", modifier.name),
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Warns about a send-off wildly out of proportion to the distance, which is
+/// almost always minutes written for seconds or the other way around,
+/// returning whether it did
+fn check_send_off_units(path: &SetPath, stmt: &Statement, warnings: &mut Vec<Warning>) -> bool {
+    let meters = u64::from(stmt.distance.meters());
+    let send_offs = match &stmt.interval {
+        Some(Interval::Sequence(intervals)) => intervals.as_slice(),
+        interval => interval.as_slice(),
+    };
+    for interval in send_offs {
+        let secs = u64::from(interval.seconds());
+        if secs == 0 || meters == 0 {
+            continue;
+        }
+        let per_100 = secs * 100 / meters;
+        let (judgement, suggestion) = if per_100 < FASTEST_SEND_OFF_PER_100 {
+            let minutes = match *interval {
+                Interval::Seconds(secs)
+                | Interval::MinutesSeconds {
+                    minutes: 0,
+                    seconds: secs,
+                } => Some(Interval::MinutesSeconds {
+                    minutes: secs,
+                    seconds: 0,
+                }),
+                _ => None,
+            };
+            ("fast", minutes)
+        } else if per_100 > SLOWEST_SEND_OFF_PER_100 {
+            let seconds = match *interval {
+                Interval::MinutesSeconds {
+                    minutes,
+                    seconds: 0,
+                } => Some(Interval::Seconds(minutes)),
+                _ => None,
+            };
+            ("slow", seconds)
+        } else {
+            continue;
+        };
+        let plausible = |interval: &Interval| {
+            let per_100 = u64::from(interval.seconds()) * 100 / meters;
+            (FASTEST_SEND_OFF_PER_100..=SLOWEST_SEND_OFF_PER_100).contains(&per_100)
+        };
+        warnings.push(Warning {
+            path: Some(path.clone()),
+            message: format!(
+                "send-off {} is far too {} for {} ({} per 100m); {}",
+                interval,
+                judgement,
+                stmt.distance,
+                format_duration(u32::try_from(per_100).unwrap_or(u32::MAX)),
+                match suggestion.filter(plausible) {
+                    Some(suggestion) => format!("did you mean `{}`?", suggestion),
+                    None => "check the units of the distance and interval".to_string(),
+                }
+            ),
+        });
+        return true;
+    }
+    false
+}
+
+/// Warns about a progression the rest of the statement contradicts: reps
+/// marked to descend with target times that don't, or that hold one race
+/// pace, a descend range past the last rep, and `build` on a rep too short to
+/// build over
+fn check_progression(path: &SetPath, set: &Set, warnings: &mut Vec<Warning>) {
+    let Some(stmt) = set.statement() else {
+        return;
+    };
+    let mut warn = |message: String| {
+        warnings.push(Warning {
+            path: Some(path.clone()),
+            message,
+        })
+    };
+
+    match stmt.descend_range() {
+        Some((from, to)) if from == 0 || from >= to => {
+            warn(format!("`descend {}-{}` isn't a range of reps", from, to));
+        }
+        Some((from, to)) => {
+            if let Set::Repetition { count, .. } = set {
+                if to > *count {
+                    warn(format!(
+                        "`descend {}-{}` goes past the last of {} reps",
+                        from, to, count
+                    ));
+                }
+            }
+            let targets: Vec<u32> = (from..=to)
+                .filter_map(|rep| stmt.target(rep as usize - 1))
+                .collect();
+            if targets.windows(2).any(|pair| pair[1] >= pair[0]) {
+                warn(format!(
+                    "`descend {}-{}` has target times that don't get faster",
+                    from, to
+                ));
+            }
+        }
+        None if stmt.is_descending() && !stmt.targets_descend() => {
+            warn("descending set has target times that don't get faster".to_string());
+        }
+        None => {}
+    }
+
+    if stmt.is_descending() {
+        if let Some(hold) = stmt.hold {
+            warn(format!("descending set holds one race pace, {}", hold));
+        }
+    }
+    if stmt.is_build() && stmt.distance.meters() <= 25 {
+        warn(format!(
+            "`build` on a {} rep leaves no distance to build over",
+            stmt.distance
+        ));
+    }
+}
+
+/// Warns when a block with a time budget takes longer than it, by its
+/// send-offs or else the pace model
+///
+/// The budget is for one pass through the block, however often it's repeated.
+fn check_budget(
+    path: &SetPath,
+    set: &Set,
+    model: Option<&dyn PaceModel>,
+    warnings: &mut Vec<Warning>,
+) {
+    let mut set = set;
+    while let Set::Repetition { set: inner, .. } = set {
+        set = inner;
+    }
+    let Set::Block {
+        label,
+        budget: Some(budget),
+        ..
+    } = set
+    else {
+        return;
+    };
+
+    let estimated = match model {
+        Some(model) => set.estimated_swim_time(model) + set.dryland_time(),
+        None => set.total_time(),
+    };
+    if estimated > *budget {
+        warnings.push(Warning {
+            path: Some(path.clone()),
+            message: format!(
+                "{} takes about {}, over its budget of {}",
+                label.as_deref().unwrap_or("block"),
+                format_duration(estimated),
+                format_duration(*budget)
+            ),
+        });
+    }
+}
+
+/// Warns about volume, send-offs and breath holding beyond the preset's limits
+fn check_preset(workout: &Workout, preset: Preset, warnings: &mut Vec<Warning>) {
+    let limits = preset.limits();
+
+    let total = workout.total_distance();
+    if total > limits.max_distance {
+        warnings.push(Warning {
+            path: None,
+            message: format!(
+                "workout is {}m, above the {} maximum of {}m",
+                total, preset, limits.max_distance
+            ),
+        });
+    }
+
+    for (path, set) in workout.numbered_sets() {
+        let Some(stmt) = set.statement() else {
+            continue;
+        };
+        let mut warn = |message: String| {
+            warnings.push(Warning {
+                path: Some(path.clone()),
+                message,
+            })
+        };
+
+        if let Some(pace) = stmt
+            .pace_per_100()
+            .filter(|pace| *pace < limits.fastest_pace)
+        {
+            warn(format!(
+                "send-off needs {}/100m, faster than {} swimmers plausibly hold ({}/100m)",
+                format_duration(pace),
+                preset,
+                format_duration(limits.fastest_pace)
+            ));
+        }
+        if stmt.is_hypoxic() && stmt.distance.meters() > limits.max_hypoxic {
+            warn(match limits.max_hypoxic {
+                0 => format!("hypoxic swimming isn't safe for {} swimmers", preset),
+                max => format!(
+                    "{} of hypoxic swimming per rep is beyond the {} limit of {}m",
+                    stmt.distance, preset, max
+                ),
+            });
+        }
+    }
+}
+
+/// Suggests grouping sets by gear when swimmers change equipment more often
+/// than the configured limit, pointing at the set with the most changes
+fn check_equipment_changes(workout: &Workout, config: &Config, warnings: &mut Vec<Warning>) {
+    let max = config
+        .max_equipment_changes
+        .unwrap_or(MAX_EQUIPMENT_CHANGES);
+    let changes = equipment_changes(&workout.sets);
+    if changes <= max {
+        return;
+    }
+
+    let mut message = format!(
+        "equipment changes {} times, more than {}; grouping sets that use the same gear saves time on deck",
+        changes, max
+    );
+    let busiest = workout
+        .sets
+        .iter()
+        .enumerate()
+        .map(|(i, set)| (i, equipment_changes(std::slice::from_ref(set))))
+        .max_by_key(|&(i, changes)| (changes, std::cmp::Reverse(i)));
+    if let Some((i, most)) = busiest.filter(|&(_, most)| most * 2 > changes) {
+        message.push_str(&format!(" ({} of them within set {})", most, i + 1));
+    }
+    warnings.push(Warning {
+        path: None,
+        message,
+    });
+}
+
+/// Warns about strokes swum more or less than the configured limits allow
+fn check_stroke_limits(
+    workout: &Workout,
+    config: &Config,
+    drills: &DrillDictionary,
+    warnings: &mut Vec<Warning>,
+) {
+    let distribution = workout.stroke_distribution_with(drills);
+    let total = workout.total_distance();
+
+    for (stroke, limits) in &config.strokes {
+        let meters = distribution.get(stroke).copied().unwrap_or(0);
+        let percent = (meters * 100).checked_div(total).unwrap_or(0);
+        let mut warn = |message: String| {
+            warnings.push(Warning {
+                path: None,
+                message,
+            })
+        };
+
+        if let Some(min) = limits.min.filter(|min| meters < *min) {
+            warn(format!(
+                "{}m of {} is below the minimum of {}m",
+                meters, stroke, min
+            ));
+        }
+        if let Some(max) = limits.max.filter(|max| meters > *max) {
+            warn(format!(
+                "{}m of {} is above the maximum of {}m",
+                meters, stroke, max
+            ));
+        }
+        // Compare exactly rather than with the rounded-down percentage
+        if let Some(min) = limits.min_percent.filter(|min| meters * 100 < min * total) {
+            warn(format!(
+                "{} is {}% of the workout, below the minimum of {}%",
+                stroke, percent, min
+            ));
+        }
+        if let Some(max) = limits.max_percent.filter(|max| meters * 100 > max * total) {
+            warn(format!(
+                "{} is {}% of the workout, above the maximum of {}%",
+                stroke, percent, max
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, pace::CssModel, parser::Parser};
+    use logos::Logos;
+
+    fn lint_source(source: &str) -> Vec<Warning> {
+        lint(
+            &Parser::new(Token::lexer(source)).parse().unwrap(),
+            &Config::default(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_missing_warmup_and_cooldown() {
+        let warnings = lint_source("8x100m free @1:30\n4x50m fly(sprint) @1:00");
+        assert_eq!(
+            warnings,
+            vec![Warning {
+                path: None,
+                message: "workout appears to have neither a warmup nor a cooldown".to_string(),
+            }]
+        );
+
+        assert!(lint_source("400m free\n8x100m free @1:30").is_empty());
+        assert!(lint_source("warm-up: { 4x50m fly(sprint) @1:00 }\n8x100m free @1:30").is_empty());
+    }
+
+    #[test]
+    fn test_equipment_changes() {
+        let source =
+            "400m free\n4x {\n    100m pull [buoy, paddles]\n    50m kick [fins]\n}\n200m free";
+        assert_eq!(
+            lint_source(source)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["equipment changes 9 times, more than 6; grouping sets that use the same gear saves time on deck (7 of them within set 2)"]
+        );
+
+        let workout = Parser::new(Token::lexer(source)).parse().unwrap();
+        let config = Config::parse("max_equipment_changes = 10").unwrap();
+        assert!(lint(&workout, &config, None).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_drills() {
+        let warnings = lint_source("400m free\n4x { 50m drill(zipper, fist)\n50m drill(flail) }");
+        assert_eq!(
+            warnings,
+            vec![Warning {
+                path: Some(SetPath::new(vec![1, 1])),
+                message: "unknown drill `flail`".to_string(),
+            }]
+        );
+        assert_eq!(warnings[0].to_string(), "set 2.2: unknown drill `flail`");
+
+        let config = Config::parse("[drills]\nflail = \"fly\"").unwrap();
+        let workout = Parser::new(Token::lexer("400m free\n4x50m drill(flail)"))
+            .parse()
+            .unwrap();
+        assert!(lint(&workout, &config, None).is_empty());
+    }
+
+    #[test]
+    fn test_equipment_modifiers() {
+        assert_eq!(
+            lint_source("400m free\n4x50m drill(catch-up, Fins)\n200m free")
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["set 2: `Fins` is equipment; list it in brackets, e.g., `[fins]`"]
+        );
+    }
+
+    #[test]
+    fn test_stroke_limits() {
+        let config = Config::parse(
+            "[strokes.back]\nmin = 200\n[strokes.fly]\nmax_percent = 10\n[strokes.free]\nmin_percent = 50\nmax = 2000",
+        )
+        .unwrap();
+        let workout = Parser::new(Token::lexer(
+            "400m free(easy)\n8x50m fly @1:00\n4x50m drill(fist) @1:00\n100m back(easy)",
+        ))
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            lint(&workout, &config, None)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "100m of back is below the minimum of 200m",
+                "fly is 36% of the workout, above the maximum of 10%",
+            ]
+        );
+
+        let workout = Parser::new(Token::lexer("800m free(easy)\n200m back\n100m fly(easy)"))
+            .parse()
+            .unwrap();
+        assert!(lint(&workout, &config, None).is_empty());
+    }
+
+    #[test]
+    fn test_descending_targets() {
+        let warnings = lint_source(
+            "400m free\n4x100m free(descend) targets(1:10, 1:08, 1:08, 1:04)\n4x50m back targets(40s, 39s, 38s, 37s)\n2x50m fly(desc)",
+        );
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["set 2: descending set has target times that don't get faster"]
+        );
+    }
+
+    #[test]
+    fn test_progressions() {
+        let warnings = lint_source(
+            "400m free\n8x100m free(descend 1-4) targets(1:10, 1:08, 1:06, 1:04, 1:12, 1:10, 1:08, 1:06)\n4x100m free(descend 1-4) targets(1:10, 1:08, 1:08, 1:04)\n4x50m free(descend 2-6)\n4x50m back(descend 3-1)\n4x100m free(descend) hold rp200\n4x25m fly(build)\n4x50m fly(build)",
+        );
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "set 3: `descend 1-4` has target times that don't get faster",
+                "set 4: `descend 2-6` goes past the last of 4 reps",
+                "set 5: `descend 3-1` isn't a range of reps",
+                "set 6: descending set holds one race pace, rp200",
+                "set 7: `build` on a 25m rep leaves no distance to build over",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_time_budgets() {
+        let workout = Parser::new(Token::lexer(
+            "400m free(easy)\nMain (10:00): {\n  8x100m free @1:30\n  4x50m fly @1:00\n}\n2x Kick (3:00): { 4x50m kick }\n200m free(easy)",
+        ))
+        .parse()
+        .unwrap();
+        let warnings = lint(&workout, &Config::default(), None);
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["set 2: Main takes about 16:00, over its budget of 10:00"]
+        );
+
+        let model = CssModel::new(90);
+        let warnings = lint(&workout, &Config::default(), Some(&model));
+        assert_eq!(
+            warnings[1].to_string(),
+            "set 3: Kick takes about 4:32, over its budget of 3:00"
+        );
+        assert_eq!(
+            workout.sets[1].to_string(),
+            "Main (10:00): {\n    8x 100m free @1:30\n    4x 50m fly @1:00\n}"
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        let workout = Parser::new(Token::lexer(
+            "800m free(easy)\n60x100m free @1:00\n4x50m free(hypoxic) @1:00\n200m free(easy)",
+        ))
+        .parse()
+        .unwrap();
+        let lint_with = |preset: &str| {
+            let config = Config {
+                preset: Some(preset.parse().unwrap()),
+                ..Config::default()
+            };
+            lint(&workout, &config, None)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(lint_with("senior").is_empty());
+        assert_eq!(
+            lint_with("masters"),
+            vec![
+                "workout is 7200m, above the masters maximum of 6000m",
+                "set 2: send-off needs 1:00/100m, faster than masters swimmers plausibly hold (1:05/100m)",
+                "set 3: 50m of hypoxic swimming per rep is beyond the masters limit of 25m",
+            ]
+        );
+        assert_eq!(
+            lint_with("10u")[2],
+            "set 3: hypoxic swimming isn't safe for age-group 10&U swimmers"
+        );
+    }
+
+    #[test]
+    fn test_send_off_units() {
+        let workout = Parser::new(Token::lexer(
+            "400m free\n4x50m free @15:00\n1km free @:30\n4x50m back @50:00\n4x100m fly @1:40/1:35/:05\n25m kick @1:00",
+        ))
+        .parse()
+        .unwrap();
+        let model = CssModel::new(90);
+
+        assert_eq!(
+            lint(&workout, &Config::default(), Some(&model))
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "set 2: send-off @15:00 is far too slow for 50m (30:00 per 100m); check the units of the distance and interval",
+                "set 3: send-off @0:30 is far too fast for 1km (0:03 per 100m); did you mean `@30:00`?",
+                "set 4: send-off @50:00 is far too slow for 50m (1:40:00 per 100m); did you mean `@50s`?",
+                "set 5: send-off @0:05 is far too fast for 100m (0:05 per 100m); did you mean `@5:00`?",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_impossible_send_offs() {
+        let workout = Parser::new(Token::lexer("400m free\n4x100m back @1:30\n4x50m fly @50s"))
+            .parse()
+            .unwrap();
+        let model = CssModel::new(90);
+
+        assert_eq!(
+            lint(&workout, &Config::default(), Some(&model))
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["set 2: send-off @1:30 is faster than an all-out 100m (about 91s)"]
+        );
+        assert!(lint(&workout, &Config::default(), None).is_empty());
+    }
+}
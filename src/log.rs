@@ -0,0 +1,318 @@
+use std::{collections::BTreeMap, fmt};
+
+use chrono::{Datelike, Days, NaiveDate};
+use serde::Serialize;
+
+use crate::{
+    analysis::{kind_distances, Analyse, SetKind},
+    ast::{Intensity, Workout},
+    config::SeasonGoals,
+    drills::DrillDictionary,
+};
+
+/// Percentage points an intensity's share may miss its goal by and still be on plan
+pub const MIX_TOLERANCE: u32 = 5;
+
+/// Workouts swum on known dates, e.g., a season of practices
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrainingLog {
+    entries: Vec<LogEntry>,
+}
+
+/// A workout in a training log
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub date: NaiveDate,
+    pub workout: Workout,
+}
+
+/// Per-week totals for plotting, one array per series
+///
+/// `weeks` holds the Monday of every week from the first entry to the last,
+/// including weeks without workouts, and each series has a value per week.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WeeklySeries {
+    pub weeks: Vec<NaiveDate>,
+    pub series: BTreeMap<String, Vec<u32>>,
+}
+
+/// A week of the log measured against the season's goals
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeekProgress {
+    /// Monday of the week
+    pub week: NaiveDate,
+    pub meters: u32,
+    pub volume_goal: Option<u32>,
+    /// Intensities swum or with a goal, easiest first
+    pub intensities: Vec<IntensityProgress>,
+}
+
+/// Meters swum at an intensity in a week, and the share aimed for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntensityProgress {
+    pub intensity: Intensity,
+    pub meters: u32,
+    /// Goal as a percentage of the week's meters
+    pub goal: Option<u32>,
+}
+
+impl TrainingLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log of workouts dated by their `date` metadata (e.g., `date: 2024-03-18`)
+    pub fn from_workouts(workouts: impl IntoIterator<Item = Workout>) -> Result<Self, String> {
+        let mut log = Self::new();
+        for workout in workouts {
+            let date = workout
+                .metadata
+                .get("date")
+                .ok_or("workout has no `date`")?;
+            let date = date
+                .parse()
+                .map_err(|_| format!("invalid date `{}`", date))?;
+            log.push(date, workout);
+        }
+        Ok(log)
+    }
+
+    /// Adds a workout, keeping entries in date order
+    pub fn push(&mut self, date: NaiveDate, workout: Workout) {
+        let index = self.entries.partition_point(|entry| entry.date <= date);
+        self.entries.insert(index, LogEntry { date, workout });
+    }
+
+    /// Entries in date order
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    /// Meters swum each week at each intensity
+    pub fn weekly_by_intensity(&self) -> WeeklySeries {
+        self.weekly(|workout, add| {
+            workout.for_each_statement(&mut |stmt, reps| {
+                add(stmt.intensity().to_string(), stmt.distance.meters() * reps)
+            })
+        })
+    }
+
+    /// Meters swum each week with each stroke, counting drills in `drills`
+    /// towards the stroke they train
+    pub fn weekly_by_stroke(&self, drills: &DrillDictionary) -> WeeklySeries {
+        self.weekly(|workout, add| {
+            for (stroke, meters) in workout.stroke_distribution_with(drills) {
+                add(stroke, meters);
+            }
+        })
+    }
+
+    /// Meters swum each week in each kind of set, e.g., main set or kick
+    pub fn weekly_by_kind(&self, drills: &DrillDictionary) -> WeeklySeries {
+        self.weekly(|workout, add| {
+            for (kind, meters) in kind_distances(workout, drills) {
+                add(kind.to_string(), meters);
+            }
+        })
+    }
+
+    /// Meters swum in each kind of set over the whole log
+    pub fn distance_by_kind(&self, drills: &DrillDictionary) -> BTreeMap<SetKind, u32> {
+        let mut distances = BTreeMap::new();
+        for entry in &self.entries {
+            for (kind, meters) in kind_distances(&entry.workout, drills) {
+                *distances.entry(kind).or_default() += meters;
+            }
+        }
+        distances
+    }
+
+    /// Each week's volume and intensity mix next to the goals for them
+    pub fn progress(&self, goals: &SeasonGoals) -> Vec<WeekProgress> {
+        let by_intensity = self.weekly_by_intensity();
+        by_intensity
+            .weeks
+            .iter()
+            .enumerate()
+            .map(|(i, week)| {
+                let meters_at = |intensity: Intensity| {
+                    by_intensity
+                        .series
+                        .get(&intensity.to_string())
+                        .map_or(0, |series| series[i])
+                };
+                let intensities = (1..=5)
+                    .filter_map(Intensity::from_zone)
+                    .map(|intensity| IntensityProgress {
+                        intensity,
+                        meters: meters_at(intensity),
+                        goal: goals.intensity.get(&intensity).copied(),
+                    })
+                    .filter(|progress| progress.meters > 0 || progress.goal.is_some())
+                    .collect::<Vec<_>>();
+                WeekProgress {
+                    week: *week,
+                    meters: intensities.iter().map(|progress| progress.meters).sum(),
+                    volume_goal: goals.weekly_volume,
+                    intensities,
+                }
+            })
+            .collect()
+    }
+
+    /// Weekly series from the meters `visit` reports for each workout
+    fn weekly(&self, visit: impl Fn(&Workout, &mut dyn FnMut(String, u32))) -> WeeklySeries {
+        let (Some(first), Some(last)) = (self.entries.first(), self.entries.last()) else {
+            return WeeklySeries::default();
+        };
+        let start = week_start(first.date);
+        let weeks: Vec<NaiveDate> = (0..)
+            .map(|week| start + Days::new(7 * week))
+            .take_while(|monday| *monday <= last.date)
+            .collect();
+
+        let mut series: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        for entry in &self.entries {
+            let week = (week_start(entry.date) - start).num_weeks() as usize;
+            visit(&entry.workout, &mut |name, meters| {
+                series.entry(name).or_insert_with(|| vec![0; weeks.len()])[week] += meters;
+            });
+        }
+
+        WeeklySeries { weeks, series }
+    }
+}
+
+impl WeekProgress {
+    /// Meters short of the volume goal, zero once it's met
+    pub fn deficit(&self) -> u32 {
+        self.volume_goal
+            .map_or(0, |goal| goal.saturating_sub(self.meters))
+    }
+
+    /// Whether the week met its volume goal with every intensity within
+    /// [`MIX_TOLERANCE`] of its share
+    pub fn on_plan(&self) -> bool {
+        self.deficit() == 0
+            && self.intensities.iter().all(|progress| {
+                progress.goal.is_none_or(|goal| {
+                    progress.percent(self.meters).abs_diff(goal) <= MIX_TOLERANCE
+                })
+            })
+    }
+}
+
+impl IntensityProgress {
+    /// Share of `total` meters swum at this intensity, rounded down
+    pub fn percent(&self, total: u32) -> u32 {
+        (self.meters * 100).checked_div(total).unwrap_or(0)
+    }
+}
+
+impl fmt::Display for WeekProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}  {}m", self.week, self.meters)?;
+        if let Some(goal) = self.volume_goal {
+            write!(f, " of {}m", goal)?;
+            match self.deficit() {
+                0 => {}
+                deficit => write!(f, " ({}m short)", deficit)?,
+            }
+        }
+        for progress in &self.intensities {
+            write!(
+                f,
+                ", {} {}%",
+                progress.intensity,
+                progress.percent(self.meters)
+            )?;
+            if let Some(goal) = progress.goal {
+                write!(f, " of {}%", goal)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Monday of the week containing `date`
+pub(crate) fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().num_days_from_monday().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn parse(source: &str) -> Workout {
+        Parser::new(Token::lexer(source)).parse().unwrap()
+    }
+
+    #[test]
+    fn test_weekly_series() {
+        let log = TrainingLog::from_workouts([
+            parse("date: 2024-03-20\n400m free(easy)\n8x50m fly(sprint) @1:00"),
+            parse("date: 2024-03-04\n1000m free\n200m drill(catch-up)"),
+            parse("date: 2024-03-18\n4x100m back(race-pace) @1:45"),
+        ])
+        .unwrap();
+
+        let date = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        assert_eq!(log.entries()[0].date, date(4));
+
+        let intensity = log.weekly_by_intensity();
+        assert_eq!(intensity.weeks, vec![date(4), date(11), date(18)]);
+        assert_eq!(intensity.series["moderate"], vec![1200, 0, 0]);
+        assert_eq!(intensity.series["sprint"], vec![0, 0, 400]);
+        assert_eq!(intensity.series["race-pace"], vec![0, 0, 400]);
+
+        let strokes = log.weekly_by_stroke(&DrillDictionary::builtin());
+        assert_eq!(strokes.series["free"], vec![1200, 0, 400]);
+
+        let kinds = log.weekly_by_kind(&DrillDictionary::builtin());
+        assert_eq!(kinds.series["warmup"], vec![1000, 0, 400]);
+        assert_eq!(kinds.series["sprint"], vec![0, 0, 400]);
+        assert_eq!(
+            log.distance_by_kind(&DrillDictionary::builtin()),
+            BTreeMap::from([
+                (SetKind::Warmup, 1400),
+                (SetKind::Main, 400),
+                (SetKind::Sprint, 400),
+                (SetKind::Cooldown, 200),
+            ])
+        );
+
+        assert!(TrainingLog::from_workouts([parse("400m free")]).is_err());
+        assert_eq!(
+            TrainingLog::new().weekly_by_intensity(),
+            WeeklySeries::default()
+        );
+    }
+
+    #[test]
+    fn test_progress() {
+        let log = TrainingLog::from_workouts([
+            parse("date: 2024-03-04\n1800m free(easy)\n200m fly(sprint)"),
+            parse("date: 2024-03-13\n1000m free(easy)\n500m free @z3"),
+        ])
+        .unwrap();
+        let goals =
+            SeasonGoals::parse("weekly_volume = 2000\n[intensity]\neasy = 85\nthreshold = 10")
+                .unwrap();
+
+        let progress = log.progress(&goals);
+        assert_eq!(
+            progress.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "2024-03-04  2000m of 2000m, easy 90% of 85%, threshold 0% of 10%, sprint 10%",
+                "2024-03-11  1500m of 2000m (500m short), easy 66% of 85%, threshold 33% of 10%",
+            ]
+        );
+        assert!(!progress[0].on_plan());
+        assert_eq!(progress[1].deficit(), 500);
+
+        let goals = SeasonGoals::parse("weekly_volume = 2000\n[intensity]\neasy = 90").unwrap();
+        assert!(log.progress(&goals)[0].on_plan());
+    }
+}
@@ -1,19 +1,1655 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::IsTerminal,
+};
+
 use logos::Logos;
-use swim_parser::lexer::Token;
+use swim_parser::{
+    analysis::{detect_sections, key_sets, section_names, Analyse},
+    annotate::{AnnotatedFile, AnnotationFormatter, GithubFormatter, SarifFormatter},
+    ast::{Set, Workout},
+    calendar::{calendar, dated_file_name, with_date, write_ics},
+    chat::{chat_messages, DISCORD_LIMIT},
+    check::{check, suggest_intervals, unlikely_send_offs, Problem, DEFAULT_VARIABILITY},
+    config::{Config, SeasonGoals},
+    corpus::check_corpus,
+    diagnostic::{Diagnostic, Diagnostics, Severity},
+    explain::explain,
+    fit::load_laps,
+    fix::{apply_fixes, fix_source},
+    grammar::{ebnf, markdown},
+    history::PaceHistory,
+    json::{validate_json, SCHEMA_VERSION},
+    lanes::LaneBoard,
+    lexer::{contextual, Token},
+    library::{DirectoryStorage, Library, Search, Storage, NEAR_DUPLICATE},
+    lint::lint,
+    log::TrainingLog,
+    pace::{CssModel, Fatigue, PaceModel},
+    parser::{Parser, ParserOptions},
+    path::SetPath,
+    plan::WeekPlan,
+    pool::Course,
+    preset::Preset,
+    profile::SwimmerProfile,
+    query::Query,
+    reconcile::reconcile,
+    resolve::{
+        resolve_base_intervals, resolve_intensities, resolve_race_paces, BaseIntervals, Rounding,
+    },
+    steps::{Exporter, JsonExporter, Step, WorkoutSteps},
+    summary::{JsonFormatter, MarkdownFormatter, Summary, SummaryFormatter, TextFormatter},
+    timeline::{parse_clock_time, section_starts, timeline},
+    tree::Tree,
+};
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
-    if args.len() != 2 {
-        eprintln!("Usage: {} <file>", args[0]);
+    if args.get(1).is_some_and(|command| command == "stats") {
+        stats(&args[2..]);
+        return;
+    }
+    if args.get(1).is_some_and(|command| command == "corpus") {
+        corpus(&args[2..]);
+        return;
+    }
+    if args.get(1).is_some_and(|command| command == "grammar") {
+        grammar(&args[2..]);
+        return;
+    }
+
+    if args.len() < 3 {
+        eprintln!(
+            "Usage: {} <analyse|show|export|reconcile|record|merge|split|check|expand|explain|schedule|share|fix|tokens|validate-export> <file> [<actual.fit>|<file>...] [--set <number>] [--only <section>] [--sets <from>..<to>] [--stroke <stroke>] [--strict] [--deny-warnings] [--config <file>] [--preset <senior|masters|age-group-10u>] [--profile <file>] [--format json|ast|chat|text|markdown] [--limit <chars>] [--from json|csv|notes] [--define <name>[=true|false]] [--scrub] [--swimmer] [--seed <seed>] [--tree] [--lanes] [--round <exact|nearest-5|up-5>] [--snap <scm|lcm|33m>] [--json] [--plan] [-o <file|dir>] [--by section] [--duration <90min|1h30>] [--start <6:30 AM>] [--pace <1:35>] [--suggest-intervals [--dir <dir>]] [--output github|sarif] [--write]",
+            args[0]
+        );
+        eprintln!(
+            "       {} lib <duplicates|similar <file>|nearest <file>|search|tags|templatize <file>> [--dir <dir>] [--index <library.db>] [--threshold <0-1>] [--count <n>] [--tag <tag>] [--min-distance <m>] [--max-distance <m>] [-o <file>]",
+            args[0]
+        );
+        eprintln!(
+            "       {} schedule <plan.toml> --plan --start <2024-06-03> [--weeks <n>] [--format ics] [-o <dir|file>] [--dir <dir>] [--index <library.db>]",
+            args[0]
+        );
+        eprintln!(
+            "       {} stats [--dir <dir>] [--goals <season.toml>]",
+            args[0]
+        );
+        eprintln!("       {} open <code> [-o <file>]", args[0]);
+        eprintln!("       {} grammar [--format markdown|ebnf]", args[0]);
+        eprintln!("       {} corpus [--dir <dir>] [--bless]", args[0]);
+        std::process::exit(1);
+    }
+
+    if args[1] == "lib" {
+        library(&args[2], &args[3..]);
+        return;
+    }
+    if args[1] == "open" {
+        open(&args[2], &args[3..]);
+        return;
+    }
+    if args[1] == "export" && flag(&args[3..], "--plan") {
+        export_plan(&args[2], &args[3..]);
+        return;
+    }
+    if args[1] == "schedule" && flag(&args[3..], "--plan") {
+        schedule_plan(&args[2], &args[3..]);
+        return;
+    }
+
+    let set_file = std::fs::read_to_string(&args[2]).unwrap();
+    let options = &args[3..];
+
+    match args[1].as_str() {
+        "analyse" => analyse(&set_file, options),
+        "show" => show(&set_file, options),
+        "export" => export(&set_file, options),
+        "reconcile" => reconcile_with(&set_file, options),
+        "record" => record(&args[2], &set_file, options),
+        "merge" => merge(&set_file, options),
+        "split" => split(&args[2], &set_file, options),
+        "check" => check_feasibility(&args[2], &set_file, options),
+        "expand" => expand(&set_file, options),
+        "explain" => explain_workout(&set_file, options),
+        "schedule" => schedule(&set_file, options),
+        "share" => println!("{}", parse_unresolved(&set_file, options).share_code()),
+        "fix" => fix(&args[2], &set_file, options),
+        "tokens" => tokens(&set_file),
+        "validate-export" => validate_export(&args[2], &set_file),
+        command => {
+            eprintln!("Unknown command: {}", command);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse(set_file: &str, options: &[String], profile: Option<&SwimmerProfile>) -> Workout {
+    let mut workout = parse_unresolved(set_file, options);
+    if let Err(errors) = resolve(&mut workout, options, profile) {
+        exit_with_errors(errors);
+    }
+    workout
+}
+
+/// Resolves `@base` intervals, from the workout's header or the profile,
+/// race paces and the intensities statements inherit from their blocks
+fn resolve(
+    workout: &mut Workout,
+    options: &[String],
+    profile: Option<&SwimmerProfile>,
+) -> Result<(), Vec<String>> {
+    let mut bases = profile
+        .map(SwimmerProfile::base_intervals)
+        .unwrap_or_default();
+    let declared = BaseIntervals::from_metadata(&workout.metadata).map_err(|error| vec![error])?;
+    bases.default = declared.default.or(bases.default);
+    bases.strokes.extend(declared.strokes);
+    bases.rounding = rounding(options).unwrap_or_default();
+    resolve_base_intervals(workout, &bases)?;
+    let goals = profile
+        .map(|profile| profile.goals.clone())
+        .unwrap_or_default();
+    resolve_race_paces(workout, &goals)?;
+    resolve_intensities(workout);
+    Ok(())
+}
+
+/// Parses the workout as written, leaving `@base` intervals in place
+///
+/// With `--from json`, the file is a workout exported with `--format ast`;
+/// with `--from csv`, a spreadsheet of distance, stroke, interval and reps;
+/// with `--from notes`, plain-text practice notes, read as well as they can be.
+fn parse_unresolved(set_file: &str, options: &[String]) -> Workout {
+    match option(options, "--from") {
+        Some("json") => {
+            return Workout::from_json(set_file).unwrap_or_else(|errors| exit_with_errors(errors))
+        }
+        Some("csv") => {
+            return Workout::from_csv(set_file).unwrap_or_else(|errors| exit_with_errors(errors))
+        }
+        Some("notes") => {
+            let import = Workout::from_notes(set_file);
+            report(&import.diagnostics(), "imported", options);
+            eprintln!("import confidence: {:.0}%", import.confidence() * 100.0);
+            return import.workout;
+        }
+        _ => {}
+    }
+    let parser_options = parser_options(options);
+    let mut parser = Parser::with_options(Token::lexer(set_file), parser_options);
+    let workout = parser
+        .parse()
+        .unwrap_or_else(|errors| exit_with_errors(errors));
+    report(parser.diagnostics(), "parsed", options);
+    workout
+}
+
+/// Prints warnings and notes with a count of each, exiting if there are
+/// warnings and `--deny-warnings` is given
+fn report(diagnostics: &Diagnostics, done: &str, options: &[String]) {
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+    if let Some(summary) = diagnostics.summary() {
+        eprintln!("{} with {}", done, summary);
+    }
+    if flag(options, "--deny-warnings") && diagnostics.at_least(Severity::Warning).next().is_some()
+    {
+        std::process::exit(1);
+    }
+}
+
+/// Applies the suggested fixes for missing units, misspelled strokes and
+/// unclosed braces, writing the file back with `--write` or else printing
+/// the fixed workout
+fn fix(path: &str, set_file: &str, options: &[String]) {
+    let parser_options = parser_options(options);
+    let (fixed, applied) = fix_source(set_file, &parser_options);
+
+    if flag(options, "--write") {
+        if applied > 0 {
+            std::fs::write(path, &fixed)
+                .unwrap_or_else(|error| exit_with_errors(vec![format!("{}: {}", path, error)]));
+        }
+    } else {
+        print!("{}", fixed);
+    }
+    let plural = if applied == 1 { "" } else { "es" };
+    eprintln!("applied {} fix{}", applied, plural);
+
+    let mut parser = Parser::with_options(Token::lexer(&fixed), parser_options);
+    if let Err(errors) = parser.parse() {
+        exit_with_errors(errors);
+    }
+    report(parser.diagnostics(), "fixed", options);
+}
+
+/// Parser options from `--strict`, the parameters and the configured drills
+fn parser_options(options: &[String]) -> ParserOptions {
+    ParserOptions {
+        strict_newlines: flag(options, "--strict"),
+        parameters: parameters(options),
+        drills: load_config(options).drill_dictionary(),
+        ..ParserOptions::default()
+    }
+}
+
+/// Parameters for `if` blocks: the configured ones, overridden by each
+/// `--define <name>[=true|false]`
+fn parameters(options: &[String]) -> BTreeMap<String, bool> {
+    let mut parameters = load_config(options).parameters;
+    let defines = options
+        .windows(2)
+        .filter(|pair| pair[0] == "--define")
+        .map(|pair| pair[1].as_str());
+    for define in defines {
+        let (name, value) = define.split_once('=').unwrap_or((define, "true"));
+        let value = value.parse().unwrap_or_else(|_| {
+            exit_with_errors(vec![format!(
+                "invalid value `{}` for `{}`, expected true or false",
+                value, name
+            )])
+        });
+        parameters.insert(name.to_string(), value);
+    }
+    parameters
+}
+
+fn exit_with_errors<E: std::fmt::Display>(errors: Vec<E>) -> ! {
+    for error in errors {
+        eprintln!("error: {}", error);
+    }
+    std::process::exit(1);
+}
+
+fn analyse(set_file: &str, options: &[String]) {
+    let profile = load_profile(options);
+    let workout = parse(set_file, options, profile.as_ref());
+
+    let config = load_config(options);
+    let model = profile.as_ref().and_then(SwimmerProfile::pace_model);
+    let model = model.as_ref().map(|model| model as &dyn PaceModel);
+    let fatigue = profile.as_ref().and_then(SwimmerProfile::fatigue);
+
+    if let Some(query) = query(options) {
+        report_totals(
+            &query.select(&workout),
+            &config,
+            model,
+            fatigue.as_ref(),
+            options,
+        );
+        return;
+    }
+
+    report_totals(&workout, &config, model, fatigue.as_ref(), options);
+    if option(options, "--format").is_some_and(|format| format != "text") {
+        return;
+    }
+
+    let goals = profile.as_ref().map(|profile| &profile.goals);
+    if let Some(goals) = goals.filter(|goals| !goals.events.is_empty()) {
+        println!("Race pace:");
+        for ((stroke, event), goal) in &goals.events {
+            let split = |meters| goals.split(stroke, *event, meters).unwrap_or_default();
+            println!(
+                "  {}m {} in {}: {:.1}s per 25, {:.1}s per 50",
+                event,
+                stroke,
+                format_time(*goal),
+                split(25),
+                split(50)
+            );
+        }
+    }
+
+    println!("Sets:");
+    let width = terminal_width();
+    let drills = config.drill_dictionary();
+    let sections = section_names(&workout);
+    for (path, set) in workout.numbered_sets() {
+        let mut line = format!(
+            "  {:<8}{} ({}m",
+            path.to_string(),
+            describe(set),
+            set.total_distance()
+        );
+        if let Some(kind) = set.kind(&sections[path.indices()[0]], &drills) {
+            line += &format!(", {}", kind);
+        }
+        line.push(')');
+        if let Some(stmt) = set.statement() {
+            if let Some(pace) = stmt.pace_per_100() {
+                line += &format!(" pace {}/100", format_time(pace));
+                let side = profile
+                    .as_ref()
+                    .and_then(|profile| profile.css_side(&stmt.stroke.name, pace));
+                if let Some(side) = side {
+                    line += &format!(", {}", side);
+                }
+            }
+        }
+        match width {
+            Some(width) => println!("{}", truncate(&line, width)),
+            None => println!("{}", line),
+        }
+    }
+
+    let total = workout.total_distance().max(1);
+    for (path, set) in key_sets(&workout) {
+        print!(
+            "Key set {}: {}m ({}% of the distance)",
+            path,
+            set.total_distance(),
+            set.total_distance() * 100 / total
+        );
+        if let Some(intensity) = set.peak_intensity() {
+            print!(", up to {}", intensity);
+        }
+        println!();
+    }
+
+    let sections = detect_sections(&workout);
+    if let Some(warmup) = sections.warmup {
+        println!("Likely warmup: {}", format_range(warmup));
+    }
+    if let Some(cooldown) = sections.cooldown {
+        println!("Likely cooldown: {}", format_range(cooldown));
+    }
+
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.extend(
+        lint(&workout, &config, model)
+            .into_iter()
+            .map(Diagnostic::from),
+    );
+    report(&diagnostics, "analysed", options);
+}
+
+/// Prints the workout's summary in the `--format` given, text by default
+fn report_totals(
+    workout: &Workout,
+    config: &Config,
+    model: Option<&dyn PaceModel>,
+    fatigue: Option<&Fatigue>,
+    options: &[String],
+) {
+    let formatter: Box<dyn SummaryFormatter> = match option(options, "--format").unwrap_or("text") {
+        "text" => Box::new(TextFormatter),
+        "json" => Box::new(JsonFormatter),
+        "markdown" => Box::new(MarkdownFormatter),
+        format => {
+            eprintln!("error: unknown summary format `{}`", format);
+            std::process::exit(1);
+        }
+    };
+    let summary = Summary::new(workout, &config.drill_dictionary(), model, fatigue);
+    formatter
+        .format(&summary, &mut std::io::stdout())
+        .unwrap_or_else(|error| exit_with_errors(vec![error]));
+}
+
+/// Config from `--config <file>`, or `swim.toml` in the current directory if
+/// it exists, with the `--preset` option applied
+fn load_config(options: &[String]) -> Config {
+    let path = match option(options, "--config") {
+        Some(path) => Some(std::path::PathBuf::from(path)),
+        None => Some(std::path::PathBuf::from("swim.toml")).filter(|path| path.exists()),
+    };
+    let mut config = match path {
+        Some(path) => Config::load(&path).unwrap_or_else(|error| exit_with_errors(vec![error])),
+        None => Config::default(),
+    };
+    if let Some(preset) = option(options, "--preset") {
+        config.preset = Some(
+            preset
+                .parse()
+                .unwrap_or_else(|error| exit_with_errors(vec![error])),
+        );
+    }
+    config
+}
+
+/// Swimmer profile from `--profile <file>`, if given
+fn load_profile(options: &[String]) -> Option<SwimmerProfile> {
+    let path = option(options, "--profile")?;
+    Some(
+        SwimmerProfile::load(std::path::Path::new(path))
+            .unwrap_or_else(|error| exit_with_errors(vec![error])),
+    )
+}
+
+/// Selection given by the `--only`, `--sets` and `--stroke` options, if any
+fn query(options: &[String]) -> Option<Query> {
+    let mut query = Query::new();
+    let mut selected = false;
+
+    if let Some(section) = option(options, "--only") {
+        query = query.section(section);
+        selected = true;
+    }
+    if let Some(sets) = option(options, "--sets") {
+        let (from, to) = sets.split_once("..").unwrap_or((sets, sets));
+        match (from.parse(), to.parse()) {
+            (Ok(from), Ok(to)) => query = query.sets(from..=to),
+            _ => {
+                eprintln!("error: invalid set range `{}`", sets);
+                std::process::exit(1);
+            }
+        }
+        selected = true;
+    }
+    if let Some(stroke) = option(options, "--stroke") {
+        query = query.stroke(stroke);
+        selected = true;
+    }
+
+    selected.then_some(query)
+}
+
+fn show(set_file: &str, options: &[String]) {
+    let profile = load_profile(options);
+    let workout = shuffle(parse(set_file, options, profile.as_ref()), options);
+    let mut workout = if flag(options, "--swimmer") {
+        workout.swimmer_view()
+    } else {
+        workout
+    };
+    if let Some(course) = snap_course(options) {
+        for adjustment in workout.snap_to_pool(course) {
+            eprintln!("note: {} for a {}", adjustment, course);
+        }
+    }
+
+    match option(options, "--set") {
+        Some(number) => {
+            let path: SetPath = number.parse().unwrap_or_else(|error| {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            });
+            match workout.get(&path) {
+                Some(set) => print!("{}", fit_to_terminal(&format!("{}\n", set), 0)),
+                None => {
+                    eprintln!("error: workout has no set {}", path);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None if flag(options, "--lanes") => {
+            let lanes = load_config(options).lanes;
+            if lanes.is_empty() {
+                exit_with_errors(vec![
+                    "no lanes configured; add [lanes.<name>] tables to the config".to_string(),
+                ]);
+            }
+            let course = snap_course(options).unwrap_or(Course::Short);
+            let rounding = rounding(options).unwrap_or(Rounding::Nearest(5));
+            print!("{}", LaneBoard::new(&workout, &lanes, course, rounding));
+        }
+        None if flag(options, "--tree") => print!("{}", Tree(&workout)),
+        None => print!(
+            "{}",
+            fit_to_terminal(&workout.to_string(), workout.metadata.entries.len())
+        ),
+    }
+}
+
+/// Rounding of computed send-offs from `--round <policy>` or the config, if
+/// either sets one
+fn rounding(options: &[String]) -> Option<Rounding> {
+    match option(options, "--round") {
+        Some(policy) => Some(
+            policy
+                .parse()
+                .unwrap_or_else(|error| exit_with_errors(vec![error])),
+        ),
+        None => load_config(options).rounding,
+    }
+}
+
+/// Pool of `--snap <scm|lcm|33m>`, if given
+fn snap_course(options: &[String]) -> Option<Course> {
+    let pool = option(options, "--snap")?;
+    Some(
+        pool.parse()
+            .unwrap_or_else(|error| exit_with_errors(vec![error])),
+    )
+}
+
+/// Checks a JSON workout against the export schema before it is imported
+fn validate_export(path: &str, json: &str) {
+    if let Err(errors) = validate_json(json) {
+        exit_with_errors(errors);
+    }
+    eprintln!("{}: valid, schema version {}", path, SCHEMA_VERSION);
+}
+
+/// Workout with its main set shuffled by `--seed <seed>` (or
+/// `--shuffle <seed>`), if given
+fn shuffle(workout: Workout, options: &[String]) -> Workout {
+    let Some(seed) = option(options, "--seed").or_else(|| option(options, "--shuffle")) else {
+        return workout;
+    };
+    let seed = seed.parse().unwrap_or_else(|_| {
+        exit_with_errors(vec![format!(
+            "invalid seed `{}`, expected a whole number",
+            seed
+        )])
+    });
+    workout.shuffled(seed)
+}
+
+/// Exports the workout, or only the part picked with `--only`, `--sets` or
+/// `--stroke`, e.g., just the main set for a watch
+fn export(set_file: &str, options: &[String]) {
+    let profile = load_profile(options);
+    let workout = shuffle(parse(set_file, options, profile.as_ref()), options);
+
+    let workout = if flag(options, "--scrub") {
+        workout.scrubbed()
+    } else {
+        workout
+    };
+    let workout = match query(options) {
+        Some(query) => query.select(&workout),
+        None => workout,
+    };
+    if workout.sets.is_empty() {
+        exit_with_errors(vec!["no sets to export".to_string()]);
+    }
+
+    let exporter: Box<dyn Exporter> = match option(options, "--format").unwrap_or("json") {
+        "json" => Box::new(JsonExporter),
+        "ast" => {
+            println!("{}", workout.to_json());
+            return;
+        }
+        "chat" => {
+            let limit = match option(options, "--limit") {
+                Some(limit) => limit.parse().unwrap_or_else(|_| {
+                    exit_with_errors(vec![format!("invalid message limit `{}`", limit)])
+                }),
+                None => DISCORD_LIMIT,
+            };
+            let messages = chat_messages(&workout, limit)
+                .unwrap_or_else(|error| exit_with_errors(vec![error]));
+            for message in messages {
+                println!("{}\n", message);
+            }
+            return;
+        }
+        format => {
+            eprintln!("error: unknown export format `{}`", format);
+            std::process::exit(1);
+        }
+    };
+    exporter
+        .export(
+            &WorkoutSteps::from_workout(&workout),
+            &mut std::io::stdout(),
+        )
+        .unwrap_or_else(|error| exit_with_errors(vec![error]));
+}
+
+/// Exports every workout of a weekly plan into the `-o` directory, one file
+/// per day, with `schedule.json` giving the day and date of each
+fn export_plan(file: &str, options: &[String]) {
+    let Some(dir) = option(options, "-o") else {
+        exit_with_errors(vec!["export --plan needs -o <dir>".to_string()]);
+    };
+    let dir = std::path::Path::new(dir);
+    let plan = WeekPlan::load(file.as_ref()).unwrap_or_else(|error| exit_with_errors(vec![error]));
+    let profile = load_profile(options);
+
+    let workouts: Vec<Workout> = plan
+        .days
+        .iter()
+        .map(|(_, path)| {
+            let source = std::fs::read_to_string(path).unwrap_or_else(|error| {
+                exit_with_errors(vec![format!("{}: {}", path.display(), error)])
+            });
+            parse(&source, options, profile.as_ref())
+        })
+        .collect();
+    let schedule = plan.schedule(&workouts);
+
+    let write = |name: &str, contents: &[u8]| {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap_or_else(|error| {
+            exit_with_errors(vec![format!("{}: {}", path.display(), error)])
+        });
+        println!("{}", path.display());
+    };
+    for (day, workout) in schedule.iter().zip(&workouts) {
+        let mut out = Vec::new();
+        JsonExporter
+            .export(&WorkoutSteps::from_workout(workout), &mut out)
+            .expect("writing to memory");
+        write(&day.file, &out);
+    }
+    let schedule = serde_json::to_string_pretty(&schedule).expect("schedules always serialize");
+    write("schedule.json", format!("{}\n", schedule).as_bytes());
+}
+
+/// Writes the times swum, from the FIT file given as the first option, into
+/// the workout's `TT __:__` placeholders
+fn record(path: &str, set_file: &str, options: &[String]) {
+    let Some(actual) = options.first() else {
+        eprintln!("error: record needs the FIT file of the swim");
+        std::process::exit(1);
+    };
+    let laps = load_laps(std::path::Path::new(actual))
+        .unwrap_or_else(|error| exit_with_errors(vec![error]));
+    let parser_options = parser_options(options);
+    let mut parser = Parser::with_options(Token::lexer(set_file), parser_options);
+    let workout = parser
+        .parse()
+        .unwrap_or_else(|errors| exit_with_errors(errors));
+    let report = reconcile(&WorkoutSteps::from_workout(&workout), &laps);
+    let (recorded, applied) = apply_fixes(set_file, &report.trial_results(parser.placeholders()));
+
+    if flag(options, "--write") {
+        if applied > 0 {
+            std::fs::write(path, &recorded)
+                .unwrap_or_else(|error| exit_with_errors(vec![format!("{}: {}", path, error)]));
+        }
+    } else {
+        print!("{}", recorded);
+    }
+    let trials = parser.placeholders().len();
+    eprintln!(
+        "recorded {} of {} time trial{}",
+        applied,
+        trials,
+        if trials == 1 { "" } else { "s" }
+    );
+}
+
+/// Compares the workout with the laps of the FIT file given as the first option
+fn reconcile_with(set_file: &str, options: &[String]) {
+    let Some(actual) = options.first() else {
+        eprintln!("error: reconcile needs the FIT file of the swim");
+        std::process::exit(1);
+    };
+    let laps = load_laps(std::path::Path::new(actual))
+        .unwrap_or_else(|error| exit_with_errors(vec![error]));
+    let profile = load_profile(options);
+    let workout = parse(set_file, options, profile.as_ref());
+    let report = reconcile(&WorkoutSteps::from_workout(&workout), &laps);
+
+    if flag(options, "--json") {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        return;
+    }
+
+    for set in &report.sets {
+        print!(
+            "  {:<8}{:<8}{}/{} reps, {}m of {}m",
+            set.path.to_string(),
+            set.stroke,
+            set.swum_reps,
+            set.planned_reps,
+            set.swum_distance,
+            set.planned_distance
+        );
+        match (set.achieved, set.target) {
+            (Some(achieved), Some(target)) => {
+                print!(", {} on {}", format_time(achieved), format_time(target))
+            }
+            (Some(achieved), None) => print!(", {}", format_time(achieved)),
+            _ => {}
+        }
+        if let Some(met) = set.targets_met {
+            print!(", {}/{} targets met", met, set.swum_reps);
+        }
+        if set.skipped() {
+            print!(" (skipped)");
+        }
+        println!();
+    }
+    if !report.extra_laps.is_empty() {
+        let laps: Vec<String> = report
+            .extra_laps
+            .iter()
+            .map(|meters| format!("{}m", meters))
+            .collect();
+        println!("Extra laps: {}", laps.join(", "));
+    }
+}
+
+/// Storage of `--dir`, the configured library or the current directory,
+/// through the `--index` or configured index if one is given
+fn library_storage(options: &[String], config: &Config) -> Box<dyn Storage> {
+    let dir = option(options, "--dir")
+        .map(std::path::PathBuf::from)
+        .or_else(|| config.library.clone())
+        .unwrap_or_else(|| ".".into());
+    let index = option(options, "--index")
+        .map(std::path::PathBuf::from)
+        .or_else(|| config.library_index.clone());
+    match index {
+        #[cfg(feature = "sqlite")]
+        Some(index) => Box::new(
+            swim_parser::sqlite::SqliteStorage::open(&index, &dir)
+                .unwrap_or_else(|error| exit_with_errors(vec![error])),
+        ),
+        #[cfg(not(feature = "sqlite"))]
+        Some(index) => {
+            eprintln!(
+                "warning: ignoring index {}; swim was built without the `sqlite` feature",
+                index.display()
+            );
+            Box::new(DirectoryStorage::new(dir))
+        }
+        None => Box::new(DirectoryStorage::new(dir)),
+    }
+}
+
+/// Prints warnings for the library files a command skipped
+fn warn_skipped(errors: Vec<String>) {
+    for error in errors {
+        eprintln!("warning: skipping {}", error);
+    }
+}
+
+/// Library of `--dir`, the configured library or the current directory
+fn load_library(options: &[String], config: &Config) -> Library {
+    load_from(&*library_storage(options, config))
+}
+
+/// Every workout in `storage`, warning about the files skipped
+fn load_from(storage: &dyn Storage) -> Library {
+    let (library, errors) = storage.load();
+    warn_skipped(errors);
+    library
+}
+
+/// Library commands, over `--dir`, the configured library or the current directory
+fn library(command: &str, options: &[String]) {
+    if command == "templatize" {
+        templatize(options);
+        return;
+    }
+    let storage = library_storage(options, &load_config(options));
+    let threshold = match option(options, "--threshold") {
+        Some(threshold) => threshold.parse().unwrap_or_else(|_| {
+            eprintln!("error: invalid threshold `{}`", threshold);
+            std::process::exit(1);
+        }),
+        None => NEAR_DUPLICATE,
+    };
+
+    match command {
+        "duplicates" => {
+            let library = load_from(&*storage);
+            for duplicate in library.duplicates(threshold) {
+                println!(
+                    "{:>3.0}%  {}  {}",
+                    duplicate.similarity * 100.0,
+                    library.entries[duplicate.first].path.display(),
+                    library.entries[duplicate.second].path.display()
+                );
+            }
+        }
+        "similar" => {
+            let Some(file) = options.first() else {
+                eprintln!("error: lib similar needs a workout file");
+                std::process::exit(1);
+            };
+            let source = std::fs::read_to_string(file).unwrap();
+            let workout = parse(&source, options, None);
+            let library = load_from(&*storage);
+            for (entry, similarity) in library.similar_to(&workout, threshold) {
+                let date = entry
+                    .date
+                    .map_or("undated".to_string(), |date| date.to_string());
+                println!(
+                    "{:>3.0}%  {}  {}",
+                    similarity * 100.0,
+                    date,
+                    entry.path.display()
+                );
+            }
+        }
+        "nearest" => {
+            let Some(file) = options.first() else {
+                eprintln!("error: lib nearest needs a workout file");
+                std::process::exit(1);
+            };
+            let count = match option(options, "--count") {
+                Some(count) => count.parse().unwrap_or_else(|_| {
+                    eprintln!("error: invalid count `{}`", count);
+                    std::process::exit(1);
+                }),
+                None => 5,
+            };
+            let source = std::fs::read_to_string(file).unwrap();
+            let workout = parse(&source, options, None);
+            let library = load_from(&*storage);
+            for (entry, distance) in library.nearest(&workout, count) {
+                println!(
+                    "{:>5.2}  {}  {}",
+                    distance,
+                    entry.workout.metadata.get("title").unwrap_or("untitled"),
+                    entry.path.display()
+                );
+            }
+        }
+        "search" => {
+            let (entries, errors) = storage.search(&search(options));
+            warn_skipped(errors);
+            for entry in entries {
+                let workout = &entry.workout;
+                println!(
+                    "{}  {}m  {}  {}{}",
+                    entry.path.display(),
+                    workout.total_distance(),
+                    format_time(workout.total_time()),
+                    workout.metadata.get("title").unwrap_or("untitled"),
+                    format_tags(&workout.metadata.tags())
+                );
+                if let Some(set) = main_set(workout) {
+                    println!("    {}", set.summary());
+                }
+            }
+        }
+        "tags" => {
+            let (tags, errors) = storage.tags();
+            warn_skipped(errors);
+            for (tag, count) in tags {
+                println!("{}: {}", tag, count);
+            }
+        }
+        command => {
+            eprintln!("Unknown library command: {}", command);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Prints the skeleton of a workout as a template, or writes it to the `-o` file
+fn templatize(options: &[String]) {
+    let Some(file) = options.first() else {
+        eprintln!("error: lib templatize needs a workout file");
+        std::process::exit(1);
+    };
+    let source = std::fs::read_to_string(file).unwrap();
+    let skeleton = parse_unresolved(&source, options).skeleton();
+    match option(options, "-o") {
+        Some(path) => std::fs::write(path, skeleton.to_string())
+            .unwrap_or_else(|error| exit_with_errors(vec![format!("{}: {}", path, error)])),
+        None => print!("{}", skeleton),
+    }
+}
+
+/// Appends the workouts named before the first option, writing to `-o` or stdout
+fn merge(set_file: &str, options: &[String]) {
+    let mut workout = parse_unresolved(set_file, options);
+    for file in options.iter().take_while(|option| !option.starts_with('-')) {
+        let source = std::fs::read_to_string(file).unwrap_or_else(|error| {
+            exit_with_errors(vec![format!("{}: {}", file, error)]);
+        });
+        if let Err(errors) = workout.concat(parse_unresolved(&source, options)) {
+            exit_with_errors(errors);
+        }
+    }
+
+    match option(options, "-o") {
+        Some(path) => std::fs::write(path, workout.to_string())
+            .unwrap_or_else(|error| exit_with_errors(vec![format!("{}: {}", path, error)])),
+        None if flag(options, "--lanes") => {
+            let lanes = load_config(options).lanes;
+            if lanes.is_empty() {
+                exit_with_errors(vec![
+                    "no lanes configured; add [lanes.<name>] tables to the config".to_string(),
+                ]);
+            }
+            let course = snap_course(options).unwrap_or(Course::Short);
+            let rounding = rounding(options).unwrap_or(Rounding::Nearest(5));
+            print!("{}", LaneBoard::new(&workout, &lanes, course, rounding));
+        }
+        None if flag(options, "--tree") => print!("{}", Tree(&workout)),
+        None => print!("{}", workout),
+    }
+}
+
+/// Weekly volume and intensity mix of the dated workouts in the library,
+/// against the goals of `--goals <file>` or the config's `[goals]`
+fn stats(options: &[String]) {
+    let config = load_config(options);
+    let library = load_library(options, &config);
+    let drills = config.drill_dictionary();
+    let goals = match option(options, "--goals") {
+        Some(path) => {
+            SeasonGoals::load(path.as_ref()).unwrap_or_else(|error| exit_with_errors(vec![error]))
+        }
+        None => config.goals,
+    };
+
+    let mut log = TrainingLog::new();
+    for entry in library.entries {
+        match entry.date {
+            Some(date) => log.push(date, entry.workout),
+            None => eprintln!("warning: skipping {}: no `date`", entry.path.display()),
+        }
+    }
+
+    let progress = log.progress(&goals);
+    for week in &progress {
+        println!("{}", week);
+    }
+    if goals != SeasonGoals::default() {
+        let on_plan = progress.iter().filter(|week| week.on_plan()).count();
+        println!("On plan in {} of {} weeks", on_plan, progress.len());
+    }
+
+    let kinds = log.distance_by_kind(&drills);
+    let total: u32 = kinds.values().sum();
+    if !kinds.is_empty() {
+        println!("Set types:");
+        for (kind, meters) in &kinds {
+            let percent = (meters * 100).checked_div(total).unwrap_or(0);
+            println!("  {}: {}m ({}%)", kind, meters, percent);
+        }
+    }
+}
+
+/// Prints the workout of a share code, or writes it to the `-o` file
+fn open(code: &str, options: &[String]) {
+    let workout = Workout::from_share_code(code).unwrap_or_else(|errors| exit_with_errors(errors));
+    match option(options, "-o") {
+        Some(path) => std::fs::write(path, workout.to_string())
+            .unwrap_or_else(|error| exit_with_errors(vec![format!("{}: {}", path, error)])),
+        None => print!("{}", workout),
+    }
+}
+
+/// Splits the workout into sections, writing each to a file in the `-o`
+/// directory or printing them one after another
+fn split(file: &str, set_file: &str, options: &[String]) {
+    match option(options, "--by").unwrap_or("section") {
+        "section" => {}
+        by => {
+            eprintln!("error: can't split by `{}`", by);
+            std::process::exit(1);
+        }
+    }
+    let workout = parse_unresolved(set_file, options);
+    let stem = std::path::Path::new(file)
+        .file_stem()
+        .map_or("workout".into(), |stem| stem.to_string_lossy());
+
+    for (name, part) in workout.split_sections() {
+        match option(options, "-o") {
+            Some(dir) => {
+                let path = std::path::Path::new(dir).join(format!("{}-{}.swim", stem, name));
+                std::fs::write(&path, part.to_string()).unwrap_or_else(|error| {
+                    exit_with_errors(vec![format!("{}: {}", path.display(), error)])
+                });
+                println!("{}", path.display());
+            }
+            None => print!("// {}\n{}\n", name, part),
+        }
+    }
+}
+
+/// Wall-clock start of each section from the workout's `start` metadata or
+/// `--start`, e.g., for a meet warmup with a hard start
+fn schedule(set_file: &str, options: &[String]) {
+    let profile = load_profile(options);
+    let workout = parse(set_file, options, profile.as_ref());
+    let model = profile.as_ref().and_then(SwimmerProfile::pace_model);
+
+    let Some(text) = option(options, "--start").or_else(|| workout.metadata.get("start")) else {
+        exit_with_errors(vec![
+            "no start time; add `start: 6:30 AM` to the metadata or pass --start".to_string(),
+        ]);
+    };
+    let Some(start) = parse_clock_time(text) else {
+        exit_with_errors(vec![format!("invalid start time `{}`", text)]);
+    };
+    let at = |offset: std::time::Duration| {
+        let offset = chrono::Duration::from_std(offset).unwrap_or_default();
+        (start + offset).format("%-I:%M %p")
+    };
+
+    if let Some(date) = workout
+        .metadata
+        .get("date")
+        .and_then(|date| date.parse::<chrono::NaiveDate>().ok())
+    {
+        println!("{}", date.format("%A %-d %B %Y"));
+    }
+    let (sections, end) = section_starts(
+        &workout,
+        model.as_ref().map(|model| model as &dyn PaceModel),
+    );
+    for section in sections {
+        println!("{} starts {}", section.name, at(section.start));
+    }
+    println!("Finishes {}", at(end));
+}
+
+/// Lays the weekly plan out on the calendar for `--weeks` weeks from `--start`,
+/// skipping the configured rest days. Dated copies of the workouts are
+/// written to `-o` or the library, whose index is brought up to date, or
+/// with `--format ics` one iCalendar file to `-o` or stdout.
+fn schedule_plan(path: &str, options: &[String]) {
+    let Some(text) = option(options, "--start") else {
+        exit_with_errors(vec!["no start date; pass --start <2024-06-03>".to_string()]);
+    };
+    let start = text
+        .parse::<chrono::NaiveDate>()
+        .unwrap_or_else(|_| exit_with_errors(vec![format!("invalid start date `{}`", text)]));
+    let config = load_config(options);
+    let plan = WeekPlan::load(path.as_ref()).unwrap_or_else(|error| exit_with_errors(vec![error]));
+    let weeks = match option(options, "--weeks") {
+        Some(weeks) => weeks.parse().unwrap_or_else(|_| {
+            exit_with_errors(vec![format!("invalid number of weeks `{}`", weeks)])
+        }),
+        None => 1,
+    };
+    let days = calendar(&plan, start, weeks, &config.rest_days)
+        .unwrap_or_else(|error| exit_with_errors(vec![error]));
+    let profile = load_profile(options);
+    let sources: Vec<String> = days
+        .iter()
+        .map(|day| {
+            std::fs::read_to_string(&day.path).unwrap_or_else(|error| {
+                exit_with_errors(vec![format!("{}: {}", day.path.display(), error)])
+            })
+        })
+        .collect();
+    let workouts: Vec<Workout> = sources
+        .iter()
+        .map(|source| parse(source, options, profile.as_ref()))
+        .collect();
+
+    if option(options, "--format") == Some("ics") {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        let stamp = chrono::DateTime::from_timestamp(now.as_secs() as i64, 0)
+            .unwrap_or_default()
+            .naive_utc();
+        let mut out = Vec::new();
+        write_ics(&days, &workouts, stamp, &mut out).expect("writing to memory");
+        match option(options, "-o") {
+            Some(file) => std::fs::write(file, out)
+                .unwrap_or_else(|error| exit_with_errors(vec![format!("{}: {}", file, error)])),
+            None => print!("{}", String::from_utf8_lossy(&out)),
+        }
+        return;
+    }
+
+    let library = option(options, "--dir")
+        .map(std::path::PathBuf::from)
+        .or_else(|| config.library.clone())
+        .unwrap_or_else(|| ".".into());
+    let dir = option(options, "-o").map_or(library.clone(), std::path::PathBuf::from);
+    for (day, source) in days.iter().zip(&sources) {
+        let path = dir.join(dated_file_name(day.date, &day.path));
+        std::fs::write(&path, with_date(source, day.date)).unwrap_or_else(|error| {
+            exit_with_errors(vec![format!("{}: {}", path.display(), error)])
+        });
+        println!("{}", path.display());
+    }
+    if dir.canonicalize().ok() == library.canonicalize().ok() {
+        warn_skipped(library_storage(options, &config).refresh());
+    }
+}
+
+/// Prints each line of the workout with what it means, for swimmers new to
+/// written sets; rests are worked out at the `--pace` per 100m given, or the
+/// profile's
+fn explain_workout(set_file: &str, options: &[String]) {
+    let profile = load_profile(options);
+    let workout = parse(set_file, options, profile.as_ref());
+    let model = match option(options, "--pace") {
+        Some(pace) => Some(CssModel::new(parse_pace(pace).unwrap_or_else(|| {
+            exit_with_errors(vec![format!("invalid pace `{}`", pace)])
+        }))),
+        None => profile.as_ref().and_then(SwimmerProfile::pace_model),
+    };
+    let model = model.as_ref().map(|model| model as &dyn PaceModel);
+
+    for line in explain(&workout, &load_config(options).drill_dictionary(), model) {
+        println!("{}", line);
+    }
+    let time = match model {
+        Some(model) => workout.estimated_swim_time(model) + workout.dryland_time(),
+        None => workout.total_time(),
+    };
+    print!("\nIn all: {}m", workout.total_distance());
+    if time > 0 {
+        print!(", about {}", format_time(time));
+    }
+    println!();
+}
+
+/// Parses a pace per 100m such as `1:35`, or seconds such as `95`
+fn parse_pace(pace: &str) -> Option<u32> {
+    let secs = match pace.split_once(':') {
+        Some((minutes, seconds)) => {
+            let seconds = seconds
+                .parse::<u32>()
+                .ok()
+                .filter(|&seconds| seconds < 60)?;
+            minutes
+                .parse::<u32>()
+                .ok()?
+                .checked_mul(60)?
+                .checked_add(seconds)?
+        }
+        None => pace.trim_end_matches('s').parse().ok()?,
+    };
+    (secs > 0).then_some(secs)
+}
+
+/// Prints every rep in order with the distance and clock time at its end,
+/// as a lane sheet; with a profile, reps without a send-off take the
+/// predicted time
+fn expand(set_file: &str, options: &[String]) {
+    let profile = load_profile(options);
+    let workout = parse(set_file, options, profile.as_ref());
+    let model = profile.as_ref().and_then(SwimmerProfile::pace_model);
+    let steps = WorkoutSteps::from_workout(&workout);
+    let events = timeline(&steps, model.as_ref().map(|model| model as &dyn PaceModel));
+
+    let mut reps: HashMap<&SetPath, (u32, u32)> = HashMap::new();
+    for event in &events {
+        if let Step::Swim(swim) = event.step {
+            reps.entry(&swim.path).or_default().1 += 1;
+        }
+    }
+
+    // Without send-offs or a pace model there is no clock to show
+    let timed = model.is_some() || events.iter().any(|event| !event.duration.is_zero());
+    // Narrow terminals drop the set column and cut long swims short
+    let fixed = 4 + 2 + 8 + 7 + if timed { 9 } else { 0 };
+    let width = terminal_width();
+    let narrow = width.is_some_and(|width| width < fixed + 8 + 28);
+    let swim_width = match width {
+        Some(width) if narrow => width.saturating_sub(fixed).max(12),
+        _ => 28,
+    };
+    let set_column = |path: &str| match narrow {
+        true => String::new(),
+        false => format!("{:<8}", path),
+    };
+
+    let mut distance = 0;
+    print!(
+        "{:>4}  {}{:<8}{:<swim_width$}{:>7}",
+        "#",
+        set_column("Set"),
+        "Rep",
+        "Swim",
+        "Total"
+    );
+    println!("{}", if timed { "    Clock" } else { "" });
+    for (i, event) in events.iter().enumerate() {
+        let (path, rep, mut description) = match event.step {
+            Step::Swim(swim) => {
+                let (done, of) = reps.get_mut(&swim.path).expect("counted above");
+                *done += 1;
+                distance += swim.meters;
+
+                let mut description = format!("{}m {}", swim.meters, swim.stroke);
+                if !swim.modifiers.is_empty() {
+                    description += &format!("({})", swim.modifiers.join(", "));
+                }
+                if let Some(send_off) = swim.send_off_for(*done as usize - 1) {
+                    description += &format!(" @{}", format_time(send_off));
+                }
+                if !swim.targets.is_empty() {
+                    let goal = swim.targets[(*done as usize - 1) % swim.targets.len()];
+                    description += &format!(" goal {}", format_time(goal));
+                }
+                (&swim.path, format!("{}/{}", done, of), description)
+            }
+            Step::Dryland(dryland) => (&dryland.path, String::new(), dryland.name.clone()),
+            Step::Rest(rest) => {
+                let description = match (rest.duration, rest.heart_rate_below) {
+                    (Some(secs), _) => format!("rest {}", format_time(secs)),
+                    (None, Some(below)) => format!("rest to HR<{}", below),
+                    (None, None) => "rest".to_string(),
+                };
+                (&rest.path, String::new(), description)
+            }
+            Step::Repeat { .. } => continue,
+        };
+        if width.is_some() {
+            description = truncate(&description, swim_width - 1);
+        }
+        print!(
+            "{:>4}  {}{:<8}{:<swim_width$}{:>6}m",
+            i + 1,
+            set_column(&path.to_string()),
+            rep,
+            description,
+            distance
+        );
+        if timed {
+            print!("  {:>7}", format_time(event.end().as_secs() as u32));
+        }
+        println!();
+    }
+}
+
+/// Checks the workout against the profile's paces and the `--duration` of
+/// pool time, exiting with an error if it doesn't fit
+fn check_feasibility(path: &str, set_file: &str, options: &[String]) {
+    if let Some(output) = option(options, "--output") {
+        annotate_check(path, set_file, options, output);
+        return;
+    }
+    let profile = load_profile(options);
+    let model = profile
+        .as_ref()
+        .and_then(SwimmerProfile::pace_model)
+        .or_else(|| load_config(options).preset.map(Preset::pace_model));
+    if flag(options, "--suggest-intervals") {
+        suggest_send_offs(&parse(set_file, options, profile.as_ref()), options);
+        if model.is_none() {
+            return;
+        }
+    }
+    let Some(model) = model else {
+        eprintln!("error: check needs a --profile with a css or threshold pace, or a --preset");
         std::process::exit(1);
+    };
+    let workout = parse(set_file, options, profile.as_ref());
+    let problems = feasibility_problems(&workout, &model, profile.as_ref(), options);
+    if problems.is_empty() {
+        println!("Workout fits");
+        return;
+    }
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    std::process::exit(1);
+}
+
+/// Send-offs the swimmer can't make, unlikely send-offs and a session too
+/// long for the `--duration` given
+fn feasibility_problems(
+    workout: &Workout,
+    model: &dyn PaceModel,
+    profile: Option<&SwimmerProfile>,
+    options: &[String],
+) -> Vec<Problem> {
+    let available = option(options, "--duration").map(|duration| {
+        parse_session_duration(duration)
+            .unwrap_or_else(|| exit_with_errors(vec![format!("invalid duration `{}`", duration)]))
+    });
+
+    let mut problems = check(workout, model, available);
+    let variability = profile
+        .and_then(|profile| profile.variability)
+        .unwrap_or(DEFAULT_VARIABILITY);
+    problems.extend(unlikely_send_offs(
+        workout,
+        model,
+        &swim_history(options),
+        variability,
+    ));
+    problems
+}
+
+/// Prints parse errors and warnings, lint warnings and, given a profile or
+/// preset, feasibility problems in the `--output` CI annotation format,
+/// failing on errors, or also on warnings with `--deny-warnings`
+fn annotate_check(path: &str, set_file: &str, options: &[String], output: &str) {
+    let formatter: Box<dyn AnnotationFormatter> = match output {
+        "github" => Box::new(GithubFormatter),
+        "sarif" => Box::new(SarifFormatter),
+        output => {
+            eprintln!("error: unknown output format `{}`", output);
+            std::process::exit(1);
+        }
+    };
+    let profile = load_profile(options);
+    let config = load_config(options);
+    let model = profile
+        .as_ref()
+        .and_then(SwimmerProfile::pace_model)
+        .or_else(|| config.preset.map(Preset::pace_model));
+
+    let parser_options = parser_options(options);
+    let mut parser = Parser::with_options(Token::lexer(set_file), parser_options);
+    let mut diagnostics = Diagnostics::new();
+    match parser.parse() {
+        Err(errors) => diagnostics.extend(errors.iter().map(Diagnostic::from)),
+        Ok(mut workout) => {
+            diagnostics.extend(parser.diagnostics().iter().cloned());
+            match resolve(&mut workout, options, profile.as_ref()) {
+                Err(errors) => diagnostics.extend(errors.into_iter().map(|message| Diagnostic {
+                    severity: Severity::Error,
+                    path: None,
+                    span: None,
+                    message,
+                    fix: None,
+                })),
+                Ok(()) => {
+                    let model = model.as_ref().map(|model| model as &dyn PaceModel);
+                    diagnostics.extend(
+                        lint(&workout, &config, model)
+                            .into_iter()
+                            .map(Diagnostic::from),
+                    );
+                    if let Some(model) = model {
+                        diagnostics.extend(
+                            feasibility_problems(&workout, model, profile.as_ref(), options)
+                                .into_iter()
+                                .map(Diagnostic::from),
+                        );
+                    }
+                }
+            }
+        }
     }
 
-    let set_file = std::fs::read_to_string(&args[1]).unwrap();
+    let file = AnnotatedFile {
+        name: path,
+        source: set_file,
+        set_spans: parser.set_spans(),
+    };
+    formatter
+        .format(&file, &diagnostics, &mut std::io::stdout())
+        .unwrap_or_else(|error| exit_with_errors(vec![error]));
+    let threshold = if flag(options, "--deny-warnings") {
+        Severity::Warning
+    } else {
+        Severity::Error
+    };
+    if diagnostics.at_least(threshold).next().is_some() {
+        std::process::exit(1);
+    }
+}
+
+/// Recent swims in `--dir` or the configured library, if either is given
+fn swim_history(options: &[String]) -> PaceHistory {
+    let config = load_config(options);
+    if option(options, "--dir").is_none() && config.library.is_none() {
+        return PaceHistory::new();
+    }
+    let (history, errors) = PaceHistory::from_library(&load_library(options, &config));
+    for error in errors {
+        eprintln!("warning: skipping {}", error);
+    }
+    history
+}
+
+/// Prints send-offs from the recent swims in the log directory: `--dir`,
+/// the configured library or the current directory
+fn suggest_send_offs(workout: &Workout, options: &[String]) {
+    let library = load_library(options, &load_config(options));
+    let (history, errors) = PaceHistory::from_library(&library);
+    for error in errors {
+        eprintln!("warning: skipping {}", error);
+    }
+
+    let suggestions = suggest_intervals(workout, &history);
+    if suggestions.is_empty() {
+        println!("No recent swims like these sets");
+    }
+    for suggestion in &suggestions {
+        println!("{}", suggestion);
+    }
+}
+
+/// Seconds in a session length like `90min`, `90`, `1h` or `1h30`
+fn parse_session_duration(duration: &str) -> Option<u32> {
+    let minutes = match duration.split_once('h') {
+        Some((hours, minutes)) => {
+            let minutes = minutes.trim_end_matches("min").trim_end_matches('m');
+            hours
+                .parse::<u32>()
+                .ok()?
+                .checked_mul(60)?
+                .checked_add(if minutes.is_empty() {
+                    0
+                } else {
+                    minutes.parse::<u32>().ok()?
+                })?
+        }
+        None => duration
+            .trim_end_matches("min")
+            .trim_end_matches('m')
+            .parse()
+            .ok()?,
+    };
+    minutes.checked_mul(60)
+}
+
+/// Checks the workouts in `--dir`, the configured library or the current
+/// directory against their snapshots, or writes the snapshots with `--bless`
+fn corpus(options: &[String]) {
+    let dir = option(options, "--dir")
+        .map(std::path::PathBuf::from)
+        .or_else(|| load_config(options).library)
+        .unwrap_or_else(|| ".".into());
+    let report = check_corpus(&dir, flag(options, "--bless"))
+        .unwrap_or_else(|error| exit_with_errors(vec![error]));
+    println!("{}", report);
+    if !report.passed() {
+        std::process::exit(1);
+    }
+}
+
+/// Prints the language reference generated from the grammar, as Markdown
+/// by default
+fn grammar(options: &[String]) {
+    match option(options, "--format").unwrap_or("markdown") {
+        "markdown" => print!("{}", markdown()),
+        "ebnf" => print!("{}", ebnf()),
+        format => {
+            eprintln!("error: unknown grammar format `{}`", format);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn tokens(set_file: &str) {
+    for (token, span) in contextual(Token::lexer(set_file)) {
+        println!("{:?}: {:?}", token, &set_file[span]);
+    }
+}
+
+/// Set that best shows what a workout is: its first key set, or else the
+/// longest set between the warmup and cooldown
+fn main_set(workout: &Workout) -> Option<&Set> {
+    if let Some((_, set)) = key_sets(workout).into_iter().next() {
+        return Some(set);
+    }
+    detect_sections(workout)
+        .main(workout)
+        .map(|i| &workout.sets[i])
+        .max_by_key(|set| set.total_distance())
+}
+
+/// Library search given by the `--tag`, `--min-distance` and `--max-distance` options
+fn search(options: &[String]) -> Search {
+    let mut search = Search::new();
+    for (i, option) in options.iter().enumerate() {
+        if option == "--tag" {
+            if let Some(tag) = options.get(i + 1) {
+                search = search.tag(tag);
+            }
+        }
+    }
+    if let Some(meters) = option(options, "--min-distance") {
+        search = search.min_distance(parse_meters(meters));
+    }
+    if let Some(meters) = option(options, "--max-distance") {
+        search = search.max_distance(parse_meters(meters));
+    }
+    search
+}
+
+fn parse_meters(meters: &str) -> u32 {
+    meters
+        .trim_end_matches('m')
+        .parse()
+        .unwrap_or_else(|_| exit_with_errors(vec![format!("invalid distance `{}`", meters)]))
+}
+
+fn format_tags(tags: &[String]) -> String {
+    if tags.is_empty() {
+        String::new()
+    } else {
+        format!("  [{}]", tags.join(", "))
+    }
+}
+
+/// Value following `name` in the command line options
+fn option<'a>(options: &'a [String], name: &str) -> Option<&'a str> {
+    let index = options.iter().position(|option| option == name)?;
+    options.get(index + 1).map(String::as_str)
+}
+
+/// Whether `name` is present in the command line options
+fn flag(options: &[String], name: &str) -> bool {
+    options.iter().any(|option| option == name)
+}
+
+/// One-line description of a set, eliding nested blocks
+fn describe(set: &Set) -> String {
+    match set {
+        Set::Repetition { count, set, .. } => format!("{}x {}", count, describe(set)),
+        Set::Block { .. } => "{ … }".to_string(),
+        Set::Dryland { .. } => "dryland { … }".to_string(),
+        Set::Statement(stmt) => stmt.to_string(),
+    }
+}
+
+/// Formats a range of top-level set indices as 1-based set numbers
+fn format_range(range: std::ops::Range<usize>) -> String {
+    if range.len() == 1 {
+        format!("set {}", range.start + 1)
+    } else {
+        format!("sets {}-{}", range.start + 1, range.end)
+    }
+}
+
+/// Width of the terminal standard output goes to, from `COLUMNS` or else 80,
+/// or `None` when output is piped or redirected and should be left whole
+fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    let columns = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok());
+    Some(columns.filter(|&columns| columns > 0).unwrap_or(80))
+}
+
+/// Cuts `text` to `width` characters, ending it with `…` if anything was cut
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// Fits formatted workout text, starting with `metadata` lines of metadata,
+/// to the terminal, if output goes to one
+fn fit_to_terminal(text: &str, metadata: usize) -> String {
+    match terminal_width() {
+        Some(width) => fit_to_width(text, width, metadata),
+        None => text.to_string(),
+    }
+}
+
+/// Fits each line of formatted workout text, starting with `metadata` lines
+/// of metadata, to `width` characters
+///
+/// Long lines of sets wrap at spaces with `\` continuations, so the text
+/// still parses, and coach notes are cut short to fit after the last piece.
+/// Metadata values run to the end of the line, so a long metadata line is
+/// cut short instead.
+fn fit_to_width(text: &str, width: usize, metadata: usize) -> String {
+    let mut fitted = String::new();
+    for (i, line) in text.lines().enumerate() {
+        if i < metadata {
+            fitted += &truncate(line, width);
+            fitted.push('\n');
+            continue;
+        }
+        let (code, note) = match line.split_once(" // coach: ") {
+            Some((code, note)) => (code, Some(note)),
+            None => (line, None),
+        };
+        let indent = code.len() - code.trim_start().len();
+
+        let mut current = code[..indent].to_string();
+        for word in code[indent..].split(' ') {
+            let start = current.len() == indent;
+            if !start && current.chars().count() + 1 + word.chars().count() + " \\".len() > width {
+                fitted += &current;
+                fitted += " \\\n";
+                current = " ".repeat(indent + 4);
+            } else if !start {
+                current.push(' ');
+            }
+            current += word;
+        }
+        if let Some(note) = note {
+            let room = width.saturating_sub(current.chars().count() + " // coach: ".len());
+            current += " // coach: ";
+            current += &truncate(note, room.max(1));
+        }
+        fitted += &current;
+        fitted.push('\n');
+    }
+    fitted
+}
+
+fn format_time(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_session_duration() {
+        assert_eq!(parse_session_duration("90min"), Some(5400));
+        assert_eq!(parse_session_duration("1h30"), Some(5400));
+        assert_eq!(parse_session_duration("2h"), Some(7200));
+        assert_eq!(parse_session_duration("99999999"), None);
+        assert_eq!(parse_session_duration("99999999h"), None);
+        assert_eq!(parse_session_duration("1h4294967295"), None);
+    }
+
+    #[test]
+    fn test_parse_pace() {
+        assert_eq!(parse_pace("1:35"), Some(95));
+        assert_eq!(parse_pace("95s"), Some(95));
+        assert_eq!(parse_pace("0"), None);
+        assert_eq!(parse_pace("1:60"), None);
+        assert_eq!(parse_pace("99999999:00"), None);
+    }
 
-    let mut lexer = Token::lexer(&set_file);
+    #[test]
+    fn test_fit_to_width() {
+        let source = "title: A long title for the Tuesday practice\nMain (10:00): {\n    4x 100m fly(catch-up, fist) [paddles, snorkel] @1:40\n}\n200m free // coach: long strokes, count them\n";
+        let workout = Parser::new(Token::lexer(source)).parse().unwrap();
 
-    while let Some(token) = lexer.next() {
-        println!("{:?}: {:?}", token, lexer.slice());
+        let fitted = fit_to_width(&workout.to_string(), 40, 1);
+        assert_eq!(
+            fitted,
+            "title: A long title for the Tuesday pra…\nMain (10:00): {\n    4x 100m fly(catch-up, fist) \\\n        [paddles, snorkel] @1:40\n}\n200m free // coach: long strokes, count…\n"
+        );
+        assert!(fitted.lines().all(|line| line.chars().count() <= 40));
+        let refitted = Parser::new(Token::lexer(&fitted)).parse().unwrap();
+        assert_eq!(refitted.sets[..1], workout.sets[..1]);
+        assert_eq!(fit_to_width(source, 80, 1), source);
     }
 }
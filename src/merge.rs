@@ -0,0 +1,225 @@
+use crate::{
+    ast::{Set, Workout},
+    query::Query,
+    resolve::{resolve_base_intervals, BaseIntervals},
+};
+
+/// Sections a workout is split into, in order
+const SECTIONS: &[&str] = &["warmup", "main", "cooldown"];
+
+impl Workout {
+    /// Appends the sets of `other`, e.g., to build a double-session day
+    ///
+    /// Metadata entries only in `other` are added and titles are joined with
+    /// ` + `; for other keys the first workout's value wins. If `other`
+    /// declares different bases, its `@base` intervals are resolved with its
+    /// own bases first so they keep their meaning. Set numbers in `other`
+    /// shift by the number of top-level sets already present.
+    ///
+    /// Blocks of `other` labeled like a block already present, e.g., two
+    /// `warmup:` sections, take its title as a suffix, `warmup-PM`; if it has
+    /// no title to tell them apart, that's an error.
+    pub fn concat(&mut self, mut other: Workout) -> Result<(), Vec<String>> {
+        let ours = BaseIntervals::from_metadata(&self.metadata).map_err(|error| vec![error])?;
+        let theirs = BaseIntervals::from_metadata(&other.metadata).map_err(|error| vec![error])?;
+        let declares_bases = theirs.default.is_some() || !theirs.strokes.is_empty();
+        if declares_bases && theirs != ours {
+            resolve_base_intervals(&mut other, &theirs)?;
+        }
+
+        let mut taken = Vec::new();
+        labels(&self.sets, &mut taken);
+        let suffix = other.metadata.get("title").map(label_suffix);
+        relabel(&mut other.sets, &taken, suffix.as_deref()).map_err(|error| vec![error])?;
+
+        for (key, value) in other.metadata.entries {
+            match self.metadata.entries.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, existing)) if key == "title" && *existing != value => {
+                    *existing = format!("{} + {}", existing, value);
+                }
+                Some(_) => {}
+                None if declares_bases && key.starts_with("base") => {}
+                None => self.metadata.entries.push((key, value)),
+            }
+        }
+        self.sets.extend(other.sets);
+
+        Ok(())
+    }
+
+    /// Splits the workout into its warmup, main set and cooldown, the inverse
+    /// of [`Workout::concat`]
+    ///
+    /// Each part keeps the metadata, with the section name added to the title.
+    /// Sections the workout doesn't have are left out.
+    pub fn split_sections(&self) -> Vec<(&'static str, Workout)> {
+        SECTIONS
+            .iter()
+            .map(|name| (*name, Query::new().section(name).select(self)))
+            .filter(|(_, part)| !part.sets.is_empty())
+            .map(|(name, mut part)| {
+                if let Some((_, title)) =
+                    part.metadata.entries.iter_mut().find(|(k, _)| k == "title")
+                {
+                    *title = format!("{} ({})", title, name);
+                }
+                (name, part)
+            })
+            .collect()
+    }
+}
+
+/// Labels of the blocks among `sets`, at any depth
+fn labels(sets: &[Set], out: &mut Vec<String>) {
+    for set in sets {
+        match set {
+            Set::Repetition { set, .. } => labels(std::slice::from_ref(set), out),
+            Set::Block { sets, label, .. } => {
+                out.extend(label.clone());
+                labels(sets, out);
+            }
+            Set::Statement(_) | Set::Dryland { .. } => {}
+        }
+    }
+}
+
+/// Adds `suffix` to the labels among `sets` that are already `taken`
+fn relabel(sets: &mut [Set], taken: &[String], suffix: Option<&str>) -> Result<(), String> {
+    let is_taken = |label: &str| taken.iter().any(|t| t.eq_ignore_ascii_case(label));
+    for set in sets {
+        match set {
+            Set::Repetition { set, .. } => relabel(std::slice::from_mut(set), taken, suffix)?,
+            Set::Block { sets, label, .. } => {
+                if let Some(label) = label.as_mut().filter(|label| is_taken(label)) {
+                    let renamed = suffix
+                        .filter(|suffix| !suffix.is_empty())
+                        .map(|suffix| format!("{}-{}", label, suffix))
+                        .filter(|renamed| !is_taken(renamed));
+                    let Some(renamed) = renamed else {
+                        return Err(format!(
+                            "both workouts have a `{}` block; give the second a title",
+                            label
+                        ));
+                    };
+                    *label = renamed;
+                }
+                relabel(sets, taken, suffix)?;
+            }
+            Set::Statement(_) | Set::Dryland { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+/// A title as it can follow a block label, its words joined with `-` and
+/// kept to letters, e.g., `PM` or `Tuesday-PM`
+fn label_suffix(title: &str) -> String {
+    title
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(char::is_ascii_alphabetic)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::Workout, lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn parse(source: &str) -> Workout {
+        Parser::new(Token::lexer(source)).parse().unwrap()
+    }
+
+    #[test]
+    fn test_concat() {
+        let mut workout =
+            parse("title: AM\ndate: 2024-03-18\nbase: 1:30\n400m free\n4x100m free @base");
+        workout
+            .concat(parse(
+                "title: PM\ndate: 2024-03-18\ncoach: Sam\nbase: 1:40\n4x200m free @base+5",
+            ))
+            .unwrap();
+
+        assert_eq!(
+            workout.to_string(),
+            "title: AM + PM\ndate: 2024-03-18\nbase: 1:30\ncoach: Sam\n400m free\n4x 100m free @base\n4x 200m free @3:25\n"
+        );
+
+        let mut workout = parse("base: 1:30\n400m free");
+        workout
+            .concat(parse("base: 1:30\n4x100m free @base"))
+            .unwrap();
+        assert_eq!(
+            workout.to_string(),
+            "base: 1:30\n400m free\n4x 100m free @base\n"
+        );
+
+        let mut workout = parse("base: 1:30\n400m free");
+        assert!(workout
+            .concat(parse("base.back: 1:40\n4x100m free @base"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_concat_labels() {
+        let mut workout = parse("title: AM\nwarmup: { 400m free }\nmain: { 8x100m free @1:30 }");
+        workout
+            .concat(parse(
+                "title: Tuesday PM\nwarmup: { 200m free }\nMain: { 4x50m fly @1:00 }",
+            ))
+            .unwrap();
+        let merged = workout.to_string();
+        assert_eq!(
+            merged,
+            "title: AM + Tuesday PM\nwarmup: {\n    400m free\n}\nmain: {\n    8x 100m free @1:30\n}\nwarmup-Tuesday-PM: {\n    200m free\n}\nMain-Tuesday-PM: {\n    4x 50m fly @1:00\n}\n"
+        );
+        assert_eq!(parse(&merged), workout);
+
+        let mut workout = parse("warmup: { 400m free }");
+        assert_eq!(
+            workout.concat(parse("warmup: { 200m free }")),
+            Err(vec![
+                "both workouts have a `warmup` block; give the second a title".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_split_sections() {
+        let workout = parse("title: Tuesday\n400m free(easy)\n8x100m free @1:30\n200m back(easy)");
+        let parts: Vec<_> = workout
+            .split_sections()
+            .into_iter()
+            .map(|(name, part)| (name, part.to_string()))
+            .collect();
+
+        assert_eq!(
+            parts,
+            vec![
+                (
+                    "warmup",
+                    "title: Tuesday (warmup)\n400m free(easy)\n".to_string()
+                ),
+                (
+                    "main",
+                    "title: Tuesday (main)\n8x 100m free @1:30\n".to_string()
+                ),
+                (
+                    "cooldown",
+                    "title: Tuesday (cooldown)\n200m back(easy)\n".to_string()
+                ),
+            ]
+        );
+
+        let mut merged = parse("title: Tuesday");
+        for (_, part) in workout.split_sections() {
+            merged.sets.extend(part.sets);
+        }
+        assert_eq!(merged, workout);
+    }
+}
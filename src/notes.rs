@@ -0,0 +1,605 @@
+//! Import of practices written as plain text, such as years of old coach
+//! notes
+//!
+//! Notes follow no syntax, so the importer reads what it can: `8 x 100 free
+//! on 1:30` is a repeated swim, `4 rounds:` or `Warm up:` starts a block of
+//! the lines indented under it (or, if none are, the lines up to the next
+//! blank line). Every line gets a [`Confidence`], so a coach can check the
+//! guesses before the workout replaces the notes. Sets that would take the
+//! workout over the parser's default limits are left out.
+
+use std::ops::{Range, RangeInclusive};
+
+use crate::{
+    analysis::is_equipment,
+    ast::{
+        Distance, DistanceUnit, Equipment, Interval, Modifier, Modifiers, Set, Statement, Stroke,
+        Workout,
+    },
+    diagnostic::{Diagnostic, Diagnostics, Severity},
+    fix::{closest_stroke, STROKES},
+    parser::{limit_error, parse_duration, ParseErrorKind, ParserOptions},
+};
+
+/// Shorthand for strokes seen in notes
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("fr", "free"),
+    ("frs", "free"),
+    ("bk", "back"),
+    ("br", "breast"),
+    ("brst", "breast"),
+    ("fl", "fly"),
+    ("k", "kick"),
+    ("p", "pull"),
+    ("dr", "drill"),
+    ("ch", "choice"),
+];
+
+/// Words joining the parts of a swim, e.g., `with fins`
+const FILLER: &[&str] = &["with", "w/", "and", "of", "the", "at"];
+
+/// How sure the importer is that it read a line as meant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confidence {
+    /// Read as written
+    Certain,
+    /// Read with some guessing, e.g., an abbreviated stroke
+    Guessed,
+    /// Not understood and left out of the workout
+    Skipped,
+}
+
+/// How one line of the notes was read
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineReading {
+    /// Line number, counting from 1
+    pub line: usize,
+    /// Byte range of the line in the notes
+    pub span: Range<usize>,
+    pub confidence: Confidence,
+    /// What was guessed or why the line was skipped
+    pub notes: Vec<String>,
+}
+
+/// A best-effort workout read from notes, with how each line was read
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotesImport {
+    pub workout: Workout,
+    /// Readings of the non-blank lines, in order
+    pub lines: Vec<LineReading>,
+}
+
+impl NotesImport {
+    /// Share of the lines read as written, counting guessed lines as half,
+    /// from 0 to 1
+    pub fn confidence(&self) -> f64 {
+        if self.lines.is_empty() {
+            return 1.0;
+        }
+        let score: f64 = self
+            .lines
+            .iter()
+            .map(|reading| match reading.confidence {
+                Confidence::Certain => 1.0,
+                Confidence::Guessed => 0.5,
+                Confidence::Skipped => 0.0,
+            })
+            .sum();
+        score / self.lines.len() as f64
+    }
+
+    /// Guesses as notes and skipped lines as warnings, located at their line
+    pub fn diagnostics(&self) -> Diagnostics {
+        let mut diagnostics = Diagnostics::new();
+        for reading in &self.lines {
+            let severity = match reading.confidence {
+                Confidence::Certain => continue,
+                Confidence::Guessed => Severity::Note,
+                Confidence::Skipped => Severity::Warning,
+            };
+            diagnostics.push(Diagnostic {
+                severity,
+                path: None,
+                span: Some(reading.span.clone()),
+                message: format!("line {}: {}", reading.line, reading.notes.join("; ")),
+                fix: None,
+            });
+        }
+        diagnostics
+    }
+}
+
+impl Workout {
+    /// Reads loosely formatted practice notes into a workout, leaving out
+    /// lines that aren't understood
+    pub fn from_notes(notes: &str) -> NotesImport {
+        let mut offset = 0;
+        let lines = notes
+            .split_inclusive('\n')
+            .enumerate()
+            .map(|(i, text)| {
+                let start = offset;
+                offset += text.len();
+                let text = text.trim_end();
+                Line {
+                    number: i + 1,
+                    span: start..start + text.len(),
+                    indent: text.len() - text.trim_start().len(),
+                    text: text.trim(),
+                }
+            })
+            .collect();
+
+        let mut importer = Importer {
+            lines,
+            position: 0,
+            readings: Vec::new(),
+            top_lines: Vec::new(),
+        };
+        let mut workout = Workout {
+            metadata: Default::default(),
+            sets: importer.sets(Scope::Top),
+        };
+        let (mut top_lines, mut lines) = (importer.top_lines, importer.readings);
+        while let Some(error) = limit_error(&workout, &ParserOptions::default()) {
+            let (Some(&i), ParseErrorKind::LimitExceeded { limit, max }) = (
+                error.path.as_ref().and_then(|path| path.indices().first()),
+                error.kind,
+            ) else {
+                break;
+            };
+            workout.sets.remove(i);
+            let left_out = top_lines.remove(i);
+            for reading in &mut lines {
+                if left_out.contains(&reading.line) {
+                    reading.confidence = Confidence::Skipped;
+                    reading.notes = vec![format!(
+                        "skipped `{}`, as it takes the {} over the maximum of {}",
+                        notes[reading.span.clone()].trim(),
+                        limit,
+                        max
+                    )];
+                }
+            }
+        }
+        lines.sort_by_key(|reading| reading.line);
+        NotesImport { workout, lines }
+    }
+}
+
+struct Line<'a> {
+    number: usize,
+    span: Range<usize>,
+    indent: usize,
+    /// The line without surrounding whitespace
+    text: &'a str,
+}
+
+/// Lines belonging to a block being read
+#[derive(Clone, Copy)]
+enum Scope {
+    Top,
+    /// Lines indented further than the block's heading
+    Indented(usize),
+    /// Lines up to the next blank line or heading
+    Paragraph,
+}
+
+/// A line starting a block, e.g., `4 rounds:` or `Warm up:`
+struct Heading {
+    count: u32,
+    /// Section name as written, e.g., `Warm up`
+    section: Option<String>,
+}
+
+struct Importer<'a> {
+    lines: Vec<Line<'a>>,
+    position: usize,
+    readings: Vec<LineReading>,
+    /// Line numbers each top-level set was read from
+    top_lines: Vec<RangeInclusive<usize>>,
+}
+
+impl Importer<'_> {
+    fn sets(&mut self, scope: Scope) -> Vec<Set> {
+        let mut sets = Vec::new();
+        while let Some(line) = self.lines.get(self.position) {
+            let heading = heading(line.text);
+            match scope {
+                _ if line.text.is_empty() => {
+                    if let Scope::Paragraph = scope {
+                        break;
+                    }
+                    self.position += 1;
+                    continue;
+                }
+                Scope::Indented(indent) if line.indent <= indent => break,
+                Scope::Paragraph if heading.is_some() => break,
+                _ => {}
+            }
+
+            let (number, span, indent) = (line.number, line.span.clone(), line.indent);
+            let text = line.text;
+            self.position += 1;
+            let Some(heading) = heading else {
+                match swim(text) {
+                    Ok((set, notes)) => {
+                        sets.push(set);
+                        self.read(number, span, notes, Confidence::Guessed);
+                        if let Scope::Top = scope {
+                            self.top_lines.push(number..=number);
+                        }
+                    }
+                    Err(reason) => self.read(number, span, vec![reason], Confidence::Skipped),
+                }
+                continue;
+            };
+
+            let indented = self
+                .lines
+                .get(self.position)
+                .is_some_and(|next| !next.text.is_empty() && next.indent > indent);
+            let inner = self.sets(match indented {
+                true => Scope::Indented(indent),
+                false => Scope::Paragraph,
+            });
+            if inner.is_empty() {
+                let reason = format!("skipped `{}`, a heading with no swims under it", text);
+                self.read(number, span, vec![reason], Confidence::Skipped);
+                continue;
+            }
+            let label = heading
+                .section
+                .as_deref()
+                .map(|name| words(name).join("-").to_ascii_lowercase());
+            let notes = heading
+                .section
+                .iter()
+                .zip(&label)
+                .filter(|(name, label)| name != label)
+                .map(|(name, label)| format!("read `{}` as the section `{}`", name, label))
+                .collect();
+            self.read(number, span, notes, Confidence::Guessed);
+            let block = Set::Block {
+                sets: inner,
+                intensity: None,
+                key: false,
+                label,
+                budget: None,
+            };
+            sets.push(match heading.count {
+                1 => block,
+                count => Set::Repetition {
+                    count,
+                    set: Box::new(block),
+                    rounds: Vec::new(),
+                },
+            });
+            if let Scope::Top = scope {
+                let last = self.lines[self.position - 1].number;
+                self.top_lines.push(number..=last);
+            }
+        }
+        sets
+    }
+
+    /// Records how a line was read, as `confidence` if there are notes on
+    /// guesses made
+    fn read(
+        &mut self,
+        line: usize,
+        span: Range<usize>,
+        notes: Vec<String>,
+        confidence: Confidence,
+    ) {
+        let confidence = match notes.is_empty() {
+            true => Confidence::Certain,
+            false => confidence,
+        };
+        self.readings.push(LineReading {
+            line,
+            span,
+            confidence,
+            notes,
+        });
+    }
+}
+
+/// Reads a block heading: a count of rounds, a section name, or both
+fn heading(text: &str) -> Option<Heading> {
+    let (text, colon) = match text.strip_suffix(':') {
+        Some(text) => (text, true),
+        None => (text, false),
+    };
+    let words = words(text);
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let count = match words.as_slice() {
+        [count, "x" | "rounds" | "round" | "times" | "sets"]
+        | [count, "x" | "rounds" | "round" | "times" | "sets", "of"]
+        | ["repeat", count]
+        | ["repeat", count, "times" | "x"]
+        | ["repeat", "x", count] => count.parse().ok().filter(|count| *count > 0)?,
+        _ if colon && !words.is_empty() && words.iter().all(|word| is_word(word)) => {
+            return Some(Heading {
+                count: 1,
+                section: Some(text.to_string()),
+            });
+        }
+        _ => return None,
+    };
+    Some(Heading {
+        count,
+        section: None,
+    })
+}
+
+/// Reads a swim, e.g., `8 x 100 free on 1:30`, with notes on what was guessed
+fn swim(text: &str) -> Result<(Set, Vec<String>), String> {
+    let words = words(text);
+    let skipped = |reason: &str| format!("skipped `{}`, {}", text, reason);
+    let mut notes = Vec::new();
+    let mut position = 0;
+
+    let mut reps = 1;
+    if let (Some(count), Some("x")) = (
+        words.first().and_then(|word| word.parse::<u32>().ok()),
+        words.get(1).map(String::as_str),
+    ) {
+        reps = count;
+        position = 2;
+    }
+    let distance = words
+        .get(position)
+        .and_then(|word| distance(word, words.get(position + 1).map(String::as_str)))
+        .filter(|(distance, _)| distance.value > 0 && reps > 0)
+        .ok_or_else(|| skipped("as it doesn't start with a distance"))?;
+    if words[position].ends_with(['y', 'd', 's']) {
+        notes.push(format!("read `{}` as meters", words[position]));
+    }
+    position += distance.1;
+
+    let mut stroke = None;
+    let mut modifiers = Modifiers::new();
+    let mut equipment = Vec::new();
+    let mut interval = None;
+    while let Some(word) = words.get(position) {
+        position += 1;
+        let word = word.as_str();
+        if matches!(word, "on" | "@" | "every") {
+            if let Some(secs) = words.get(position).and_then(|time| duration(time)) {
+                interval = Some(Interval::from_seconds(secs));
+                position += 1;
+            }
+        } else if let Some(secs) = duration(word).filter(|_| word.contains(':')) {
+            notes.push(format!("read `{}` as the send-off", word));
+            interval = Some(Interval::from_seconds(secs));
+        } else if stroke.is_none() && STROKES.contains(&word) {
+            stroke = Some(word.to_string());
+        } else if let Some((_, name)) = ABBREVIATIONS
+            .iter()
+            .find(|(short, _)| stroke.is_none() && *short == word)
+        {
+            notes.push(format!("read `{}` as `{}`", word, name));
+            stroke = Some(name.to_string());
+        } else if let Some(name) = closest_stroke(word)
+            .map(|(name, _)| name)
+            .filter(|_| stroke.is_none())
+        {
+            notes.push(format!("read `{}` as `{}`", word, name));
+            stroke = Some(name.to_string());
+        } else if is_equipment(word) {
+            equipment.push(Equipment {
+                name: word.to_string(),
+                parameters: Vec::new(),
+            });
+        } else if FILLER.contains(&word) {
+        } else if is_word(word) {
+            modifiers.push(Modifier::new(word));
+        } else {
+            notes.push(format!("left out `{}`", word));
+        }
+    }
+    let stroke = stroke.unwrap_or_else(|| {
+        notes.push("no stroke given; read as `swim`".to_string());
+        "swim".to_string()
+    });
+
+    let stmt = Statement {
+        distance: distance.0,
+        stroke: Stroke {
+            name: stroke,
+            modifiers,
+        },
+        equipment,
+        targets: Vec::new(),
+        interval,
+        intensity: None,
+        alternation: None,
+        rep_strokes: Vec::new(),
+        segments: Vec::new(),
+        key: false,
+        coach_notes: Vec::new(),
+        hold: None,
+        trial: None,
+    };
+    let set = match reps {
+        1 => Set::Statement(Box::new(stmt)),
+        count => Set::Repetition {
+            count,
+            set: Box::new(Set::Statement(Box::new(stmt))),
+            rounds: Vec::new(),
+        },
+    };
+    Ok((set, notes))
+}
+
+/// Distance written as `word`, possibly with its unit as the next word, and
+/// how many words it took
+fn distance(word: &str, next: Option<&str>) -> Option<(Distance, usize)> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let value = digits.parse().ok()?;
+    let (unit, words) = match (&word[digits.len()..], next) {
+        ("", Some("m" | "meters" | "y" | "yd" | "yds" | "yards")) => ("m", 2),
+        ("", Some("km")) => ("km", 2),
+        ("", _) => ("m", 1),
+        (unit, _) => (unit, 1),
+    };
+    let unit = match unit {
+        "m" | "meters" | "y" | "yd" | "yds" | "yards" => DistanceUnit::Meters,
+        "k" | "km" => DistanceUnit::Kilometers,
+        _ => return None,
+    };
+    Some((Distance { value, unit }, words))
+}
+
+/// Seconds of a send-off written as `1:30`, `:45` or `45`
+fn duration(word: &str) -> Option<u32> {
+    match word.strip_prefix(':') {
+        Some(seconds) => parse_duration(&format!("0:{}", seconds)),
+        None => parse_duration(word),
+    }
+    .filter(|secs| *secs > 0)
+}
+
+/// Lowercase words of a line, with repeats like `8x100` split into `8`, `x`
+/// and `100` and a leading `@` split off
+fn words(text: &str) -> Vec<String> {
+    let text = text.to_lowercase().replace('×', "x");
+    let mut words = Vec::new();
+    for word in text.split(|c: char| c.is_whitespace() || c == ',') {
+        let word = word.trim_start_matches(['-', '*', '•']);
+        let word = match word.strip_prefix('@') {
+            Some(rest) => {
+                words.push("@".to_string());
+                rest
+            }
+            None => word,
+        };
+        match word.split_once('x') {
+            Some((count, rest))
+                if count.bytes().all(|b| b.is_ascii_digit()) && !count.is_empty() =>
+            {
+                words.push(count.to_string());
+                words.push("x".to_string());
+                if !rest.is_empty() {
+                    words.push(rest.to_string());
+                }
+            }
+            Some(("", rest)) if !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit()) => {
+                words.push("x".to_string());
+                words.push(rest.to_string());
+            }
+            _ if !word.is_empty() => words.push(word.to_string()),
+            _ => {}
+        }
+    }
+    words
+}
+
+/// Whether `word` lexes as a single word of the syntax
+fn is_word(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_alphabetic())
+        && word.chars().all(|c| c.is_ascii_alphabetic() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_notes() {
+        let import = Workout::from_notes(
+            "Warm up:\n  400 free easy\n  4x50 kick w/ fins\n\nMain set\n3 rounds:\n- 8 x 100 fr on 1:30\n- 200 pull @ 3:00 paddles\n\n100 choice :45\ncool down: 200 easy",
+        );
+
+        assert_eq!(
+            import.workout.to_string(),
+            "warm-up: {\n    400m free(easy)\n    4x 50m kick [fins]\n}\n3x {\n    8x 100m free @1:30\n    200m pull [paddles] @3:00\n}\n100m choice @45s\n"
+        );
+        let confidences: Vec<(usize, Confidence)> = import
+            .lines
+            .iter()
+            .map(|reading| (reading.line, reading.confidence))
+            .collect();
+        assert_eq!(
+            confidences,
+            vec![
+                (1, Confidence::Guessed),
+                (2, Confidence::Certain),
+                (3, Confidence::Certain),
+                (5, Confidence::Skipped),
+                (6, Confidence::Certain),
+                (7, Confidence::Guessed),
+                (8, Confidence::Certain),
+                (10, Confidence::Guessed),
+                (11, Confidence::Skipped),
+            ]
+        );
+        assert!((import.confidence() - 5.5 / 9.0).abs() < 1e-9);
+
+        let messages: Vec<String> = import
+            .diagnostics()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "note: line 1: read `Warm up` as the section `warm-up`",
+                "warning: line 5: skipped `Main set`, as it doesn't start with a distance",
+                "note: line 7: read `fr` as `free`",
+                "note: line 10: read `:45` as the send-off",
+                "warning: line 11: skipped `cool down: 200 easy`, as it doesn't start with a distance",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_guesses() {
+        let import = Workout::from_notes("6 x 50 frestyle\n2x:\n100 #sprint\n1.5k swim");
+        assert_eq!(
+            import.workout.to_string(),
+            "6x 50m freestyle\n2x {\n    100m swim\n}\n"
+        );
+        assert_eq!(
+            import.lines[2].notes,
+            vec!["left out `#sprint`", "no stroke given; read as `swim`"]
+        );
+        assert_eq!(import.lines[3].confidence, Confidence::Skipped);
+        assert_eq!(
+            import.diagnostics().iter().next().unwrap().span,
+            Some(0..15)
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let import =
+            Workout::from_notes("400 free\n99999 rounds:\n  100 free\n  50 back\n\n200 back");
+        assert_eq!(import.workout.to_string(), "400m free\n200m back\n");
+        let confidences: Vec<Confidence> = import
+            .lines
+            .iter()
+            .map(|reading| reading.confidence)
+            .collect();
+        assert_eq!(
+            confidences,
+            vec![
+                Confidence::Certain,
+                Confidence::Skipped,
+                Confidence::Skipped,
+                Confidence::Skipped,
+                Confidence::Certain,
+            ]
+        );
+        assert_eq!(
+            import.lines[1].notes,
+            vec!["skipped `99999 rounds:`, as it takes the number of expanded reps over the maximum of 100000"]
+        );
+
+        let import = Workout::from_notes("4294967295 x 4294967295 free\n100 back");
+        assert_eq!(import.workout.to_string(), "100m back\n");
+        assert_eq!(import.lines[0].confidence, Confidence::Skipped);
+        assert_eq!(import.confidence(), 0.5);
+    }
+}
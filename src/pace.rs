@@ -0,0 +1,221 @@
+use std::{collections::HashMap, time::Duration};
+
+pub use crate::ast::Intensity;
+
+/// Seconds a swimmer is assumed to take to bring their heart rate down on a
+/// `rest to HR<120` rest, unless their profile says otherwise
+pub const DEFAULT_RECOVERY: u32 = 45;
+
+/// Predicts how long a swimmer takes to swim a distance
+///
+/// Time estimation and the impossible send-off check go through this trait,
+/// so clubs with their own pace tables can plug them in.
+pub trait PaceModel {
+    /// Predicted time in seconds to swim `meters` of `stroke` at `intensity`
+    fn predict(&self, meters: u32, stroke: &str, intensity: Intensity) -> u32;
+
+    /// Predicted time as a duration, for models more precise than a second
+    fn predict_duration(&self, meters: u32, stroke: &str, intensity: Intensity) -> Duration {
+        Duration::from_secs(self.predict(meters, stroke, intensity).into())
+    }
+
+    /// Seconds the swimmer takes to recover on a heart-rate rest
+    fn recovery(&self) -> u32 {
+        DEFAULT_RECOVERY
+    }
+}
+
+/// How much slower a swimmer gets as work piles up within a session
+///
+/// Pace models predict fresh times; long practices drift slower than that.
+/// Fatigue scales each prediction by the load swum before it, counting hard
+/// meters for more than easy ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fatigue {
+    /// Percent slower for each 1000m of load swum before
+    pub percent_per_km: f64,
+}
+
+impl Fatigue {
+    pub fn new(percent_per_km: f64) -> Self {
+        Self { percent_per_km }
+    }
+
+    /// Load of swimming `meters` at `intensity`, in meters weighted by how
+    /// hard they are
+    pub fn load(meters: u32, intensity: Intensity) -> f64 {
+        let weight = match intensity {
+            Intensity::Easy => 0.5,
+            Intensity::Moderate => 1.0,
+            Intensity::Threshold => 1.5,
+            Intensity::RacePace => 2.0,
+            Intensity::Sprint => 2.5,
+        };
+        f64::from(meters) * weight
+    }
+
+    /// Factor on predicted times after `load` of earlier work
+    pub fn factor(&self, load: f64) -> f64 {
+        1.0 + self.percent_per_km.max(0.0) / 100.0 * load / 1000.0
+    }
+}
+
+/// Pace model scaling the swimmer's critical swim speed by stroke and intensity
+///
+/// Strokes with a threshold pace of their own are scaled from it instead, as
+/// a swimmer's fly or breast can be far off what their CSS suggests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CssModel {
+    /// Critical swim speed as seconds per 100m freestyle
+    pub css: u32,
+    /// Threshold pace in seconds per 100m, by lowercase stroke name
+    pub thresholds: HashMap<String, u32>,
+    /// Seconds taken to recover on a heart-rate rest
+    pub recovery: u32,
+}
+
+impl CssModel {
+    pub fn new(css: u32) -> Self {
+        Self {
+            css,
+            thresholds: HashMap::new(),
+            recovery: DEFAULT_RECOVERY,
+        }
+    }
+
+    /// Uses `pace` per 100m as the threshold pace of `stroke`
+    pub fn with_threshold(mut self, stroke: &str, pace: u32) -> Self {
+        self.thresholds.insert(stroke.to_ascii_lowercase(), pace);
+        self
+    }
+
+    /// Assumes `secs` to recover on a heart-rate rest
+    pub fn with_recovery(mut self, secs: u32) -> Self {
+        self.recovery = secs;
+        self
+    }
+
+    /// Model from threshold paces alone, taking CSS from the free pace or,
+    /// failing that, from another stroke's pace
+    pub fn from_thresholds(thresholds: HashMap<String, u32>) -> Option<Self> {
+        let css = match thresholds.get("free") {
+            Some(free) => *free,
+            None => {
+                let (stroke, pace) = thresholds.iter().min()?;
+                pace * 100 / Self::stroke_factor(stroke)
+            }
+        };
+        Some(Self {
+            css,
+            thresholds,
+            recovery: DEFAULT_RECOVERY,
+        })
+    }
+
+    /// Threshold pace per 100m of `stroke`
+    pub fn threshold(&self, stroke: &str) -> u32 {
+        match self.thresholds.get(&stroke.to_ascii_lowercase()) {
+            Some(pace) => *pace,
+            None => self.css * Self::stroke_factor(stroke) / 100,
+        }
+    }
+
+    /// Pace relative to freestyle, in percent
+    fn stroke_factor(stroke: &str) -> u32 {
+        match stroke.to_ascii_lowercase().as_str() {
+            "free" | "freestyle" => 100,
+            "pull" => 105,
+            "back" | "backstroke" => 110,
+            "im" => 112,
+            "fly" | "butterfly" => 115,
+            "breast" | "breaststroke" => 125,
+            "drill" => 130,
+            "kick" => 145,
+            _ => 110,
+        }
+    }
+
+    /// Pace relative to CSS, in percent
+    fn intensity_factor(intensity: Intensity) -> u32 {
+        match intensity {
+            Intensity::Easy => 115,
+            Intensity::Moderate => 105,
+            Intensity::Threshold => 100,
+            Intensity::RacePace => 97,
+            Intensity::Sprint => 92,
+        }
+    }
+}
+
+impl PaceModel for CssModel {
+    fn predict(&self, meters: u32, stroke: &str, intensity: Intensity) -> u32 {
+        self.predict_duration(meters, stroke, intensity).as_secs() as u32
+    }
+
+    fn predict_duration(&self, meters: u32, stroke: &str, intensity: Intensity) -> Duration {
+        let (pace, stroke_factor) = match self.thresholds.get(&stroke.to_ascii_lowercase()) {
+            Some(threshold) => (*threshold, 100),
+            None => (self.css, Self::stroke_factor(stroke)),
+        };
+        let micros = u64::from(pace)
+            * u64::from(meters)
+            * u64::from(stroke_factor)
+            * u64::from(Self::intensity_factor(intensity));
+        Duration::from_millis(micros / 1000)
+    }
+
+    fn recovery(&self) -> u32 {
+        self.recovery
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_css_model() {
+        let model = CssModel::new(100);
+
+        assert_eq!(model.predict(100, "free", Intensity::Moderate), 105);
+        assert_eq!(model.predict(200, "Free", Intensity::Easy), 230);
+        assert_eq!(model.predict(50, "breast", Intensity::Sprint), 57);
+        assert_eq!(
+            model.predict_duration(50, "breast", Intensity::Sprint),
+            Duration::from_millis(57_500)
+        );
+        assert!(
+            model.predict(100, "kick", Intensity::Moderate)
+                > model.predict(100, "free", Intensity::Moderate)
+        );
+    }
+
+    #[test]
+    fn test_stroke_thresholds() {
+        let model = CssModel::new(100).with_threshold("Fly", 140);
+
+        assert_eq!(model.predict(100, "fly", Intensity::Threshold), 140);
+        assert_eq!(model.predict(200, "fly", Intensity::Easy), 322);
+        assert_eq!(model.predict(100, "back", Intensity::Threshold), 110);
+        assert_eq!(model.threshold("FLY"), 140);
+        assert_eq!(model.threshold("breast"), 125);
+
+        let model = CssModel::from_thresholds(HashMap::from([("back".to_string(), 121)])).unwrap();
+        assert_eq!(model.css, 110);
+        assert_eq!(model.threshold("free"), 110);
+        assert_eq!(CssModel::from_thresholds(HashMap::new()), None);
+        assert_eq!(model.recovery(), DEFAULT_RECOVERY);
+        assert_eq!(model.with_recovery(60).recovery(), 60);
+    }
+
+    #[test]
+    fn test_fatigue() {
+        let fatigue = Fatigue::new(2.0);
+
+        assert_eq!(Fatigue::load(400, Intensity::Easy), 200.0);
+        assert_eq!(Fatigue::load(100, Intensity::Sprint), 250.0);
+        assert_eq!(fatigue.factor(0.0), 1.0);
+        assert!((fatigue.factor(1500.0) - 1.03).abs() < 1e-9);
+        assert_eq!(Fatigue::new(-1.0).factor(1000.0), 1.0);
+    }
+}
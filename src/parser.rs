@@ -1,23 +1,2891 @@
-use logos::Lexer;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
 
-use crate::{ast::Workout, lexer::Token};
+use logos::{Lexer, Span};
+
+use crate::{
+    analysis::Analyse,
+    ast::{
+        Alternation, Distance, DistanceUnit, DrylandAmount, DrylandExercise, Equipment, Intensity,
+        Interval, Metadata, Modifier, Modifiers, Name, RacePace, Round, Segment, Set, Statement,
+        Stroke, Trial, Value, Workout,
+    },
+    diagnostic::{Diagnostic, Diagnostics, Severity},
+    drills::DrillDictionary,
+    fix::{closest_stroke, Fix},
+    lexer::{contextual, LexError, Token},
+    path::SetPath,
+};
+
+/// Options controlling how strictly workouts are parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParserOptions {
+    /// Require each statement to end its line, rejecting `100m free 200m back`
+    pub strict_newlines: bool,
+    /// Maximum nesting of repetitions and blocks
+    pub max_depth: u32,
+    /// Maximum number of sets in the workout
+    pub max_sets: u32,
+    /// Maximum number of statements swum once every repetition is expanded
+    pub max_expanded_reps: u64,
+    /// Maximum total distance of the workout in meters
+    pub max_total_distance: u64,
+    /// Maximum total time of the workout's send-offs, rests and dryland in
+    /// seconds
+    pub max_total_time: u64,
+    /// Values of the parameters tested by `if` blocks
+    pub parameters: BTreeMap<String, bool>,
+    /// Drills, which are not misspelled strokes however close they are
+    pub drills: DrillDictionary,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            strict_newlines: false,
+            max_depth: 32,
+            max_sets: 10_000,
+            max_expanded_reps: 100_000,
+            max_total_distance: 1_000_000,
+            max_total_time: 10_000_000,
+            parameters: BTreeMap::new(),
+            drills: DrillDictionary::builtin(),
+        }
+    }
+}
+
+/// Parser resource limits, guarding against hostile input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Depth,
+    Sets,
+    ExpandedReps,
+    TotalDistance,
+    TotalTime,
+}
+
+/// Error found while parsing a workout
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Number of the set being parsed, if any
+    pub path: Option<SetPath>,
+    /// Location of the offending input, or `None` at the end of input
+    pub span: Option<Span>,
+    /// Edit that would correct the input, if there's an obvious one
+    pub fix: Option<Fix>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// Input that doesn't fit the grammar
+    Syntax {
+        message: String,
+        found: Option<String>,
+    },
+    /// Input that isn't a valid token
+    InvalidToken { found: String },
+    /// A number too large to hold, e.g., `99999999999m`
+    NumberTooLarge { found: String, max: u32 },
+    /// Input exceeding one of the configured limits
+    LimitExceeded { limit: Limit, max: u64 },
+}
 
 pub struct Parser<'source> {
-    lexer: Lexer<'source, Token<'source>>,
-    errors: Vec<String>,
+    options: ParserOptions,
+    source: &'source str,
+    tokens: Vec<(Result<Token<'source>, LexError>, Span)>,
+    position: usize,
+    /// Coach notes, kept apart from the tokens so they can appear anywhere
+    notes: Vec<(Span, &'source str)>,
+    /// Modifier names seen so far, shared by their later uses
+    #[cfg(feature = "compact-modifiers")]
+    names: HashMap<&'source str, Name>,
+    /// Index of the first note not yet attached to a statement
+    next_note: usize,
+    /// Path of the set currently being parsed, used to locate errors
+    path: Vec<usize>,
+    /// Nesting of the set currently being parsed
+    depth: u32,
+    /// Number of times the set currently being parsed is repeated
+    multiplier: u64,
+    /// Count of the innermost repetition, which `targets(...)` must match
+    reps: u32,
+    /// Send-offs named in the `intervals { ... }` header
+    intervals: HashMap<&'source str, Interval>,
+    /// Named send-offs no statement has used yet, in definition order
+    unused_intervals: Vec<(&'source str, Span)>,
+    sets: u32,
+    expanded_reps: u64,
+    errors: Vec<ParseError>,
+    /// Warnings and notes about input that still parses
+    diagnostics: Diagnostics,
+    /// Time trial placeholders, by the path of their statement
+    placeholders: Vec<(SetPath, Span)>,
+    /// First token of each set, by path
+    set_spans: Vec<(SetPath, Span)>,
 }
 
 impl<'source> Parser<'source> {
     pub fn new(lexer: Lexer<'source, Token<'source>>) -> Self {
+        Self::with_options(lexer, ParserOptions::default())
+    }
+
+    pub fn with_options(lexer: Lexer<'source, Token<'source>>, options: ParserOptions) -> Self {
+        let source = lexer.source();
+        // Typical workouts have about one token per four bytes
+        let mut tokens = Vec::with_capacity(source.len() / 4);
+        let mut notes = Vec::new();
+        for (token, span) in contextual(lexer) {
+            match token {
+                Ok(Token::CoachNote(note)) => notes.push((span, note)),
+                token => tokens.push((token, span)),
+            }
+        }
+
         Self {
-            lexer,
+            options,
+            source,
+            tokens,
+            position: 0,
+            notes,
+            #[cfg(feature = "compact-modifiers")]
+            names: HashMap::new(),
+            next_note: 0,
+            path: Vec::new(),
+            depth: 0,
+            multiplier: 1,
+            reps: 1,
+            intervals: HashMap::new(),
+            unused_intervals: Vec::new(),
+            sets: 0,
+            expanded_reps: 0,
             errors: Vec::new(),
+            diagnostics: Diagnostics::new(),
+            placeholders: Vec::new(),
+            set_spans: Vec::new(),
+        }
+    }
+
+    /// Warnings and notes found while parsing
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// Where the `__:__` placeholders of time trials are, by the path of
+    /// their statement, once parsed; placeholders in branches an `if` drops
+    /// are left out
+    pub fn placeholders(&self) -> &[(SetPath, Span)] {
+        &self.placeholders
+    }
+
+    /// Where each set is in the source, by path, once parsed, from its first
+    /// token to its last, for placing messages about a set and editing it;
+    /// sets in branches an `if` drops are left out
+    pub fn set_spans(&self) -> &[(SetPath, Span)] {
+        &self.set_spans
+    }
+
+    pub fn parse(&mut self) -> Result<Workout, Vec<ParseError>> {
+        let Some(metadata) = self.parse_metadata() else {
+            return Err(std::mem::take(&mut self.errors));
+        };
+        let mut sets = Vec::new();
+
+        while !self.at_end() {
+            self.path.clear();
+            self.path.push(sets.len());
+            if self.parse_entry(&mut sets, 0).is_none() {
+                break;
+            }
+        }
+
+        // Notes after the last statement belong to it
+        if let Some(stmt) = last_statement_mut(&mut sets) {
+            stmt.coach_notes.extend(self.take_notes_before(usize::MAX));
+        }
+        self.path.clear();
+        for (name, span) in std::mem::take(&mut self.unused_intervals) {
+            let message = format!("interval `{}` is never used", name);
+            self.diagnose(Severity::Warning, &message, Some(span));
+        }
+
+        if self.errors.is_empty() {
+            self.check_totals(&sets);
+        }
+        if self.errors.is_empty() {
+            Ok(Workout { metadata, sets })
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// Parses `key: value` header lines, keeping each value as written up to
+    /// the end of its line, and `intervals { ... }` and `let` headers
+    fn parse_metadata(&mut self) -> Option<Metadata> {
+        let mut metadata = Metadata::default();
+
+        loop {
+            match (self.peek(), self.peek_nth(1)) {
+                (Some(Token::Word(_) | Token::Base(_)), Some(Token::Colon))
+                    if self.peek_nth(2) != Some(&Token::BraceOpen) => {}
+                (Some(Token::Word("intervals")), Some(Token::BraceOpen)) => {
+                    self.parse_named_intervals()?;
+                    continue;
+                }
+                (Some(Token::Word("let")), Some(Token::Word(_) | Token::Base(0)))
+                    if self.peek_nth(2) == Some(&Token::Equals) =>
+                {
+                    self.next();
+                    self.parse_interval_definition()?;
+                    continue;
+                }
+                _ => break,
+            }
+
+            let key = self.source[self.tokens[self.position].1.clone()].to_string();
+            if metadata.get(&key).is_some() {
+                let message = format!("`{}` is given more than once; the first one is used", key);
+                let span = self.tokens[self.position].1.clone();
+                self.diagnose(Severity::Warning, &message, Some(span));
+            }
+            self.next();
+            self.next();
+
+            let start = self.tokens[self.position - 1].1.end;
+            let line_end = self.source[start..]
+                .find('\n')
+                .map_or(self.source.len(), |i| start + i);
+            let mut end = start;
+            while let Some((_, span)) = self.tokens.get(self.position) {
+                if span.start >= line_end {
+                    break;
+                }
+                end = span.end;
+                self.position += 1;
+            }
+
+            let value = self.source[start..end].trim().to_string();
+            metadata.entries.push((key, value));
+        }
+
+        Some(metadata)
+    }
+
+    /// Parses `intervals { base = 1:30, sprint = 2:00 }`, naming send-offs
+    /// that statements refer to as `@base` or `@sprint`
+    fn parse_named_intervals(&mut self) -> Option<()> {
+        self.next();
+        self.next();
+
+        while self.peek() != Some(&Token::BraceClose) {
+            self.parse_interval_definition()?;
+            if self.peek() == Some(&Token::Comma) {
+                self.next();
+            }
+        }
+        self.next();
+        Some(())
+    }
+
+    /// Parses a named send-off, `sprint = 2:00`, as in an `intervals { ... }`
+    /// header or after `let`
+    fn parse_interval_definition(&mut self) -> Option<()> {
+        let name = match self.peek() {
+            Some(Token::Word(_) | Token::Base(0)) => {
+                &self.source[self.tokens[self.position].1.clone()]
+            }
+            _ => {
+                self.error("expected interval name");
+                return None;
+            }
+        };
+        if self.intervals.contains_key(name) {
+            self.error(&format!("interval `{}` is already defined", name));
+            return None;
+        }
+        let span = self.tokens[self.position].1.clone();
+        if Intensity::from_name(name).is_some() {
+            let message = format!(
+                "`@{}` means the named interval rather than the intensity",
+                name
+            );
+            self.diagnose(Severity::Note, &message, Some(span.clone()));
+        }
+        self.unused_intervals.push((name, span));
+        self.next();
+        self.expect(Token::Equals, "`=` after interval name")?;
+        if self.peek().is_some() && self.duration_is_zero() {
+            self.error("interval of 0 seconds; did you mean to omit it?");
+        }
+        let interval = self.parse_duration("interval", true)?;
+        self.intervals.insert(name, interval);
+        Some(())
+    }
+
+    /// Parses an entry of the workout or of a block into `sets`: a set, or
+    /// the sets of the branch of an `if` its parameter selects
+    ///
+    /// `offset` is the number of the entries before `sets`, which are
+    /// numbered alongside them when `sets` is a branch. Returns whether the
+    /// entry adds a set whatever the parameters.
+    fn parse_entry(&mut self, sets: &mut Vec<Set>, offset: usize) -> Option<bool> {
+        if let Some(index) = self.path.last_mut() {
+            *index = offset + sets.len();
+        }
+        if self.peek() == Some(&Token::Word("if")) {
+            let (branch, always) = self.parse_conditional(offset + sets.len())?;
+            sets.extend(branch);
+            Some(always)
+        } else {
+            sets.push(self.parse_numbered_set()?);
+            Some(true)
+        }
+    }
+
+    /// Parses `if <parameter> { ... } else { ... }`, keeping only the sets
+    /// of the branch the parameter selects, and whether either branch would
+    /// add a set
+    ///
+    /// Both branches must parse, and a block must keep a set whichever
+    /// branches are taken, so a file is valid whatever it's given.
+    fn parse_conditional(&mut self, offset: usize) -> Option<(Vec<Set>, bool)> {
+        self.next();
+        let value = match self.peek() {
+            Some(Token::Word(name)) => match self.options.parameters.get(*name) {
+                Some(value) => *value,
+                None => {
+                    self.error(&format!("parameter `{}` is not defined", name));
+                    false
+                }
+            },
+            _ => {
+                self.error("expected parameter name after `if`");
+                return None;
+            }
+        };
+        self.next();
+
+        let start = self.located();
+        let (then, then_always) = self.parse_branch(offset)?;
+        let middle = self.located();
+        let (otherwise, otherwise_always) = if self.peek() == Some(&Token::Word("else")) {
+            self.next();
+            self.parse_branch(offset)?
+        } else {
+            (Vec::new(), false)
+        };
+        // Both branches number their sets alike, so only the kept one's
+        // locations can stand
+        let (from, to) = if value {
+            (middle, self.located())
+        } else {
+            (start, middle)
+        };
+        self.placeholders.drain(from.0..to.0);
+        self.set_spans.drain(from.1..to.1);
+
+        let sets = if value { then } else { otherwise };
+        Some((sets, then_always && otherwise_always))
+    }
+
+    /// Counts of the placeholders and set spans recorded so far
+    fn located(&self) -> (usize, usize) {
+        (self.placeholders.len(), self.set_spans.len())
+    }
+
+    /// Parses the braced sets of an `if` or `else`, numbered from `offset`,
+    /// and whether the branch keeps a set whatever the parameters
+    fn parse_branch(&mut self, offset: usize) -> Option<(Vec<Set>, bool)> {
+        self.expect(Token::BraceOpen, "`{`")?;
+        let (mut sets, mut entries) = (Vec::new(), 0);
+        let mut always = false;
+
+        while self.peek() != Some(&Token::BraceClose) {
+            if self.at_end() {
+                self.error("expected `}` to close branch");
+                self.suggest_closing_brace();
+                return None;
+            }
+            always |= self.parse_entry(&mut sets, offset)?;
+            entries += 1;
+        }
+
+        if entries == 0 {
+            self.error("branch must contain at least one set");
+            return None;
+        }
+        self.next();
+        Some((sets, always))
+    }
+
+    /// Parses a set of the workout or of a block, enforcing the set limit
+    fn parse_numbered_set(&mut self) -> Option<Set> {
+        self.sets += 1;
+        if self.sets > self.options.max_sets {
+            self.limit_exceeded(Limit::Sets, self.options.max_sets.into());
+            return None;
+        }
+        let Some((_, span)) = self.tokens.get(self.position) else {
+            return self.parse_set();
+        };
+        let index = self.set_spans.len();
+        self.set_spans
+            .push((SetPath::new(self.path.clone()), span.clone()));
+        let set = self.parse_set()?;
+        if let Some((_, last)) = self
+            .position
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+        {
+            self.set_spans[index].1.end = last.end;
+        }
+        Some(set)
+    }
+
+    fn parse_set(&mut self) -> Option<Set> {
+        match self.peek() {
+            Some(Token::BraceOpen) => self.nested(Self::parse_block),
+            Some(Token::Word("dryland")) => self.parse_dryland(),
+            Some(Token::Word(_))
+                if self.peek_nth(1) == Some(&Token::ParenOpen)
+                    || (self.peek_nth(1) == Some(&Token::Colon)
+                        && self.peek_nth(2) == Some(&Token::BraceOpen)) =>
+            {
+                self.parse_labeled_block()
+            }
+            Some(Token::Number(value)) => {
+                let value = *value;
+                if self.peek_nth(1) == Some(&Token::Times) {
+                    if value == 0 {
+                        self.error(
+                            "repetition count must be at least 1; remove the set to skip it",
+                        );
+                    }
+                    self.next();
+                    self.next();
+                    if self.at_end() {
+                        self.error("expected a block or statement after `x`");
+                        return None;
+                    }
+                    let (multiplier, reps) = (self.multiplier, self.reps);
+                    self.multiplier = multiplier.saturating_mul(value.into());
+                    self.reps = value;
+                    let set = self.nested(Self::parse_set)?;
+                    self.multiplier = multiplier;
+                    self.reps = reps;
+                    let rounds = match set {
+                        Set::Block { .. } => self.parse_rounds(value)?,
+                        _ => Vec::new(),
+                    };
+                    Some(Set::Repetition {
+                        count: value,
+                        set: Box::new(set),
+                        rounds,
+                    })
+                } else {
+                    self.expanded_reps = self.expanded_reps.saturating_add(self.multiplier);
+                    if self.expanded_reps > self.options.max_expanded_reps {
+                        self.limit_exceeded(Limit::ExpandedReps, self.options.max_expanded_reps);
+                        return None;
+                    }
+                    if value == 0 {
+                        self.error("distance must be at least 1m; remove the set to skip it");
+                    }
+                    self.next();
+                    self.parse_statement(value)
+                        .map(|stmt| Set::Statement(Box::new(stmt)))
+                }
+            }
+            Some(&Token::NumberList(list))
+                if matches!(self.peek_nth(1), Some(Token::Meters | Token::Kilometers)) =>
+            {
+                self.parse_ladder(list)
+            }
+            _ => {
+                self.error("expected a set");
+                None
+            }
+        }
+    }
+
+    /// Parses a pyramid or ladder such as `100-200-300-200-100m free @base`
+    /// into a block of one statement per distance, the rest of the statement
+    /// shared by each
+    fn parse_ladder(&mut self, list: &str) -> Option<Set> {
+        let Some(distances) = list
+            .split('-')
+            .map(|n| n.parse().ok())
+            .collect::<Option<Vec<u32>>>()
+        else {
+            self.error("number in list is too large");
+            return None;
+        };
+        self.expanded_reps = self
+            .expanded_reps
+            .saturating_add(self.multiplier.saturating_mul(distances.len() as u64));
+        if self.expanded_reps > self.options.max_expanded_reps {
+            self.limit_exceeded(Limit::ExpandedReps, self.options.max_expanded_reps);
+            return None;
+        }
+        if distances.contains(&0) {
+            self.error("distance must be at least 1m; remove the set to skip it");
+        }
+        self.next();
+        let stmt = self.parse_statement(distances[0])?;
+
+        let sets = distances
+            .into_iter()
+            .map(|value| {
+                let mut stmt = stmt.clone();
+                stmt.distance.value = value;
+                Set::Statement(Box::new(stmt))
+            })
+            .collect();
+        Some(Set::Block {
+            sets,
+            intensity: None,
+            key: false,
+            label: None,
+            budget: None,
+        })
+    }
+
+    fn parse_block(&mut self) -> Option<Set> {
+        self.expect(Token::BraceOpen, "`{`")?;
+        let (mut sets, mut entries) = (Vec::new(), 0);
+        let mut always = false;
+
+        while self.peek() != Some(&Token::BraceClose) {
+            if self.at_end() {
+                self.error("expected `}` to close block");
+                self.suggest_closing_brace();
+                return None;
+            }
+            self.path.push(sets.len());
+            always |= self.parse_entry(&mut sets, 0)?;
+            self.path.pop();
+            entries += 1;
+        }
+
+        if entries == 0 {
+            self.error("block must contain at least one set");
+            return None;
+        }
+        if !always {
+            self.error("block must keep a set whatever its parameters; add an `else`");
+            return None;
+        }
+        self.next();
+        let intensity = self.parse_intensity()?;
+        let key = self.parse_key();
+
+        Some(Set::Block {
+            sets,
+            intensity,
+            key,
+            label: None,
+            budget: None,
+        })
+    }
+
+    /// Parses instructions for single rounds of a repeated block, e.g.,
+    /// `(round 2 threshold, round 3 race pace)` after `3x { ... }`
+    fn parse_rounds(&mut self, count: u32) -> Option<Vec<Round>> {
+        let mut rounds: Vec<Round> = Vec::new();
+        if self.peek() != Some(&Token::ParenOpen)
+            || self.peek_nth(1) != Some(&Token::Word("round"))
+            || self.newline_before(self.position)
+        {
+            return Some(rounds);
+        }
+        self.next();
+
+        loop {
+            if self.peek() != Some(&Token::Word("round")) {
+                self.error("expected `round` in round list");
+                return None;
+            }
+            self.next();
+            let round = match self.peek() {
+                Some(&Token::Number(round)) if (1..=count).contains(&round) => round,
+                Some(Token::Number(_)) => {
+                    self.error(&format!(
+                        "round must be from 1 to {}, the number of repetitions",
+                        count
+                    ));
+                    return None;
+                }
+                _ => {
+                    self.error("expected round number after `round`");
+                    return None;
+                }
+            };
+            if rounds.iter().any(|given| given.round == round) {
+                self.error(&format!("round {} is given more than once", round));
+                return None;
+            }
+            self.next();
+
+            let mut words = Vec::new();
+            while let Some(&Token::Word(word)) = self.peek() {
+                if self.newline_before(self.position) {
+                    break;
+                }
+                words.push(word);
+                self.next();
+            }
+            if words.is_empty() {
+                self.error("expected instruction after round number");
+                return None;
+            }
+            rounds.push(Round::new(round, &words.join(" ")));
+
+            match self.peek() {
+                Some(Token::Comma) => self.next(),
+                Some(Token::ParenClose) => {
+                    self.next();
+                    break;
+                }
+                _ => {
+                    self.error("expected `,` or `)` in round list");
+                    return None;
+                }
+            };
+        }
+
+        rounds.sort_by_key(|round| round.round);
+        Some(rounds)
+    }
+
+    /// Parses a named block, a section such as `warmup: { ... }`, with an
+    /// optional time budget, `Main (25:00): { ... }`
+    fn parse_labeled_block(&mut self) -> Option<Set> {
+        let name = self.expect_word("expected block name")?;
+        let seconds = if self.peek() == Some(&Token::ParenOpen) {
+            self.next();
+            let seconds = self.parse_duration("time budget", false)?.seconds();
+            self.expect(Token::ParenClose, "`)` after time budget")?;
+            self.expect(Token::Colon, "`:` after time budget")?;
+            Some(seconds)
+        } else {
+            self.expect(Token::Colon, "`:` after block name")?;
+            None
+        };
+        if self.peek() != Some(&Token::BraceOpen) {
+            self.error("expected `{` after block name");
+            return None;
+        }
+
+        let mut block = self.nested(Self::parse_block)?;
+        if let Set::Block { label, budget, .. } = &mut block {
+            *label = Some(name);
+            *budget = seconds;
+        }
+        Some(block)
+    }
+
+    fn parse_dryland(&mut self) -> Option<Set> {
+        self.next();
+        self.expect(Token::BraceOpen, "`{` after `dryland`")?;
+        let mut exercises = Vec::new();
+
+        loop {
+            match self.peek() {
+                Some(Token::BraceClose) if exercises.is_empty() => {
+                    self.error("dryland block must contain at least one exercise");
+                    return None;
+                }
+                Some(Token::BraceClose) => {
+                    self.next();
+                    break;
+                }
+                Some(Token::Comma) => {
+                    self.next();
+                }
+                Some(_) => exercises.push(self.parse_dryland_exercise()?),
+                None => {
+                    self.error("expected `}` to close dryland block");
+                    self.suggest_closing_brace();
+                    return None;
+                }
+            }
+        }
+
+        Some(Set::Dryland { exercises })
+    }
+
+    fn parse_dryland_exercise(&mut self) -> Option<DrylandExercise> {
+        let mut sets = 1;
+        if let Some(Token::Number(count)) = self.peek() {
+            let count = *count;
+            if self.peek_nth(1) == Some(&Token::Times) {
+                sets = count;
+                self.next();
+                self.next();
+            }
         }
+
+        let amount = match self.peek() {
+            Some(Token::Number(reps)) if !self.at_distance() => {
+                let reps = *reps;
+                self.next();
+                DrylandAmount::Reps(reps)
+            }
+            _ => DrylandAmount::Duration(
+                self.parse_duration("repetitions or duration for dryland exercise", false)?
+                    .seconds(),
+            ),
+        };
+
+        let mut words = vec![self.expect_word("expected exercise name")?];
+        while let Some(Token::Word(word)) = self.peek() {
+            words.push(word.to_string());
+            self.next();
+        }
+
+        Some(DrylandExercise {
+            sets,
+            amount,
+            name: words.join(" "),
+        })
+    }
+
+    fn parse_statement(&mut self, value: u32) -> Option<Statement> {
+        let unit = match self.peek() {
+            Some(Token::Meters) => DistanceUnit::Meters,
+            Some(Token::Kilometers) => DistanceUnit::Kilometers,
+            _ => {
+                self.error("expected distance unit `m` or `km`");
+                let number = self.tokens[self.position - 1].1.end;
+                self.suggest(Fix::insert(number, "m"));
+                return None;
+            }
+        };
+        self.next();
+        let distance = Distance { value, unit };
+        let (stroke, alternation, rep_strokes) = if self.at_rep_strokes() {
+            let (stroke, rep_strokes) = self.parse_rep_strokes()?;
+            (stroke, None, rep_strokes)
+        } else {
+            let (stroke, alternation) = self.parse_stroke()?;
+            (stroke, alternation, Vec::new())
+        };
+        let segments = if self.at_segments() {
+            if alternation.is_some() {
+                self.error("a statement can't both alternate strokes and be swum in parts");
+                return None;
+            }
+            self.parse_segments(&distance)?
+        } else {
+            Vec::new()
+        };
+
+        let equipment = if self.peek() == Some(&Token::BracketOpen) {
+            self.parse_equipment()?
+        } else {
+            Vec::new()
+        };
+
+        let (targets, hold, trial) = match self.peek() {
+            Some(Token::Word("targets")) => (self.parse_targets()?, None, None),
+            Some(Token::Word("hold")) => (Vec::new(), Some(self.parse_hold()?), None),
+            Some(Token::Word("TT")) => (Vec::new(), None, Some(self.parse_trial()?)),
+            _ => (Vec::new(), None, None),
+        };
+
+        let interval = if self.peek() == Some(&Token::At)
+            && (!self.at_intensity() || self.at_named_interval())
+        {
+            self.next();
+            Some(self.parse_interval()?)
+        } else if self.peek() == Some(&Token::Word("rest"))
+            && self.peek_nth(1) == Some(&Token::Word("to"))
+        {
+            Some(self.parse_heart_rate_rest()?)
+        } else if let Some(&Token::Rest(secs)) = self.peek() {
+            if secs == 0 {
+                self.error("rest must be positive; leave it out for none");
+                return None;
+            }
+            self.next();
+            Some(Interval::Rest(secs))
+        } else {
+            None
+        };
+        let intensity = self.parse_intensity()?;
+        let key = self.parse_key();
+
+        if self.options.strict_newlines
+            && !self.at_end()
+            && self.peek() != Some(&Token::BraceClose)
+            && !self.newline_before(self.position)
+        {
+            self.error("expected a new line after statement");
+            return None;
+        }
+
+        Some(Statement {
+            distance,
+            stroke,
+            equipment,
+            targets,
+            interval,
+            intensity,
+            alternation,
+            rep_strokes,
+            segments,
+            key,
+            coach_notes: self.take_notes_before(self.line_end()),
+            hold,
+            trial,
+        })
+    }
+
+    /// Parses a stroke, or strokes alternated with `free/back(easy) by 50`
+    fn parse_stroke(&mut self) -> Option<(Stroke, Option<Alternation>)> {
+        let name = self.expect_word("expected stroke name")?;
+        self.check_spelling(self.position - 1);
+        let mut strokes = Vec::new();
+        while self.peek() == Some(&Token::Slash) {
+            if strokes.is_empty() {
+                strokes.push(name.clone());
+            }
+            self.next();
+            strokes.push(self.expect_word("expected stroke name after `/`")?);
+            self.check_spelling(self.position - 1);
+        }
+        let modifiers = self.parse_modifiers()?;
+
+        let alternation = if !strokes.is_empty() {
+            if self.peek() != Some(&Token::Word("by")) {
+                self.error("expected `by <distance>` after alternating strokes");
+                return None;
+            }
+            self.next();
+            let Some(Token::Number(every)) = self.peek() else {
+                self.error("expected distance after `by`");
+                return None;
+            };
+            let every = *every;
+            self.next();
+            if self.peek() == Some(&Token::Meters) {
+                self.next();
+            }
+            if every == 0 {
+                self.error("alternation distance must be positive");
+                return None;
+            }
+            Some(Alternation { strokes, every })
+        } else {
+            None
+        };
+
+        Some((Stroke { name, modifiers }, alternation))
+    }
+
+    /// Whether the stroke is given per rep, e.g., `odds:free`
+    fn at_rep_strokes(&self) -> bool {
+        matches!(self.peek(), Some(Token::Word("odds" | "evens")))
+            && self.peek_nth(1) == Some(&Token::Colon)
+    }
+
+    /// Parses strokes taken by odd and even reps in turn, `odds:free
+    /// evens:back(easy)`, with modifiers for every rep after the last
+    fn parse_rep_strokes(&mut self) -> Option<(Stroke, Vec<String>)> {
+        if self.multiplier <= 1 {
+            self.error(
+                "odd and even reps need a repeated statement, e.g., `8x50m odds:free evens:back`",
+            );
+            return None;
+        }
+        let (mut odds, mut evens) = (None, None);
+        while self.at_rep_strokes() {
+            let Some(&Token::Word(parity)) = self.peek() else {
+                break;
+            };
+            if parity == "odds" && odds.is_some() || parity == "evens" && evens.is_some() {
+                self.error(&format!("`{}:` is given more than once", parity));
+                return None;
+            }
+            self.next();
+            self.next();
+            let stroke = self.expect_word(&format!("expected stroke name after `{}:`", parity))?;
+            self.check_spelling(self.position - 1);
+            match parity {
+                "odds" => odds = Some(stroke),
+                _ => evens = Some(stroke),
+            }
+        }
+        let (Some(odds), Some(evens)) = (odds, evens) else {
+            self.error("expected strokes for both `odds:` and `evens:`");
+            return None;
+        };
+        let modifiers = self.parse_modifiers()?;
+        Some((
+            Stroke {
+                name: odds.clone(),
+                modifiers,
+            },
+            vec![odds, evens],
+        ))
+    }
+
+    /// Whether a list of the parts of each rep comes next on the line, e.g.,
+    /// `(25 drill/25 swim)`
+    fn at_segments(&self) -> bool {
+        self.peek() == Some(&Token::ParenOpen)
+            && matches!(self.peek_nth(1), Some(Token::Number(_)))
+            && !self.newline_before(self.position + 1)
+    }
+
+    /// Parses the parts each rep is swum in, `(25 drill(catch-up)/25 swim)`,
+    /// which must add up to the statement's distance
+    ///
+    /// A mistake in the list is recorded and the rest of it skipped, as for
+    /// modifier lists, leaving the statement in one part.
+    fn parse_segments(&mut self, distance: &Distance) -> Option<Vec<Segment>> {
+        let start = self.position;
+        self.next();
+        let mut segments = Vec::new();
+        if self.parse_segment_entries(&mut segments).is_none() {
+            self.skip_modifier_list();
+            return Some(Vec::new());
+        }
+
+        let total: u64 = segments
+            .iter()
+            .map(|segment| u64::from(segment.distance.meters()))
+            .sum();
+        if total != u64::from(distance.meters()) {
+            let end = self.position + 1;
+            self.position = start;
+            self.error_spanning(
+                end - start,
+                &format!(
+                    "parts add up to {}m, not the {}m of the statement",
+                    total,
+                    distance.meters()
+                ),
+            );
+            return None;
+        }
+        self.next();
+        Some(segments)
+    }
+
+    /// Parses the parts of a list up to its closing `)`
+    fn parse_segment_entries(&mut self, segments: &mut Vec<Segment>) -> Option<()> {
+        loop {
+            let value = match self.peek() {
+                Some(&Token::Number(value)) if !self.newline_before(self.position) => value,
+                _ => {
+                    self.error("expected distance of the part, e.g., `25 drill`");
+                    return None;
+                }
+            };
+            if value == 0 {
+                self.error("distance of a part must be positive");
+                return None;
+            }
+            self.next();
+            let unit = match self.peek() {
+                Some(Token::Kilometers) => DistanceUnit::Kilometers,
+                _ => DistanceUnit::Meters,
+            };
+            if matches!(self.peek(), Some(Token::Meters | Token::Kilometers)) {
+                self.next();
+            }
+            let name = self.expect_word("expected stroke name of the part")?;
+            self.check_spelling(self.position - 1);
+            let modifiers = self.parse_modifiers()?;
+            segments.push(Segment {
+                distance: Distance { value, unit },
+                stroke: Stroke { name, modifiers },
+            });
+
+            let same_line = !self.newline_before(self.position);
+            match self.peek() {
+                Some(Token::Slash) if same_line => self.next(),
+                Some(Token::ParenClose) if same_line => return Some(()),
+                _ => {
+                    self.error("expected `/` or `)` in list of parts");
+                    if !same_line {
+                        self.suggest(Fix::insert(self.line_end(), ")"));
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn parse_equipment(&mut self) -> Option<Vec<Equipment>> {
+        self.expect(Token::BracketOpen, "`[`")?;
+        let mut equipment = Vec::new();
+
+        loop {
+            let name = self.expect_word("expected equipment")?;
+            let parameters = self.parse_word_list("equipment parameter")?;
+            equipment.push(Equipment { name, parameters });
+
+            match self.peek() {
+                Some(Token::Comma) => self.next(),
+                Some(Token::BracketClose) => {
+                    self.next();
+                    break;
+                }
+                _ => {
+                    self.error("expected `,` or `]` in equipment list");
+                    return None;
+                }
+            };
+        }
+
+        Some(equipment)
+    }
+
+    /// Parses `targets(1:10, 1:08)`, one goal time per rep of the enclosing
+    /// repetition
+    fn parse_targets(&mut self) -> Option<Vec<u32>> {
+        self.next();
+        self.expect(Token::ParenOpen, "`(` after `targets`")?;
+        let mut targets = Vec::new();
+
+        loop {
+            targets.push(self.parse_duration("target time", true)?.seconds());
+
+            match self.peek() {
+                Some(Token::Comma) => self.next(),
+                Some(Token::ParenClose) => break,
+                _ => {
+                    self.error("expected `,` or `)` in target list");
+                    return None;
+                }
+            };
+        }
+
+        if targets.len() != self.reps as usize {
+            self.error(&format!(
+                "expected one target time per rep ({}) but got {}",
+                self.reps,
+                targets.len()
+            ));
+            return None;
+        }
+        self.next();
+
+        Some(targets)
+    }
+
+    /// Parses `hold rp200+1`, a race pace every rep is held at
+    fn parse_hold(&mut self) -> Option<RacePace> {
+        self.next();
+        let Some(Token::RacePace(_)) = self.peek() else {
+            self.error("expected race pace after `hold`, e.g., `rp200`");
+            return None;
+        };
+        self.parse_race_pace()
+    }
+
+    /// Parses a time trial result, `TT __:__` or `TT 2:31`, keeping where a
+    /// placeholder is so the result can be written in later
+    fn parse_trial(&mut self) -> Option<Trial> {
+        if self.multiplier > 1 {
+            self.error("a time trial is a single swim and can't be repeated");
+            return None;
+        }
+        self.next();
+        if self.peek() == Some(&Token::Placeholder) {
+            let span = self.tokens[self.position].1.clone();
+            self.placeholders
+                .push((SetPath::new(self.path.clone()), span));
+            self.next();
+            return Some(Trial::Pending);
+        }
+        let time = self.parse_duration("time or `__:__` after `TT`", false)?;
+        Some(Trial::Recorded(time.seconds()))
+    }
+
+    /// Parses `rest to HR<120`, resting after each rep until the heart rate
+    /// drops below the given beats per minute
+    fn parse_heart_rate_rest(&mut self) -> Option<Interval> {
+        self.next();
+        self.next();
+        let Some(&Token::HeartRate(below)) = self.peek() else {
+            self.error("expected heart rate after `rest to`, e.g., `HR<120`");
+            return None;
+        };
+        if below == 0 {
+            self.error("heart rate to rest to must be positive");
+            return None;
+        }
+        self.next();
+        Some(Interval::HeartRate { below })
+    }
+
+    fn parse_race_pace(&mut self) -> Option<RacePace> {
+        let Some(&Token::RacePace((event, offset))) = self.peek() else {
+            self.error("expected race pace");
+            return None;
+        };
+        if event == 0 {
+            self.error("race-pace event distance must be positive");
+            return None;
+        }
+        self.next();
+        Some(RacePace { event, offset })
+    }
+
+    /// Parses an optional parenthesized, comma separated list of words
+    fn parse_word_list(&mut self, description: &str) -> Option<Vec<String>> {
+        let mut words = Vec::new();
+        if self.peek() != Some(&Token::ParenOpen) {
+            return Some(words);
+        }
+        self.next();
+
+        loop {
+            match self.peek() {
+                Some(Token::Word(word)) | Some(Token::NumberList(word)) => {
+                    words.push(word.to_string());
+                    self.next();
+                }
+                _ => {
+                    self.error(&format!("expected {}", description));
+                    return None;
+                }
+            }
+            match self.peek() {
+                Some(Token::Comma) => self.next(),
+                Some(Token::ParenClose) => {
+                    self.next();
+                    break;
+                }
+                _ => {
+                    self.error(&format!("expected `,` or `)` in {} list", description));
+                    return None;
+                }
+            };
+        }
+
+        Some(words)
+    }
+
+    /// Parses a parenthesized modifier list, each modifier a word followed
+    /// by optional arguments, e.g., `(descend 1-3, kick 15m)`
+    ///
+    /// A mistake in the list is recorded and the rest of the list skipped,
+    /// so the sets after it are still checked.
+    fn parse_modifiers(&mut self) -> Option<Modifiers> {
+        let mut modifiers = Modifiers::new();
+        if self.peek() != Some(&Token::ParenOpen) || self.at_segments() {
+            return Some(modifiers);
+        }
+        self.next();
+
+        if self.parse_modifier_entries(&mut modifiers).is_none() {
+            self.skip_modifier_list();
+        }
+        Some(modifiers)
+    }
+
+    /// Parses the modifiers of a list through its closing `)`
+    fn parse_modifier_entries(&mut self, modifiers: &mut Modifiers) -> Option<()> {
+        loop {
+            let mut modifier = Modifier::new(self.expect_name("expected modifier")?);
+            while matches!(self.peek(), Some(Token::Number(_) | Token::NumberList(_)))
+                && !self.newline_before(self.position)
+            {
+                modifier.args.push(self.parse_modifier_arg()?);
+            }
+            modifiers.push(modifier);
+            match self.peek() {
+                Some(Token::Comma) => self.next(),
+                Some(Token::ParenClose) => {
+                    self.next();
+                    return Some(());
+                }
+                _ => {
+                    self.error("expected `,` or `)` in modifier list");
+                    if self.newline_before(self.position) {
+                        self.suggest(Fix::insert(self.line_end(), ")"));
+                    }
+                    return None;
+                }
+            };
+        }
+    }
+
+    /// Skips what's left of a modifier list after a mistake, through its
+    /// `)` or to the end of its line, closing it there
+    fn skip_modifier_list(&mut self) {
+        while !self.newline_before(self.position) {
+            let closing = self.peek() == Some(&Token::ParenClose);
+            self.next();
+            if closing {
+                break;
+            }
+        }
+    }
+
+    /// Parses a modifier argument: a count, number list or distance
+    fn parse_modifier_arg(&mut self) -> Option<Value> {
+        match self.peek() {
+            Some(&Token::NumberList(list)) => {
+                let Some(numbers) = list.split('-').map(|n| n.parse().ok()).collect() else {
+                    self.error("number in list is too large");
+                    return None;
+                };
+                self.next();
+                Some(Value::NumberList(numbers))
+            }
+            Some(&Token::Number(value)) if self.at_distance() => {
+                if value == 0 {
+                    self.error_spanning(2, "modifier distance must be positive");
+                    return None;
+                }
+                let unit = match self.peek_nth(1) {
+                    Some(Token::Kilometers) => DistanceUnit::Kilometers,
+                    _ => DistanceUnit::Meters,
+                };
+                self.next();
+                self.next();
+                Some(Value::Distance(Distance { value, unit }))
+            }
+            Some(&Token::Number(value)) => {
+                self.next();
+                Some(Value::Number(value))
+            }
+            _ => {
+                self.error("expected modifier argument");
+                None
+            }
+        }
+    }
+
+    /// Parses the interval after `@`, or a list of send-offs the reps take in
+    /// turn, e.g., `1:40/1:35/1:30`
+    fn parse_interval(&mut self) -> Option<Interval> {
+        let first = self.parse_send_off()?;
+        if self.peek() != Some(&Token::Slash) {
+            return Some(first);
+        }
+        let mut intervals = vec![first];
+        loop {
+            if matches!(
+                intervals.last(),
+                Some(Interval::Base { .. } | Interval::RacePace(_))
+            ) {
+                self.error(
+                    "a list of send-offs takes times or named send-offs, not `base` or race pace",
+                );
+                return None;
+            }
+            if self.peek() != Some(&Token::Slash) {
+                return Some(Interval::Sequence(intervals));
+            }
+            self.next();
+            intervals.push(self.parse_send_off()?);
+        }
+    }
+
+    fn parse_send_off(&mut self) -> Option<Interval> {
+        if let Some((name, interval)) = match self.peek() {
+            Some(&Token::Word(name)) => self
+                .intervals
+                .get(name)
+                .map(|interval| (name, interval.clone())),
+            _ => None,
+        } {
+            self.use_interval(name);
+            self.next();
+            return Some(interval);
+        }
+        if let Some(Token::Base(offset)) = self.peek() {
+            let offset = *offset;
+            let interval = match self.intervals.get("base") {
+                Some(_) => self.offset_interval("base", offset)?,
+                None => Interval::Base { offset },
+            };
+            self.next();
+            return Some(interval);
+        }
+        if let Some(&Token::NamedOffset((name, offset))) = self.peek() {
+            if !self.intervals.contains_key(name) {
+                self.error(&format!("unknown interval `{}`", name));
+                return None;
+            }
+            let interval = self.offset_interval(name, offset)?;
+            self.next();
+            return Some(interval);
+        }
+
+        if let Some(Token::RacePace(_)) = self.peek() {
+            return self.parse_race_pace().map(Interval::RacePace);
+        }
+
+        if self.peek().is_some() && self.duration_is_zero() {
+            self.error("interval of 0 seconds; did you mean to omit it?");
+        }
+        self.parse_duration("interval after `@`", true)
+    }
+
+    /// The named send-off `name` moved by `offset` seconds, e.g., `@sprint+5`
+    fn offset_interval(&mut self, name: &'source str, offset: i32) -> Option<Interval> {
+        self.use_interval(name);
+        let secs = self.intervals[name].seconds() as i64 + offset as i64;
+        if secs <= 0 {
+            self.error(&format!("{} interval is not positive", name));
+            return None;
+        }
+        Some(Interval::from_seconds(secs as u32))
+    }
+
+    /// Parses a duration as written: `1:30`, `:50`, `90s` or, if `bare` is
+    /// allowed, `90`
+    ///
+    /// A distance such as `100m` gets its own error, since it is the usual
+    /// mistake where a duration is expected.
+    fn parse_duration(&mut self, expected: &str, bare: bool) -> Option<Interval> {
+        let interval = match self.peek() {
+            Some(Token::Time((minutes, seconds))) => {
+                let (minutes, seconds) = (*minutes, *seconds);
+                if seconds >= 60 {
+                    self.error("seconds must be below 60");
+                    return None;
+                }
+                Interval::MinutesSeconds { minutes, seconds }
+            }
+            Some(Token::Colon) => match self.peek_nth(1) {
+                Some(Token::Number(seconds)) if *seconds < 60 => {
+                    let seconds = *seconds;
+                    self.next();
+                    Interval::MinutesSeconds {
+                        minutes: 0,
+                        seconds,
+                    }
+                }
+                _ => {
+                    self.error(&format!("expected {}", expected));
+                    return None;
+                }
+            },
+            Some(Token::Seconds(secs)) => Interval::Seconds(*secs),
+            Some(Token::Number(_)) if self.at_distance() => {
+                self.error_spanning(2, &format!("expected {}, not a distance", expected));
+                return None;
+            }
+            Some(Token::Number(secs)) if bare => Interval::Seconds(*secs),
+            _ => {
+                self.error(&format!("expected {}", expected));
+                return None;
+            }
+        };
+        self.next();
+        Some(interval)
+    }
+
+    /// Whether the next tokens are a number with a distance unit
+    fn at_distance(&self) -> bool {
+        matches!(self.peek(), Some(Token::Number(_)))
+            && matches!(
+                self.peek_nth(1),
+                Some(Token::Meters) | Some(Token::Kilometers)
+            )
+    }
+
+    /// Whether the next tokens are a duration of zero
+    fn duration_is_zero(&self) -> bool {
+        match self.peek() {
+            Some(Token::Time((0, 0))) | Some(Token::Seconds(0)) => true,
+            Some(Token::Number(0)) => !self.at_distance(),
+            Some(Token::Colon) => self.peek_nth(1) == Some(&Token::Number(0)),
+            _ => false,
+        }
+    }
+
+    /// Coach notes not yet attached that start before `end`: those on the
+    /// current statement's line or on their own lines before it
+    fn take_notes_before(&mut self, end: usize) -> Vec<String> {
+        let mut notes = Vec::new();
+        while let Some((span, note)) = self.notes.get(self.next_note) {
+            if span.start >= end {
+                break;
+            }
+            notes.push(note.to_string());
+            self.next_note += 1;
+        }
+        notes
+    }
+
+    /// Offset of the end of the line holding the last token consumed
+    fn line_end(&self) -> usize {
+        let end = self
+            .position
+            .checked_sub(1)
+            .map_or(0, |p| self.tokens[p].1.end);
+        self.source[end..]
+            .find('\n')
+            .map_or(self.source.len(), |i| end + i)
     }
 
-    pub fn parse(&mut self) -> Result<Workout, Vec<String>> {
-        let workout = Workout { sets: vec![] };
+    /// Whether the next tokens are an `@z3`, `@threshold`, `!Z3` or `!85%`
+    /// intensity
+    fn at_intensity(&self) -> bool {
+        match self.peek() {
+            Some(Token::At) => matches!(
+                self.peek_nth(1),
+                Some(Token::Zone(_)) | Some(Token::Word(_))
+            ),
+            Some(Token::Bang) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the next tokens are `@` and a name from the `intervals` header,
+    /// which wins over an intensity of the same name
+    fn at_named_interval(&self) -> bool {
+        self.peek() == Some(&Token::At)
+            && matches!(self.peek_nth(1), Some(Token::Word(name)) if self.intervals.contains_key(name))
+    }
+
+    /// Parses an optional `@z3` or `@threshold` intensity, or an effort
+    /// `!Z3` or `!85%`
+    fn parse_intensity(&mut self) -> Option<Option<Intensity>> {
+        if !self.at_intensity() {
+            return Some(None);
+        }
+        let bang = self.peek() == Some(&Token::Bang);
+        self.next();
+        if bang {
+            return self.parse_effort().map(Some);
+        }
+
+        let intensity = match self.peek() {
+            Some(Token::Zone(zone)) => Intensity::from_zone(*zone),
+            Some(Token::Word(word)) => Intensity::from_name(word),
+            _ => None,
+        };
+        match intensity {
+            Some(intensity) => {
+                self.next();
+                Some(Some(intensity))
+            }
+            None => {
+                self.error("expected intensity zone `z1` to `z5` or intensity name");
+                None
+            }
+        }
+    }
+
+    /// Parses the zone or share of all-out effort after `!`
+    fn parse_effort(&mut self) -> Option<Intensity> {
+        let intensity = match self.peek() {
+            Some(Token::Zone(zone)) => Intensity::from_zone(*zone),
+            Some(&Token::Effort(percent)) => {
+                let intensity = Intensity::from_effort(percent);
+                if intensity.is_none() {
+                    self.error("effort must be from 1% to 100%");
+                    return None;
+                }
+                intensity
+            }
+            _ => None,
+        };
+        if intensity.is_none() {
+            self.error("expected zone `Z1` to `Z5` or effort, e.g., `85%`, after `!`");
+            return None;
+        }
+        self.next();
+        intensity
+    }
+
+    /// Parses a trailing `key` (or `primary`) marker
+    fn parse_key(&mut self) -> bool {
+        let key = matches!(self.peek(), Some(Token::Word("key" | "primary")));
+        if key {
+            self.next();
+        }
+        key
+    }
+
+    fn expect(&mut self, token: Token<'source>, description: &str) -> Option<()> {
+        if self.peek() == Some(&token) {
+            self.next();
+            Some(())
+        } else {
+            self.error(&format!("expected {}", description));
+            None
+        }
+    }
+
+    fn expect_word(&mut self, message: &str) -> Option<String> {
+        match self.peek() {
+            Some(Token::Word(word)) | Some(Token::NumberList(word)) => {
+                let word = word.to_string();
+                self.next();
+                Some(word)
+            }
+            _ => {
+                self.error(message);
+                None
+            }
+        }
+    }
+
+    /// Like [`expect_word`](Self::expect_word), for a modifier name, which
+    /// is interned with the `compact-modifiers` feature
+    fn expect_name(&mut self, message: &str) -> Option<Name> {
+        match self.peek() {
+            Some(&Token::Word(word)) | Some(&Token::NumberList(word)) => {
+                self.next();
+                #[cfg(feature = "compact-modifiers")]
+                let name = self
+                    .names
+                    .entry(word)
+                    .or_insert_with(|| word.into())
+                    .clone();
+                #[cfg(not(feature = "compact-modifiers"))]
+                let name = word.to_string();
+                Some(name)
+            }
+            _ => {
+                self.error(message);
+                None
+            }
+        }
+    }
+
+    /// Parses a set nested one level deeper, enforcing the depth limit
+    fn nested(&mut self, parse: fn(&mut Self) -> Option<Set>) -> Option<Set> {
+        if self.depth >= self.options.max_depth {
+            self.limit_exceeded(Limit::Depth, self.options.max_depth.into());
+            return None;
+        }
+        self.depth += 1;
+        let set = parse(self);
+        self.depth -= 1;
+        set
+    }
+
+    /// Records an error at the current token
+    fn error(&mut self, message: &str) {
+        let kind = match self.tokens.get(self.position) {
+            Some((Ok(_), span)) => ParseErrorKind::Syntax {
+                message: message.to_string(),
+                found: Some(self.source[span.clone()].to_string()),
+            },
+            Some((Err(LexError::Invalid), span)) => ParseErrorKind::InvalidToken {
+                found: self.source[span.clone()].to_string(),
+            },
+            Some((Err(LexError::NumberTooLarge), span)) => ParseErrorKind::NumberTooLarge {
+                found: self.source[span.clone()].to_string(),
+                max: u32::MAX,
+            },
+            None => ParseErrorKind::Syntax {
+                message: message.to_string(),
+                found: None,
+            },
+        };
+        self.push_error(kind);
+    }
+
+    /// Records an error covering the next `count` tokens, e.g., both the
+    /// number and unit of a distance
+    fn error_spanning(&mut self, count: usize, message: &str) {
+        let (Some((_, first)), Some((_, last))) = (
+            self.tokens.get(self.position),
+            self.tokens.get(self.position + count - 1),
+        ) else {
+            return self.error(message);
+        };
+        let span = first.start..last.end;
+        self.errors.push(ParseError {
+            kind: ParseErrorKind::Syntax {
+                message: message.to_string(),
+                found: Some(self.source[span.clone()].to_string()),
+            },
+            path: (!self.path.is_empty()).then(|| SetPath::new(self.path.clone())),
+            span: Some(span),
+            fix: None,
+        });
+    }
+
+    /// Records a warning or note in the set being parsed
+    fn diagnose(&mut self, severity: Severity, message: &str, span: Option<Span>) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            path: (!self.path.is_empty()).then(|| SetPath::new(self.path.clone())),
+            span,
+            message: message.to_string(),
+            fix: None,
+        });
+    }
+
+    /// Attaches a fix to the error just recorded
+    fn suggest(&mut self, fix: Fix) {
+        if let Some(error) = self.errors.last_mut() {
+            error.fix = Some(fix);
+        }
+    }
+
+    /// Suggests closing an unclosed brace at the end of the input
+    fn suggest_closing_brace(&mut self) {
+        if self.at_end() {
+            let closing = if self.source.ends_with('\n') {
+                "}\n"
+            } else {
+                "\n}"
+            };
+            self.suggest(Fix::insert(self.source.len(), closing));
+        }
+    }
+
+    /// Warns about a stroke name at `position` that looks like a misspelled
+    /// stroke, unless it is one of the drills, with a fix replacing it when
+    /// it is a single edit away
+    fn check_spelling(&mut self, position: usize) {
+        let Some((Ok(Token::Word(name)), span)) = self.tokens.get(position) else {
+            return;
+        };
+        let (name, span) = (*name, span.clone());
+        let Some((stroke, edits)) = closest_stroke(name) else {
+            return;
+        };
+        if self.options.drills.stroke(name).is_some() {
+            return;
+        }
+        self.diagnose(
+            Severity::Warning,
+            &format!("unknown stroke `{}`; did you mean `{}`?", name, stroke),
+            Some(span.clone()),
+        );
+        if edits > 1 {
+            return;
+        }
+        if let Some(diagnostic) = self.diagnostics.last_mut() {
+            diagnostic.fix = Some(Fix::replace(span, stroke));
+        }
+    }
+
+    fn use_interval(&mut self, name: &str) {
+        self.unused_intervals.retain(|(unused, _)| *unused != name);
+    }
+
+    /// Reports the set that takes the workout's total distance or time over
+    /// its limit, if one does
+    fn check_totals(&mut self, sets: &[Set]) {
+        let mut usage = Usage::default();
+        for (i, set) in sets.iter().enumerate() {
+            usage.add_totals(set);
+            let Some((limit, max)) = usage.exceeded(&self.options) else {
+                continue;
+            };
+            let path = SetPath::new(vec![i]);
+            let span = self
+                .set_spans
+                .iter()
+                .find(|(spanned, _)| *spanned == path)
+                .map(|(_, span)| span.clone());
+            self.errors.push(ParseError {
+                kind: ParseErrorKind::LimitExceeded { limit, max },
+                path: Some(path),
+                span,
+                fix: None,
+            });
+            return;
+        }
+    }
+
+    fn limit_exceeded(&mut self, limit: Limit, max: u64) {
+        self.push_error(ParseErrorKind::LimitExceeded { limit, max });
+    }
+
+    /// Records an error at the current token in the set being parsed
+    fn push_error(&mut self, kind: ParseErrorKind) {
+        let path = (!self.path.is_empty()).then(|| SetPath::new(self.path.clone()));
+        let span = self.tokens.get(self.position).map(|(_, span)| span.clone());
+        self.errors.push(ParseError {
+            kind,
+            path,
+            span,
+            fix: None,
+        });
+    }
+
+    /// Whether a line break (other than a `\` continuation) precedes the token at `position`
+    fn newline_before(&self, position: usize) -> bool {
+        let (Some((_, previous)), Some((_, next))) = (
+            position.checked_sub(1).and_then(|p| self.tokens.get(p)),
+            self.tokens.get(position),
+        ) else {
+            return true;
+        };
+
+        let mut lines = self.source[previous.end..next.start].split('\n');
+        lines.next_back();
+        lines.any(|line| !line.trim_end().ends_with('\\'))
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&Token<'source>> {
+        match self.tokens.get(self.position) {
+            Some((Ok(token), _)) => Some(token),
+            _ => None,
+        }
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&Token<'source>> {
+        match self.tokens.get(self.position + n) {
+            Some((Ok(token), _)) => Some(token),
+            _ => None,
+        }
+    }
+
+    /// Moves past the current token
+    fn next(&mut self) {
+        if !self.at_end() {
+            self.position += 1;
+        }
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Depth => write!(f, "nesting depth"),
+            Limit::Sets => write!(f, "number of sets"),
+            Limit::ExpandedReps => write!(f, "number of expanded reps"),
+            Limit::TotalDistance => write!(f, "total distance"),
+            Limit::TotalTime => write!(f, "total time"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "set {}: ", path)?;
+        }
+        write!(f, "{}", self.message())
+    }
+}
+
+impl ParseError {
+    /// Offending input, or `None` at the end of input
+    pub fn found(&self) -> Option<&str> {
+        match &self.kind {
+            ParseErrorKind::Syntax { found, .. } => found.as_deref(),
+            ParseErrorKind::InvalidToken { found }
+            | ParseErrorKind::NumberTooLarge { found, .. } => Some(found),
+            ParseErrorKind::LimitExceeded { .. } => None,
+        }
+    }
+
+    /// The error with its location in the source but not the set it is in
+    pub fn message(&self) -> String {
+        let message = match &self.kind {
+            ParseErrorKind::Syntax { message, .. } => message.clone(),
+            ParseErrorKind::InvalidToken { .. } => "unexpected input".to_string(),
+            ParseErrorKind::NumberTooLarge { max, .. } => {
+                format!("number too large; the maximum is {}", max)
+            }
+            ParseErrorKind::LimitExceeded { limit, max } => {
+                let message = format!("{} exceeds the maximum of {}", limit, max);
+                return match &self.span {
+                    Some(span) => format!("{} at {}..{}", message, span.start, span.end),
+                    None => message,
+                };
+            }
+        };
+        match (&self.span, self.found()) {
+            (Some(span), Some(found)) => format!(
+                "{} at {}..{}, found `{}`",
+                message, span.start, span.end, found
+            ),
+            _ => format!("{}, found end of input", message),
+        }
+    }
+}
+
+/// Last statement of the sets, looking inside repetitions and blocks
+/// How much of the limits in [`ParserOptions`] a workout's sets use
+#[derive(Debug, Default)]
+struct Usage {
+    depth: u32,
+    sets: u64,
+    expanded_reps: u64,
+    distance: u64,
+    time: u64,
+}
+
+impl Usage {
+    /// Adds the distance and time of a set, counting rest after reps as time
+    fn add_totals(&mut self, set: &Set) {
+        self.distance = self.distance.saturating_add(set.total_distance().into());
+        self.time = self.time.saturating_add(set.total_time().into());
+        set.for_each_statement(&mut |stmt, reps| {
+            if let Some(interval) = &stmt.interval {
+                let rest = u64::from(interval.rest(0)) * u64::from(reps);
+                self.time = self.time.saturating_add(rest);
+            }
+        });
+    }
+
+    /// Adds the nesting, sets and expanded reps of a set inside `depth`
+    /// repetitions and blocks that are repeated `multiplier` times
+    fn add_structure(&mut self, set: &Set, depth: u32, multiplier: u64) {
+        match set {
+            Set::Repetition { count, set, .. } => {
+                self.depth = self.depth.max(depth + 1);
+                let multiplier = multiplier.saturating_mul((*count).into());
+                self.add_structure(set, depth + 1, multiplier);
+            }
+            Set::Block { sets, .. } => {
+                self.depth = self.depth.max(depth + 1);
+                self.sets = self.sets.saturating_add(sets.len() as u64);
+                for set in sets {
+                    self.add_structure(set, depth + 1, multiplier);
+                }
+            }
+            Set::Statement(_) => self.expanded_reps = self.expanded_reps.saturating_add(multiplier),
+            Set::Dryland { .. } => {}
+        }
+    }
+
+    /// The first limit of `options` the sets go over, with its maximum
+    fn exceeded(&self, options: &ParserOptions) -> Option<(Limit, u64)> {
+        [
+            (Limit::Depth, self.depth.into(), options.max_depth.into()),
+            (Limit::Sets, self.sets, options.max_sets.into()),
+            (
+                Limit::ExpandedReps,
+                self.expanded_reps,
+                options.max_expanded_reps,
+            ),
+            (
+                Limit::TotalDistance,
+                self.distance,
+                options.max_total_distance,
+            ),
+            (Limit::TotalTime, self.time, options.max_total_time),
+        ]
+        .into_iter()
+        .find(|(_, used, max)| used > max)
+        .map(|(limit, _, max)| (limit, max))
+    }
+}
+
+/// The first limit of `options` a workout that wasn't parsed, e.g., one
+/// imported from JSON, goes over, at the top-level set that takes it over
+pub fn limit_error(workout: &Workout, options: &ParserOptions) -> Option<ParseError> {
+    let mut usage = Usage::default();
+    for (i, set) in workout.sets.iter().enumerate() {
+        usage.sets += 1;
+        usage.add_structure(set, 0, 1);
+        usage.add_totals(set);
+        if let Some((limit, max)) = usage.exceeded(options) {
+            return Some(ParseError {
+                kind: ParseErrorKind::LimitExceeded { limit, max },
+                path: Some(SetPath::new(vec![i])),
+                span: None,
+                fix: None,
+            });
+        }
+    }
+    None
+}
+
+fn last_statement_mut(sets: &mut [Set]) -> Option<&mut Statement> {
+    sets.iter_mut().rev().find_map(|set| match set {
+        Set::Statement(stmt) => Some(stmt.as_mut()),
+        Set::Repetition { set, .. } => last_statement_mut(std::slice::from_mut(set)),
+        Set::Block { sets, .. } => last_statement_mut(sets),
+        Set::Dryland { .. } => None,
+    })
+}
+
+/// Splits a `minutes:seconds` time literal, rejecting out of range seconds
+fn parse_time(time: &str) -> Option<(u32, u32)> {
+    let (minutes, seconds) = time.trim_end_matches('s').split_once(':')?;
+    let seconds = seconds.parse().ok().filter(|seconds| *seconds < 60)?;
+    Some((minutes.parse().ok()?, seconds))
+}
+
+/// Parses a duration written as `1:30`, `90s` or `90` into seconds
+pub(crate) fn parse_duration(text: &str) -> Option<u32> {
+    if text.contains(':') {
+        parse_time(text)
+            .and_then(|(minutes, seconds)| minutes.checked_mul(60)?.checked_add(seconds))
+    } else {
+        text.trim_end_matches('s').parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use logos::Logos;
+
+    fn parse(source: &str) -> Result<Workout, Vec<String>> {
+        parse_with(source, ParserOptions::default())
+    }
+
+    fn parse_with(source: &str, options: ParserOptions) -> Result<Workout, Vec<String>> {
+        Parser::with_options(Token::lexer(source), options)
+            .parse()
+            .map_err(|errors| errors.iter().map(ToString::to_string).collect())
+    }
+
+    #[test]
+    fn test_statement() {
+        let workout = parse("100m freestyle(drill, kick) @1:30").unwrap();
+
+        assert_eq!(
+            workout.sets,
+            vec![Set::Statement(Box::new(Statement {
+                distance: Distance {
+                    value: 100,
+                    unit: DistanceUnit::Meters,
+                },
+                stroke: Stroke {
+                    name: "freestyle".to_string(),
+                    modifiers: [Modifier::new("drill"), Modifier::new("kick")]
+                        .into_iter()
+                        .collect(),
+                },
+                equipment: vec![],
+                targets: vec![],
+                interval: Some(Interval::MinutesSeconds {
+                    minutes: 1,
+                    seconds: 30,
+                }),
+                intensity: None,
+                alternation: None,
+                rep_strokes: Vec::new(),
+                segments: Vec::new(),
+                key: false,
+                coach_notes: vec![],
+                hold: None,
+                trial: None,
+            }))]
+        );
+    }
+
+    #[test]
+    fn test_modifier_arguments() {
+        let workout = parse("4x 100m free(descend 1-3, breathe 3, kick 15m, easy) @1:40").unwrap();
+        assert_eq!(
+            workout.sets[0]
+                .statement()
+                .unwrap()
+                .stroke
+                .modifiers
+                .as_slice(),
+            vec![
+                Modifier {
+                    name: "descend".into(),
+                    args: vec![Value::NumberList(vec![1, 3])],
+                },
+                Modifier {
+                    name: "breathe".into(),
+                    args: vec![Value::Number(3)],
+                },
+                Modifier {
+                    name: "kick".into(),
+                    args: vec![Value::Distance(Distance {
+                        value: 15,
+                        unit: DistanceUnit::Meters,
+                    })],
+                },
+                Modifier::new("easy"),
+            ]
+        );
+        assert_eq!(
+            workout.to_string(),
+            "4x 100m free(descend 1-3, breathe 3, kick 15m, easy) @1:40\n"
+        );
+
+        assert_eq!(
+            parse("100m free(kick 0m)").unwrap_err(),
+            vec!["set 1: modifier distance must be positive at 15..17, found `0m`"]
+        );
+        assert_eq!(
+            parse("100m free(breathe left)").unwrap_err(),
+            vec!["set 1: expected `,` or `)` in modifier list at 18..22, found `left`"]
+        );
+    }
+
+    #[test]
+    fn test_modifier_recovery() {
+        assert_eq!(
+            parse("100m free(drill, , kick) @1:30\n200m back(drill\n4x50m fly(kick 0m) @1:00")
+                .unwrap_err(),
+            vec![
+                "set 1: expected modifier at 17..18, found `,`",
+                "set 2: expected `,` or `)` in modifier list at 47..48, found `4`",
+                "set 3: modifier distance must be positive at 62..64, found `0m`",
+            ]
+        );
+
+        let errors = Parser::new(Token::lexer("200m back(drill\n100m free"))
+            .parse()
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].fix, Some(Fix::insert(15, ")")));
+
+        let errors = Parser::new(Token::lexer("200m back(drill kick) @3:00 [fins"))
+            .parse()
+            .unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_keyword_like_names() {
+        let workout =
+            parse("4x50m drill(x, m, z2) [km]\n100m base(scull)\n4 x 25m x-over @z3").unwrap();
+        let names = |i: usize| {
+            let stmt = workout.sets[i].statement().unwrap();
+            std::iter::once(stmt.stroke.name.clone())
+                .chain(stmt.stroke.modifiers.iter().map(|m| m.name.to_string()))
+                .chain(stmt.equipment.iter().map(|gear| gear.name.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(0), vec!["drill", "x", "m", "z2", "km"]);
+        assert_eq!(names(1), vec!["base", "scull"]);
+        assert_eq!(names(2), vec!["x-over"]);
+        assert_eq!(
+            workout.sets[2].statement().unwrap().intensity,
+            Some(Intensity::Threshold)
+        );
+    }
+
+    #[test]
+    fn test_alternation() {
+        let workout =
+            parse("400m free/back(easy) by 50m @6:00\n300m fly/back/breast by 25").unwrap();
+
+        let stmt = workout.sets[0].statement().unwrap();
+        assert_eq!(stmt.stroke.name, "free");
+        assert_eq!(
+            stmt.alternation,
+            Some(Alternation {
+                strokes: vec!["free".to_string(), "back".to_string()],
+                every: 50,
+            })
+        );
+        assert_eq!(
+            workout.to_string(),
+            "400m free/back(easy) by 50 @6:00\n300m fly/back/breast by 25\n"
+        );
+
+        assert_eq!(
+            parse("400m free/back @6:00").unwrap_err(),
+            vec!["set 1: expected `by <distance>` after alternating strokes at 15..16, found `@`"]
+        );
+    }
+
+    #[test]
+    fn test_targets() {
+        let workout = parse(
+            "4x100m free targets(1:10, 1:08, 1:06, 1:04) @1:30\n2x { 50m fly targets(40s, 38) }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            workout.sets[0].statement().unwrap().targets,
+            vec![70, 68, 66, 64]
+        );
+        assert_eq!(
+            workout.to_string(),
+            "4x 100m free targets(1:10, 1:08, 1:06, 1:04) @1:30\n2x {\n    50m fly targets(40s, 38s)\n}\n"
+        );
+
+        assert_eq!(
+            parse("4x100m free targets(1:10, 1:08, 1:06)").unwrap_err(),
+            vec!["set 1: expected one target time per rep (4) but got 3 at 36..37, found `)`"]
+        );
+        assert_eq!(
+            parse("100m free targets(1:10, 1:08)").unwrap_err(),
+            vec!["set 1: expected one target time per rep (1) but got 2 at 28..29, found `)`"]
+        );
+        assert_eq!(
+            parse("100m free hold 1:10").unwrap_err(),
+            vec!["set 1: expected race pace after `hold`, e.g., `rp200` at 15..19, found `1:10`"]
+        );
+        assert_eq!(
+            parse("100m free @rp0").unwrap_err(),
+            vec!["set 1: race-pace event distance must be positive at 11..14, found `rp0`"]
+        );
+        assert!(parse("4x100m free targets()").is_err());
+    }
+
+    #[test]
+    fn test_time_trials() {
+        let mut parser = Parser::new(Token::lexer(
+            "400m free\n200m free TT __:__ @z5\n4x { 50m back }\n100m fly TT 1:05",
+        ));
+        let workout = parser.parse().unwrap();
+        let trial = |i: usize| workout.sets[i].statement().unwrap().trial;
+        assert_eq!(trial(1), Some(Trial::Pending));
+        assert_eq!(trial(3), Some(Trial::Recorded(65)));
+        assert_eq!(parser.placeholders(), &[(SetPath::new(vec![1]), 23..28)]);
+        assert_eq!(
+            workout.to_string(),
+            "400m free\n200m free TT __:__ @z5\n4x {\n    50m back\n}\n100m fly TT 1:05\n"
+        );
+
+        assert_eq!(
+            parse("2x { 200m free TT __:__ }").unwrap_err(),
+            vec!["set 1.1: a time trial is a single swim and can't be repeated at 15..17, found `TT`"]
+        );
+        assert_eq!(
+            parse("200m free TT soon").unwrap_err(),
+            vec!["set 1: expected time or `__:__` after `TT` at 13..17, found `soon`"]
+        );
+    }
+
+    #[test]
+    fn test_heart_rate_rest() {
+        let workout = parse("6x200m free rest to HR<130 @z2").unwrap();
+        let stmt = workout.sets[0].statement().unwrap();
+        assert_eq!(stmt.interval, Some(Interval::HeartRate { below: 130 }));
+        assert_eq!(stmt.intensity(), Intensity::Moderate);
+        assert_eq!(workout.to_string(), "6x 200m free rest to HR<130 @z2\n");
+
+        assert_eq!(
+            parse("6x200m free rest to 130").unwrap_err(),
+            vec![
+                "set 1: expected heart rate after `rest to`, e.g., `HR<120` at 20..23, found `130`"
+            ]
+        );
+        assert_eq!(
+            parse("6x200m free rest to HR<0").unwrap_err(),
+            vec!["set 1: heart rate to rest to must be positive at 20..24, found `HR<0`"]
+        );
+    }
+
+    #[test]
+    fn test_rests() {
+        let workout = parse("4x50m free r:20\n200m back r:75 @z1").unwrap();
+        assert_eq!(
+            workout.sets[0].statement().unwrap().interval,
+            Some(Interval::Rest(20))
+        );
+        assert_eq!(
+            workout.to_string(),
+            "4x 50m free r:20\n200m back r:1:15 @z1\n"
+        );
+
+        assert_eq!(
+            parse("4x50m free r:0").unwrap_err(),
+            vec!["set 1: rest must be positive; leave it out for none at 11..14, found `r:0`"]
+        );
+    }
+
+    #[test]
+    fn test_interval_lists() {
+        let workout = parse("let fast = 1:25\n6x100m free @1:40/1:35/fast/95s").unwrap();
+        let interval = workout.sets[0]
+            .statement()
+            .unwrap()
+            .interval
+            .clone()
+            .unwrap();
+        assert_eq!(
+            interval,
+            Interval::Sequence(vec![
+                Interval::MinutesSeconds {
+                    minutes: 1,
+                    seconds: 40
+                },
+                Interval::MinutesSeconds {
+                    minutes: 1,
+                    seconds: 35
+                },
+                Interval::MinutesSeconds {
+                    minutes: 1,
+                    seconds: 25
+                },
+                Interval::Seconds(95),
+            ])
+        );
+        assert_eq!(interval.seconds(), 85);
+        assert_eq!(interval.for_rep(5).seconds(), 95);
+        assert_eq!(interval.total_seconds(6), 100 + 95 + 85 + 95 + 100 + 95);
+        assert_eq!(
+            workout.sets[0].to_string(),
+            "6x 100m free @1:40/1:35/1:25/95s"
+        );
+
+        assert_eq!(
+            parse("4x100m free @1:40/").unwrap_err(),
+            vec!["set 1: expected interval after `@`, found end of input"]
+        );
+        assert_eq!(
+            parse("4x100m free @base+5/1:30").unwrap_err(),
+            vec!["set 1: a list of send-offs takes times or named send-offs, not `base` or race pace at 19..20, found `/`"]
+        );
+    }
+
+    #[test]
+    fn test_rep_strokes() {
+        let workout = parse("8x50m evens:back odds:free(easy) [fins] @1:00").unwrap();
+        let stmt = workout.sets[0].statement().unwrap();
+        assert_eq!(stmt.stroke.name, "free");
+        assert_eq!(stmt.rep_strokes, vec!["free", "back"]);
+        assert_eq!(
+            workout.sets[0].to_string(),
+            "8x 50m odds:free evens:back(easy) [fins] @1:00"
+        );
+        assert_eq!(parse(&workout.to_string()), Ok(workout));
+
+        assert_eq!(
+            parse("50m odds:free evens:back").unwrap_err(),
+            vec!["set 1: odd and even reps need a repeated statement, e.g., `8x50m odds:free evens:back` at 4..8, found `odds`"]
+        );
+        assert_eq!(
+            parse("4x50m odds:free odds:back").unwrap_err(),
+            vec!["set 1: `odds:` is given more than once at 16..20, found `odds`"]
+        );
+        assert_eq!(
+            parse("4x50m odds:free @1:00").unwrap_err(),
+            vec!["set 1: expected strokes for both `odds:` and `evens:` at 16..17, found `@`"]
+        );
+    }
+
+    #[test]
+    fn test_segments() {
+        let workout =
+            parse("4x50m free(descend 1-4) (25m drill(catch-up)/25 swim) [fins] @1:00").unwrap();
+        let stmt = workout.sets[0].statement().unwrap();
+        let parts: Vec<(u32, &str)> = stmt
+            .segments
+            .iter()
+            .map(|segment| (segment.distance.meters(), segment.stroke.name.as_str()))
+            .collect();
+        assert_eq!(parts, vec![(25, "drill"), (25, "swim")]);
+        assert_eq!(stmt.stroke.modifiers.len(), 1);
+        assert_eq!(
+            workout.sets[0].to_string(),
+            "4x 50m free(descend 1-4) (25 drill(catch-up)/25 swim) [fins] @1:00"
+        );
+        assert_eq!(parse(&workout.to_string()), Ok(workout));
+
+        assert_eq!(
+            parse("50m free (25 drill/20 swim)").unwrap_err(),
+            vec!["set 1: parts add up to 45m, not the 50m of the statement at 9..27, found `(25 drill/20 swim)`"]
+        );
+        assert_eq!(
+            parse("50m free (25 drill, 25 swim)").unwrap_err(),
+            vec!["set 1: expected `/` or `)` in list of parts at 18..19, found `,`"]
+        );
+        assert_eq!(
+            parse("100m free(4294967295 drill/1 swim)").unwrap_err(),
+            vec!["set 1: parts add up to 4294967296m, not the 100m of the statement at 9..34, found `(4294967295 drill/1 swim)`"]
+        );
+
+        let errors = Parser::new(Token::lexer(
+            "200m back(\n50m fly\n4x50m free(25 drill/25 swim\n100m back",
+        ))
+        .parse()
+        .unwrap_err();
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "set 1: expected modifier at 11..13, found `50`",
+                "set 3: expected `/` or `)` in list of parts at 47..50, found `100`",
+            ]
+        );
+        assert_eq!(errors[1].fix, Some(Fix::insert(46, ")")));
+    }
+
+    #[test]
+    fn test_ladders() {
+        let workout = parse("100-200-300-200-100m free(build) @base+5\n2x 50-100m fly").unwrap();
+        let Set::Block { sets, .. } = &workout.sets[0] else {
+            panic!("expected a block");
+        };
+        let distances: Vec<u32> = sets
+            .iter()
+            .map(|set| set.statement().unwrap().distance.value)
+            .collect();
+        assert_eq!(distances, vec![100, 200, 300, 200, 100]);
+        assert_eq!(sets[2].to_string(), "300m free(build) @base+5");
+        assert_eq!(
+            workout.sets[1].to_string(),
+            "2x {\n    50m fly\n    100m fly\n}"
+        );
+
+        assert_eq!(
+            parse("100-0-100m free").unwrap_err(),
+            vec!["set 1: distance must be at least 1m; remove the set to skip it at 0..9, found `100-0-100`"]
+        );
+    }
+
+    #[test]
+    fn test_zero_counts() {
+        assert_eq!(
+            parse("0x100m free").unwrap_err(),
+            vec!["set 1: repetition count must be at least 1; remove the set to skip it at 0..1, found `0`"]
+        );
+        assert_eq!(
+            parse("400m free\n4x { 0m fly }").unwrap_err(),
+            vec!["set 2.1: distance must be at least 1m; remove the set to skip it at 15..16, found `0`"]
+        );
+        assert_eq!(
+            parse("100m free @0s\n100m free @0:00").unwrap_err(),
+            vec![
+                "set 1: interval of 0 seconds; did you mean to omit it? at 11..13, found `0s`",
+                "set 2: interval of 0 seconds; did you mean to omit it? at 25..29, found `0:00`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!(
+            parse("100m free @:50\n50m back @45").unwrap().to_string(),
+            "100m free @0:50\n50m back @45s\n"
+        );
+        assert_eq!(
+            parse("4x100m free @100m").unwrap_err(),
+            vec!["set 1: expected interval after `@`, not a distance at 13..17, found `100m`"]
+        );
+        assert_eq!(
+            parse("100m free @1:75").unwrap_err(),
+            vec!["set 1: seconds must be below 60 at 11..15, found `1:75`"]
+        );
+        assert_eq!(
+            parse("dryland { 1km plank }").unwrap_err(),
+            vec!["set 1: expected repetitions or duration for dryland exercise, not a distance at 10..13, found `1km`"]
+        );
+    }
+
+    #[test]
+    fn test_key_sets() {
+        let workout =
+            parse("4x100m free @1:30 @z4 key\n2x { 50m fly\n50m back } primary\n200m free")
+                .unwrap();
+
+        assert!(workout.sets[0].is_key());
+        assert!(workout.sets[1].is_key());
+        assert!(!workout.sets[2].is_key());
+        assert_eq!(
+            workout.to_string(),
+            "4x 100m free @1:30 @z4 key\n2x {\n    50m fly\n    50m back\n} key\n200m free\n"
+        );
+    }
+
+    #[test]
+    fn test_intensities() {
+        let workout =
+            parse("4x { 100m free @1:30\n50m fly @z5 } @z3\n200m back @1:30 @threshold").unwrap();
+
+        let Set::Repetition { set, .. } = &workout.sets[0] else {
+            panic!("expected a repetition");
+        };
+        let Set::Block {
+            sets, intensity, ..
+        } = set.as_ref()
+        else {
+            panic!("expected a block");
+        };
+        assert_eq!(*intensity, Some(Intensity::Threshold));
+        assert_eq!(sets[0].statement().unwrap().intensity, None);
+        assert_eq!(
+            sets[1].statement().unwrap().intensity,
+            Some(Intensity::Sprint)
+        );
+        assert_eq!(sets[1].statement().unwrap().interval, None);
+        assert_eq!(workout.sets[1].to_string(), "200m back @1:30 @z3");
+
+        assert_eq!(
+            parse("100m free @hard").unwrap_err(),
+            vec!["set 1: expected intensity zone `z1` to `z5` or intensity name at 11..15, found `hard`"]
+        );
+    }
+
+    #[test]
+    fn test_efforts() {
+        let workout = parse(
+            "100m free @1:30 !Z3
+4x { 50m fly !96% } !z2
+200m back !60%",
+        )
+        .unwrap();
+
+        let intensities: Vec<Option<Intensity>> = workout
+            .numbered_sets()
+            .iter()
+            .filter_map(|(_, set)| set.statement())
+            .map(|stmt| stmt.intensity)
+            .collect();
+        assert_eq!(
+            intensities,
+            vec![
+                Some(Intensity::Threshold),
+                Some(Intensity::Sprint),
+                Some(Intensity::Easy)
+            ]
+        );
+        assert_eq!(
+            workout.to_string(),
+            "100m free @1:30 @z3\n4x {\n    50m fly @z5\n} @z2\n200m back @z1\n"
+        );
+
+        assert_eq!(
+            parse("100m free !120%").unwrap_err(),
+            vec!["set 1: effort must be from 1% to 100% at 11..15, found `120%`"]
+        );
+        assert_eq!(
+            parse("100m free !hard").unwrap_err(),
+            vec!["set 1: expected zone `Z1` to `Z5` or effort, e.g., `85%`, after `!` at 11..15, found `hard`"]
+        );
+    }
+
+    #[test]
+    fn test_repetitions_and_blocks() {
+        let workout =
+            parse("1x100m fly @ 1:30\n4x {\n  25m choice (easy) @ 60s\n  12x50m free @ 60s\n}")
+                .unwrap();
+
+        assert_eq!(workout.sets.len(), 2);
+        let Set::Repetition { count, set, .. } = &workout.sets[1] else {
+            panic!("expected repetition");
+        };
+        assert_eq!(*count, 4);
+        let Set::Block { sets, .. } = set.as_ref() else {
+            panic!("expected block");
+        };
+        assert_eq!(sets.len(), 2);
+        assert!(matches!(sets[1], Set::Repetition { count: 12, .. }));
+    }
+
+    #[test]
+    fn test_sections() {
+        let source = "title: Tuesday\nwarmup: {\n    400m free\n}\nMain (20:00): {\n    8x 100m free @1:30\n}\ncooldown: {\n    200m back\n}\n";
+        let workout = parse(source).unwrap();
+
+        assert_eq!(workout.metadata.entries.len(), 1);
+        assert_eq!(workout.sets.len(), 3);
+        assert!(matches!(
+            &workout.sets[0],
+            Set::Block { label: Some(label), budget: None, .. } if label == "warmup"
+        ));
+        assert!(matches!(
+            &workout.sets[1],
+            Set::Block {
+                budget: Some(1200),
+                ..
+            }
+        ));
+        assert_eq!(workout.to_string(), source);
+
+        assert_eq!(
+            parse("warmup { 400m free }").unwrap_err(),
+            vec!["set 1: expected a set at 0..6, found `warmup`"]
+        );
+    }
+
+    #[test]
+    fn test_rounds() {
+        let workout =
+            parse("3x {\n    100m free @1:40\n} @z2 (round 3 race pace, round 2 faster)").unwrap();
+        let Set::Repetition { rounds, .. } = &workout.sets[0] else {
+            panic!("expected repetition");
+        };
+        assert_eq!(
+            rounds,
+            &vec![
+                Round {
+                    round: 2,
+                    instruction: "faster".to_string(),
+                    intensity: None,
+                },
+                Round {
+                    round: 3,
+                    instruction: "race pace".to_string(),
+                    intensity: Some(Intensity::RacePace),
+                },
+            ]
+        );
+        assert_eq!(
+            workout.to_string(),
+            "3x {\n    100m free @1:40\n} @z2 (round 2 faster, round 3 race pace)\n"
+        );
+        assert_eq!(
+            Round::new(1, "z4").intensity,
+            Round::new(1, "race-pace").intensity
+        );
+        assert!(parse("2x {\n    100m free\n} (round 1 z4)").is_ok());
+
+        assert_eq!(
+            parse("2x { 100m free } (round 3 sprint)").unwrap_err(),
+            vec![
+                "set 1: round must be from 1 to 2, the number of repetitions at 24..25, found `3`"
+            ]
+        );
+        assert_eq!(
+            parse("2x { 100m free } (round 1 sprint, round 1 easy)").unwrap_err(),
+            vec!["set 1: round 1 is given more than once at 40..41, found `1`"]
+        );
+        assert_eq!(
+            parse("2x { 100m free } (round 2)").unwrap_err(),
+            vec!["set 1: expected instruction after round number at 25..26, found `)`"]
+        );
+    }
+
+    #[test]
+    fn test_equipment() {
+        let workout = parse("4x100m free [paddles(small), fins(short), snorkel] @1:30").unwrap();
+
+        let Set::Repetition { set, .. } = &workout.sets[0] else {
+            panic!("expected repetition");
+        };
+        let Set::Statement(stmt) = set.as_ref() else {
+            panic!("expected statement");
+        };
+        assert_eq!(
+            stmt.equipment,
+            vec![
+                Equipment {
+                    name: "paddles".to_string(),
+                    parameters: vec!["small".to_string()],
+                },
+                Equipment {
+                    name: "fins".to_string(),
+                    parameters: vec!["short".to_string()],
+                },
+                Equipment {
+                    name: "snorkel".to_string(),
+                    parameters: vec![],
+                },
+            ]
+        );
+        assert!(stmt.interval.is_some());
+
+        let errors = parse("100m free [paddles @1:30").unwrap_err();
+        assert_eq!(
+            errors,
+            vec!["set 1: expected `,` or `]` in equipment list at 19..20, found `@`"]
+        );
+    }
+
+    #[test]
+    fn test_dryland() {
+        let workout =
+            parse("dryland { 3x 20 squats, 2:00 plank, 30s jumping jacks }\n100m free").unwrap();
+
+        assert_eq!(
+            workout.sets[0],
+            Set::Dryland {
+                exercises: vec![
+                    DrylandExercise {
+                        sets: 3,
+                        amount: DrylandAmount::Reps(20),
+                        name: "squats".to_string(),
+                    },
+                    DrylandExercise {
+                        sets: 1,
+                        amount: DrylandAmount::Duration(120),
+                        name: "plank".to_string(),
+                    },
+                    DrylandExercise {
+                        sets: 1,
+                        amount: DrylandAmount::Duration(30),
+                        name: "jumping jacks".to_string(),
+                    },
+                ],
+            }
+        );
+        assert_eq!(workout.to_string().lines().next(), Some("dryland {"));
+        assert_eq!(parse(&workout.to_string()).unwrap(), workout);
+    }
+
+    #[test]
+    fn test_wrapped_statement() {
+        let workout = parse(
+            "4x100m free(drill, \n    catch-up, fist) \\\n  [paddles] \\\n  @1:40\n200m back",
+        )
+        .unwrap();
+
+        assert_eq!(workout.sets.len(), 2);
+        assert_eq!(
+            workout.sets[0].to_string(),
+            "4x 100m free(drill, catch-up, fist) [paddles] @1:40"
+        );
+    }
+
+    #[test]
+    fn test_strict_newlines() {
+        let strict = |source| {
+            let options = ParserOptions {
+                strict_newlines: true,
+                ..ParserOptions::default()
+            };
+            parse_with(source, options)
+        };
+
+        assert_eq!(parse("100m free 200m back").unwrap().sets.len(), 2);
+        assert_eq!(
+            strict("100m free 200m back").unwrap_err(),
+            vec!["set 1: expected a new line after statement at 10..13, found `200`"]
+        );
+        assert_eq!(
+            strict("2x { 50m fly @1:00 50m back }").unwrap_err(),
+            vec!["set 1.1: expected a new line after statement at 19..21, found `50`"]
+        );
+
+        let workout = strict("4x { 100m free @1:30 }\n100m back \\\n  @1:40 // easy\n\n200m fly");
+        assert_eq!(workout.unwrap().sets.len(), 3);
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let source = "400m free\nif sprint_day { 8x25m all-out @1:00 } else {\n  8x50m smooth @ :50\n  100m easy\n}\n200m free";
+        let defined = |value| {
+            let options = ParserOptions {
+                parameters: BTreeMap::from([("sprint_day".to_string(), value)]),
+                ..ParserOptions::default()
+            };
+            parse_with(source, options)
+        };
+
+        assert_eq!(
+            defined(true).unwrap().to_string(),
+            "400m free\n8x 25m all-out @1:00\n200m free\n"
+        );
+        assert_eq!(
+            defined(false).unwrap().to_string(),
+            "400m free\n8x 50m smooth @0:50\n100m easy\n200m free\n"
+        );
+        assert_eq!(
+            parse(source).unwrap_err(),
+            vec!["set 2: parameter `sprint_day` is not defined at 13..23, found `sprint_day`"]
+        );
+
+        let options = ParserOptions {
+            parameters: BTreeMap::from([("a".to_string(), false)]),
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            parse_with(
+                "100m free\nif a { 100m free } else { 100m back\n2x { 50m fly\n50 back } }",
+                options.clone()
+            )
+            .unwrap_err(),
+            vec!["set 3.2: expected distance unit `m` or `km` at 62..66, found `back`"]
+        );
+        assert_eq!(
+            parse_with("if a { 100m free } else { }", options.clone()).unwrap_err(),
+            vec!["set 1: branch must contain at least one set at 26..27, found `}`"]
+        );
+        for value in [true, false] {
+            let options = ParserOptions {
+                parameters: BTreeMap::from([("a".to_string(), value)]),
+                ..ParserOptions::default()
+            };
+            assert_eq!(
+                parse_with("4x { if a { 100m free } }", options.clone()).unwrap_err(),
+                vec!["set 1: block must keep a set whatever its parameters; add an `else` at 24..25, found `}`"]
+            );
+            assert!(parse_with(
+                "4x { if a { 100m free } else { 100m back } }",
+                options.clone()
+            )
+            .is_ok());
+            assert!(parse_with("4x { 50m kick\nif a { 100m free } }", options).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_named_intervals() {
+        let workout = parse(
+            "title: Tuesday\nintervals { base = 1:30, sprint = 2:00\n  easy = 100 }\n4x100m free @base+5\n2x200m free @sprint @sprint\n100m back @easy\n100m free @easy",
+        )
+        .unwrap();
+        assert_eq!(workout.metadata.entries.len(), 1);
+        assert_eq!(
+            workout.to_string().lines().skip(1).collect::<Vec<_>>(),
+            vec![
+                "4x 100m free @1:35",
+                "2x 200m free @2:00 @z5",
+                "100m back @100s",
+                "100m free @100s",
+            ]
+        );
+
+        assert_eq!(
+            parse("intervals { base = 1:30 }\n100m free @base-90").unwrap_err(),
+            vec!["set 1: base interval is not positive at 37..44, found `base-90`"]
+        );
+        assert_eq!(
+            parse("intervals { fast = 1:00, fast = 50 }\n100m free").unwrap_err(),
+            vec!["interval `fast` is already defined at 25..29, found `fast`"]
+        );
+        assert_eq!(
+            parse("intervals { fast 1:00 }\n100m free").unwrap_err(),
+            vec!["expected `=` after interval name at 17..21, found `1:00`"]
+        );
+        assert_eq!(
+            parse("100m free @base").unwrap().to_string(),
+            "100m free @base\n"
+        );
+    }
+
+    #[test]
+    fn test_let_intervals() {
+        let workout = parse(
+            "title: Tuesday\nlet base = 1:30\nlet fast = 1:20\n4x100m free @base-10\n4x100m free @fast+5\n100m back @fast",
+        )
+        .unwrap();
+        assert_eq!(
+            workout.to_string(),
+            "title: Tuesday\n4x 100m free @1:20\n4x 100m free @1:25\n100m back @1:20\n"
+        );
+
+        assert_eq!(
+            parse("let fast = 1:20\n100m free @fast-80").unwrap_err(),
+            vec!["set 1: fast interval is not positive at 27..34, found `fast-80`"]
+        );
+        assert_eq!(
+            parse("100m free @slow+5").unwrap_err(),
+            vec!["set 1: unknown interval `slow` at 11..17, found `slow+5`"]
+        );
+        assert_eq!(
+            parse("let fast = 1:20\nlet fast = 1:10\n100m free @fast").unwrap_err(),
+            vec!["interval `fast` is already defined at 20..24, found `fast`"]
+        );
+    }
+
+    #[test]
+    fn test_metadata_and_base_intervals() {
+        let source = "title: Tuesday AM # comment\nbase: 1:30\nbase.back: 1:45\ndate: 2024-06-03\n\n4x100m free @base+5\n100m back @base";
+        let workout = parse(source).unwrap();
+
+        assert_eq!(
+            workout.metadata.entries,
+            vec![
+                ("title".to_string(), "Tuesday AM".to_string()),
+                ("base".to_string(), "1:30".to_string()),
+                ("base.back".to_string(), "1:45".to_string()),
+                ("date".to_string(), "2024-06-03".to_string()),
+            ]
+        );
+        assert_eq!(workout.metadata.get("base"), Some("1:30"));
+        assert_eq!(workout.sets.len(), 2);
+        assert_eq!(
+            workout.to_string(),
+            "title: Tuesday AM\nbase: 1:30\nbase.back: 1:45\ndate: 2024-06-03\n4x 100m free @base+5\n100m back @base\n"
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let options = ParserOptions {
+            max_depth: 3,
+            max_sets: 5,
+            max_expanded_reps: 1000,
+            ..ParserOptions::default()
+        };
+
+        assert!(parse_with("2x { 10x50m free }", options.clone()).is_ok());
+        assert_eq!(
+            parse_with("2x { 2x { 50m free } }", options.clone()).unwrap_err(),
+            vec!["set 1.1: nesting depth exceeds the maximum of 3 at 8..9"]
+        );
+        assert_eq!(
+            parse_with(
+                "50m free\n50m free\n4x { 50m free\n50m free\n50m free }",
+                options.clone()
+            )
+            .unwrap_err(),
+            vec!["set 3.3: number of sets exceeds the maximum of 5 at 41..43"]
+        );
+        let shallow = ParserOptions {
+            max_expanded_reps: 1000,
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            parse_with("1000000x { 1000000x { 50m free } }", shallow).unwrap_err(),
+            vec!["set 1.1.1: number of expanded reps exceeds the maximum of 1000 at 22..24"]
+        );
+        assert_eq!(
+            parse("5000000km free").unwrap_err(),
+            vec!["set 1: total distance exceeds the maximum of 1000000 at 0..14"]
+        );
+        assert_eq!(
+            parse("400m free\n1000x 1000m free").unwrap_err(),
+            vec!["set 2: total distance exceeds the maximum of 1000000 at 10..26"]
+        );
+        assert_eq!(
+            parse("100m free @1000000:00").unwrap_err(),
+            vec!["set 1: total time exceeds the maximum of 10000000 at 0..21"]
+        );
+        assert_eq!(
+            parse("dryland { 100000x 100000 squats }").unwrap_err(),
+            vec!["set 1: total time exceeds the maximum of 10000000 at 0..33"]
+        );
+        assert_eq!(
+            parse("4x50m free r:4294967295").unwrap_err(),
+            vec!["set 1: total time exceeds the maximum of 10000000 at 0..23"]
+        );
+
+        let errors = Parser::with_options(Token::lexer("9x{9x{9x{9x 1m a}}}"), options)
+            .parse()
+            .unwrap_err();
+        assert_eq!(
+            errors[0].kind,
+            ParseErrorKind::LimitExceeded {
+                limit: Limit::Depth,
+                max: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let errors = parse("100m @1:30").unwrap_err();
+        assert_eq!(
+            errors,
+            vec!["set 1: expected stroke name at 5..6, found `@`"]
+        );
+
+        let errors = parse("4x { 100m free").unwrap_err();
+        assert_eq!(
+            errors,
+            vec!["set 1: expected `}` to close block, found end of input"]
+        );
+
+        let errors = parse("100m free\n2x { 50m back\n100 free }").unwrap_err();
+        assert_eq!(
+            errors,
+            vec!["set 2.2: expected distance unit `m` or `km` at 28..32, found `free`"]
+        );
+
+        let errors = parse("400m free\n99999999999m free").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "set 2: number too large; the maximum is 4294967295 at 10..21, found `99999999999`"
+            ]
+        );
+        assert!(parse("100m free @99999999:00").is_err());
+    }
 
-        Ok(workout)
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1:30"), Some(90));
+        assert_eq!(parse_duration("90s"), Some(90));
+        assert_eq!(parse_duration("1:60"), None);
+        assert_eq!(parse_duration("99999999:00"), None);
     }
 }
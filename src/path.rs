@@ -0,0 +1,169 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Serialize, Serializer};
+
+use crate::ast::{Set, Workout};
+
+/// Stable address of a set, as indices from the top level down (e.g., `2.3`
+/// is the third set inside the second top-level set)
+///
+/// Repetitions are transparent: the sets of a repeated block are numbered
+/// directly below the repetition.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SetPath(Vec<usize>);
+
+impl SetPath {
+    /// Path from zero-based indices
+    pub fn new(indices: Vec<usize>) -> Self {
+        Self(indices)
+    }
+
+    /// Zero-based indices from the top level down
+    pub fn indices(&self) -> &[usize] {
+        &self.0
+    }
+
+    /// Path of the `index`th (zero-based) child of this set
+    pub fn child(&self, index: usize) -> Self {
+        let mut indices = self.0.clone();
+        indices.push(index);
+        Self(indices)
+    }
+}
+
+impl fmt::Display for SetPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, index) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", index + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SetPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split('.')
+            .map(|part| match part.parse::<usize>() {
+                Ok(number) if number > 0 => Ok(number - 1),
+                _ => Err(format!("invalid set number `{}`", s)),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+impl Serialize for SetPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Set {
+    /// Sets numbered directly below this one
+    pub fn children(&self) -> &[Set] {
+        match self {
+            Set::Repetition { set, .. } => set.children(),
+            Set::Block { sets, .. } => sets,
+            Set::Statement(_) | Set::Dryland { .. } => &[],
+        }
+    }
+
+    /// Sets numbered directly below this one, for editing
+    pub fn children_mut(&mut self) -> &mut [Set] {
+        match self {
+            Set::Repetition { set, .. } => set.children_mut(),
+            Set::Block { sets, .. } => sets,
+            Set::Statement(_) | Set::Dryland { .. } => &mut [],
+        }
+    }
+}
+
+impl Workout {
+    /// Set at `path`, if it exists
+    pub fn get(&self, path: &SetPath) -> Option<&Set> {
+        let (first, rest) = path.indices().split_first()?;
+        let mut set = self.sets.get(*first)?;
+        for index in rest {
+            set = set.children().get(*index)?;
+        }
+        Some(set)
+    }
+
+    /// Set at `path` for editing in place, if it exists
+    pub fn get_mut(&mut self, path: &SetPath) -> Option<&mut Set> {
+        let (first, rest) = path.indices().split_first()?;
+        let mut set = self.sets.get_mut(*first)?;
+        for index in rest {
+            set = set.children_mut().get_mut(*index)?;
+        }
+        Some(set)
+    }
+
+    /// Every set with its path, in document order
+    pub fn numbered_sets(&self) -> Vec<(SetPath, &Set)> {
+        fn visit<'a>(path: SetPath, set: &'a Set, out: &mut Vec<(SetPath, &'a Set)>) {
+            out.push((path.clone(), set));
+            for (i, child) in set.children().iter().enumerate() {
+                visit(path.child(i), child, out);
+            }
+        }
+
+        let mut out = Vec::new();
+        for (i, set) in self.sets.iter().enumerate() {
+            visit(SetPath::new(vec![i]), set, &mut out);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    #[test]
+    fn test_set_paths() {
+        let workout = Parser::new(Token::lexer(
+            "400m free\n4x {\n  100m back @1:40\n  2x { 50m fly @1:00\n 50m free @50s }\n}",
+        ))
+        .parse()
+        .unwrap();
+
+        let paths: Vec<String> = workout
+            .numbered_sets()
+            .iter()
+            .map(|(path, _)| path.to_string())
+            .collect();
+        assert_eq!(paths, vec!["1", "2", "2.1", "2.2", "2.2.1", "2.2.2"]);
+
+        let path: SetPath = "2.2.1".parse().unwrap();
+        assert_eq!(path.indices(), &[1, 1, 0]);
+        assert_eq!(workout.get(&path).unwrap().to_string(), "50m fly @1:00");
+        assert!(workout.get(&"3".parse().unwrap()).is_none());
+        assert!("2.0".parse::<SetPath>().is_err());
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut workout = Parser::new(Token::lexer("400m free\n4x {\n  100m back @1:40\n}"))
+            .parse()
+            .unwrap();
+
+        let Some(Set::Statement(stmt)) = workout.get_mut(&"2.1".parse().unwrap()) else {
+            panic!("expected a statement at 2.1");
+        };
+        stmt.stroke.name = "fly".to_string();
+        assert_eq!(
+            workout.to_string(),
+            "400m free\n4x {\n    100m fly @1:40\n}\n"
+        );
+        assert!(workout.get_mut(&"1.1".parse().unwrap()).is_none());
+        assert!(workout.get_mut(&SetPath::new(vec![])).is_none());
+    }
+}
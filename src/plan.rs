@@ -0,0 +1,214 @@
+//! Weekly training plans: a TOML file naming the workout file for each day
+//!
+//! ```toml
+//! week = 2024-03-11
+//!
+//! [days]
+//! monday = "threshold.swim"
+//! thursday = "race-pace.swim"
+//! ```
+//!
+//! Workout paths are relative to the plan file. `week` is optional and may
+//! be any day of the week it dates.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Days, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::{ast::Workout, log::week_start};
+
+/// Workouts planned for the days of a week
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WeekPlan {
+    /// Monday of the planned week, if the plan is dated
+    pub week: Option<NaiveDate>,
+    /// Workout file for each planned day, in day order
+    pub days: Vec<(Weekday, PathBuf)>,
+}
+
+/// A planned day, with the file its exported workout is written to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScheduledDay {
+    /// Lowercase day name, e.g., `monday`
+    pub day: String,
+    pub date: Option<NaiveDate>,
+    /// The workout's `title` metadata, if any
+    pub title: Option<String>,
+    pub file: String,
+}
+
+/// A week written as a TOML date (`2024-03-11`) or a string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Week {
+    Date(toml::value::Datetime),
+    Text(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPlan {
+    week: Option<Week>,
+    #[serde(default)]
+    days: BTreeMap<String, PathBuf>,
+}
+
+impl WeekPlan {
+    /// Reads the plan from a TOML file, resolving workout paths against its
+    /// directory
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+        let mut plan =
+            Self::parse(&contents).map_err(|error| format!("{}: {}", path.display(), error))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for (_, file) in &mut plan.days {
+            *file = dir.join(&*file);
+        }
+        Ok(plan)
+    }
+
+    /// Parses the plan from TOML
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let raw: RawPlan = toml::from_str(contents).map_err(|error| error.message().to_string())?;
+
+        let week = match raw.week {
+            Some(week) => {
+                let text = match week {
+                    Week::Date(date) => date.to_string(),
+                    Week::Text(text) => text,
+                };
+                let date: NaiveDate = text
+                    .parse()
+                    .map_err(|_| format!("invalid week `{}`", text))?;
+                Some(week_start(date))
+            }
+            None => None,
+        };
+
+        let mut days: Vec<(Weekday, PathBuf)> = Vec::new();
+        for (name, file) in raw.days {
+            let day: Weekday = name
+                .parse()
+                .map_err(|_| format!("unknown day `{}`", name))?;
+            if days.iter().any(|(planned, _)| *planned == day) {
+                return Err(format!("{} is planned twice", day_name(day)));
+            }
+            days.push((day, file));
+        }
+        days.sort_by_key(|(day, _)| day.num_days_from_monday());
+
+        Ok(WeekPlan { week, days })
+    }
+
+    /// Date of `day` in the planned week, if the plan is dated
+    pub fn date(&self, day: Weekday) -> Option<NaiveDate> {
+        let week = self.week?;
+        Some(week + Days::new(day.num_days_from_monday().into()))
+    }
+
+    /// Schedule of the planned days, given their workouts in day order
+    pub fn schedule(&self, workouts: &[Workout]) -> Vec<ScheduledDay> {
+        self.days
+            .iter()
+            .zip(workouts)
+            .map(|((day, _), workout)| ScheduledDay {
+                day: day_name(*day).to_string(),
+                date: self.date(*day),
+                title: workout.metadata.get("title").map(str::to_string),
+                file: format!("{}.json", day_name(*day)),
+            })
+            .collect()
+    }
+}
+
+fn day_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    #[test]
+    fn test_parse() {
+        let plan = WeekPlan::parse(
+            "week = 2024-03-13\n[days]\nthursday = \"race.swim\"\nMon = \"threshold.swim\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(plan.week, NaiveDate::from_ymd_opt(2024, 3, 11));
+        assert_eq!(
+            plan.days,
+            vec![
+                (Weekday::Mon, PathBuf::from("threshold.swim")),
+                (Weekday::Thu, PathBuf::from("race.swim")),
+            ]
+        );
+        assert_eq!(
+            plan.date(Weekday::Thu),
+            NaiveDate::from_ymd_opt(2024, 3, 14)
+        );
+        assert_eq!(
+            WeekPlan::parse("[days]\nmonday = \"a.swim\"").unwrap().week,
+            None
+        );
+
+        assert_eq!(
+            WeekPlan::parse("[days]\nfunday = \"a.swim\""),
+            Err("unknown day `funday`".to_string())
+        );
+        assert_eq!(
+            WeekPlan::parse("[days]\nmon = \"a.swim\"\nmonday = \"b.swim\""),
+            Err("monday is planned twice".to_string())
+        );
+        assert_eq!(
+            WeekPlan::parse("week = \"soon\""),
+            Err("invalid week `soon`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_schedule() {
+        let plan = WeekPlan::parse(
+            "week = \"2024-03-11\"\n[days]\nmonday = \"a.swim\"\nfriday = \"b.swim\"",
+        )
+        .unwrap();
+        let workouts: Vec<Workout> = ["title: Threshold\n8x100m free @1:30", "400m free"]
+            .iter()
+            .map(|source| Parser::new(Token::lexer(source)).parse().unwrap())
+            .collect();
+
+        assert_eq!(
+            plan.schedule(&workouts),
+            vec![
+                ScheduledDay {
+                    day: "monday".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 3, 11),
+                    title: Some("Threshold".to_string()),
+                    file: "monday.json".to_string(),
+                },
+                ScheduledDay {
+                    day: "friday".to_string(),
+                    date: NaiveDate::from_ymd_opt(2024, 3, 15),
+                    title: None,
+                    file: "friday.json".to_string(),
+                },
+            ]
+        );
+    }
+}
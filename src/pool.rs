@@ -0,0 +1,322 @@
+use std::{fmt, str::FromStr};
+
+use serde::Serialize;
+
+use crate::{
+    analysis::Analyse,
+    ast::{Distance, DistanceUnit, Segment, Set, Statement, Workout},
+    path::SetPath,
+};
+
+/// Pool a workout is swum in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Course {
+    /// 25m pool
+    Short,
+    /// 50m pool
+    Long,
+    /// Pool of any other length in meters (e.g., 33m)
+    Other(u32),
+}
+
+/// Distance changed by [`Workout::snap_to_pool`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adjustment {
+    pub path: SetPath,
+    /// Meters per rep before snapping
+    pub from: u32,
+    /// Meters per rep after snapping
+    pub to: u32,
+}
+
+/// Walls swum to and pushed off from in a pool, e.g., for swimmers minding
+/// shoulder load or practicing underwaters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Turns {
+    /// Length of the pool in meters
+    pub pool: u32,
+    /// Turns within reps, each length after the first
+    pub turns: u32,
+    /// Push-offs from the wall, one to start each rep and one per turn
+    pub push_offs: u32,
+}
+
+impl Course {
+    /// Length of the pool in meters
+    pub fn length(&self) -> u32 {
+        match self {
+            Course::Short => 25,
+            Course::Long => 50,
+            Course::Other(length) => *length,
+        }
+    }
+
+    /// Nearest whole number of lengths to `meters`, rounding halves up and
+    /// never below one length
+    pub fn snap(&self, meters: u32) -> u32 {
+        let length = self.length();
+        (meters.saturating_add(length / 2) / length).max(1) * length
+    }
+
+    /// Turns and push-offs of one rep of `meters`, counting a part length as
+    /// a whole one
+    pub fn turns(&self, meters: u32) -> Turns {
+        let lengths = meters.div_ceil(self.length());
+        Turns {
+            pool: self.length(),
+            turns: lengths.saturating_sub(1),
+            push_offs: lengths,
+        }
+    }
+}
+
+impl FromStr for Course {
+    type Err = String;
+
+    /// Parses `scm`, `lcm` or a length such as `33m`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "scm" | "short" => Ok(Course::Short),
+            "lcm" | "long" => Ok(Course::Long),
+            length => match length.trim_end_matches('m').parse() {
+                Ok(25) => Ok(Course::Short),
+                Ok(50) => Ok(Course::Long),
+                Ok(length) if length > 0 => Ok(Course::Other(length)),
+                _ => Err(format!("invalid pool `{}`", s)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Course {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}m pool", self.length())
+    }
+}
+
+impl fmt::Display for Adjustment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "set {}: {}m → {}m", self.path, self.from, self.to)
+    }
+}
+
+impl Workout {
+    /// Pool given with `pool: scm` (or `course: lcm`, `pool: 33m`), if any
+    /// and valid
+    pub fn course(&self) -> Option<Course> {
+        self.metadata
+            .get("pool")
+            .or_else(|| self.metadata.get("course"))?
+            .parse()
+            .ok()
+    }
+
+    /// Turns and push-offs of every rep swum in `course`
+    pub fn turns(&self, course: Course) -> Turns {
+        let mut total = Turns {
+            pool: course.length(),
+            ..Turns::default()
+        };
+        self.for_each_statement(&mut |stmt, reps| {
+            let turns = course.turns(stmt.distance.meters());
+            total.turns += turns.turns * reps;
+            total.push_offs += turns.push_offs * reps;
+        });
+        total
+    }
+
+    /// Rounds every rep distance to whole lengths of `course`, e.g., after
+    /// scaling a workout or converting it from another pool
+    ///
+    /// Alternation distances and the parts of statements swum in parts are
+    /// snapped too. Kilometer distances that no longer come to whole
+    /// kilometers are written in meters. Returns the statements whose
+    /// distance changed.
+    pub fn snap_to_pool(&mut self, course: Course) -> Vec<Adjustment> {
+        let mut adjustments = Vec::new();
+        for (i, set) in self.sets.iter_mut().enumerate() {
+            snap_set(set, SetPath::new(vec![i]), course, &mut adjustments);
+        }
+        adjustments
+    }
+}
+
+fn snap_set(set: &mut Set, path: SetPath, course: Course, adjustments: &mut Vec<Adjustment>) {
+    match set {
+        Set::Repetition { set, .. } => snap_set(set, path, course, adjustments),
+        Set::Block { sets, .. } => {
+            for (i, set) in sets.iter_mut().enumerate() {
+                snap_set(set, path.child(i), course, adjustments);
+            }
+        }
+        Set::Statement(stmt) => {
+            let from = stmt.distance.meters();
+            let to = snap_statement(stmt, course.snap(from), course);
+            if from != to {
+                adjustments.push(Adjustment { path, from, to });
+            }
+        }
+        Set::Dryland { .. } => {}
+    }
+}
+
+/// Sets the statement's distance to `meters`, which should be whole lengths
+/// of `course`, and snaps its alternation and parts to whole lengths; returns
+/// the distance set
+///
+/// Parts share the lengths in proportion to their distance, each keeping at
+/// least one, so a statement with more parts than lengths grows to one length
+/// per part.
+pub(crate) fn snap_statement(stmt: &mut Statement, meters: u32, course: Course) -> u32 {
+    let meters = meters.max(stmt.segments.len() as u32 * course.length());
+    if stmt.distance.meters() != meters {
+        stmt.distance.value = meters;
+        stmt.distance.unit = DistanceUnit::Meters;
+        if meters.is_multiple_of(1000) {
+            stmt.distance.value = meters / 1000;
+            stmt.distance.unit = DistanceUnit::Kilometers;
+        }
+    }
+    if let Some(alternation) = &mut stmt.alternation {
+        alternation.every = course.snap(alternation.every);
+    }
+    if !stmt.segments.is_empty() {
+        snap_segments(
+            &mut stmt.segments,
+            meters / course.length(),
+            course.length(),
+        );
+    }
+    meters
+}
+
+/// Shares `lengths` lengths of `length` meters between the parts in
+/// proportion to their distance, at least one each, by largest remainder
+fn snap_segments(segments: &mut [Segment], lengths: u32, length: u32) {
+    let total: u64 = segments
+        .iter()
+        .map(|segment| u64::from(segment.distance.meters()))
+        .sum();
+    let ideal: Vec<f64> = segments
+        .iter()
+        .map(|segment| {
+            f64::from(segment.distance.meters()) * f64::from(lengths) / total.max(1) as f64
+        })
+        .collect();
+    let mut shares: Vec<u32> = ideal
+        .iter()
+        .map(|&ideal| (ideal.floor() as u32).max(1))
+        .collect();
+
+    while shares.iter().sum::<u32>() > lengths {
+        let largest = (0..shares.len())
+            .max_by_key(|&i| shares[i])
+            .expect("a statement in parts has parts");
+        shares[largest] -= 1;
+    }
+    let mut order: Vec<usize> = (0..shares.len()).collect();
+    order.sort_by(|&a, &b| {
+        let remainder = |i: usize| ideal[i] - f64::from(shares[i]);
+        remainder(b).total_cmp(&remainder(a))
+    });
+    for i in order
+        .into_iter()
+        .take((lengths - shares.iter().sum::<u32>()) as usize)
+    {
+        shares[i] += 1;
+    }
+
+    for (segment, share) in segments.iter_mut().zip(shares) {
+        segment.distance = Distance {
+            value: share * length,
+            unit: DistanceUnit::Meters,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    #[test]
+    fn test_snap() {
+        assert_eq!(Course::Short.snap(60), 50);
+        assert_eq!(Course::Short.snap(63), 75);
+        assert_eq!(Course::Long.snap(10), 50);
+        assert_eq!(Course::Other(33).snap(100), 99);
+        assert_eq!("33m".parse(), Ok(Course::Other(33)));
+        assert_eq!("LCM".parse(), Ok(Course::Long));
+        assert!("0m".parse::<Course>().is_err());
+    }
+
+    #[test]
+    fn test_turns() {
+        assert_eq!(
+            Course::Short.turns(100),
+            Turns {
+                pool: 25,
+                turns: 3,
+                push_offs: 4
+            }
+        );
+        assert_eq!(Course::Long.turns(75).turns, 1);
+
+        let workout = Parser::new(Token::lexer(
+            "pool: lcm\n400m free\n8x { 50m fly\n100m back }\ndryland { 20 squats }",
+        ))
+        .parse()
+        .unwrap();
+        assert_eq!(workout.course(), Some(Course::Long));
+        assert_eq!(
+            workout.turns(Course::Long),
+            Turns {
+                pool: 50,
+                turns: 7 + 8,
+                push_offs: 8 + 8 * 3
+            }
+        );
+        assert_eq!(workout.turns(Course::Short).turns, 15 + 8 * 4);
+    }
+
+    #[test]
+    fn test_snap_to_pool() {
+        let mut workout = Parser::new(Token::lexer(
+            "400m free\n4x { 60m fly @1:00\n40m back }\n1km free/back by 110",
+        ))
+        .parse()
+        .unwrap();
+
+        let adjustments = workout.snap_to_pool(Course::Short);
+        assert_eq!(
+            workout.to_string(),
+            "400m free\n4x {\n    50m fly @1:00\n    50m back\n}\n1km free/back by 100\n"
+        );
+        assert_eq!(
+            adjustments
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["set 2.1: 60m → 50m", "set 2.2: 40m → 50m"]
+        );
+
+        let adjustments = workout.snap_to_pool(Course::Other(33));
+        assert_eq!(adjustments.len(), 4);
+        assert_eq!(workout.sets[2].to_string(), "990m free/back by 99");
+
+        let mut workout = Parser::new(Token::lexer(
+            "4x75m free (25 drill/50 swim)\n75m back (25 kick/25 drill/25 swim)",
+        ))
+        .parse()
+        .unwrap();
+        let adjustments = workout.snap_to_pool(Course::Long);
+        let snapped = workout.to_string();
+        assert_eq!(
+            snapped,
+            "4x 100m free (50 drill/50 swim)\n150m back (50 kick/50 drill/50 swim)\n"
+        );
+        assert_eq!(adjustments[1].to, 150);
+        assert!(Parser::new(Token::lexer(&snapped)).parse().is_ok());
+    }
+}
@@ -0,0 +1,109 @@
+//! Bundled limits for a kind of squad, so lints and checks fit the swimmers
+//!
+//! A preset is chosen with `--preset` or `preset = "masters"` in the config.
+
+use std::{fmt, str::FromStr};
+
+use serde::Deserialize;
+
+use crate::pace::CssModel;
+
+/// Kind of squad a workout is written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// Senior club and college swimmers
+    Senior,
+    /// Adult fitness and masters swimmers
+    Masters,
+    /// Age-group swimmers aged 10 and under
+    #[serde(rename = "age-group-10u")]
+    AgeGroup10U,
+}
+
+/// Limits of a preset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresetLimits {
+    /// CSS in seconds per 100m of a typical swimmer, for checks without a profile
+    pub typical_css: u32,
+    /// Fastest plausible send-off pace in seconds per 100m
+    pub fastest_pace: u32,
+    /// Most meters per rep on restricted breathing
+    pub max_hypoxic: u32,
+    /// Most meters in a workout
+    pub max_distance: u32,
+}
+
+impl Preset {
+    pub fn limits(self) -> PresetLimits {
+        match self {
+            Preset::Senior => PresetLimits {
+                typical_css: 80,
+                fastest_pace: 55,
+                max_hypoxic: 50,
+                max_distance: 10_000,
+            },
+            Preset::Masters => PresetLimits {
+                typical_css: 105,
+                fastest_pace: 65,
+                max_hypoxic: 25,
+                max_distance: 6_000,
+            },
+            Preset::AgeGroup10U => PresetLimits {
+                typical_css: 120,
+                fastest_pace: 75,
+                max_hypoxic: 0,
+                max_distance: 3_500,
+            },
+        }
+    }
+
+    /// Pace model of a typical swimmer in the squad
+    pub fn pace_model(self) -> CssModel {
+        CssModel::new(self.limits().typical_css)
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "senior" => Ok(Preset::Senior),
+            "masters" => Ok(Preset::Masters),
+            "age-group-10u" | "10u" | "10&u" => Ok(Preset::AgeGroup10U),
+            _ => Err(format!(
+                "unknown preset `{}`, expected senior, masters or age-group-10u",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Preset::Senior => write!(f, "senior"),
+            Preset::Masters => write!(f, "masters"),
+            Preset::AgeGroup10U => write!(f, "age-group 10&U"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        assert_eq!("Masters".parse(), Ok(Preset::Masters));
+        assert_eq!("10u".parse(), Ok(Preset::AgeGroup10U));
+        assert!("novice".parse::<Preset>().is_err());
+
+        for preset in [Preset::Senior, Preset::Masters, Preset::AgeGroup10U] {
+            let limits = preset.limits();
+            assert!(limits.fastest_pace < limits.typical_css);
+        }
+        assert!(Preset::AgeGroup10U.limits().max_distance < Preset::Senior.limits().max_distance);
+    }
+}
@@ -0,0 +1,254 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    pace::{CssModel, Fatigue},
+    parser::parse_duration,
+    resolve::{BaseIntervals, RaceGoals},
+};
+
+/// Paces within this many seconds per 100m of CSS count as being at CSS
+const CSS_TOLERANCE: u32 = 2;
+
+/// Most a swimmer can slow for each 1000m of work, in percent
+const MAX_FATIGUE: f64 = 100.0;
+
+/// A swimmer's paces, usually loaded from a TOML file
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SwimmerProfile {
+    pub name: Option<String>,
+    /// Critical swim speed as seconds per 100m
+    #[serde(deserialize_with = "optional_duration")]
+    pub css: Option<u32>,
+    /// Base send-offs per 100m by stroke, with `default` applying to other strokes
+    #[serde(deserialize_with = "durations")]
+    pub base: HashMap<String, u32>,
+    /// Threshold paces per 100m by stroke, for strokes far off the CSS
+    #[serde(deserialize_with = "durations")]
+    pub threshold: HashMap<String, u32>,
+    /// Goal times for target events, e.g., `"200 free" = "2:05"`
+    #[serde(deserialize_with = "race_goals")]
+    pub goals: RaceGoals,
+    /// Percent the swimmer slows for each 1000m of work earlier in a
+    /// session, e.g., `fatigue = 1.5`
+    #[serde(deserialize_with = "fatigue")]
+    pub fatigue: Option<f64>,
+    /// Typical spread of the swimmer's rep times, as a percent of the rep
+    /// time, for judging how likely a send-off is to be made
+    pub variability: Option<f64>,
+    /// Time the swimmer takes to recover on a heart-rate rest, e.g.,
+    /// `recovery = "1:00"`
+    #[serde(deserialize_with = "optional_duration")]
+    pub recovery: Option<u32>,
+}
+
+/// Which side of CSS a pace falls on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CssSide {
+    Faster,
+    At,
+    Slower,
+}
+
+impl SwimmerProfile {
+    /// Reads the profile from a TOML file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+        Self::parse(&contents).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    /// Parses the profile from TOML
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|error| error.message().to_string())
+    }
+
+    /// Which side of the swimmer's threshold pace for `stroke` a pace per
+    /// 100m falls on, using CSS for strokes without their own
+    pub fn css_side(&self, stroke: &str, pace: u32) -> Option<CssSide> {
+        let css = self.pace_model()?.threshold(stroke);
+        Some(if pace + CSS_TOLERANCE < css {
+            CssSide::Faster
+        } else if pace > css + CSS_TOLERANCE {
+            CssSide::Slower
+        } else {
+            CssSide::At
+        })
+    }
+
+    /// Pace model based on the swimmer's CSS and stroke thresholds, if any
+    /// are known
+    pub fn pace_model(&self) -> Option<CssModel> {
+        let thresholds = self
+            .threshold
+            .iter()
+            .map(|(stroke, pace)| (stroke.to_ascii_lowercase(), *pace))
+            .collect();
+        let model = match self.css {
+            Some(css) => Some(CssModel {
+                thresholds,
+                ..CssModel::new(css)
+            }),
+            None => CssModel::from_thresholds(thresholds),
+        }?;
+        Some(match self.recovery {
+            Some(secs) => model.with_recovery(secs),
+            None => model,
+        })
+    }
+
+    /// How the swimmer slows over a session, if the profile says
+    pub fn fatigue(&self) -> Option<Fatigue> {
+        self.fatigue.map(Fatigue::new)
+    }
+
+    /// Base send-offs declared in the profile
+    pub fn base_intervals(&self) -> BaseIntervals {
+        let mut bases = BaseIntervals::default();
+        for (stroke, secs) in &self.base {
+            if stroke == "default" {
+                bases.default = Some(*secs);
+            } else {
+                bases.strokes.insert(stroke.clone(), *secs);
+            }
+        }
+        bases
+    }
+}
+
+impl fmt::Display for CssSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CssSide::Faster => write!(f, "faster than CSS"),
+            CssSide::At => write!(f, "at CSS"),
+            CssSide::Slower => write!(f, "slower than CSS"),
+        }
+    }
+}
+
+/// A duration written either as seconds (`95`) or as a string (`"1:35"`)
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Duration {
+    Seconds(u32),
+    Text(String),
+}
+
+impl Duration {
+    fn seconds<E: serde::de::Error>(self) -> Result<u32, E> {
+        match self {
+            Duration::Seconds(secs) => Ok(secs),
+            Duration::Text(text) => parse_duration(&text)
+                .ok_or_else(|| E::custom(format!("invalid duration `{}`", text))),
+        }
+    }
+}
+
+fn optional_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    Option::<Duration>::deserialize(deserializer)?
+        .map(Duration::seconds)
+        .transpose()
+}
+
+fn durations<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, u32>, D::Error> {
+    HashMap::<String, Duration>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, duration)| Ok((key, duration.seconds()?)))
+        .collect()
+}
+
+fn fatigue<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    let fatigue = Option::<f64>::deserialize(deserializer)?;
+    match fatigue {
+        Some(percent) if !(0.0..=MAX_FATIGUE).contains(&percent) => {
+            Err(serde::de::Error::custom(format!(
+                "invalid fatigue `{}`; expected a percent from 0 to {}",
+                percent, MAX_FATIGUE
+            )))
+        }
+        _ => Ok(fatigue),
+    }
+}
+
+fn race_goals<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RaceGoals, D::Error> {
+    let mut goals = RaceGoals::default();
+    for (event, secs) in durations(deserializer)? {
+        goals
+            .insert(&event, secs)
+            .map_err(serde::de::Error::custom)?;
+    }
+    Ok(goals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pace::{Intensity, PaceModel};
+
+    #[test]
+    fn test_parse() {
+        let profile = SwimmerProfile::parse(
+            "name = \"Sam\"\ncss = \"1:30\"\n[base]\ndefault = 95\nback = \"1:45\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(profile.name.as_deref(), Some("Sam"));
+        assert_eq!(profile.css, Some(90));
+        let bases = profile.base_intervals();
+        assert_eq!(bases.get("free"), Some(95));
+        assert_eq!(bases.get("back"), Some(105));
+
+        assert_eq!(
+            SwimmerProfile::parse("").unwrap(),
+            SwimmerProfile::default()
+        );
+        assert!(SwimmerProfile::parse("css = \"fast\"").is_err());
+    }
+
+    #[test]
+    fn test_fatigue() {
+        let profile = SwimmerProfile::parse("fatigue = 1.5").unwrap();
+        assert_eq!(profile.fatigue, Some(1.5));
+        for fatigue in ["-1.0", "nan", "1e308"] {
+            assert!(SwimmerProfile::parse(&format!("fatigue = {}", fatigue))
+                .unwrap_err()
+                .contains("invalid fatigue"));
+        }
+    }
+
+    #[test]
+    fn test_race_goals() {
+        let profile =
+            SwimmerProfile::parse("[goals]\n\"200 free\" = \"2:05\"\n\"50 fly\" = 29\n").unwrap();
+        assert_eq!(profile.goals.get("free", 200), Some(125));
+        assert_eq!(profile.goals.get("fly", 50), Some(29));
+        assert_eq!(profile.goals.get("free", 100), None);
+
+        assert!(SwimmerProfile::parse("[goals]\nfree = \"2:05\"\n")
+            .unwrap_err()
+            .contains("invalid goal event `free`"));
+    }
+
+    #[test]
+    fn test_css_side() {
+        let profile = SwimmerProfile {
+            css: Some(90),
+            ..SwimmerProfile::default()
+        };
+
+        assert_eq!(profile.css_side("free", 80), Some(CssSide::Faster));
+        assert_eq!(profile.css_side("free", 92), Some(CssSide::At));
+        assert_eq!(profile.css_side("free", 100), Some(CssSide::Slower));
+        assert_eq!(profile.css_side("back", 100), Some(CssSide::At));
+        assert_eq!(SwimmerProfile::default().css_side("free", 80), None);
+
+        let profile = SwimmerProfile::parse("css = 90\n[threshold]\nfly = \"2:00\"").unwrap();
+        assert_eq!(profile.css_side("fly", 115), Some(CssSide::Faster));
+        assert_eq!(profile.css_side("fly", 120), Some(CssSide::At));
+        let model = profile.pace_model().unwrap();
+        assert_eq!(model.predict(100, "fly", Intensity::Threshold), 120);
+        assert_eq!(model.predict(100, "free", Intensity::Threshold), 90);
+    }
+}
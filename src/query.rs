@@ -0,0 +1,264 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    analysis::detect_sections,
+    ast::{Set, Statement, Workout},
+    path::SetPath,
+};
+
+/// Selection of part of a workout, for analysing it on its own
+///
+/// Filters combine: a set is kept only if it matches every filter given.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    section: Option<String>,
+    sets: Option<RangeInclusive<usize>>,
+    stroke: Option<String>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep only the named section: the blocks labeled with the name, or else
+    /// the detected `warmup`, `main` or `cooldown`
+    pub fn section(mut self, name: &str) -> Self {
+        self.section = Some(name.to_string());
+        self
+    }
+
+    /// Keep only the top-level sets with these 1-based numbers
+    pub fn sets(mut self, numbers: RangeInclusive<usize>) -> Self {
+        self.sets = Some(numbers);
+        self
+    }
+
+    /// Keep only statements swum with this stroke
+    pub fn stroke(mut self, stroke: &str) -> Self {
+        self.stroke = Some(stroke.to_string());
+        self
+    }
+
+    /// Whether the statement passes the statement-level filters
+    pub fn matches(&self, stmt: &Statement) -> bool {
+        self.stroke
+            .as_ref()
+            .is_none_or(|stroke| stmt.stroke.name.eq_ignore_ascii_case(stroke))
+    }
+
+    /// Workout containing only the selected sets and statements
+    pub fn select(&self, workout: &Workout) -> Workout {
+        let section = self
+            .section
+            .as_ref()
+            .map(|name| section_sets(workout, name));
+
+        let sets = workout
+            .sets
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.includes(section.as_deref(), *i))
+            .filter_map(|(_, set)| self.prune(set))
+            .collect();
+
+        Workout {
+            metadata: workout.metadata.clone(),
+            sets,
+        }
+    }
+
+    /// Paths of the statements and dryland blocks the query selects, as
+    /// numbered in the whole workout
+    ///
+    /// A repeated statement is at the path of its repetition, as with
+    /// [`Workout::numbered_sets`].
+    pub fn paths(&self, workout: &Workout) -> Vec<SetPath> {
+        let section = self
+            .section
+            .as_ref()
+            .map(|name| section_sets(workout, name));
+
+        workout
+            .numbered_sets()
+            .into_iter()
+            .filter(|(path, mut set)| {
+                while let Set::Repetition { set: repeated, .. } = set {
+                    set = repeated;
+                }
+                self.includes(section.as_deref(), path.indices()[0])
+                    && match set {
+                        Set::Statement(stmt) => self.matches(stmt),
+                        Set::Dryland { .. } => self.stroke.is_none(),
+                        Set::Repetition { .. } | Set::Block { .. } => false,
+                    }
+            })
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    /// Whether the top-level set at `index` passes the section and set
+    /// number filters, `section` being the sets of the section
+    fn includes(&self, section: Option<&[usize]>, index: usize) -> bool {
+        section.is_none_or(|indices| indices.contains(&index))
+            && self
+                .sets
+                .as_ref()
+                .is_none_or(|range| range.contains(&(index + 1)))
+    }
+
+    /// Removes statements that don't match, dropping sets left empty
+    fn prune(&self, set: &Set) -> Option<Set> {
+        match set {
+            Set::Repetition { count, set, rounds } => self.prune(set).map(|set| Set::Repetition {
+                count: *count,
+                set: Box::new(set),
+                rounds: rounds.clone(),
+            }),
+            Set::Block {
+                sets,
+                intensity,
+                key,
+                label,
+                budget,
+            } => {
+                let sets: Vec<Set> = sets.iter().filter_map(|set| self.prune(set)).collect();
+                (!sets.is_empty()).then_some(Set::Block {
+                    sets,
+                    intensity: *intensity,
+                    key: *key,
+                    label: label.clone(),
+                    budget: *budget,
+                })
+            }
+            Set::Statement(stmt) => self.matches(stmt).then(|| set.clone()),
+            Set::Dryland { .. } => self.stroke.is_none().then(|| set.clone()),
+        }
+    }
+}
+
+/// Indices of the top-level sets in the named section
+pub(crate) fn section_sets(workout: &Workout, name: &str) -> Vec<usize> {
+    let labeled: Vec<usize> = workout
+        .sets
+        .iter()
+        .enumerate()
+        .filter(|(_, set)| {
+            set.label()
+                .is_some_and(|label| label.eq_ignore_ascii_case(name))
+        })
+        .map(|(i, _)| i)
+        .collect();
+    if !labeled.is_empty() {
+        return labeled;
+    }
+
+    let sections = detect_sections(workout);
+    match name.to_ascii_lowercase().as_str() {
+        "warmup" => sections.warmup.unwrap_or_default().collect(),
+        "cooldown" => sections.cooldown.unwrap_or_default().collect(),
+        "main" => sections.main(workout).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::Analyse, lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn parse(source: &str) -> Workout {
+        Parser::new(Token::lexer(source)).parse().unwrap()
+    }
+
+    #[test]
+    fn test_select() {
+        let workout = parse(
+            "400m free(easy)\n4x { 100m fly @1:40\n100m free @1:30 }\n8x50m fly @1:00\n200m back(easy)",
+        );
+
+        assert_eq!(
+            Query::new()
+                .section("main")
+                .select(&workout)
+                .total_distance(),
+            1200
+        );
+        assert_eq!(
+            Query::new()
+                .section("Warmup")
+                .select(&workout)
+                .total_distance(),
+            400
+        );
+        assert_eq!(
+            Query::new().sets(2..=3).select(&workout).total_distance(),
+            1200
+        );
+        assert_eq!(
+            Query::new().stroke("fly").select(&workout).total_distance(),
+            800
+        );
+        assert_eq!(
+            Query::new()
+                .sets(1..=2)
+                .stroke("fly")
+                .select(&workout)
+                .to_string(),
+            "4x {\n    100m fly @1:40\n}\n"
+        );
+
+        let labeled = parse(
+            "400m free(easy)\nKick (8:00): {\n    4x 50m kick\n}\n2x Main (20:00): {\n    4x 100m free @1:30\n}\n200m back(easy)",
+        );
+        assert_eq!(
+            Query::new()
+                .section("main")
+                .select(&labeled)
+                .total_distance(),
+            800
+        );
+        assert_eq!(
+            Query::new()
+                .section("kick")
+                .select(&labeled)
+                .total_distance(),
+            200
+        );
+        assert_eq!(
+            Query::new()
+                .section("warmup")
+                .select(&labeled)
+                .total_distance(),
+            400
+        );
+        assert!(Query::new()
+            .section("kick")
+            .select(&workout)
+            .sets
+            .is_empty());
+    }
+
+    #[test]
+    fn test_paths() {
+        let workout = parse(
+            "400m free(easy)\n4x { 100m fly @1:40\n100m free @1:30 }\n8x50m fly @1:00\ndryland { 20 squats }",
+        );
+        let paths = |query: Query| -> Vec<String> {
+            query
+                .paths(&workout)
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        };
+
+        assert_eq!(paths(Query::new().stroke("fly")), vec!["2.1", "3"]);
+        assert_eq!(paths(Query::new().sets(2..=2)), vec!["2.1", "2.2"]);
+        assert_eq!(paths(Query::new().sets(3..=4)), vec!["3", "4"]);
+        assert_eq!(
+            Query::new().stroke("fly").paths(&workout)[0],
+            "2.1".parse().unwrap()
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::{
+    fit::Lap,
+    fix::Fix,
+    path::SetPath,
+    steps::{SwimStep, WorkoutSteps},
+};
+
+/// How many planned reps ahead a lap may match before it is treated as the
+/// current rep swum at a different distance
+const LOOKAHEAD: usize = 10;
+
+/// Comparison of a planned workout with what was swum
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reconciliation {
+    pub sets: Vec<SetComparison>,
+    /// Laps left over after every planned rep was matched
+    pub extra_laps: Vec<u32>,
+}
+
+/// Planned and swum figures for a single statement
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SetComparison {
+    pub path: SetPath,
+    pub stroke: String,
+    pub planned_reps: u32,
+    pub swum_reps: u32,
+    pub planned_distance: u32,
+    pub swum_distance: u32,
+    /// Send-off per rep, in seconds
+    pub target: Option<u32>,
+    /// Average elapsed time per swum rep, in seconds
+    pub achieved: Option<u32>,
+    /// Swum reps at or under their goal time, if the set lists goal times
+    pub targets_met: Option<u32>,
+}
+
+impl Reconciliation {
+    /// Fixes writing the time swum into time trial placeholders, given with
+    /// the path of their statement, e.g., `TT __:__` to `TT 2:31`
+    ///
+    /// Placeholders of trials with no lap matched are left as they are.
+    pub fn trial_results(&self, placeholders: &[(SetPath, Range<usize>)]) -> Vec<Fix> {
+        placeholders
+            .iter()
+            .filter_map(|(path, span)| {
+                let set = self.sets.iter().find(|set| set.path == *path)?;
+                let time = set.achieved?;
+                Some(Fix::replace(
+                    span.clone(),
+                    &format!("{}:{:02}", time / 60, time % 60),
+                ))
+            })
+            .collect()
+    }
+}
+
+impl SetComparison {
+    /// Whether none of the set's reps were swum
+    pub fn skipped(&self) -> bool {
+        self.swum_reps == 0
+    }
+}
+
+/// Matches the laps of a swim against the planned reps, in order
+///
+/// Rest laps are ignored. A lap of a different distance from the current rep
+/// is matched to a later rep if one of the next ten has its distance, and
+/// the reps in between count as skipped. Reps with a goal time from
+/// `targets(...)` are scored against it.
+pub fn reconcile(plan: &WorkoutSteps, laps: &[Lap]) -> Reconciliation {
+    let reps = plan.swims();
+    let laps: Vec<&Lap> = laps.iter().filter(|lap| !lap.is_rest()).collect();
+    let mut matched: Vec<Option<&Lap>> = vec![None; reps.len()];
+
+    let mut rep = 0;
+    let mut lap = 0;
+    while rep < reps.len() && lap < laps.len() {
+        let ahead = (rep..reps.len().min(rep + LOOKAHEAD + 1))
+            .find(|&i| reps[i].meters == laps[lap].meters)
+            .unwrap_or(rep);
+        matched[ahead] = Some(laps[lap]);
+        rep = ahead + 1;
+        lap += 1;
+    }
+
+    let mut sets: Vec<SetComparison> = Vec::new();
+    let mut times: Vec<u32> = Vec::new();
+    for (step, lap) in reps.iter().zip(&matched) {
+        let index = match sets.iter().position(|set| set.path == step.path) {
+            Some(index) => index,
+            None => {
+                sets.push(comparison(step));
+                times.push(0);
+                sets.len() - 1
+            }
+        };
+        let set = &mut sets[index];
+        let goal = step
+            .targets
+            .get(set.planned_reps as usize % step.targets.len().max(1))
+            .copied();
+        set.planned_reps += 1;
+        set.planned_distance += step.meters;
+        if let Some(lap) = lap {
+            set.swum_reps += 1;
+            set.swum_distance += lap.meters;
+            times[index] += lap.seconds;
+            if goal.is_some_and(|goal| lap.seconds <= goal) {
+                *set.targets_met.get_or_insert(0) += 1;
+            }
+        }
+    }
+    for (set, time) in sets.iter_mut().zip(times) {
+        set.achieved = (set.swum_reps > 0).then(|| time / set.swum_reps);
+    }
+
+    Reconciliation {
+        sets,
+        extra_laps: laps[lap..].iter().map(|lap| lap.meters).collect(),
+    }
+}
+
+fn comparison(step: &SwimStep) -> SetComparison {
+    SetComparison {
+        path: step.path.clone(),
+        stroke: step.stroke.clone(),
+        planned_reps: 0,
+        swum_reps: 0,
+        planned_distance: 0,
+        swum_distance: 0,
+        target: step.send_off,
+        achieved: None,
+        targets_met: (!step.targets.is_empty()).then_some(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fix::apply_fixes,
+        lexer::Token,
+        parser::{Parser, ParserOptions},
+    };
+    use logos::Logos;
+
+    fn lap(meters: u32, seconds: u32) -> Lap {
+        Lap { meters, seconds }
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let workout = Parser::new(Token::lexer(
+            "200m free\n2x100m back @1:45\n4x50m fly @1:00\n100m free",
+        ))
+        .parse()
+        .unwrap();
+        let plan = WorkoutSteps::from_workout(&workout);
+        let laps = [
+            lap(200, 180),
+            lap(0, 30),
+            lap(100, 95),
+            lap(100, 99),
+            lap(50, 45),
+            lap(50, 47),
+            lap(100, 90),
+        ];
+
+        let report = reconcile(&plan, &laps);
+
+        assert_eq!(report.sets.len(), 4);
+        assert_eq!(report.sets[1].swum_reps, 2);
+        assert_eq!(report.sets[1].target, Some(105));
+        assert_eq!(report.sets[1].achieved, Some(97));
+        assert_eq!(report.sets[2].planned_distance, 200);
+        assert_eq!(report.sets[2].swum_distance, 100);
+        assert_eq!(report.sets[3].swum_distance, 100);
+        assert!(!report.sets[3].skipped());
+        assert!(report.extra_laps.is_empty());
+
+        let report = reconcile(
+            &plan,
+            &[
+                lap(200, 180),
+                lap(100, 90),
+                lap(100, 90),
+                lap(100, 85),
+                lap(25, 20),
+            ],
+        );
+        assert_eq!(report.sets[1].swum_reps, 2);
+        assert_eq!(report.sets[2].swum_reps, 0);
+        assert!(report.sets[2].skipped());
+        assert_eq!(report.sets[3].swum_reps, 1);
+        assert_eq!(report.extra_laps, vec![25]);
+        assert_eq!(report.sets[1].targets_met, None);
+    }
+
+    #[test]
+    fn test_reconcile_targets() {
+        let workout = Parser::new(Token::lexer(
+            "2x { 4x100m free targets(1:10, 1:08, 1:06, 1:04) }",
+        ))
+        .parse()
+        .unwrap();
+        let plan = WorkoutSteps::from_workout(&workout);
+        let laps: Vec<Lap> = [70, 69, 66, 60, 75, 68].map(|secs| lap(100, secs)).to_vec();
+
+        let report = reconcile(&plan, &laps);
+        assert_eq!(report.sets[0].swum_reps, 6);
+        assert_eq!(report.sets[0].targets_met, Some(4));
+    }
+
+    #[test]
+    fn test_trial_results() {
+        let source = "200m free\nif long {\n    400m free TT __:__\n}\n200m free TT __:__\n100m back TT __:__";
+        let mut parser = Parser::with_options(
+            Token::lexer(source),
+            ParserOptions {
+                parameters: [("long".to_string(), false)].into(),
+                ..ParserOptions::default()
+            },
+        );
+        let workout = parser.parse().unwrap();
+        assert_eq!(parser.placeholders().len(), 2);
+
+        let report = reconcile(
+            &WorkoutSteps::from_workout(&workout),
+            &[lap(200, 200), lap(200, 151)],
+        );
+        let (recorded, applied) = apply_fixes(source, &report.trial_results(parser.placeholders()));
+        assert_eq!(applied, 1);
+        assert!(recorded.ends_with("200m free TT 2:31\n100m back TT __:__"));
+    }
+}
@@ -0,0 +1,460 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    ast::{Intensity, Interval, Metadata, RacePace, Set, Workout},
+    parser::parse_duration,
+    path::SetPath,
+};
+
+/// How computed send-offs are rounded, so they can be read off a pace clock
+///
+/// Written `exact`, `nearest-5` or `up-5`, with any step in seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Rounding {
+    /// Whole seconds, as computed
+    #[default]
+    Exact,
+    /// Nearest multiple of the step, rounding halves up
+    Nearest(u32),
+    /// Next multiple of the step, so the swimmer never gets less rest
+    Up(u32),
+}
+
+/// Base send-off per 100m, by stroke, used to resolve `@base` intervals
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BaseIntervals {
+    /// Base in seconds for strokes without their own entry
+    pub default: Option<u32>,
+    /// Base in seconds for specific strokes
+    pub strokes: HashMap<String, u32>,
+    /// Rounding of the send-offs worked out from the bases
+    pub rounding: Rounding,
+}
+
+impl Rounding {
+    /// `secs` rounded by the policy, never below one step, saturating at the
+    /// longest time a `u32` holds
+    pub fn apply(&self, secs: u32) -> u32 {
+        match *self {
+            Rounding::Exact => secs,
+            Rounding::Nearest(step) => (secs.saturating_add(step / 2) / step).max(1) * step,
+            Rounding::Up(step) => secs.div_ceil(step).max(1).saturating_mul(step),
+        }
+    }
+}
+
+impl FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid rounding `{}`, expected `exact`, `nearest-<secs>` or `up-<secs>`",
+                s
+            )
+        };
+        if s == "exact" {
+            return Ok(Rounding::Exact);
+        }
+        let (mode, step) = s.split_once('-').ok_or_else(invalid)?;
+        let step: u32 = step
+            .trim_end_matches('s')
+            .parse()
+            .ok()
+            .filter(|&step| step > 0)
+            .ok_or_else(invalid)?;
+        match mode {
+            "nearest" => Ok(Rounding::Nearest(step)),
+            "up" => Ok(Rounding::Up(step)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for Rounding {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rounding::Exact => write!(f, "exact"),
+            Rounding::Nearest(step) => write!(f, "nearest-{}", step),
+            Rounding::Up(step) => write!(f, "up-{}", step),
+        }
+    }
+}
+
+impl BaseIntervals {
+    /// Bases declared in the metadata as `base: 1:30` and `base.<stroke>: 1:45`
+    pub fn from_metadata(metadata: &Metadata) -> Result<Self, String> {
+        let mut bases = Self::default();
+
+        for (key, value) in &metadata.entries {
+            let stroke = match key.strip_prefix("base") {
+                Some("") => None,
+                Some(rest) => match rest.strip_prefix('.') {
+                    Some(stroke) => Some(stroke),
+                    None => continue,
+                },
+                None => continue,
+            };
+            let secs = parse_duration(value)
+                .ok_or_else(|| format!("invalid base interval `{}` for `{}`", value, key))?;
+            match stroke {
+                Some(stroke) => {
+                    bases.strokes.insert(stroke.to_string(), secs);
+                }
+                None => bases.default = Some(secs),
+            }
+        }
+
+        Ok(bases)
+    }
+
+    /// Base in seconds per 100m for `stroke`
+    pub fn get(&self, stroke: &str) -> Option<u32> {
+        self.strokes.get(stroke).copied().or(self.default)
+    }
+}
+
+/// Replaces every `@base` interval with a concrete one: the stroke's base
+/// scaled to the statement's distance, plus the offset, rounded by the bases'
+/// rounding policy
+pub fn resolve_base_intervals(
+    workout: &mut Workout,
+    bases: &BaseIntervals,
+) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    for (i, set) in workout.sets.iter_mut().enumerate() {
+        resolve_set(set, SetPath::new(vec![i]), bases, &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn resolve_set(set: &mut Set, path: SetPath, bases: &BaseIntervals, errors: &mut Vec<String>) {
+    match set {
+        Set::Repetition { set, .. } => resolve_set(set, path, bases, errors),
+        Set::Block { sets, .. } => {
+            for (i, set) in sets.iter_mut().enumerate() {
+                resolve_set(set, path.child(i), bases, errors);
+            }
+        }
+        Set::Statement(stmt) => {
+            let Some(Interval::Base { offset }) = stmt.interval else {
+                return;
+            };
+            let Some(base) = bases.get(&stmt.stroke.name) else {
+                errors.push(format!(
+                    "set {}: no base interval declared for {}",
+                    path, stmt.stroke.name
+                ));
+                return;
+            };
+            let secs = (base * stmt.distance.meters()) as i64 / 100 + offset as i64;
+            if secs <= 0 {
+                errors.push(format!(
+                    "set {}: base interval {} is not positive",
+                    path,
+                    Interval::Base { offset }
+                ));
+                return;
+            }
+            stmt.interval = Some(Interval::from_seconds(bases.rounding.apply(secs as u32)));
+        }
+        Set::Dryland { .. } => {}
+    }
+}
+
+/// Goal times for the swimmer's target events, used to resolve `rp200` race
+/// paces
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RaceGoals {
+    /// Goal time in seconds by stroke and event distance in meters
+    pub events: BTreeMap<(String, u32), u32>,
+}
+
+impl RaceGoals {
+    /// Adds the goal for an event written as `200 free`
+    pub fn insert(&mut self, event: &str, secs: u32) -> Result<(), String> {
+        let parsed = event
+            .split_once(' ')
+            .and_then(|(meters, stroke)| Some((meters.parse::<u32>().ok()?, stroke.trim())))
+            .filter(|(meters, stroke)| *meters > 0 && !stroke.is_empty());
+        let Some((meters, stroke)) = parsed else {
+            return Err(format!(
+                "invalid goal event `{}`, expected e.g. `200 free`",
+                event
+            ));
+        };
+        self.events
+            .insert((stroke.to_ascii_lowercase(), meters), secs);
+        Ok(())
+    }
+
+    /// Goal time in seconds for the `event` meters of `stroke`
+    pub fn get(&self, stroke: &str, event: u32) -> Option<u32> {
+        self.events
+            .get(&(stroke.to_ascii_lowercase(), event))
+            .copied()
+    }
+
+    /// Seconds to swim `meters` at the race pace of the event
+    pub fn split(&self, stroke: &str, event: u32, meters: u32) -> Option<f64> {
+        let goal = self.get(stroke, event)?;
+        Some(f64::from(goal) * f64::from(meters) / f64::from(event))
+    }
+}
+
+/// Replaces every `@rp200` interval with a concrete one, and every `hold rp200`
+/// with a target time per rep: the race pace of the goal event for the
+/// statement's stroke, scaled to its distance, plus the offset
+pub fn resolve_race_paces(workout: &mut Workout, goals: &RaceGoals) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+    for (i, set) in workout.sets.iter_mut().enumerate() {
+        resolve_race_pace(set, SetPath::new(vec![i]), 1, goals, &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn resolve_race_pace(
+    set: &mut Set,
+    path: SetPath,
+    reps: u32,
+    goals: &RaceGoals,
+    errors: &mut Vec<String>,
+) {
+    match set {
+        Set::Repetition { count, set, .. } => resolve_race_pace(set, path, *count, goals, errors),
+        Set::Block { sets, .. } => {
+            for (i, set) in sets.iter_mut().enumerate() {
+                resolve_race_pace(set, path.child(i), reps, goals, errors);
+            }
+        }
+        Set::Statement(stmt) => {
+            let meters = stmt.distance.meters();
+            let mut time = |race_pace: RacePace| {
+                let Some(split) = goals.split(&stmt.stroke.name, race_pace.event, meters) else {
+                    errors.push(format!(
+                        "set {}: no {}m {} goal time in the profile",
+                        path, race_pace.event, stmt.stroke.name
+                    ));
+                    return None;
+                };
+                let secs = split.round() as i64 + race_pace.offset as i64;
+                if secs <= 0 {
+                    errors.push(format!(
+                        "set {}: race pace {} is not positive",
+                        path, race_pace
+                    ));
+                    return None;
+                }
+                Some(secs as u32)
+            };
+
+            if let Some(Interval::RacePace(race_pace)) = stmt.interval {
+                if let Some(secs) = time(race_pace) {
+                    stmt.interval = Some(Interval::from_seconds(secs));
+                }
+            }
+            if let Some(race_pace) = stmt.hold {
+                if let Some(secs) = time(race_pace) {
+                    stmt.targets = vec![secs; reps as usize];
+                    stmt.hold = None;
+                }
+            }
+        }
+        Set::Dryland { .. } => {}
+    }
+}
+
+/// Gives statements without an intensity of their own (from `@z3` or their
+/// modifiers) the intensity of the innermost enclosing block that has one
+pub fn resolve_intensities(workout: &mut Workout) {
+    for set in &mut workout.sets {
+        inherit_intensity(set, None);
+    }
+}
+
+fn inherit_intensity(set: &mut Set, inherited: Option<Intensity>) {
+    match set {
+        Set::Repetition { set, .. } => inherit_intensity(set, inherited),
+        Set::Block {
+            sets, intensity, ..
+        } => {
+            let inherited = intensity.or(inherited);
+            for set in sets {
+                inherit_intensity(set, inherited);
+            }
+        }
+        Set::Statement(stmt) => {
+            if stmt.designated_intensity().is_none() {
+                stmt.intensity = inherited;
+            }
+        }
+        Set::Dryland { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::Analyse, lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn parse(source: &str) -> Workout {
+        Parser::new(Token::lexer(source)).parse().unwrap()
+    }
+
+    #[test]
+    fn test_resolve_base_intervals() {
+        let mut workout = parse(
+            "base: 1:30\nbase.back: 1:40\n4x100m free @base\n4x { 50m back @base+5\n200m fly @base-10 }",
+        );
+        let bases = BaseIntervals::from_metadata(&workout.metadata).unwrap();
+        resolve_base_intervals(&mut workout, &bases).unwrap();
+
+        assert_eq!(
+            workout.to_string().lines().skip(2).collect::<Vec<_>>(),
+            vec![
+                "4x 100m free @1:30",
+                "4x {",
+                "    50m back @55s",
+                "    200m fly @2:50",
+                "}"
+            ]
+        );
+        assert_eq!(workout.swim_time(), 4 * 90 + 4 * (55 + 170));
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!("nearest-5".parse(), Ok(Rounding::Nearest(5)));
+        assert_eq!("up-10s".parse(), Ok(Rounding::Up(10)));
+        assert_eq!("exact".parse(), Ok(Rounding::Exact));
+        assert!("up-0".parse::<Rounding>().is_err());
+        assert!("down-5".parse::<Rounding>().is_err());
+        assert_eq!(Rounding::Nearest(5).apply(72), 70);
+        assert_eq!(Rounding::Nearest(5).apply(73), 75);
+        assert_eq!(Rounding::Up(5).apply(71), 75);
+        assert_eq!(Rounding::Up(5).apply(2), 5);
+        assert_eq!(Rounding::Exact.apply(71), 71);
+        assert_eq!(Rounding::Nearest(5).apply(u32::MAX), u32::MAX);
+        assert_eq!(Rounding::Up(7).apply(u32::MAX), u32::MAX);
+
+        let mut workout = parse("base: 1:25\n4x50m free @base\n100m free @base+3");
+        let mut bases = BaseIntervals::from_metadata(&workout.metadata).unwrap();
+        bases.rounding = Rounding::Up(5);
+        resolve_base_intervals(&mut workout, &bases).unwrap();
+        assert_eq!(
+            workout.to_string().lines().skip(1).collect::<Vec<_>>(),
+            vec!["4x 50m free @45s", "100m free @1:30"]
+        );
+    }
+
+    #[test]
+    fn test_missing_base() {
+        let mut workout =
+            parse("base.free: 1:30\n100m free @base\n2x { 100m free\n100m back @base }");
+        let bases = BaseIntervals::from_metadata(&workout.metadata).unwrap();
+
+        assert_eq!(
+            resolve_base_intervals(&mut workout, &bases),
+            Err(vec![
+                "set 2.2: no base interval declared for back".to_string()
+            ])
+        );
+
+        let metadata = parse("base: fast\n100m free").metadata;
+        assert_eq!(
+            BaseIntervals::from_metadata(&metadata),
+            Err("invalid base interval `fast` for `base`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_race_paces() {
+        let mut goals = RaceGoals::default();
+        goals.insert("200 free", 125).unwrap();
+        goals.insert("100 Back", 65).unwrap();
+        assert_eq!(goals.split("free", 200, 25), Some(15.625));
+        assert_eq!(goals.split("free", 200, 50), Some(31.25));
+
+        let mut workout = parse(
+            "4x 50m free hold rp200+1 @1:00
+3x { 100m free @rp200+15
+25m back hold rp100-2 }
+100m fly @rp100",
+        );
+        assert_eq!(
+            workout.to_string().lines().next(),
+            Some("4x 50m free hold rp200+1 @1:00")
+        );
+        assert_eq!(
+            resolve_race_paces(&mut workout, &goals),
+            Err(vec![
+                "set 3: no 100m fly goal time in the profile".to_string()
+            ])
+        );
+        assert_eq!(
+            workout.to_string().lines().take(4).collect::<Vec<_>>(),
+            vec![
+                "4x 50m free targets(32s, 32s, 32s, 32s) @1:00",
+                "3x {",
+                "    100m free @1:18",
+                "    25m back targets(14s, 14s, 14s)",
+            ]
+        );
+
+        assert_eq!(
+            goals.insert("free", 60),
+            Err("invalid goal event `free`, expected e.g. `200 free`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_intensities() {
+        let mut workout = parse(
+            "400m free(easy)\n4x { 100m free @1:30\n50m fly(sprint)\n2x { 50m back } @z1 } @z3",
+        );
+        resolve_intensities(&mut workout);
+
+        let intensities: Vec<_> = workout
+            .numbered_sets()
+            .iter()
+            .filter_map(|(_, set)| set.statement())
+            .map(|stmt| stmt.intensity())
+            .collect();
+        assert_eq!(
+            intensities,
+            vec![
+                Intensity::Easy,
+                Intensity::Threshold,
+                Intensity::Sprint,
+                Intensity::Easy
+            ]
+        );
+        assert_eq!(workout.sets[0].to_string(), "400m free(easy)");
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{
+    analysis::SetKind,
+    ast::{Set, Trial, Workout},
+};
+
+/// Metadata keys kept when scrubbing, because they change how sets are read
+/// or say nothing about who swam them
+const PUBLIC_KEYS: &[&str] = &["base", "tags"];
+
+impl Workout {
+    /// Copy of the workout safe to share publicly
+    ///
+    /// Metadata is dropped except base intervals (`base`, `base.<stroke>`),
+    /// which the sets need, and tags. Names, notes, dates and titles go.
+    /// Comments are never part of the parsed workout, and coach notes are
+    /// dropped, so they are gone too. Block labels, which can name a swimmer
+    /// or a meet, become `warmup`, `cooldown` or `main`, and recorded time
+    /// trial results go back to `TT __:__`.
+    pub fn scrubbed(&self) -> Workout {
+        fn scrub(set: &mut Set) {
+            match set {
+                Set::Repetition { set, .. } => scrub(set),
+                Set::Block { sets, label, .. } => {
+                    if let Some(label) = label {
+                        *label = SetKind::of_section(label)
+                            .unwrap_or(SetKind::Main)
+                            .to_string();
+                    }
+                    sets.iter_mut().for_each(scrub);
+                }
+                Set::Statement(stmt) => {
+                    if let Some(Trial::Recorded(_)) = stmt.trial {
+                        stmt.trial = Some(Trial::Pending);
+                    }
+                }
+                Set::Dryland { .. } => {}
+            }
+        }
+
+        let mut workout = self.swimmer_view();
+        workout.metadata.entries.retain(|(key, _)| {
+            let key = key.split('.').next().unwrap_or(key);
+            PUBLIC_KEYS.contains(&key)
+        });
+        workout.sets.iter_mut().for_each(scrub);
+        workout
+    }
+
+    /// Copy of the workout to hand to swimmers, without `// coach:` notes
+    pub fn swimmer_view(&self) -> Workout {
+        fn strip(set: &mut Set) {
+            match set {
+                Set::Repetition { set, .. } => strip(set),
+                Set::Block { sets, .. } => sets.iter_mut().for_each(strip),
+                Set::Statement(stmt) => stmt.coach_notes.clear(),
+                Set::Dryland { .. } => {}
+            }
+        }
+
+        let mut workout = self.clone();
+        workout.sets.iter_mut().for_each(strip);
+        workout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    #[test]
+    fn test_scrubbed() {
+        let workout = Parser::new(Token::lexer(
+            "title: Sam's taper\ndate: 2024-03-18\nswimmer: Sam Smith\nbase: 1:30\nbase.back: 1:40\ntags: [taper]\nbasement: 2\n// for Sam\n4x100m free @base",
+        ))
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            workout.scrubbed().to_string(),
+            "base: 1:30\nbase.back: 1:40\ntags: [taper]\n4x 100m free @base\n"
+        );
+
+        let workout = Parser::new(Token::lexer(
+            "Warm-up: { 400m free }\nSams-Set (20:00): { 200m free TT 2:31 }\ncooldown: { 200m back }",
+        ))
+        .parse()
+        .unwrap();
+        assert_eq!(
+            workout.scrubbed().to_string(),
+            "warmup: {\n    400m free\n}\nmain (20:00): {\n    200m free TT __:__\n}\ncooldown: {\n    200m back\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_swimmer_view() {
+        let workout = Parser::new(Token::lexer(
+            "400m free(easy)\n# coach: watch Sam's turns\n4x {\n  100m fly @1:40 // coach: hold 1:25s\n  50m free @1:00\n}\n// coach: cut if tired",
+        ))
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            workout.to_string(),
+            "400m free(easy)\n4x {\n    100m fly @1:40 // coach: watch Sam's turns; hold 1:25s\n    50m free @1:00 // coach: cut if tired\n}\n"
+        );
+        assert_eq!(
+            workout.swimmer_view().to_string(),
+            "400m free(easy)\n4x {\n    100m fly @1:40\n    50m free @1:00\n}\n"
+        );
+        assert!(!workout.scrubbed().to_string().contains("coach"));
+    }
+}
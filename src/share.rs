@@ -0,0 +1,119 @@
+//! Short codes for texting a workout instead of sending its file
+//!
+//! A code is the swimmer's view of the workout, formatted without
+//! indentation, deflated and written in URL-safe base64 after a version
+//! prefix, so the encoding can change without breaking codes already sent.
+//! Coach notes are left out, as codes are sent to swimmers.
+
+use std::io::{Read, Write};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use logos::Logos;
+
+use crate::{ast::Workout, lexer::Token, parser::Parser};
+
+/// Prefix of codes in the current encoding
+const VERSION: &str = "1";
+
+/// Most bytes of workout a code may inflate to, so a small code can't
+/// inflate to gigabytes
+const MAX_SHARE_BYTES: u64 = 1 << 20;
+
+impl Workout {
+    /// Share code of the workout, e.g., `1y8xNDBQyC...`
+    pub fn share_code(&self) -> String {
+        let text = self
+            .swimmer_view()
+            .to_string()
+            .lines()
+            .map(str::trim_start)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder
+            .write_all(text.as_bytes())
+            .and_then(|_| encoder.finish())
+            .map(|deflated| format!("{}{}", VERSION, URL_SAFE_NO_PAD.encode(deflated)))
+            .expect("writing to memory can't fail")
+    }
+
+    /// Reads a workout back from its share code
+    pub fn from_share_code(code: &str) -> Result<Workout, Vec<String>> {
+        let code = code.trim();
+        let encoded = code
+            .strip_prefix(VERSION)
+            .ok_or_else(|| vec![format!("unknown share code version in `{}`", code)])?;
+        let invalid = || vec!["invalid share code".to_string()];
+        let deflated = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| invalid())?;
+        let mut text = String::new();
+        DeflateDecoder::new(deflated.as_slice())
+            .take(MAX_SHARE_BYTES + 1)
+            .read_to_string(&mut text)
+            .map_err(|_| invalid())?;
+        if text.len() as u64 > MAX_SHARE_BYTES {
+            return Err(vec![format!(
+                "share code inflates to more than {} bytes",
+                MAX_SHARE_BYTES
+            )]);
+        }
+
+        Parser::new(Token::lexer(&text))
+            .parse()
+            .map_err(|errors| errors.iter().map(ToString::to_string).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_code() {
+        let source = "title: Tuesday\n400m free(easy)\n4x {\n    100m fly @1:40 @z4 key\n    50m back @base+5\n}\n200m free // coach: long strokes\n";
+        let workout = Parser::new(Token::lexer(source)).parse().unwrap();
+
+        let code = workout.share_code();
+        assert!(code.starts_with(VERSION));
+        assert!(code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        let shared = Workout::from_share_code(&code).unwrap();
+        assert_eq!(shared, workout.swimmer_view());
+        assert!(!shared.to_string().contains("coach"));
+    }
+
+    #[test]
+    fn test_invalid_codes() {
+        assert_eq!(
+            Workout::from_share_code("2abc"),
+            Err(vec!["unknown share code version in `2abc`".to_string()])
+        );
+        assert_eq!(
+            Workout::from_share_code("1***"),
+            Err(vec!["invalid share code".to_string()])
+        );
+        assert_eq!(
+            Workout::from_share_code("1AAAA"),
+            Err(vec!["invalid share code".to_string()])
+        );
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder
+            .write_all(&vec![b'\n'; MAX_SHARE_BYTES as usize + 1])
+            .unwrap();
+        let code = format!(
+            "{}{}",
+            VERSION,
+            URL_SAFE_NO_PAD.encode(encoder.finish().unwrap())
+        );
+        assert!(code.len() < 2000);
+        assert_eq!(
+            Workout::from_share_code(&code),
+            Err(vec![format!(
+                "share code inflates to more than {} bytes",
+                MAX_SHARE_BYTES
+            )])
+        );
+    }
+}
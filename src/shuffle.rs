@@ -0,0 +1,130 @@
+use crate::{
+    ast::{Set, Workout},
+    query::section_sets,
+};
+
+/// Reproducible pseudo-random numbers (SplitMix64), so a seed always gives
+/// the same order
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Shuffles the items in place (Fisher-Yates)
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+impl Workout {
+    /// Copy of the workout with the main-set items in an order picked by
+    /// `seed`, e.g., to give each lane a variation of one workout
+    ///
+    /// The warmup and cooldown stay where they are, as do dryland sets. A
+    /// main set written as a single block has the sets inside it shuffled.
+    /// The seed is kept as `seed` metadata, so the variation can be made
+    /// again from the original.
+    pub fn shuffled(&self, seed: u64) -> Workout {
+        let mut workout = self.clone();
+        workout.metadata.set("seed", &seed.to_string());
+        let mut rng = SplitMix(seed);
+
+        let main = section_sets(&workout, "main");
+        if let [index] = main[..] {
+            if let Some(sets) = block_sets(&mut workout.sets[index]) {
+                shuffle_swims(sets, &mut rng);
+                return workout;
+            }
+        }
+
+        let positions: Vec<usize> = main
+            .into_iter()
+            .filter(|&i| !matches!(workout.sets[i], Set::Dryland { .. }))
+            .collect();
+        let mut order = positions.clone();
+        rng.shuffle(&mut order);
+        let sets: Vec<Set> = order.iter().map(|&i| workout.sets[i].clone()).collect();
+        for (position, set) in positions.into_iter().zip(sets) {
+            workout.sets[position] = set;
+        }
+        workout
+    }
+}
+
+/// Sets of a block, or of the block a repetition repeats
+fn block_sets(set: &mut Set) -> Option<&mut Vec<Set>> {
+    match set {
+        Set::Block { sets, .. } => Some(sets),
+        Set::Repetition { set, .. } => block_sets(set),
+        _ => None,
+    }
+}
+
+/// Shuffles the sets other than dryland among their own positions
+fn shuffle_swims(sets: &mut [Set], rng: &mut SplitMix) {
+    let positions: Vec<usize> = (0..sets.len())
+        .filter(|&i| !matches!(sets[i], Set::Dryland { .. }))
+        .collect();
+    let mut swims: Vec<Set> = positions.iter().map(|&i| sets[i].clone()).collect();
+    rng.shuffle(&mut swims);
+    for (position, set) in positions.into_iter().zip(swims) {
+        sets[position] = set;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::Analyse, lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn parse(source: &str) -> Workout {
+        Parser::new(Token::lexer(source)).parse().unwrap()
+    }
+
+    #[test]
+    fn test_shuffled() {
+        let workout = parse(
+            "400m free(easy)\n4x100m fly @1:40\n8x50m back @1:00\n4x200m free @3:00\n6x75m breast @1:30\n200m free(easy)",
+        );
+        let shuffled = workout.shuffled(7);
+
+        assert_eq!(shuffled, workout.shuffled(7));
+        assert_eq!(shuffled.metadata.get("seed"), Some("7"));
+        assert_eq!(shuffled.shuffled(8).metadata.entries.len(), 1);
+        assert_ne!(shuffled.sets, workout.sets);
+        assert_eq!(shuffled.sets[0], workout.sets[0]);
+        assert_eq!(shuffled.sets[5], workout.sets[5]);
+        assert_eq!(shuffled.total_distance(), workout.total_distance());
+        let mut main: Vec<String> = shuffled.sets[1..5]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        main.sort();
+        let mut expected: Vec<String> =
+            workout.sets[1..5].iter().map(ToString::to_string).collect();
+        expected.sort();
+        assert_eq!(main, expected);
+
+        let workout = parse(
+            "Warmup (6:00): { 400m free }\nMain (20:00): {\n    4x100m fly @1:40\n    8x50m back @1:00\n    dryland { 20 squats }\n    4x200m free @3:00\n    6x75m breast @1:30\n}\nCooldown (4:00): { 200m free }",
+        );
+        let shuffled = workout.shuffled(3);
+        assert_eq!(shuffled.sets[0], workout.sets[0]);
+        assert_eq!(shuffled.sets[2], workout.sets[2]);
+        assert_eq!(
+            shuffled.sets[1].children()[2],
+            workout.sets[1].children()[2]
+        );
+        assert_ne!(shuffled.sets[1], workout.sets[1]);
+    }
+}
@@ -0,0 +1,153 @@
+use std::fmt;
+
+use crate::ast::{Set, Statement, Stroke, Workout};
+
+/// Metadata keys a template keeps; dates, swimmers and base intervals belong
+/// to the workout it came from
+const TEMPLATE_KEYS: &[&str] = &["title", "tags"];
+
+/// Structure of a workout without its numbers, printed as a template to fill
+/// in with every distance left as a `_` blank
+///
+/// Repetition counts, strokes and modifiers, odd and even rep strokes, the
+/// strokes of parts, equipment, intensities, key marks and block labels are
+/// kept. Send-offs, target times, block budgets and coach notes are dropped.
+/// Dryland blocks are kept whole.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skeleton(Workout);
+
+impl Workout {
+    /// Reusable template of the workout's structure
+    pub fn skeleton(&self) -> Skeleton {
+        let mut workout = self.clone();
+        workout
+            .metadata
+            .entries
+            .retain(|(key, _)| TEMPLATE_KEYS.contains(&key.as_str()));
+        Skeleton(workout)
+    }
+}
+
+impl fmt::Display for Skeleton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.0.metadata.entries {
+            writeln!(f, "{}: {}", key, value)?;
+        }
+        for set in &self.0.sets {
+            write_set(f, set, 0)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_set(f: &mut fmt::Formatter<'_>, set: &Set, depth: usize) -> fmt::Result {
+    match set {
+        Set::Repetition { count, set, .. } => {
+            write!(f, "{}x ", count)?;
+            write_set(f, set, depth)
+        }
+        Set::Block {
+            sets,
+            intensity,
+            key,
+            label,
+            ..
+        } => {
+            if let Some(label) = label {
+                write!(f, "{}: ", label)?;
+            }
+            writeln!(f, "{{")?;
+            for set in sets {
+                write!(f, "{}", "    ".repeat(depth + 1))?;
+                write_set(f, set, depth + 1)?;
+                writeln!(f)?;
+            }
+            write!(f, "{}}}", "    ".repeat(depth))?;
+            if let Some(intensity) = intensity {
+                write!(f, " @z{}", intensity.zone())?;
+            }
+            if *key {
+                write!(f, " key")?;
+            }
+            Ok(())
+        }
+        Set::Statement(stmt) => write_statement(f, stmt),
+        Set::Dryland { .. } => {
+            let dryland = set.to_string();
+            let indent = format!("\n{}", "    ".repeat(depth));
+            write!(f, "{}", dryland.replace('\n', &indent))
+        }
+    }
+}
+
+/// The statement as written, with a blank for its distance, the alternation
+/// distance and the distance of each part
+fn write_statement(f: &mut fmt::Formatter<'_>, stmt: &Statement) -> fmt::Result {
+    match &stmt.alternation {
+        Some(alternation) => {
+            let stroke = Stroke {
+                name: alternation.strokes.join("/"),
+                modifiers: stmt.stroke.modifiers.clone(),
+            };
+            write!(f, "_ {} by _", stroke)?;
+        }
+        None if stmt.rep_strokes.len() == 2 => {
+            let stroke = Stroke {
+                name: format!("evens:{}", stmt.rep_strokes[1]),
+                modifiers: stmt.stroke.modifiers.clone(),
+            };
+            write!(f, "_ odds:{} {}", stmt.rep_strokes[0], stroke)?;
+        }
+        None => write!(f, "_ {}", stmt.stroke)?,
+    }
+    if !stmt.segments.is_empty() {
+        let parts: Vec<String> = stmt
+            .segments
+            .iter()
+            .map(|segment| format!("_ {}", segment.stroke))
+            .collect();
+        write!(f, " ({})", parts.join("/"))?;
+    }
+    if !stmt.equipment.is_empty() {
+        let equipment: Vec<String> = stmt.equipment.iter().map(ToString::to_string).collect();
+        write!(f, " [{}]", equipment.join(", "))?;
+    }
+    if let Some(intensity) = stmt.intensity {
+        write!(f, " @z{}", intensity.zone())?;
+    }
+    if stmt.key {
+        write!(f, " key")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    #[test]
+    fn test_skeleton() {
+        let workout = Parser::new(Token::lexer(
+            "title: Threshold Tuesday\ndate: 2024-03-12\nbase: 1:30\ntags: [threshold]\nWarmup (10:00): {\n    400m free(easy)\n    4x 50m kick [board] @1:10\n}\nMain (20:00): {\n    5x 200m free targets(2:30, 2:28, 2:26, 2:24, 2:22) @3:00 @z4 key // coach: hold pace\n    300m free/back(drill) by 50 @base\n    3x {\n        100m fly @z5\n    }\n}\ndryland {\n    3x 20 squats\n}",
+        ))
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            workout.skeleton().to_string(),
+            "title: Threshold Tuesday\ntags: [threshold]\nWarmup: {\n    _ free(easy)\n    4x _ kick [board]\n}\nMain: {\n    5x _ free @z4 key\n    _ free/back(drill) by _\n    3x {\n        _ fly @z5\n    }\n}\ndryland {\n    3x 20 squats\n}\n"
+        );
+
+        let workout = Parser::new(Token::lexer(
+            "4x50m free (25 drill/25 swim)\n8x50m odds:free evens:back @1:00",
+        ))
+        .parse()
+        .unwrap();
+        assert_eq!(
+            workout.skeleton().to_string(),
+            "4x _ free (_ drill/_ swim)\n8x _ odds:free evens:back\n"
+        );
+    }
+}
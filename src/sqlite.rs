@@ -0,0 +1,287 @@
+//! A library kept in a SQLite index, with the `sqlite` feature
+//!
+//! The index holds each file's parsed workout along with its distance and
+//! tags, so searches and tag counts are queries rather than a parse of the
+//! whole directory. Before answering, the index is brought up to date with
+//! the directory: files are only parsed again when their size or modified
+//! time has changed, and removed files are dropped.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use rusqlite::{params, params_from_iter, types::Value, Connection};
+
+use crate::{
+    analysis::Analyse,
+    ast::Workout,
+    json::SCHEMA_VERSION,
+    library::{read_workout, workout_paths, Library, LibraryEntry, Search, Storage},
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS workouts (
+        path TEXT PRIMARY KEY,
+        size INTEGER NOT NULL,
+        modified INTEGER NOT NULL,
+        distance INTEGER NOT NULL,
+        workout TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tags (
+        path TEXT NOT NULL REFERENCES workouts (path) ON DELETE CASCADE,
+        tag TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS tags_by_tag ON tags (tag);
+    CREATE INDEX IF NOT EXISTS tags_by_path ON tags (path);
+    CREATE INDEX IF NOT EXISTS workouts_by_distance ON workouts (distance);
+";
+
+/// The `.swim` files of a directory, indexed in a SQLite database
+#[derive(Debug)]
+pub struct SqliteStorage {
+    dir: PathBuf,
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    /// Opens the index at `index` for the `.swim` files in `dir`, creating it
+    /// if needed
+    ///
+    /// An index written with another version of the workout JSON is emptied
+    /// and filled again on the next refresh.
+    pub fn open(index: &Path, dir: &Path) -> Result<Self, String> {
+        let connection = Connection::open(index).map_err(|error| error_in(index, error))?;
+        Self::with_connection(connection, dir).map_err(|error| error_in(index, error))
+    }
+
+    /// An index for `dir` held in memory, e.g., for tests
+    pub fn in_memory(dir: &Path) -> Result<Self, String> {
+        let connection = Connection::open_in_memory().map_err(|error| error.to_string())?;
+        Self::with_connection(connection, dir).map_err(|error| error.to_string())
+    }
+
+    fn with_connection(connection: Connection, dir: &Path) -> rusqlite::Result<Self> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+        let version: u32 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version != SCHEMA_VERSION {
+            connection
+                .execute_batch("DROP TABLE IF EXISTS tags; DROP TABLE IF EXISTS workouts;")?;
+            connection.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            connection,
+        })
+    }
+
+    fn try_refresh(&self, errors: &mut Vec<String>) -> rusqlite::Result<()> {
+        let paths = match workout_paths(&self.dir) {
+            Ok(paths) => paths,
+            Err(error) => {
+                errors.push(error);
+                return Ok(());
+            }
+        };
+
+        let transaction = self.connection.unchecked_transaction()?;
+        let indexed: BTreeMap<String, (i64, i64)> = transaction
+            .prepare("SELECT path, size, modified FROM workouts")?
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        for path in &paths {
+            let key = path.to_string_lossy();
+            let stamp = std::fs::metadata(path).ok().map(|metadata| {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |since| since.as_nanos() as i64);
+                (metadata.len() as i64, modified)
+            });
+            if stamp.is_some() && indexed.get(key.as_ref()) == stamp.as_ref() {
+                continue;
+            }
+
+            transaction.execute("DELETE FROM workouts WHERE path = ?1", [&key])?;
+            let workout = match read_workout(path) {
+                Ok(workout) => workout,
+                Err(read_errors) => {
+                    errors.extend(read_errors);
+                    continue;
+                }
+            };
+            let (size, modified) = stamp.unwrap_or_default();
+            transaction.execute(
+                "INSERT INTO workouts (path, size, modified, distance, workout)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    key,
+                    size,
+                    modified,
+                    workout.total_distance(),
+                    workout.to_json()
+                ],
+            )?;
+            for tag in workout.metadata.tags() {
+                transaction.execute(
+                    "INSERT INTO tags (path, tag) VALUES (?1, ?2)",
+                    params![key, tag],
+                )?;
+            }
+        }
+
+        let present: Vec<String> = paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        for path in indexed.keys().filter(|path| !present.contains(path)) {
+            transaction.execute("DELETE FROM workouts WHERE path = ?1", [path])?;
+        }
+        transaction.commit()
+    }
+
+    /// Entries of the indexed workouts `query` selects by path and JSON
+    fn entries(
+        &self,
+        query: &str,
+        values: Vec<Value>,
+        errors: &mut Vec<String>,
+    ) -> rusqlite::Result<Vec<LibraryEntry>> {
+        let mut library = Library::default();
+        let mut statement = self.connection.prepare(query)?;
+        let rows = statement.query_map(params_from_iter(values), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (path, json) = row?;
+            match Workout::from_json(&json) {
+                Ok(workout) => library.insert(path.into(), workout),
+                Err(json_errors) => errors.extend(
+                    json_errors
+                        .iter()
+                        .map(|error| format!("{}: index: {}", path, error)),
+                ),
+            }
+        }
+        Ok(library.entries)
+    }
+}
+
+impl Storage for SqliteStorage {
+    /// Brings the index up to date with the directory
+    fn refresh(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if let Err(error) = self.try_refresh(&mut errors) {
+            errors.push(error_in(&self.dir, error));
+        }
+        errors
+    }
+
+    fn load(&self) -> (Library, Vec<String>) {
+        let mut errors = self.refresh();
+        let entries = self
+            .entries(
+                "SELECT path, workout FROM workouts ORDER BY path",
+                Vec::new(),
+                &mut errors,
+            )
+            .unwrap_or_else(|error| {
+                errors.push(error_in(&self.dir, error));
+                Vec::new()
+            });
+        (Library { entries }, errors)
+    }
+
+    fn search(&self, search: &Search) -> (Vec<LibraryEntry>, Vec<String>) {
+        let mut errors = self.refresh();
+        let mut query =
+            "SELECT path, workout FROM workouts WHERE distance BETWEEN ?1 AND ?2".to_string();
+        let mut values = vec![
+            Value::from(search.min_distance.unwrap_or(0)),
+            Value::from(search.max_distance.unwrap_or(u32::MAX)),
+        ];
+        for tag in &search.tags {
+            values.push(Value::from(tag.clone()));
+            query += &format!(
+                " AND path IN (SELECT path FROM tags WHERE tag = ?{})",
+                values.len()
+            );
+        }
+        query += " ORDER BY path";
+        let entries = self
+            .entries(&query, values, &mut errors)
+            .unwrap_or_else(|error| {
+                errors.push(error_in(&self.dir, error));
+                Vec::new()
+            });
+        (entries, errors)
+    }
+
+    fn tags(&self) -> (BTreeMap<String, usize>, Vec<String>) {
+        let mut errors = self.refresh();
+        let tags = self
+            .connection
+            .prepare("SELECT tag, COUNT(*) FROM tags GROUP BY tag")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect()
+            })
+            .unwrap_or_else(|error| {
+                errors.push(error_in(&self.dir, error));
+                BTreeMap::new()
+            });
+        (tags, errors)
+    }
+}
+
+fn error_in(path: &Path, error: rusqlite::Error) -> String {
+    format!("{}: {}", path.display(), error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::DirectoryStorage;
+
+    #[test]
+    fn test_matches_directory() {
+        let dir = std::env::temp_dir().join(format!("swim-sqlite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, source: &str| std::fs::write(dir.join(name), source).unwrap();
+        write(
+            "a.swim",
+            "date: 2024-01-08\ntags: [Threshold, freestyle]\n400m free\n30x100m free @1:30",
+        );
+        write("b.swim", "tags: threshold\n10x100m free @1:30 @z3");
+        write("c.swim", "10x50m fly @1:00");
+        write("d.swim", "400m fre @");
+
+        let directory = DirectoryStorage::new(&dir);
+        let index = SqliteStorage::in_memory(&dir).unwrap();
+        let searches = [
+            Search::new(),
+            Search::new().tag("threshold"),
+            Search::new().tag("threshold").min_distance(3000),
+            Search::new().max_distance(1000),
+        ];
+        for search in &searches {
+            assert_eq!(index.search(search), directory.search(search));
+        }
+        assert_eq!(index.tags(), directory.tags());
+        assert_eq!(index.load(), directory.load());
+
+        write("c.swim", "tags: fly\n20x50m fly @1:00");
+        std::fs::remove_file(dir.join("d.swim")).unwrap();
+        let (tags, errors) = index.tags();
+        assert!(errors.is_empty());
+        assert_eq!(tags["fly"], 1);
+        assert_eq!(index.load(), directory.load());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
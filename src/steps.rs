@@ -0,0 +1,473 @@
+//! Workout steps: a flat representation of a workout for exporters
+//!
+//! Device and platform formats (FIT, TCX, Garmin, FORM, TrainingPeaks) all
+//! describe a workout as a list of steps with repeat markers rather than as a
+//! tree of blocks. [`WorkoutSteps::from_workout`] does that flattening once so
+//! exporters only need to implement [`Exporter`].
+//!
+//! Blocks are inlined into their parent, repetitions become [`Step::Repeat`],
+//! and every other value is resolved to plain meters and seconds. Swims on
+//! rest rather than a send-off are followed by a [`Step::Rest`], as FIT and
+//! Garmin workouts have rest steps ending on time or heart rate. A repeated
+//! block with instructions for some rounds becomes a repeat for each run of
+//! rounds swum alike, so `3x { ... } (round 3 race pace)` is two rounds as
+//! written and then one at race pace. Strokes taken in turn by reps, as in
+//! `4x50m odds:free evens:back`, become a repeat of one swim per stroke. Base
+//! intervals should be resolved before building steps; unresolved ones have no
+//! send-off.
+
+use std::io;
+
+use serde::Serialize;
+
+use crate::{
+    ast::{DrylandAmount, Intensity, Interval, Round, Set, Statement, Workout},
+    path::SetPath,
+};
+
+/// A workout flattened into steps
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkoutSteps {
+    /// The workout's `title` metadata, if any
+    pub name: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+/// A single step of a workout
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Step {
+    /// A single swim
+    Swim(SwimStep),
+    /// A dryland exercise, done `sets` times
+    Dryland(DrylandStep),
+    /// Rest for a time or until the heart rate drops
+    Rest(RestStep),
+    /// Steps done `count` times in a row
+    Repeat { count: u32, steps: Vec<Step> },
+}
+
+/// A single swim, e.g., one 100m of `4x 100m free @1:30`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SwimStep {
+    /// Path of the statement this step came from
+    pub path: SetPath,
+    pub meters: u32,
+    pub stroke: String,
+    pub modifiers: Vec<String>,
+    pub equipment: Vec<String>,
+    pub intensity: Intensity,
+    /// Send-off in seconds, if one is given; the first of a list
+    pub send_off: Option<u32>,
+    /// Send-off in seconds for each rep when given as a list, e.g.,
+    /// `@1:40/1:35/1:30`, cycled through like the targets
+    pub send_offs: Vec<u32>,
+    /// Goal time in seconds for each rep, cycled through on outer repeats
+    pub targets: Vec<u32>,
+}
+
+/// Rest after a swim, e.g., of `4x 50m free r:20` or `10x 100m free rest to
+/// HR<120`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RestStep {
+    /// Path of the statement the rest follows
+    pub path: SetPath,
+    /// Seconds of rest, for a timed rest
+    pub duration: Option<u32>,
+    /// Beats per minute to rest down to, for a rest lasting as long as the
+    /// swimmer takes to recover
+    pub heart_rate_below: Option<u32>,
+}
+
+/// A dryland exercise
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DrylandStep {
+    pub path: SetPath,
+    pub name: String,
+    pub sets: u32,
+    /// Repetitions per set, for counted exercises
+    pub reps: Option<u32>,
+    /// Seconds per set, for timed exercises
+    pub duration: Option<u32>,
+}
+
+/// Writes workout steps in some output format
+pub trait Exporter {
+    fn export(&self, workout: &WorkoutSteps, out: &mut dyn io::Write) -> io::Result<()>;
+}
+
+/// Exports workout steps as JSON
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonExporter;
+
+impl WorkoutSteps {
+    /// Flattens a workout into steps
+    pub fn from_workout(workout: &Workout) -> Self {
+        let steps = workout
+            .sets
+            .iter()
+            .enumerate()
+            .flat_map(|(i, set)| steps(SetPath::new(vec![i]), set))
+            .collect();
+
+        Self {
+            name: workout.metadata.get("title").map(str::to_string),
+            steps,
+        }
+    }
+
+    /// Swim, rest and dryland steps in the order they are done, with repeats
+    /// unrolled
+    pub fn unrolled(&self) -> Vec<&Step> {
+        fn visit<'a>(steps: &'a [Step], out: &mut Vec<&'a Step>) {
+            for step in steps {
+                match step {
+                    Step::Repeat { count, steps } => {
+                        for _ in 0..*count {
+                            visit(steps, out);
+                        }
+                    }
+                    step => out.push(step),
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        visit(&self.steps, &mut out);
+        out
+    }
+
+    /// Swim steps in the order they are swum, with repeats unrolled
+    pub fn swims(&self) -> Vec<&SwimStep> {
+        self.unrolled()
+            .into_iter()
+            .filter_map(|step| match step {
+                Step::Swim(swim) => Some(swim),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl SwimStep {
+    /// Send-off in seconds for the zero-based `rep` of this step
+    pub fn send_off_for(&self, rep: usize) -> Option<u32> {
+        match self.send_offs.len() {
+            0 => self.send_off,
+            len => Some(self.send_offs[rep % len]),
+        }
+    }
+}
+
+impl Exporter for JsonExporter {
+    fn export(&self, workout: &WorkoutSteps, out: &mut dyn io::Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, workout)?;
+        writeln!(out)
+    }
+}
+
+/// Steps for a set at `path`
+fn steps(path: SetPath, set: &Set) -> Vec<Step> {
+    match set {
+        Set::Repetition { count, set, rounds } => {
+            // Rounds with rep strokes differ until the strokes come round
+            // again, so runs of whole cycles of rounds are repeated
+            let cycle = rep_cycle(set);
+            let cycles: Vec<Vec<Step>> = (0..cycle)
+                .map(|rep| rep_steps(path.clone(), set, rep))
+                .collect();
+            let round = |n: u32| {
+                let mut steps = cycles[((n - 1) % cycle) as usize].clone();
+                if let Some(round) = rounds.iter().find(|round| round.round == n) {
+                    apply_round(&mut steps, round);
+                }
+                steps
+            };
+
+            let mut repeats = Vec::new();
+            let mut start = 1;
+            while start <= *count {
+                let period = cycle.min(*count - start + 1);
+                let mut times = 1;
+                while start + (times + 1) * period - 1 <= *count
+                    && (0..period).all(|i| round(start + times * period + i) == round(start + i))
+                {
+                    times += 1;
+                }
+                repeats.push(Step::Repeat {
+                    count: times,
+                    steps: (start..start + period).flat_map(round).collect(),
+                });
+                start += times * period;
+            }
+            repeats
+        }
+        Set::Block { sets, .. } => sets
+            .iter()
+            .enumerate()
+            .flat_map(|(i, set)| steps(path.child(i), set))
+            .collect(),
+        Set::Statement(stmt) if !stmt.rep_strokes.is_empty() => rep_steps(path, set, 0),
+        Set::Statement(stmt) => {
+            let (duration, heart_rate_below) = match stmt.interval {
+                Some(Interval::Rest(secs)) => (Some(secs), None),
+                Some(Interval::HeartRate { below }) => (None, Some(below)),
+                _ => return vec![Step::Swim(swim_step(path, stmt))],
+            };
+            vec![
+                Step::Swim(swim_step(path.clone(), stmt)),
+                Step::Rest(RestStep {
+                    path,
+                    duration,
+                    heart_rate_below,
+                }),
+            ]
+        }
+        Set::Dryland { exercises } => exercises
+            .iter()
+            .map(|exercise| {
+                let (reps, duration) = match exercise.amount {
+                    DrylandAmount::Reps(reps) => (Some(reps), None),
+                    DrylandAmount::Duration(secs) => (None, Some(secs)),
+                };
+                Step::Dryland(DrylandStep {
+                    path: path.clone(),
+                    name: exercise.name.clone(),
+                    sets: exercise.sets,
+                    reps,
+                    duration,
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Number of rounds after which a repeated set's rep strokes come round
+/// again, 1 if it has none
+fn rep_cycle(set: &Set) -> u32 {
+    match set {
+        Set::Statement(stmt) => stmt.rep_strokes.len().max(1) as u32,
+        Set::Block { sets, .. } => sets.iter().map(rep_cycle).fold(1, |cycle, strokes| {
+            let (mut a, mut b) = (cycle, strokes);
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            cycle / a * strokes
+        }),
+        Set::Repetition { .. } | Set::Dryland { .. } => 1,
+    }
+}
+
+/// Steps for rep `rep` (from 0) of a repeated set, with each statement
+/// swimming the stroke its rep strokes give that rep
+fn rep_steps(path: SetPath, set: &Set, rep: u32) -> Vec<Step> {
+    match set {
+        Set::Statement(stmt) if !stmt.rep_strokes.is_empty() => {
+            let strokes = stmt.split_by_rep(stmt.rep_strokes.len() as u32);
+            let (stmt, _) = &strokes[rep as usize % strokes.len()];
+            steps(path, &Set::Statement(Box::new(stmt.clone())))
+        }
+        Set::Block { sets, .. } => sets
+            .iter()
+            .enumerate()
+            .flat_map(|(i, set)| rep_steps(path.child(i), set, rep))
+            .collect(),
+        set => steps(path, set),
+    }
+}
+
+/// Swims `steps` at the intensity a round's instruction names, or with the
+/// instruction as a modifier if it names none
+fn apply_round(steps: &mut [Step], round: &Round) {
+    for step in steps {
+        match step {
+            Step::Swim(swim) => match round.intensity {
+                Some(intensity) => swim.intensity = intensity,
+                None => swim.modifiers.push(round.instruction.clone()),
+            },
+            Step::Repeat { steps, .. } => apply_round(steps, round),
+            Step::Dryland(_) | Step::Rest(_) => {}
+        }
+    }
+}
+
+fn swim_step(path: SetPath, stmt: &Statement) -> SwimStep {
+    SwimStep {
+        path,
+        meters: stmt.distance.meters(),
+        stroke: stmt.stroke.name.clone(),
+        modifiers: stmt
+            .stroke
+            .modifiers
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        equipment: stmt.equipment.iter().map(ToString::to_string).collect(),
+        intensity: stmt.intensity(),
+        send_off: match stmt.interval {
+            Some(Interval::Base { .. } | Interval::RacePace(_)) | None => None,
+            Some(ref interval) if interval.is_rest() => None,
+            Some(ref interval) => Some(interval.for_rep(0).seconds()),
+        },
+        send_offs: match stmt.interval {
+            Some(Interval::Sequence(ref intervals)) => {
+                intervals.iter().map(Interval::seconds).collect()
+            }
+            _ => Vec::new(),
+        },
+        targets: stmt.targets.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    fn parse(source: &str) -> WorkoutSteps {
+        WorkoutSteps::from_workout(&Parser::new(Token::lexer(source)).parse().unwrap())
+    }
+
+    #[test]
+    fn test_from_workout() {
+        let workout = parse(
+            "title: Tuesday\n400m free\n2x { 100m fly [fins] @1:40\n2x50m back @base }\ndryland { 20 squats }",
+        );
+
+        assert_eq!(workout.name.as_deref(), Some("Tuesday"));
+        assert_eq!(workout.steps.len(), 3);
+        let Step::Repeat { count, steps } = &workout.steps[1] else {
+            panic!("expected a repeat step");
+        };
+        assert_eq!(*count, 2);
+        assert!(matches!(&steps[1], Step::Repeat { count: 2, .. }));
+
+        let swims = workout.swims();
+        assert_eq!(swims.len(), 7);
+        assert_eq!(swims[1].path.to_string(), "2.1");
+        assert_eq!(swims[1].equipment, vec!["fins"]);
+        assert_eq!(swims[1].send_off, Some(100));
+        assert_eq!(swims[2].path.to_string(), "2.2");
+        assert_eq!(swims[2].send_off, None);
+
+        let workout = parse("4x100m free @1:40/1:35/1:30");
+        let swim = workout.swims()[0];
+        assert_eq!(swim.send_offs, vec![100, 95, 90]);
+        assert_eq!(swim.send_off, Some(100));
+        assert_eq!(swim.send_off_for(3), Some(100));
+    }
+
+    #[test]
+    fn test_rounds() {
+        let workout = parse(
+            "4x {\n    100m free @1:40\n    50m back\n} @z2 (round 2 faster, round 4 race pace)",
+        );
+        let counts: Vec<u32> = workout
+            .steps
+            .iter()
+            .map(|step| match step {
+                Step::Repeat { count, .. } => *count,
+                _ => panic!("expected repeat steps"),
+            })
+            .collect();
+        assert_eq!(counts, vec![1, 1, 1, 1]);
+
+        let swims = workout.swims();
+        assert_eq!(swims.len(), 8);
+        assert_eq!(swims[0].intensity, Intensity::Moderate);
+        assert_eq!(swims[2].modifiers, vec!["faster"]);
+        assert_eq!(swims[4].modifiers, Vec::<String>::new());
+        assert_eq!(swims[6].intensity, Intensity::RacePace);
+        assert_eq!(swims[7].intensity, Intensity::RacePace);
+
+        let workout = parse("5x {\n    100m free\n} (round 5 sprint)");
+        assert!(matches!(
+            workout.steps.as_slice(),
+            [Step::Repeat { count: 4, .. }, Step::Repeat { count: 1, .. }]
+        ));
+    }
+
+    #[test]
+    fn test_rep_strokes() {
+        let workout = parse("4x50m odds:free evens:back @1:00");
+        let Step::Repeat { count: 2, steps } = &workout.steps[0] else {
+            panic!("expected a repeat step");
+        };
+        assert_eq!(steps.len(), 2);
+        let strokes: Vec<&str> = workout
+            .unrolled()
+            .iter()
+            .filter_map(|step| match step {
+                Step::Swim(swim) => Some(swim.stroke.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(strokes, vec!["free", "back", "free", "back"]);
+
+        let workout = parse("3x { 100m fly\n50m odds:free evens:back }");
+        assert!(matches!(
+            workout.steps.as_slice(),
+            [Step::Repeat { count: 1, .. }, Step::Repeat { count: 1, .. }]
+        ));
+        let swims = workout.swims();
+        assert_eq!(swims.len(), 6);
+        assert_eq!(swims[3].stroke, "back");
+        assert_eq!(swims[5].stroke, "free");
+        assert_eq!(swims[5].path.to_string(), "1.2");
+    }
+
+    #[test]
+    fn test_rest_steps() {
+        let workout = parse("3x100m free rest to HR<120\n200m back");
+        let Step::Repeat { count: 3, steps } = &workout.steps[0] else {
+            panic!("expected a repeat step");
+        };
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0], Step::Swim(workout.swims()[0].clone()));
+        assert_eq!(workout.swims()[0].send_off, None);
+        assert_eq!(
+            steps[1],
+            Step::Rest(RestStep {
+                path: SetPath::new(vec![0]),
+                duration: None,
+                heart_rate_below: Some(120),
+            })
+        );
+        assert_eq!(workout.unrolled().len(), 7);
+
+        let workout = parse("4x50m free r:20");
+        assert_eq!(workout.swims()[0].send_off, None);
+        assert!(matches!(
+            workout.unrolled()[1],
+            Step::Rest(RestStep {
+                duration: Some(20),
+                heart_rate_below: None,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_json_export() {
+        let mut out = Vec::new();
+        JsonExporter
+            .export(&parse("2x100m free @1:30"), &mut out)
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(json["steps"][0]["type"], "repeat");
+        assert_eq!(json["steps"][0]["steps"][0]["path"], "1");
+        assert_eq!(json["steps"][0]["steps"][0]["send_off"], 90);
+
+        let mut out = Vec::new();
+        JsonExporter
+            .export(&parse("2x100m free targets(1:20, 1:15)"), &mut out)
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            json["steps"][0]["steps"][0]["targets"],
+            serde_json::json!([80, 75])
+        );
+    }
+}
@@ -0,0 +1,408 @@
+//! Headline numbers of a workout, computed once and rendered by any frontend
+
+use std::{collections::BTreeMap, io};
+
+use serde::Serialize;
+
+use crate::{
+    analysis::{kind_distances, section_distances, Analyse, SetKind},
+    ast::{Intensity, Set, Statement, Workout},
+    check::format_duration,
+    complexity::Complexity,
+    drills::DrillDictionary,
+    pace::{Fatigue, PaceModel},
+    pool::Turns,
+};
+
+/// Most characters of a set's shape in its one-line summary
+const SHAPE_WIDTH: usize = 48;
+
+/// Deepest nesting spelled out in a set's shape; deeper blocks show as `{…}`
+const SHAPE_DEPTH: usize = 2;
+
+/// Totals and mixes of a workout
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Summary {
+    /// Meters swum
+    pub distance: u32,
+    /// Seconds swum, from the send-offs and, with a pace model, predicted for
+    /// statements without one, slowing with fatigue if given
+    pub swim_time: u32,
+    /// Estimated seconds of dryland exercises
+    pub dryland_time: u32,
+    /// Number of top-level sets
+    pub sets: usize,
+    /// Meters swum in each section, e.g., `warmup: { ... }` or the detected
+    /// main set, in workout order
+    pub sections: Vec<(String, u32)>,
+    /// Meters swum in each kind of set, e.g., warmup or main
+    pub kinds: BTreeMap<SetKind, u32>,
+    pub sprint_reps: u32,
+    /// Meters swum at race pace
+    pub race_pace_distance: u32,
+    /// Meters swum with each stroke, attributing `choice`, IM and drills as
+    /// the drill dictionary says
+    pub strokes: BTreeMap<String, u32>,
+    /// Meters swum at each intensity
+    pub intensities: BTreeMap<Intensity, u32>,
+    /// Meters swum with each piece of equipment, bracketed or named as a
+    /// modifier
+    pub equipment: BTreeMap<String, u32>,
+    /// Seconds of rest the send-offs leave after the predicted swim times,
+    /// plus the rest written as rest, if there is a pace model to predict them
+    pub rest: Option<u32>,
+    /// Turns and push-offs in the pool given in the workout's `pool` metadata
+    pub turns: Option<Turns>,
+    /// How hard the workout is to remember and swim as written
+    pub complexity: Complexity,
+}
+
+impl Summary {
+    pub fn new(
+        workout: &Workout,
+        drills: &DrillDictionary,
+        model: Option<&dyn PaceModel>,
+        fatigue: Option<&Fatigue>,
+    ) -> Self {
+        let mut rest = model.map(|_| 0);
+        workout.for_each_statement(&mut |stmt, reps| {
+            if let (Some(rest), Some(model)) = (rest.as_mut(), model) {
+                if let Some(interval) = stmt.interval.as_ref().filter(|i| i.seconds() > 0) {
+                    let swim =
+                        model.predict(stmt.distance.meters(), &stmt.stroke.name, stmt.intensity());
+                    *rest = (0..reps as usize)
+                        .map(|rep| interval.for_rep(rep).seconds().saturating_sub(swim))
+                        .fold(*rest, u32::saturating_add);
+                }
+                if let Some(interval) = &stmt.interval {
+                    let after = interval.rest(model.recovery()).saturating_mul(reps);
+                    *rest = rest.saturating_add(after);
+                }
+            }
+        });
+
+        Summary {
+            distance: workout.total_distance(),
+            swim_time: match (model, fatigue) {
+                (Some(model), Some(fatigue)) => workout.fatigued_swim_time(model, fatigue),
+                (Some(model), None) => workout.estimated_swim_time(model),
+                (None, _) => workout.swim_time(),
+            },
+            dryland_time: workout.dryland_time(),
+            sets: workout.sets.len(),
+            sections: section_distances(workout),
+            kinds: kind_distances(workout, drills),
+            sprint_reps: workout.sprint_reps(),
+            race_pace_distance: workout.race_pace_distance(),
+            strokes: workout.stroke_distribution_with(drills),
+            intensities: workout.intensity_distribution(),
+            equipment: workout.equipment_usage(),
+            rest,
+            turns: workout.course().map(|course| workout.turns(course)),
+            complexity: workout.complexity(),
+        }
+    }
+
+    /// Session time in seconds, swimming and dryland
+    pub fn duration(&self) -> u32 {
+        self.swim_time + self.dryland_time
+    }
+}
+
+impl Set {
+    /// One line describing the set, for listings that have no room for the
+    /// whole set, e.g., `4×{25 choice + 12×50 free} — 2500m, ~38min`
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} — {}m", self.shape(), self.total_distance());
+        let time = self.total_time();
+        if time > 0 {
+            summary.push_str(&format!(", ~{}min", ((time + 30) / 60).max(1)));
+        }
+        summary
+    }
+
+    /// Structure of the set with its reps, strokes and repeats but no send-offs
+    /// or modifiers, cut short with `…` when long or deeply nested
+    pub fn shape(&self) -> String {
+        let shape = shape(self, 0);
+        if shape.chars().count() <= SHAPE_WIDTH {
+            return shape;
+        }
+        let mut cut: String = shape.chars().take(SHAPE_WIDTH - 1).collect();
+        cut.push('…');
+        cut
+    }
+}
+
+fn shape(set: &Set, depth: usize) -> String {
+    match set {
+        Set::Repetition { count, set, .. } => format!("{}×{}", count, shape(set, depth)),
+        Set::Block { sets, label, .. } => {
+            let label = label
+                .as_deref()
+                .map_or(String::new(), |label| format!("{} ", label));
+            if depth >= SHAPE_DEPTH {
+                return format!("{}{{…}}", label);
+            }
+            let inner: Vec<String> = sets.iter().map(|set| shape(set, depth + 1)).collect();
+            format!("{}{{{}}}", label, inner.join(" + "))
+        }
+        Set::Statement(stmt) => statement_shape(stmt),
+        Set::Dryland { .. } => "dryland".to_string(),
+    }
+}
+
+fn statement_shape(stmt: &Statement) -> String {
+    let strokes = match &stmt.alternation {
+        Some(alternation) => alternation.strokes.join("/"),
+        None => stmt.stroke.name.clone(),
+    };
+    format!("{} {}", stmt.distance.meters(), strokes)
+}
+
+/// Writes a summary in some output format
+pub trait SummaryFormatter {
+    fn format(&self, summary: &Summary, out: &mut dyn io::Write) -> io::Result<()>;
+}
+
+/// Summary as `Label: value` lines
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextFormatter;
+
+/// Summary as JSON
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFormatter;
+
+/// Summary as Markdown, for pasting into team notes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownFormatter;
+
+impl SummaryFormatter for TextFormatter {
+    fn format(&self, summary: &Summary, out: &mut dyn io::Write) -> io::Result<()> {
+        writeln!(out, "Total distance: {}m", summary.distance)?;
+        writeln!(out, "Total time: {}", format_duration(summary.duration()))?;
+        if summary.dryland_time > 0 {
+            writeln!(
+                out,
+                "Dryland time: {}",
+                format_duration(summary.dryland_time)
+            )?;
+        }
+        if let Some(rest) = summary.rest {
+            writeln!(out, "Rest: {}", format_duration(rest))?;
+        }
+        writeln!(out, "Sets: {}", summary.sets)?;
+        if let Some(turns) = summary.turns {
+            writeln!(
+                out,
+                "Turns: {} ({} push-offs, {}m pool)",
+                turns.turns, turns.push_offs, turns.pool
+            )?;
+        }
+        writeln!(out, "Sprint reps: {}", summary.sprint_reps)?;
+        writeln!(out, "Race-pace distance: {}m", summary.race_pace_distance)?;
+        writeln!(out, "Complexity: {}", summary.complexity.score)?;
+
+        if summary.sections.len() > 1 {
+            writeln!(out, "Sections:")?;
+            for (name, meters) in &summary.sections {
+                writeln!(out, "  {}: {}m", name, meters)?;
+            }
+        }
+        if !summary.kinds.is_empty() {
+            writeln!(out, "Set types:")?;
+            for (kind, meters) in &summary.kinds {
+                writeln!(out, "  {}: {}m", kind, meters)?;
+            }
+        }
+        writeln!(out, "Stroke distribution:")?;
+        for (stroke, meters) in &summary.strokes {
+            writeln!(out, "  {}: {}m", stroke, meters)?;
+        }
+        writeln!(out, "Intensity distribution:")?;
+        for (intensity, meters) in &summary.intensities {
+            writeln!(out, "  {}: {}m", intensity, meters)?;
+        }
+        if !summary.equipment.is_empty() {
+            writeln!(out, "Equipment:")?;
+            for (name, meters) in &summary.equipment {
+                writeln!(out, "  {}: {}m", name, meters)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SummaryFormatter for JsonFormatter {
+    fn format(&self, summary: &Summary, out: &mut dyn io::Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, summary)?;
+        writeln!(out)
+    }
+}
+
+impl SummaryFormatter for MarkdownFormatter {
+    fn format(&self, summary: &Summary, out: &mut dyn io::Write) -> io::Result<()> {
+        writeln!(out, "| | |")?;
+        writeln!(out, "|---|---|")?;
+        writeln!(out, "| Distance | {}m |", summary.distance)?;
+        writeln!(out, "| Time | {} |", format_duration(summary.duration()))?;
+        if summary.dryland_time > 0 {
+            writeln!(
+                out,
+                "| Dryland | {} |",
+                format_duration(summary.dryland_time)
+            )?;
+        }
+        if let Some(rest) = summary.rest {
+            writeln!(out, "| Rest | {} |", format_duration(rest))?;
+        }
+        writeln!(out, "| Sets | {} |", summary.sets)?;
+        if let Some(turns) = summary.turns {
+            writeln!(
+                out,
+                "| Turns | {} ({} push-offs, {}m pool) |",
+                turns.turns, turns.push_offs, turns.pool
+            )?;
+        }
+        writeln!(out, "| Sprint reps | {} |", summary.sprint_reps)?;
+        writeln!(
+            out,
+            "| Race-pace distance | {}m |",
+            summary.race_pace_distance
+        )?;
+        writeln!(out, "| Complexity | {} |", summary.complexity.score)?;
+
+        let mix = |meters: &BTreeMap<String, u32>| {
+            meters
+                .iter()
+                .map(|(name, meters)| format!("{} {}m", name, meters))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        if summary.sections.len() > 1 {
+            let sections: Vec<String> = summary
+                .sections
+                .iter()
+                .map(|(name, meters)| format!("{} {}m", name, meters))
+                .collect();
+            writeln!(out, "| Sections | {} |", sections.join(", "))?;
+        }
+        let kinds = summary
+            .kinds
+            .iter()
+            .map(|(kind, meters)| (kind.to_string(), *meters))
+            .collect();
+        writeln!(out, "| Set types | {} |", mix(&kinds))?;
+        writeln!(out, "| Strokes | {} |", mix(&summary.strokes))?;
+        let intensities = summary
+            .intensities
+            .iter()
+            .map(|(intensity, meters)| (intensity.to_string(), *meters))
+            .collect();
+        writeln!(out, "| Intensities | {} |", mix(&intensities))?;
+        if !summary.equipment.is_empty() {
+            writeln!(out, "| Equipment | {} |", mix(&summary.equipment))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, pace::CssModel, parser::Parser};
+    use logos::Logos;
+
+    fn summary(source: &str, model: Option<&dyn PaceModel>) -> Summary {
+        let workout = Parser::new(Token::lexer(source)).parse().unwrap();
+        Summary::new(&workout, &DrillDictionary::builtin(), model, None)
+    }
+
+    fn render(formatter: &dyn SummaryFormatter, summary: &Summary) -> String {
+        let mut out = Vec::new();
+        formatter.format(summary, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_summary() {
+        let source = "400m free(easy)\n4x { 100m free [paddles] @1:40 @z3\n50m catch-up [fins, paddles] @1:00 }\n4x 25m fly @z5\ndryland {\n    2:00 plank\n}";
+        let plain = summary(source, None);
+
+        assert_eq!(plain.distance, 1100);
+        assert_eq!(plain.swim_time, 4 * 160);
+        assert_eq!(plain.sets, 4);
+        assert_eq!(plain.strokes["free"], 1000);
+        assert_eq!(plain.intensities[&Intensity::Easy], 400);
+        assert_eq!(plain.intensities[&Intensity::Threshold], 400);
+        assert_eq!(plain.equipment["paddles"], 600);
+        assert_eq!(plain.equipment["fins"], 200);
+        assert_eq!(plain.rest, None);
+
+        let model = CssModel::new(90);
+        let timed = summary(source, Some(&model));
+        assert!(timed.rest.is_some_and(|rest| rest > 0 && rest < 4 * 160));
+        let workout = Parser::new(Token::lexer(source)).parse().unwrap();
+        let tired = Summary::new(
+            &workout,
+            &DrillDictionary::builtin(),
+            Some(&model),
+            Some(&Fatigue::new(3.0)),
+        );
+        assert!(tired.swim_time > timed.swim_time);
+        let rested = summary("4x50m free r:20\n100m back rest to HR<120", Some(&model));
+        assert_eq!(rested.rest, Some(4 * 20 + 45));
+        let slow = CssModel::new(90).with_recovery(u32::MAX);
+        let recovering = summary("4x50m free rest to HR<120", Some(&slow));
+        assert_eq!(recovering.rest, Some(u32::MAX));
+        assert_eq!(recovering.swim_time, u32::MAX);
+
+        assert_eq!(
+            render(&TextFormatter, &plain),
+            "Total distance: 1100m\nTotal time: 12:40\nDryland time: 2:00\nSets: 4\nSprint reps: 4\nRace-pace distance: 0m\nComplexity: 12\nSections:\n  Warmup: 400m\n  Main set: 700m\nSet types:\n  warmup: 400m\n  main: 600m\n  sprint: 100m\nStroke distribution:\n  fly: 100m\n  free: 1000m\nIntensity distribution:\n  easy: 400m\n  moderate: 200m\n  threshold: 400m\n  sprint: 100m\nEquipment:\n  fins: 200m\n  paddles: 600m\n"
+        );
+        assert!(render(&MarkdownFormatter, &plain).contains("| Strokes | fly 100m, free 1000m |\n"));
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&JsonFormatter, &plain)).unwrap();
+        assert_eq!(json["intensities"]["threshold"], 400);
+        assert_eq!(json["rest"], serde_json::Value::Null);
+        assert_eq!(json["kinds"]["warmup"], 400);
+        assert_eq!(json["turns"], serde_json::Value::Null);
+
+        let pooled = summary("pool: scm\n4x100m free @1:30", None);
+        assert!(render(&TextFormatter, &pooled).contains("\nTurns: 12 (16 push-offs, 25m pool)\n"));
+
+        let sectioned = summary(
+            "warmup: { 400m free }\nmain: { 8x100m free @1:30 }\n200m back(easy)",
+            None,
+        );
+        assert!(render(&MarkdownFormatter, &sectioned)
+            .contains("| Sections | warmup 400m, main 800m, Cooldown 200m |\n"));
+    }
+
+    #[test]
+    fn test_set_summary() {
+        let workout = Parser::new(Token::lexer(
+            "4x { 25m choice @30s\n12x50m free @45s }\n400m free/back by 50\n2x { 3x { 2x { 50m fly } } }\ndryland { 20 squats }",
+        ))
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            workout.sets[0].summary(),
+            "4×{25 choice + 12×50 free} — 2500m, ~38min"
+        );
+        assert_eq!(workout.sets[1].summary(), "400 free/back — 400m");
+        assert_eq!(workout.sets[2].shape(), "2×{3×{2×{…}}}");
+        assert_eq!(workout.sets[3].summary(), "dryland — 0m, ~1min");
+
+        let long = Parser::new(Token::lexer(
+            "{ 100m freestyle\n100m backstroke\n100m breaststroke\n100m butterfly }",
+        ))
+        .parse()
+        .unwrap();
+        let shape = long.sets[0].shape();
+        assert_eq!(shape.chars().count(), SHAPE_WIDTH);
+        assert!(shape.starts_with("{100 freestyle + 100 backstroke + ") && shape.ends_with('…'));
+    }
+}
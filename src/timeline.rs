@@ -0,0 +1,242 @@
+//! The workout laid out on a single clock, for timer apps and simulations
+//!
+//! Offsets are accumulated as [`Duration`]s, so a clock following the
+//! timeline never drifts from repeatedly rounding per-rep times to seconds.
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::NaiveTime;
+
+use crate::{
+    analysis::{section_names, SECONDS_PER_DRYLAND_REP},
+    ast::Workout,
+    pace::{PaceModel, DEFAULT_RECOVERY},
+    path::SetPath,
+    steps::{Step, WorkoutSteps},
+};
+
+/// Clock time formats accepted for a workout's `start` metadata
+const CLOCK_FORMATS: &[&str] = &["%H:%M", "%I:%M %p", "%I:%M%p", "%H:%M:%S"];
+
+/// A single swim or dryland exercise placed on the workout clock
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event<'a> {
+    /// The swim or dryland step done
+    pub step: &'a Step,
+    /// Time from the start of the workout
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+impl Event<'_> {
+    /// Time from the start of the workout to the end of the event
+    pub fn end(&self) -> Duration {
+        self.start + self.duration
+    }
+}
+
+/// Every rep of the workout in order with its absolute start time
+///
+/// A swim takes its send-off, the rep's own from a list, or the time `model`
+/// predicts when it has none; swims with neither take no time. Rests take
+/// their duration, or for rests to a heart rate, the model's recovery time (a
+/// default one without a model). Dryland exercises take their duration, or a
+/// few seconds per repetition.
+pub fn timeline<'a>(workout: &'a WorkoutSteps, model: Option<&dyn PaceModel>) -> Vec<Event<'a>> {
+    let mut clock = Duration::ZERO;
+    let mut reps: HashMap<&SetPath, usize> = HashMap::new();
+    workout
+        .unrolled()
+        .into_iter()
+        .map(|step| {
+            let duration = match step {
+                Step::Swim(swim) => {
+                    let rep = reps.entry(&swim.path).or_default();
+                    *rep += 1;
+                    match (swim.send_off_for(*rep - 1), model) {
+                        (Some(secs), _) => Duration::from_secs(secs.into()),
+                        (None, Some(model)) => {
+                            model.predict_duration(swim.meters, &swim.stroke, swim.intensity)
+                        }
+                        (None, None) => Duration::ZERO,
+                    }
+                }
+                Step::Dryland(dryland) => {
+                    let per_set = match (dryland.duration, dryland.reps) {
+                        (Some(secs), _) => secs.into(),
+                        (None, Some(reps)) => u64::from(reps) * u64::from(SECONDS_PER_DRYLAND_REP),
+                        (None, None) => 0,
+                    };
+                    Duration::from_secs(per_set * u64::from(dryland.sets))
+                }
+                Step::Rest(rest) => {
+                    let recovery = model.map_or(DEFAULT_RECOVERY, |m| m.recovery());
+                    Duration::from_secs(rest.duration.unwrap_or(recovery).into())
+                }
+                Step::Repeat { .. } => Duration::ZERO,
+            };
+            let event = Event {
+                step,
+                start: clock,
+                duration,
+            };
+            clock += duration;
+            event
+        })
+        .collect()
+}
+
+/// A section of the workout, e.g., the warmup, and when it starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionStart {
+    pub name: String,
+    /// Time from the start of the workout
+    pub start: Duration,
+}
+
+/// Start of each section on the workout clock, with the time the workout
+/// ends
+///
+/// Sections are named as by [`section_names`].
+pub fn section_starts(
+    workout: &Workout,
+    model: Option<&dyn PaceModel>,
+) -> (Vec<SectionStart>, Duration) {
+    let steps = WorkoutSteps::from_workout(workout);
+    let events = timeline(&steps, model);
+    let end = events.last().map_or(Duration::ZERO, Event::end);
+
+    let mut starts: Vec<SectionStart> = Vec::new();
+    for (i, name) in section_names(workout).into_iter().enumerate() {
+        if starts.last().is_some_and(|last| last.name == name) {
+            continue;
+        }
+        let start = events
+            .iter()
+            .find(|event| top_level_set(event.step) >= i)
+            .map_or(end, |event| event.start);
+        starts.push(SectionStart { name, start });
+    }
+
+    (starts, end)
+}
+
+/// Reads a wall-clock time such as `6:30`, `6:30 AM` or `18:30`
+pub fn parse_clock_time(text: &str) -> Option<NaiveTime> {
+    let text = text.trim();
+    CLOCK_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(text, format).ok())
+}
+
+/// Index of the top-level set a step belongs to
+fn top_level_set(step: &Step) -> usize {
+    match step {
+        Step::Swim(swim) => swim.path.indices()[0],
+        Step::Dryland(dryland) => dryland.path.indices()[0],
+        Step::Rest(rest) => rest.path.indices()[0],
+        Step::Repeat { .. } => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lexer::Token,
+        pace::CssModel,
+        parser::{Parser, ParserOptions},
+    };
+    use logos::Logos;
+
+    fn steps(source: &str) -> WorkoutSteps {
+        WorkoutSteps::from_workout(&Parser::new(Token::lexer(source)).parse().unwrap())
+    }
+
+    #[test]
+    fn test_timeline() {
+        let workout = steps("3x25m breast(easy)\n2x100m free @1:30\ndryland { 2x 10 squats }");
+        let model = CssModel::new(97);
+
+        let events = timeline(&workout, Some(&model));
+        let starts: Vec<u128> = events.iter().map(|event| event.start.as_millis()).collect();
+
+        // 25m breast at easy pace is 34.859s: per-rep rounding would drift
+        assert_eq!(starts, vec![0, 34_859, 69_718, 104_577, 194_577, 284_577]);
+        assert_eq!(events.last().unwrap().end(), Duration::from_millis(344_577));
+
+        let events = timeline(&workout, None);
+        assert_eq!(events[3].start, Duration::ZERO);
+        assert_eq!(events[4].start, Duration::from_secs(90));
+
+        let options = ParserOptions {
+            max_total_time: u64::MAX,
+            ..ParserOptions::default()
+        };
+        let workout = WorkoutSteps::from_workout(
+            &Parser::with_options(Token::lexer("dryland { 100000x 100000 squats }"), options)
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            timeline(&workout, None)[0].duration,
+            Duration::from_secs(100_000 * 100_000 * 3)
+        );
+
+        let workout = steps("2x { 3x100m free @1:40/1:35/1:30 }");
+        let ends: Vec<u64> = timeline(&workout, None)
+            .iter()
+            .map(|event| event.end().as_secs())
+            .collect();
+        assert_eq!(ends, vec![100, 195, 285, 385, 480, 570]);
+
+        let workout = steps("4x50m odds:free evens:back @1:00");
+        let reps: Vec<(u64, &str)> = timeline(&workout, None)
+            .iter()
+            .map(|event| match event.step {
+                Step::Swim(swim) => (event.start.as_secs(), swim.stroke.as_str()),
+                _ => panic!("expected swims"),
+            })
+            .collect();
+        assert_eq!(
+            reps,
+            vec![(0, "free"), (60, "back"), (120, "free"), (180, "back")]
+        );
+    }
+
+    #[test]
+    fn test_section_starts() {
+        let workout = Parser::new(Token::lexer(
+            "400m free(easy) @7:00\n4x50m kick(easy) @1:10\n8x100m free @1:30\nSprint (4:00): { 4x50m fly @1:00 }\n200m back(easy) @4:00",
+        ))
+        .parse()
+        .unwrap();
+
+        let (starts, end) = section_starts(&workout, None);
+        let starts: Vec<(&str, u64)> = starts
+            .iter()
+            .map(|section| (section.name.as_str(), section.start.as_secs()))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                ("Warmup", 0),
+                ("Main set", 420 + 280),
+                ("Sprint", 700 + 720),
+                ("Cooldown", 1420 + 240),
+            ]
+        );
+        assert_eq!(end, Duration::from_secs(1660 + 240));
+
+        assert_eq!(
+            parse_clock_time("6:30 AM"),
+            NaiveTime::from_hms_opt(6, 30, 0)
+        );
+        assert_eq!(
+            parse_clock_time("6:30pm"),
+            NaiveTime::from_hms_opt(18, 30, 0)
+        );
+        assert_eq!(parse_clock_time("18:05"), NaiveTime::from_hms_opt(18, 5, 0));
+        assert_eq!(parse_clock_time("dawn"), None);
+    }
+}
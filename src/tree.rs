@@ -0,0 +1,139 @@
+use std::fmt;
+
+use crate::{
+    analysis::Analyse,
+    ast::{Set, Workout},
+    path::SetPath,
+};
+
+/// Indented tree of a workout's sets, with the distance and send-off time of
+/// each node, for making sense of deeply nested workouts
+///
+/// Totals are for a single pass through the node; the repetition above it
+/// multiplies them.
+#[derive(Debug, Clone, Copy)]
+pub struct Tree<'a>(pub &'a Workout);
+
+impl fmt::Display for Tree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let workout = self.0;
+        write!(f, "{}", workout.metadata.get("title").unwrap_or("workout"))?;
+        totals(f, workout)?;
+        writeln!(f)?;
+
+        for (i, set) in workout.sets.iter().enumerate() {
+            let last = i + 1 == workout.sets.len();
+            node(f, set, SetPath::new(vec![i]), "", last)?;
+        }
+        Ok(())
+    }
+}
+
+fn node(
+    f: &mut fmt::Formatter<'_>,
+    set: &Set,
+    path: SetPath,
+    prefix: &str,
+    last: bool,
+) -> fmt::Result {
+    write!(f, "{}{} {} ", prefix, if last { "└─" } else { "├─" }, path)?;
+    label(f, set)?;
+    totals(f, set)?;
+    writeln!(f)?;
+
+    let prefix = format!("{}{}", prefix, if last { "   " } else { "│  " });
+    let children = set.children();
+    for (i, child) in children.iter().enumerate() {
+        node(f, child, path.child(i), &prefix, i + 1 == children.len())?;
+    }
+    Ok(())
+}
+
+/// What a node is; unlabeled blocks show their shape
+fn label(f: &mut fmt::Formatter<'_>, set: &Set) -> fmt::Result {
+    match set {
+        Set::Repetition { count, set, .. } => match set.as_ref() {
+            Set::Block { .. } | Set::Dryland { .. } => {
+                write!(f, "{}x ", count)?;
+                label(f, set)
+            }
+            set => write!(f, "{}x {}", count, set),
+        },
+        Set::Block {
+            intensity,
+            key,
+            label,
+            budget,
+            ..
+        } => {
+            match label {
+                Some(label) => write!(f, "{}", label)?,
+                None => write!(f, "{}", set.shape())?,
+            }
+            if let Some(budget) = budget {
+                write!(f, " ({}:{:02})", budget / 60, budget % 60)?;
+            }
+            if let Some(intensity) = intensity {
+                write!(f, " @z{}", intensity.zone())?;
+            }
+            if *key {
+                write!(f, " key")?;
+            }
+            Ok(())
+        }
+        Set::Statement(stmt) => write!(f, "{}", stmt),
+        Set::Dryland { exercises } => {
+            write!(f, "dryland: ")?;
+            for (i, exercise) in exercises.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", exercise)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn totals(f: &mut fmt::Formatter<'_>, node: &dyn Analyse) -> fmt::Result {
+    let time = node.total_time();
+    match time {
+        0 => write!(f, " ({}m)", node.total_distance()),
+        _ => write!(
+            f,
+            " ({}m, {}:{:02})",
+            node.total_distance(),
+            time / 60,
+            time % 60
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Token, parser::Parser};
+    use logos::Logos;
+
+    #[test]
+    fn test_tree() {
+        let workout = Parser::new(Token::lexer(
+            "title: Tuesday\n400m free(easy)\n3x {\n  100m fly @1:40\n  2x { 50m back @1:00\n 50m free @50s } @z2\n}\ndryland { 20 squats }",
+        ))
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            Tree(&workout).to_string(),
+            "Tuesday (1300m, 17:00)
+├─ 1 400m free(easy) (400m)
+├─ 2 3x {100 fly + 2×{50 back + 50 free}} (900m, 16:00)
+│  ├─ 2.1 100m fly @1:40 (100m, 1:40)
+│  └─ 2.2 2x {50 back + 50 free} @z2 (200m, 3:40)
+│     ├─ 2.2.1 50m back @1:00 (50m, 1:00)
+│     └─ 2.2.2 50m free @50s (50m, 0:50)
+└─ 3 dryland: 20 squats (0m, 1:00)
+"
+        );
+    }
+}
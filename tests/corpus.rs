@@ -0,0 +1,16 @@
+//! Parses every workout under `tests/corpus/` and compares its AST and
+//! summary with the snapshot next to it
+//!
+//! Run with `SWIM_BLESS=1` to write the snapshots after an intended change.
+
+use std::path::Path;
+
+use swim_parser::corpus::check_corpus;
+
+#[test]
+fn corpus_matches_snapshots() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let bless = std::env::var_os("SWIM_BLESS").is_some();
+    let report = check_corpus(&dir, bless).unwrap();
+    assert!(report.passed(), "{}", report);
+}